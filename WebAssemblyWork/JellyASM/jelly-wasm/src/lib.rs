@@ -1,5 +1,4 @@
 mod utils;
-mod lexer;
 
 extern crate js_sys;
 extern crate web_sys;
@@ -8,6 +7,7 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsObject;
 use wasm_bindgen::convert::FromWasmAbi;
 use std::fmt;
+use gelli::{Lexer, MemoryHost};
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -31,10 +31,28 @@ extern "C" {
 pub fn lex(s: String) -> String {
     utils::set_panic_hook();
 
-    let mut lex = lexer::Lexer::new(s).expect("Could not create lexer");
+    let mut lex = Lexer::new(s).expect("Could not create lexer");
     let mut s: String = String::new();
     while !lex.is_done() {
         s.push_str(&format!("{:?}\n", lex.next().expect("Could not lex line")));
     }
     s
-}
\ No newline at end of file
+}
+
+// Run a program, giving back everything it printed (or its errors)
+// The program's input is given ahead of time, one line per input_text
+//  or input_number
+#[wasm_bindgen]
+pub fn run(s: String, input: String) -> String {
+    utils::set_panic_hook();
+
+    let mut host = MemoryHost::new(&input);
+    let result = gelli::run_source(&s, &mut host, gelli::LintConfig::new());
+    let mut output = host.output;
+    if let Err(errors) = result {
+        for e in errors {
+            output.push_str(&format!("{}\n", e.render(&s, false)));
+        }
+    }
+    output
+}
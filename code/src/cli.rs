@@ -0,0 +1,308 @@
+use std::{env, process};
+use std::io::{self, IsTerminal, Read, Write};
+use gelli::lexer::{Lexer, TokenType};
+use gelli::parser::{ParseTree, Parser};
+use gelli::semantic_analyzer::{SemanticAnalyzer, SymbolTable};
+use gelli::lint::{Linter, LintConfig};
+use gelli::host::StdioHost;
+use gelli::formatter::Formatter;
+use gelli::repl::{self, Repl, Reply};
+use crate::lsp;
+use gelli::diagnostic::{self, Diagnostic, Severity, Stage};
+
+// ====================
+// =       CLI        =
+// ====================
+// The command line tool, the first argument picks what to do:
+//  code run FILE              run a program
+//  code check FILE            only look for errors
+//  code tokens FILE           print every token the lexer finds
+//  code ast FILE              print the syntax tree
+//  code symbols FILE          print the structures, functions and globals
+//  code fmt [--check] FILE    format a program (formatter.rs)
+//  code lsp                   start the language server (lsp.rs)
+//  code repl                  type in code a piece at a time (repl.rs)
+// The FILE can be "-" to read the code from stdin instead.
+// "code FILE" still runs the file, like before there were commands.
+// When a program has errors, the exit code says which stage found
+//  them (see Stage::exit_code): 3 for the lexer, 4 for the parser,
+//  5 for the semantic analyzer and 6 for a runtime error. This is the
+//  same for every command that reads a FILE, except fmt. A program
+//  that quits with a status ("quit 7") exits with that status.
+
+static USAGE: &str = "\
+usage: code <command> FILE
+
+commands:
+    run FILE              run a program
+    check FILE            check a program for errors without running it
+    tokens FILE           print the tokens of a program
+    ast FILE              print the syntax tree of a program
+    symbols FILE          print the symbol table of a program
+    fmt [--check] FILE    format a program
+    lsp                   start the language server
+    repl                  type in code and run it a piece at a time
+
+FILE can be - to read the program from stdin";
+
+pub fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        usage();
+    }
+
+    let command = args[1].as_str();
+    let rest = &args[2..];
+    match command {
+        "run" => run(file_arg(rest)),
+        "check" => check(file_arg(rest)),
+        "tokens" => tokens(file_arg(rest)),
+        "ast" => ast(file_arg(rest)),
+        "symbols" => symbols(file_arg(rest)),
+        "fmt" => fmt(rest),
+        "lsp" => lsp::main(),
+        "repl" => interactive(),
+        "help" | "-h" | "--help" => println!("{}", USAGE),
+        _ => run(file_arg(&args[1..])),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+// Every command except lsp and repl takes exactly one file
+fn file_arg(args: &[String]) -> &str {
+    match args {
+        [fname] => fname,
+        _ => usage(),
+    }
+}
+
+// Read the code from a file, or all of stdin for "-"
+pub fn read_source(fname: &str) -> Result<String, Diagnostic> {
+    if fname != "-" {
+        return Lexer::read_file(fname.to_string());
+    }
+    let mut code = String::new();
+    if io::stdin().read_to_string(&mut code).is_err() {
+        return Err(Diagnostic::new(Stage::LEX, diagnostic::FILE_ERROR, "Could not read from stdin".to_string()));
+    }
+    Ok(code)
+}
+
+// Print errors the same way for every command, pointing at the code
+fn report(code: &str, errors: &[Diagnostic]) {
+    for e in errors {
+        eprintln!("{}\n", e.render(code, diagnostic::use_color()));
+    }
+}
+
+// Output that doesn't end a line is still waiting to be printed,
+//  so it has to be flushed before exiting
+fn exit(status: i32) -> ! {
+    let _ = io::stdout().flush();
+    process::exit(status);
+}
+
+// Print the errors, and exit with the code of the stage that found them
+//  (the earliest one, a lexer error can come after syntax errors)
+// Warnings can be mixed in, but they don't change the exit code
+fn fail(code: &str, errors: Vec<Diagnostic>) -> ! {
+    report(code, &errors);
+    exit(errors.iter().filter(|e| e.severity == Severity::ERROR).map(|e| e.stage.exit_code()).min().unwrap_or(1));
+}
+
+fn read_or_exit(fname: &str) -> String {
+    match read_source(fname) {
+        Ok(c) => c,
+        Err(e) => fail("", vec![e]),
+    }
+}
+
+// Parse the code, with every syntax error if there were any
+fn parse(code: &str) -> (Option<ParseTree>, Vec<Diagnostic>) {
+    match Parser::new(code.to_string()) {
+        Ok(mut p) => p.parse(),
+        Err(e) => (None, vec![e]),
+    }
+}
+
+// Parse and analyze the code, stopping at the first stage that
+//  has errors (later stages would only repeat them)
+fn analyze(code: &str) -> Result<(ParseTree, SymbolTable), Vec<Diagnostic>> {
+    let tree = match parse(code) {
+        (Some(tree), errors) if errors.is_empty() => tree,
+        (_, errors) => return Err(errors),
+    };
+    let (symbol_table, errors) = SemanticAnalyzer::new().analyze(&tree);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok((tree, symbol_table))
+}
+
+// ====================
+// =    COMMANDS      =
+// ====================
+
+// Run a program
+// Warnings and errors go to stderr, so stdout only has what the
+//  program printed (an autograder can compare it exactly)
+fn run(fname: &str) {
+    let code = read_or_exit(fname);
+    let result = gelli::run_source(&code, StdioHost::new(), LintConfig::from_env());
+
+    // Anything the program displayed comes before the warnings (and errors)
+    let _ = io::stdout().flush();
+    match result {
+        Ok(outcome) => {
+            report(&code, &outcome.warnings);
+            exit(outcome.exit_code);
+        },
+        Err(errors) => fail(&code, errors),
+    }
+}
+
+// Everything "run" does before running the program
+fn check(fname: &str) {
+    let code = read_or_exit(fname);
+    let (tree, symbol_table) = match analyze(&code) {
+        Ok(analyzed) => analyzed,
+        Err(errors) => fail(&code, errors),
+    };
+    report(&code, &Linter::new(LintConfig::from_env()).lint(&tree, &symbol_table));
+}
+
+// One token per line, with where it is
+fn tokens(fname: &str) {
+    let code = read_or_exit(fname);
+    let mut lex = match Lexer::new(code.clone()) {
+        Ok(l) => l,
+        Err(e) => fail(&code, vec![e]),
+    };
+
+    loop {
+        match lex.next() {
+            Ok(token) => {
+                println!("{} {:?}", token.span, token.token_type);
+                if token.token_type == TokenType::EOF {
+                    break;
+                }
+            },
+            Err(e) => fail(&code, vec![e]),
+        }
+    }
+}
+
+// The tree is still printed when there are syntax errors, with
+//  the parts that couldn't be parsed marked as invalid
+fn ast(fname: &str) {
+    let code = read_or_exit(fname);
+    let (tree, errors) = parse(&code);
+    if let Some(tree) = tree {
+        tree.print();
+    }
+    if !errors.is_empty() {
+        fail(&code, errors);
+    }
+}
+
+fn symbols(fname: &str) {
+    let code = read_or_exit(fname);
+    match analyze(&code) {
+        Ok((_, symbol_table)) => symbol_table.print(),
+        Err(errors) => fail(&code, errors),
+    }
+}
+
+// Prints the formatted code, or with --check, prints nothing and
+//  exits with 1 if the file isn't already formatted (2 if it can't
+//  be formatted), so a grader can check a submission is formatted
+fn fmt(args: &[String]) {
+    let check = args.iter().any(|a| a == "--check");
+    let fname = match args.iter().find(|a| *a != "--check") {
+        Some(f) => f,
+        None => {
+            eprintln!("usage: code fmt [--check] FILE");
+            process::exit(2);
+        },
+    };
+
+    let code = match read_source(fname) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e.render("", diagnostic::use_color()));
+            process::exit(2);
+        },
+    };
+
+    let formatted = match Formatter::format(&code) {
+        Ok(f) => f,
+        Err(errors) => {
+            report(&code, &errors);
+            process::exit(2);
+        },
+    };
+
+    if check {
+        if formatted != code {
+            eprintln!("{} is not formatted", fname);
+            process::exit(1);
+        }
+    }
+    else {
+        print!("{}", formatted);
+    }
+}
+
+// Read entries one at a time and run them in the same REPL, a block
+//  is read line by line until its 'end'
+// The prompts are only shown in a terminal, so a file of entries can
+//  be piped in like "code repl < entries.txt"
+fn interactive() {
+    let prompts = io::stdin().is_terminal();
+    if prompts {
+        println!("Gelli REPL, type :help for the commands");
+    }
+
+    let mut repl = Repl::new(StdioHost::new());
+    let mut entry = String::new();
+    loop {
+        if prompts {
+            print!("{}", if entry.is_empty() { "> " } else { "... " });
+            let _ = io::stdout().flush();
+        }
+
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => entry.push_str(&line),
+        }
+        if entry.trim().is_empty() {
+            entry.clear();
+            continue;
+        }
+        if !entry.trim_start().starts_with(':') && repl::open_blocks(&entry) > 0 {
+            continue;
+        }
+
+        match repl.eval(&entry) {
+            Ok(Reply::TEXT(text)) if !text.is_empty() => println!("{}", text),
+            Ok(Reply::QUIT(status)) => exit(status),
+            Ok(_) => (),
+            Err(errors) => {
+                let _ = io::stdout().flush();
+                report(repl.source(), &errors);
+            },
+        }
+        entry.clear();
+    }
+
+    // The last entry was never finished
+    if !entry.trim().is_empty() {
+        eprintln!("The input ended before every block was ended");
+    }
+    exit(0);
+}
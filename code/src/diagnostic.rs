@@ -0,0 +1,393 @@
+use crate::lexer::Token;
+use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal};
+
+// Diagnostics are the errors (and warnings) that every stage of the
+//  interpreter reports. They carry enough information for tools (like
+//  grading scripts or an editor) to work with them, and can also be
+//  turned into a message for the user. The message can either be a
+//  single line (Display) or the source code with the problem underlined
+//  (render).
+
+// The stage of the interpreter that found the problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    LEX,
+    PARSE,
+    SEMANTIC,
+    RUNTIME,
+}
+
+// Every problem found in a piece of code, like all of its syntax errors
+pub type Diagnostics = Vec<Diagnostic>;
+
+// How serious the problem is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    ERROR,
+    WARNING,
+}
+
+// A range in the source code, rows and columns start at 1
+// 'start' and 'end' are the byte offsets of the range, the end
+//  (and end column) is one past the last character
+// A span of all zeros means the position is unknown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start_row: u32,
+    pub start_col: u32,
+    pub end_row: u32,
+    pub end_col: u32,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    // The span covered by a single token
+    pub fn from_token(token: &Token) -> Self {
+        token.span
+    }
+
+    // The span from the start of this span to the end of another
+    pub fn to(&self, other: Span) -> Self {
+        Span {
+            start_row: self.start_row,
+            start_col: self.start_col,
+            end_row: other.end_row,
+            end_col: other.end_col,
+            start: self.start,
+            end: other.end,
+        }
+    }
+
+    // An empty span where this one starts
+    pub fn start_point(&self) -> Self {
+        Span {
+            start_row: self.start_row,
+            start_col: self.start_col,
+            end_row: self.start_row,
+            end_col: self.start_col,
+            start: self.start,
+            end: self.start,
+        }
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        self.start_row == 0
+    }
+}
+
+// Written like "4:9-4:15"
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}-{}:{}", self.start_row, self.start_col, self.end_row, self.end_col)
+    }
+}
+
+// Extra information that points at another part of the code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub stage: Stage,
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    // Most errors have no help, so it is boxed to keep every Result
+    //  that can hold a Diagnostic small
+    pub help: Option<Box<str>>,
+}
+
+// ===================
+// =   ERROR CODES   =
+// ===================
+// These codes are stable, tools can rely on them not changing.
+// The letter is the stage (or W for a warning), and the number is
+//  the kind of problem.
+
+// Lexer
+pub const FILE_ERROR: &str = "L001"; // the source file could not be read
+pub const NON_ASCII: &str = "L002"; // a character that is not printable ASCII
+pub const UNKNOWN_CHARACTER: &str = "L003"; // a character that doesn't start any token
+pub const UNTERMINATED_TEXT: &str = "L004"; // text without its closing quote
+
+// Parser
+pub const UNEXPECTED_TOKEN: &str = "P001"; // the grammar expected something else
+pub const MISSING_END: &str = "P002"; // a block that is never ended
+pub const OUT_OF_ORDER: &str = "P003"; // definitions that are not in the order of the grammar
+
+// Semantic analyzer
+pub const UNKNOWN_NAME: &str = "S001"; // unknown variable, type, function, or key
+pub const DUPLICATE_NAME: &str = "S002"; // a name that is already defined
+pub const TYPE_MISMATCH: &str = "S003"; // a value has the wrong type
+pub const ARGUMENT_COUNT: &str = "S004"; // the wrong number of arguments or items
+pub const INVALID_LINK: &str = "S005"; // links used (or not used) where they shouldn't be
+pub const INVALID_INDEX: &str = "S006"; // indexing something that can't be indexed
+pub const UNCHANGEABLE: &str = "S007"; // changing something that can't be changed
+pub const UNEXPECTED: &str = "S008"; // something that isn't allowed in this spot
+pub const MISSING_RETURN: &str = "S009"; // a function that can end without returning a value
+pub const MAYBE_UNASSIGNED: &str = "S010"; // a variable that might be read before it has a value
+
+// Warnings (from the lint pass, after the semantic analyzer)
+pub const UNUSED_VARIABLE: &str = "W001"; // a variable that is never read
+pub const UNUSED_PARAMETER: &str = "W002"; // a parameter that is never used
+pub const UNREACHABLE_CODE: &str = "W003"; // code after a return, break, continue, or quit
+pub const SHADOWED_GLOBAL: &str = "W004"; // a local variable that hides a global
+pub const UNCALLED_FUNCTION: &str = "W005"; // a function that is never called
+pub const CONSTANT_CONDITION: &str = "W006"; // a loop condition that never changes
+
+// Interpreter
+pub const OUT_OF_BOUNDS: &str = "R001"; // an index outside of the array (or text)
+pub const DIVIDE_BY_ZERO: &str = "R002"; // dividing by zero
+pub const UNLINKED: &str = "R003"; // following a link that is not linked
+pub const INVALID_MEMORY: &str = "R004"; // memory that can't be used (yet)
+pub const INVALID_INPUT: &str = "R005"; // input from the user that can't be used
+pub const INVALID_ARGUMENT: &str = "R006"; // a library function given a bad value
+pub const INVALID_OPERATION: &str = "R007"; // anything else that went wrong while running
+pub const UNASSIGNED: &str = "R008"; // a variable read before it was given a value
+
+impl Diagnostic {
+    // Create a new error, without a position
+    pub fn new(stage: Stage, code: &'static str, message: String) -> Self {
+        Diagnostic {
+            stage,
+            severity: Severity::ERROR,
+            code,
+            message,
+            span: Span::default(),
+            labels: Vec::new(),
+            help: None,
+        }
+    }
+
+    // Create a new warning, without a position
+    pub fn warning(stage: Stage, code: &'static str, message: String) -> Self {
+        let mut warning = Diagnostic::new(stage, code, message);
+        warning.severity = Severity::WARNING;
+        warning
+    }
+
+    // Set where the problem is
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    // Point at another part of the code that is related to the problem
+    pub fn with_label(mut self, span: Span, message: String) -> Self {
+        self.labels.push(Label {
+            span,
+            message,
+        });
+        self
+    }
+
+    // Add a suggestion for how to fix the problem
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help = Some(help.into_boxed_str());
+        self
+    }
+}
+
+impl Stage {
+    // The exit code of the program when this stage stops it
+    // (0 is success, 1 and 2 are used by the tools, like for
+    //  a wrong command)
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Stage::LEX => 3,
+            Stage::PARSE => 4,
+            Stage::SEMANTIC => 5,
+            Stage::RUNTIME => 6,
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Stage::LEX => "Lexer",
+            Stage::PARSE => "Parse",
+            Stage::SEMANTIC => "Semantic",
+            Stage::RUNTIME => "Runtime",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Severity::ERROR => "error",
+            Severity::WARNING => "warning",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A plain one line (plus help) version of the diagnostic
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}[{}]", self.stage, self.severity, self.code)?;
+        if !self.span.is_unknown() {
+            write!(f, " on line {}:{}", self.span.start_row, self.span.start_col)?;
+        }
+        write!(f, ": {}", self.message)?;
+
+        for label in &self.labels {
+            write!(f, "\n  note (line {}:{}): {}", label.span.start_row, label.span.start_col, label.message)?;
+        }
+        if let Some(help) = &self.help {
+            write!(f, "\n  help: {}", help)?;
+        }
+
+        Ok(())
+    }
+}
+
+// ======================
+// =   TERMINAL OUTPUT  =
+// ======================
+
+// ANSI color codes used by the renderer
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// Only use color when printing straight to a terminal
+//  (and the user hasn't asked for no color)
+// Diagnostics are printed to stderr, so that is the one to check
+pub fn use_color() -> bool {
+    env::var_os("NO_COLOR").is_none() && io::stderr().is_terminal()
+}
+
+// Wrap text in a color, if colors are turned on
+fn paint(text: &str, color: &str, use_color: bool) -> String {
+    if use_color {
+        return format!{"{}{}{}", color, text, RESET};
+    }
+    text.to_string()
+}
+
+// Print the gutter for a line, like " 12 | "
+fn gutter(row: Option<u32>, width: usize, use_color: bool) -> String {
+    let number = match row {
+        Some(r) => format!{"{:>width$}", r, width = width},
+        None => " ".repeat(width),
+    };
+    paint(&format!{"{} |", number}, BLUE, use_color)
+}
+
+// Underline the part of a line between two columns (starting at 1,
+//  and not including the end). Tabs are kept so the marks line up with
+//  the line above.
+fn underline(line: &str, start_col: u32, end_col: u32, mark: char) -> String {
+    let mut result = String::new();
+    for (col, c) in (1..).zip(line.chars()) {
+        if col >= start_col {
+            break;
+        }
+        result.push(if c == '\t' { '\t' } else { ' ' });
+    }
+    // Always show at least one mark, even for things like the end of the file
+    let width = if end_col > start_col { end_col - start_col } else { 1 };
+    for _ in 0..width {
+        result.push(mark);
+    }
+    result
+}
+
+impl Diagnostic {
+    // Show the diagnostic with the lines of source code it is about,
+    //  underlining the exact span:
+    //
+    //  Parse error[P001]: Expected `while` after `end`, found `if`
+    //   --> line 4, column 9
+    //    |
+    //  4 |     end if
+    //    |         ^^
+    //    = help: did you mean `end while`?
+    pub fn render(&self, source: &str, use_color: bool) -> String {
+        let color = match self.severity {
+            Severity::ERROR => RED,
+            Severity::WARNING => YELLOW,
+        };
+        let lines: Vec<&str> = source.lines().collect();
+
+        let mut out = String::new();
+        out.push_str(&paint(&format!{"{} {}[{}]", self.stage, self.severity, self.code}, color, use_color));
+        out.push_str(&paint(&format!{": {}", self.message}, BOLD, use_color));
+        out.push('\n');
+
+        // The gutter has to fit the biggest line number shown
+        let mut last_row = self.span.end_row.max(self.span.start_row);
+        for label in &self.labels {
+            last_row = last_row.max(label.span.end_row.max(label.span.start_row));
+        }
+        let width = last_row.to_string().len();
+
+        if !self.span.is_unknown() {
+            out.push_str(&format!{"{}{} line {}, column {}\n", " ".repeat(width), paint("-->", BLUE, use_color), self.span.start_row, self.span.start_col});
+            out.push_str(&gutter(None, width, use_color));
+            out.push('\n');
+            out.push_str(&self.render_span(&lines, self.span, '^', None, color, width, use_color));
+        }
+
+        for label in &self.labels {
+            if label.span.is_unknown() {
+                out.push_str(&format!{"{} = note: {}\n", " ".repeat(width), label.message});
+            }
+            else {
+                out.push_str(&self.render_span(&lines, label.span, '-', Some(&label.message), BLUE, width, use_color));
+            }
+        }
+
+        if let Some(help) = &self.help {
+            out.push_str(&format!{"{} {} {}\n", " ".repeat(width), paint("= help:", CYAN, use_color), help});
+        }
+
+        // The caller decides what comes after the last line
+        out.truncate(out.trim_end_matches('\n').len());
+        out
+    }
+
+    // Show every line of a span, with marks under the part of the line in the span
+    #[allow(clippy::too_many_arguments)]
+    fn render_span(&self, lines: &[&str], span: Span, mark: char, message: Option<&String>, color: &str, width: usize, use_color: bool) -> String {
+        let mut out = String::new();
+        let end_row = span.end_row.max(span.start_row);
+
+        for row in span.start_row..=end_row {
+            let line = match lines.get((row - 1) as usize) {
+                Some(l) => *l,
+                None => "",
+            };
+
+            // Only the first and last line of a span stop part way through
+            let start_col = if row == span.start_row { span.start_col } else { 1 };
+            let end_col = if row == end_row { span.end_col } else { line.chars().count() as u32 + 1 };
+
+            out.push_str(&format!{"{} {}\n", gutter(Some(row), width, use_color), line});
+            let mut marks = paint(&underline(line, start_col, end_col, mark), color, use_color);
+            if row == end_row {
+                if let Some(msg) = message {
+                    marks.push(' ');
+                    marks.push_str(&paint(msg, color, use_color));
+                }
+            }
+            out.push_str(&format!{"{} {}\n", gutter(None, width, use_color), marks});
+        }
+
+        out
+    }
+}
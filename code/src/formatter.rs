@@ -0,0 +1,504 @@
+use crate::lexer::{Lexer, StreamKind, TokenType, Trivia};
+use crate::parser::{BinOp, Block, Condition, ConditionKind, Else, Expr, ExprKind, FunctionDef, If, Param, ParseTree, Parser, Reference, ReferenceKind, Stmt, StmtKind, StructDef, Target, Type, TypeKind, VarDef};
+use crate::diagnostic::Diagnostic;
+
+// =====================
+// =     FORMATTER     =
+// =====================
+// Prints a program back out in one standard layout, so any two
+//  programs that parse the same way look the same:
+//  - every block is indented by 4 spaces, and closed on its own line
+//  - keywords are lowercase, and operators have a space on each side
+//  - only the parenthesis that are needed are kept
+//  - a blank line goes between each definition, other blank lines
+//    are kept (but never more than one in a row)
+//  - comments are kept, on the line they were on (or before the
+//    statement they were inside of)
+// Formatting code that is already formatted doesn't change it
+
+const INDENT: &str = "    ";
+
+// A comment from the code, so it can be put back in
+#[derive(Debug, Clone)]
+struct Comment {
+    text: String,       // everything after the '#'
+    row: u32,
+    own_line: bool,     // true if no token comes before it on its row
+}
+
+pub struct Formatter {
+    comments: Vec<Comment>,
+    next_comment: usize,
+
+    // Rows of the keywords that aren't kept in the syntax tree
+    def_row: Option<u32>,
+    end_def_row: u32,
+    program_row: u32,
+    else_rows: Vec<u32>,
+    next_else: usize,
+
+    lines: Vec<String>,
+    indent: usize,
+    last_row: u32,
+    after_open: bool,
+    force_blank: bool,
+}
+
+impl Formatter {
+    // Format the code, or return the syntax errors that stopped it
+    pub fn format(code: &str) -> Result<String, Vec<Diagnostic>> {
+        let mut p = match Parser::new(code.to_string()) {
+            Ok(p) => p,
+            Err(e) => return Err(vec![e]),
+        };
+        let (tree, errors) = p.parse();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let tree = tree.unwrap();
+
+        let mut f = match Formatter::new(code) {
+            Ok(f) => f,
+            Err(e) => return Err(vec![e]),
+        };
+        f.print_tree(&tree);
+        Ok(f.lines.join("\n") + "\n")
+    }
+
+    // Lex the code a second time (keeping the trivia) to pick up the
+    //  comments, and the rows of the keywords the parser doesn't keep
+    fn new(code: &str) -> Result<Self, Diagnostic> {
+        let mut comments = Vec::new();
+        let mut def_row = None;
+        let mut end_def_row = 0;
+        let mut program_row = 0;
+        let mut else_rows = Vec::new();
+
+        let mut row = 1;
+        let mut token_on_row = false;
+        let mut last = TokenType::INVALID;
+        for item in Lexer::stream(code.to_string())? {
+            match item.kind {
+                StreamKind::TRIVIA(Trivia::COMMENT) => comments.push(Comment {
+                    text: item.raw[1..].trim_end().to_string(),
+                    row,
+                    own_line: !token_on_row,
+                }),
+                StreamKind::TRIVIA(_) => (),
+                StreamKind::TOKEN(token) => {
+                    token_on_row = true;
+                    match (&last, &token.token_type) {
+                        (TokenType::INVALID, TokenType::DEF) => def_row = Some(token.span.start_row),
+                        (TokenType::END, TokenType::DEF) => end_def_row = token.span.start_row,
+                        (TokenType::END, _) => (),
+                        (_, TokenType::PROGRAM) => program_row = token.span.start_row,
+                        (_, TokenType::ELSE) => else_rows.push(token.span.start_row),
+                        _ => (),
+                    }
+                    last = token.token_type;
+                },
+            }
+
+            // Text can't have a newline in it, but count them all anyway
+            let newlines = item.raw.matches('\n').count() as u32;
+            if newlines > 0 {
+                row += newlines;
+                token_on_row = false;
+            }
+        }
+
+        Ok(Formatter {
+            comments,
+            next_comment: 0,
+            def_row,
+            end_def_row,
+            program_row,
+            else_rows,
+            next_else: 0,
+            lines: Vec::new(),
+            indent: 0,
+            last_row: 0,
+            after_open: false,
+            force_blank: false,
+        })
+    }
+
+    // ====================
+    // =   LINE LAYOUT    =
+    // ====================
+
+    // Add a line at the current indent, with a blank line before it
+    //  if there was one in the code (or one is needed)
+    fn push(&mut self, text: String, row: u32) {
+        if !self.lines.is_empty() && !self.after_open &&
+           (self.force_blank || row > self.last_row + 1) {
+            self.lines.push(String::new());
+        }
+        self.lines.push(format!{"{}{}", INDENT.repeat(self.indent), text});
+        self.last_row = row;
+        self.after_open = false;
+        self.force_blank = false;
+    }
+
+    // Put every comment that comes before a row on its own line
+    fn comments_before(&mut self, row: u32) {
+        while self.next_comment < self.comments.len() && self.comments[self.next_comment].row < row {
+            let comment = self.comments[self.next_comment].clone();
+            self.next_comment += 1;
+            self.push(format!{"#{}", comment.text}, comment.row);
+        }
+    }
+
+    // A comment after the code on a row stays at the end of its line
+    fn trailing_comment(&mut self, row: u32) {
+        if self.next_comment < self.comments.len() {
+            let comment = &self.comments[self.next_comment];
+            if comment.row == row && !comment.own_line {
+                let text = format!{" #{}", comment.text};
+                self.lines.last_mut().unwrap().push_str(&text);
+                self.next_comment += 1;
+            }
+        }
+    }
+
+    // A line of code that came from the rows start to end, any
+    //  comments inside of it are moved before it
+    fn line(&mut self, text: String, start: u32, end: u32) {
+        self.comments_before(end);
+        self.push(text, start);
+        self.last_row = end;
+        self.trailing_comment(end);
+    }
+
+    // The first line of a block, the lines after it are indented
+    fn open(&mut self, text: String, start: u32, end: u32) {
+        self.line(text, start, end);
+        self.indent += 1;
+        self.after_open = true;
+    }
+
+    // The last line of a block, this never has a blank line before it
+    fn close(&mut self, text: &str, row: u32) {
+        self.comments_before(row);
+        self.indent -= 1;
+        self.after_open = true;
+        self.push(text.to_string(), row);
+        self.trailing_comment(row);
+    }
+
+    // An "else" closes one block and opens the next
+    fn reopen(&mut self, text: String, end: u32) {
+        let row = self.else_rows.get(self.next_else).copied().unwrap_or(end);
+        self.next_else += 1;
+        self.comments_before(row);
+        self.indent -= 1;
+        self.after_open = true;
+        self.line(text, row, end.max(row));
+        self.indent += 1;
+        self.after_open = true;
+    }
+
+    // ====================
+    // =   DEFINITIONS    =
+    // ====================
+
+    fn print_tree(&mut self, tree: &ParseTree) {
+        if let Some(def_row) = self.def_row {
+            self.open("definitions".to_string(), def_row, def_row);
+
+            for structure in &tree.structures {
+                self.print_struct_def(structure);
+                self.force_blank = true;
+            }
+            for global in &tree.globals {
+                self.print_stmt(global);
+            }
+            if !tree.globals.is_empty() {
+                self.force_blank = true;
+            }
+            for function in &tree.functions {
+                self.print_function_def(function);
+                self.force_blank = true;
+            }
+
+            self.force_blank = false;
+            self.close("end definitions", self.end_def_row);
+            self.force_blank = true;
+        }
+
+        self.open("program".to_string(), self.program_row, self.program_row);
+        self.print_block(&tree.body);
+        self.close("end program", tree.span.end_row);
+
+        // Comments at the end of the file
+        self.comments_before(u32::MAX);
+    }
+
+    fn print_struct_def(&mut self, structure: &StructDef) {
+        let name = structure.name.as_ref().map(|n| n.name.as_str()).unwrap_or("");
+        let row = structure.name.as_ref().map(|n| n.span.end_row).unwrap_or(structure.span.start_row);
+        self.open(format!{"structure {}", name}, structure.span.start_row, row);
+
+        for field in &structure.fields {
+            let mut text = format!{"{}: {}", field.name.name, type_text(&field.field_type)};
+            if let Some(default) = &field.default {
+                text += &format!{" = {}", expr_text(default)};
+            }
+            self.line(text, field.span.start_row, field.span.end_row);
+        }
+
+        self.close("end structure", structure.span.end_row);
+    }
+
+    fn print_function_def(&mut self, function: &FunctionDef) {
+        let name = function.name.as_ref().map(|n| n.name.as_str()).unwrap_or("");
+        let params: Vec<String> = function.params.iter().map(param_text).collect();
+        let header = format!{"function {}({}) returns {}", name, params.join(", "), type_text(&function.return_type)};
+        self.open(header, function.span.start_row, function.return_type.span.end_row);
+        self.print_block(&function.body);
+        self.close("end function", function.span.end_row);
+    }
+
+    // ====================
+    // =    STATEMENTS    =
+    // ====================
+
+    fn print_block(&mut self, block: &Block) {
+        for statement in &block.statements {
+            self.print_stmt(statement);
+        }
+    }
+
+    fn print_stmt(&mut self, stmt: &Stmt) {
+        let start = stmt.span.start_row;
+        let end = stmt.span.end_row;
+        let text = match &stmt.kind {
+            StmtKind::VARDEF(var_def) => var_def_text(var_def),
+            StmtKind::ASSIGN(target, value) => format!{"{} = {}", target_text(target), expr_text(value)},
+            StmtKind::LINK(target, linked) => format!{"{} = link to {}", target_text(target), link_text(linked.as_ref())},
+            StmtKind::UNLINK(reference) => format!{"unlink {}", reference_text(reference)},
+            StmtKind::RETURN(Some(value)) => format!{"return {}", expr_text(value)},
+            StmtKind::RETURN(None) => "return nothing".to_string(),
+            StmtKind::QUIT(Some(status)) => format!{"quit {}", expr_text(status)},
+            StmtKind::QUIT(None) => "quit".to_string(),
+            StmtKind::BREAK => "break".to_string(),
+            StmtKind::CONTINUE => "continue".to_string(),
+            StmtKind::EXPR(value) => expr_text(value),
+            StmtKind::IF(if_stmt) => {
+                self.open(format!{"if {} then", condition_text(&if_stmt.condition)}, start, if_stmt.condition.span.end_row);
+                self.print_if_rest(if_stmt, end);
+                return;
+            },
+            StmtKind::WHILE(condition, body) => {
+                self.open(format!{"while {}", condition_text(condition)}, start, condition.span.end_row);
+                self.print_block(body);
+                self.close("end while", end);
+                return;
+            },
+            StmtKind::REPEAT(times, body) => {
+                self.open(format!{"repeat {} times", expr_text(times)}, start, times.span.end_row);
+                self.print_block(body);
+                self.close("end repeat", end);
+                return;
+            },
+            StmtKind::REPEATFOR(id, values, body) => {
+                let name = id.as_ref().map(|i| i.name.as_str()).unwrap_or("");
+                self.open(format!{"repeat for all {} in {}", name, expr_text(values)}, start, values.span.end_row);
+                self.print_block(body);
+                self.close("end repeat", end);
+                return;
+            },
+            StmtKind::REPEATFOREVER(body) => {
+                self.open("repeat forever".to_string(), start, start);
+                self.print_block(body);
+                self.close("end repeat", end);
+                return;
+            },
+            StmtKind::INVALID => return,
+        };
+        self.line(text, start, end);
+    }
+
+    // The body of an if, and its else branches, the "end if" is on
+    //  the last row of the whole statement
+    fn print_if_rest(&mut self, if_stmt: &If, end: u32) {
+        self.print_block(&if_stmt.body);
+        match &if_stmt.otherwise {
+            None => (),
+            Some(Else::IF(next)) => {
+                self.reopen(format!{"else if {} then", condition_text(&next.condition)}, next.condition.span.end_row);
+                self.print_if_rest(next, end);
+                return;
+            },
+            Some(Else::BLOCK(body)) => {
+                self.reopen("else".to_string(), 0);
+                self.print_block(body);
+            },
+        }
+        self.close("end if", end);
+    }
+}
+
+// ====================
+// =      TEXT        =
+// ====================
+
+fn type_text(var_type: &Type) -> String {
+    match &var_type.kind {
+        TypeKind::NUMBER => "number".to_string(),
+        TypeKind::TEXT => "text".to_string(),
+        TypeKind::NOTHING => "nothing".to_string(),
+        TypeKind::STRUCTURE(name) => name.clone(),
+        TypeKind::LINK(linked) => format!{"link to {}", type_text(linked)},
+        TypeKind::ARRAY(bounds, arr_type) => {
+            if bounds.is_empty() {
+                return format!{"array of {}", type_text(arr_type)};
+            }
+            let bounds: Vec<String> = bounds.iter().map(|b| match &b.start {
+                Some(start) => format!{"{} to {}", expr_text(start), expr_text(&b.end)},
+                None => expr_text(&b.end),
+            }).collect();
+            format!{"array[{}] of {}", bounds.join(", "), type_text(arr_type)}
+        },
+        TypeKind::INVALID => String::new(),
+    }
+}
+
+fn param_text(param: &Param) -> String {
+    let changeable = if param.changeable { "changeable " } else { "" };
+    format!{"{}: {}{}", param.name.name, changeable, type_text(&param.param_type)}
+}
+
+fn var_def_text(var_def: &VarDef) -> String {
+    let names: Vec<&str> = var_def.names.iter().map(|n| n.name.as_str()).collect();
+    format!{"{}: {}", names.join(", "), type_text(&var_def.var_type)}
+}
+
+fn target_text(target: &Target) -> String {
+    match target {
+        Target::VARDEF(var_def) => var_def_text(var_def),
+        Target::REFERENCE(reference) => reference_text(reference),
+    }
+}
+
+fn link_text(linked: Option<&Reference>) -> String {
+    match linked {
+        Some(reference) => reference_text(reference),
+        None => "nothing".to_string(),
+    }
+}
+
+fn reference_text(reference: &Reference) -> String {
+    match &reference.kind {
+        ReferenceKind::NAME(name) => name.clone(),
+        ReferenceKind::INDEX(array, indexes) => format!{"{}[{}]", reference_text(array), list_text(indexes)},
+        ReferenceKind::FIELD(structure, key) => format!{"{}.{}", reference_text(structure), key.name},
+    }
+}
+
+fn list_text(values: &[Expr]) -> String {
+    values.iter().map(expr_text).collect::<Vec<String>>().join(", ")
+}
+
+fn condition_text(condition: &Condition) -> String {
+    // "and" holds together more than "or", so an "or" inside of
+    //  an "and" needs parenthesis
+    let and_side = |side: &Condition| match side.kind {
+        ConditionKind::OR(_, _) => format!{"({})", condition_text(side)},
+        _ => condition_text(side),
+    };
+    match &condition.kind {
+        ConditionKind::COMPARE(op, left, right) => format!{"{} {} {}", expr_text(left), op, expr_text(right)},
+        ConditionKind::AND(left, right) => format!{"{} and {}", and_side(left), and_side(right)},
+        ConditionKind::OR(left, right) => format!{"{} or {}", condition_text(left), condition_text(right)},
+        ConditionKind::ISLINKED(reference) => format!{"{} is linked", reference_text(reference)},
+        ConditionKind::ISNOTLINKED(reference) => format!{"{} is not linked", reference_text(reference)},
+        ConditionKind::INVALID => String::new(),
+    }
+}
+
+// How tightly an expression holds together, following the levels
+//  of the BNF from < bit-orable > (loosest) to < exponent >
+fn precedence(expr: &Expr) -> u8 {
+    match &expr.kind {
+        ExprKind::BINARY(op, _, _) => match op {
+            BinOp::BOR => 1,
+            BinOp::BXOR => 2,
+            BinOp::BAND => 3,
+            BinOp::BSL | BinOp::BSR => 4,
+            BinOp::ADD | BinOp::SUB => 5,
+            BinOp::MUL | BinOp::DIV | BinOp::MOD => 6,
+            BinOp::POW => 7,
+        },
+        ExprKind::NEG(_) => 7,
+        ExprKind::BITNOT(_) => 8,
+        ExprKind::ABS(_) => 9,
+        _ => 10,
+    }
+}
+
+// An expression in a spot that needs at least the given precedence
+fn operand_text(expr: &Expr, needed: u8) -> String {
+    if precedence(expr) < needed {
+        return format!{"({})", expr_text(expr)};
+    }
+    expr_text(expr)
+}
+
+fn expr_text(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::NUMBER(value) => format!{"{}", value},
+        ExprKind::TEXT(value) => text_literal(value),
+        ExprKind::REFERENCE(reference) => reference_text(reference),
+        ExprKind::CALL(name, args) => format!{"{}({})", name.name, list_text(args)},
+        ExprKind::BINARY(op, left, right) => {
+            let level = precedence(expr);
+            // "^" groups to the right and only takes a < bit-notable >
+            //  on its left, everything else groups to the left
+            let (left_needs, right_needs) = match op {
+                BinOp::POW => (8, 7),
+                _ => (level, level + 1),
+            };
+            format!{"{} {} {}", operand_text(left, left_needs), op, operand_text(right, right_needs)}
+        },
+        ExprKind::NEG(value) => format!{"-{}", operand_text(value, 7)},
+        ExprKind::ABS(value) => format!{"+{}", operand_text(value, 9)},
+        ExprKind::BITNOT(value) => format!{"bit_not {}", operand_text(value, 9)},
+        ExprKind::ARRAY(values) => format!{"[{}]", list_text(values)},
+        ExprKind::STRUCTURE(values) => format!{"{{{}}}", list_text(values)},
+        ExprKind::LINK(linked) => format!{"link to {}", link_text(linked.as_ref())},
+        ExprKind::INVALID => String::new(),
+    }
+}
+
+// Write a text value so the lexer reads it back the same way
+// The lexer keeps the backslash of \" and \', so those pairs are
+//  written as they are, and any other backslash is escaped
+fn text_literal(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+
+    // A quote can only be in the text without a backslash if it
+    //  was inside of the other kind of quotes
+    let bare_quote = |quote: char| (0..chars.len()).any(|i| chars[i] == quote && (i == 0 || chars[i - 1] != '\\'));
+    let quote = if bare_quote('"') && !bare_quote('\'') { '\'' } else { '"' };
+
+    let mut text = String::new();
+    text.push(quote);
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\t' => text.push_str("\\t"),
+            '\n' => text.push_str("\\n"),
+            '\\' if i + 1 < chars.len() && (chars[i + 1] == '"' || chars[i + 1] == '\'') => {
+                text.push('\\');
+                text.push(chars[i + 1]);
+                i += 1;
+            },
+            '\\' => text.push_str("\\\\"),
+            c => text.push(c),
+        }
+        i += 1;
+    }
+    text.push(quote);
+    text
+}
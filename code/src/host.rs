@@ -0,0 +1,219 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// =====================
+// =       HOST        =
+// =====================
+// Everything a running program does outside of itself (showing
+//  output, reading input, waiting, and picking random numbers) goes
+//  through a Host, so the interpreter doesn't need to know where it
+//  is running. The command line tool uses StdioHost, tests and
+//  autograders can use MemoryHost to give the input and check the
+//  output, and the WebAssembly build can pass it on to JavaScript.
+
+pub trait Host {
+    // Show output from the program (print, display)
+    fn write(&mut self, text: &str);
+
+    // Read a line typed by the user, without the newline
+    //  (input_text, input_number)
+    // When there is no more input this is an UnexpectedEof error
+    fn read_line(&mut self) -> io::Result<String>;
+
+    // Pause the program for a number of seconds (wait), the seconds
+    //  are never negative and always fit in a Duration
+    fn sleep(&mut self, seconds: f64);
+
+    // A random number from 0 up to (not including) 1 (random_number)
+    fn random(&mut self) -> f64;
+
+    // The current time, in seconds since the start of 1970
+    fn time(&mut self) -> f64;
+}
+
+// A borrowed host works the same, so the caller can keep the host
+//  (like to read the output of a MemoryHost afterwards)
+impl<H: Host + ?Sized> Host for &mut H {
+    fn write(&mut self, text: &str) {
+        (**self).write(text)
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        (**self).read_line()
+    }
+
+    fn sleep(&mut self, seconds: f64) {
+        (**self).sleep(seconds)
+    }
+
+    fn random(&mut self) -> f64 {
+        (**self).random()
+    }
+
+    fn time(&mut self) -> f64 {
+        (**self).time()
+    }
+}
+
+// A small xorshift random number generator
+// The same seed always gives the same numbers
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // Small seeds (like 0) have only a few bits set, and xorshift
+        //  would give back numbers close to 0 for the first few draws,
+        //  so the seed is mixed up first (splitmix64)
+        let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+
+        Random {
+            // A state of 0 would only ever give back 0
+            state: z | 1,
+        }
+    }
+
+    // Gives back a number from 0 up to (not including) 1
+    pub fn draw(&mut self) -> f64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// Every host gives the same error at the end of the input, so a
+//  program fails the same way wherever it runs
+fn no_more_input() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "there is no more input")
+}
+
+// ====================
+// =      STDIO       =
+// ====================
+
+// Runs the program in the terminal, the random numbers are seeded
+//  from the clock the first time one is needed
+pub struct StdioHost {
+    random: Option<Random>,
+}
+
+impl Default for StdioHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StdioHost {
+    pub fn new() -> Self {
+        StdioHost {
+            random: None,
+        }
+    }
+}
+
+impl Host for StdioHost {
+    fn write(&mut self, text: &str) {
+        print!("{}", text);
+    }
+
+    // Anything displayed without a newline (like a prompt) has to be
+    //  shown before waiting for the user
+    fn read_line(&mut self) -> io::Result<String> {
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Err(no_more_input());
+        }
+        Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string())
+    }
+
+    fn sleep(&mut self, seconds: f64) {
+        let _ = io::stdout().flush();
+        // wait only gives times that fit, but another caller might not
+        if let Ok(duration) = Duration::try_from_secs_f64(seconds) {
+            thread::sleep(duration);
+        }
+    }
+
+    fn random(&mut self) -> f64 {
+        if self.random.is_none() {
+            let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(1);
+            self.random = Some(Random::new(nanos));
+        }
+        self.random.as_mut().unwrap().draw()
+    }
+
+    fn time(&mut self) -> f64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or(0.0)
+    }
+}
+
+// ====================
+// =      MEMORY      =
+// ====================
+
+// Runs the program without a terminal: the input is given ahead of
+//  time, the output is kept in 'output', and waiting only moves the
+//  clock forward, so the same program and input always do the same thing
+#[derive(Debug, Clone)]
+pub struct MemoryHost {
+    pub input: VecDeque<String>,
+    pub output: String,
+    pub time: f64,
+    pub random: Random,
+}
+
+impl MemoryHost {
+    // The input is split into the lines the program will read
+    pub fn new(input: &str) -> Self {
+        MemoryHost {
+            input: input.lines().map(|l| l.to_string()).collect(),
+            output: String::new(),
+            time: 0.0,
+            random: Random::new(0),
+        }
+    }
+
+    // Pick the random numbers the program will get
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.random = Random::new(seed);
+        self
+    }
+}
+
+impl Host for MemoryHost {
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        match self.input.pop_front() {
+            Some(line) => Ok(line),
+            None => Err(no_more_input()),
+        }
+    }
+
+    fn sleep(&mut self, seconds: f64) {
+        self.time += seconds;
+    }
+
+    fn random(&mut self) -> f64 {
+        self.random.draw()
+    }
+
+    fn time(&mut self) -> f64 {
+        self.time
+    }
+}
@@ -1,21 +1,14 @@
-use std::{env};
-use crate::lexer::{TokenType};
-use crate::parser::{Parser, ParseTree, ParseType};
-use crate::semantic_analyzer::{SemanticAnalyzer};
-use crate::library_handler;
+use crate::parser::{BinOp, Block, CompareOp, Condition, ConditionKind, Else, Expr, ExprKind, FunctionDef, Ident, If, Param, ParseTree, Reference, ReferenceKind, Stmt, StmtKind, StructDef, Target, Type, TypeKind, VarDef};
+use crate::library_handler::{self, FunctionRegistry};
+use crate::host::Host;
+use crate::diagnostic::{self, Diagnostic, Span, Stage};
 use std::collections::{HashMap, BinaryHeap};
 use std::cmp::Ordering;
+use std::fmt;
 
 // A boolean to determine if debug information should be displayed
 static DEBUG: bool = false;
 
-// Handle error reporting through web assembly
-// For right now we just print the error, but later
-//  on this would be passed to JavaScript code
-macro_rules! log {
-    ($($t:tt)*) => (println!("{}",  &format_args!($ ( $t ) *).to_string() ))
-}
-
 // Handle debugging through web assembly
 // For right now we just print the error, but later
 //  on this would be passed to JavaScript code
@@ -32,6 +25,13 @@ macro_rules! debug {
 // ======================
 
 // Different pointers types that will point at the memory array
+
+// Create an error found while running the program, the position
+//  is filled in by the interpreter
+pub fn runtime_error(code: &'static str, message: String) -> Diagnostic {
+    Diagnostic::new(Stage::RUNTIME, code, message)
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum PointerType {
     LINK(Box<PointerType>), // a link pointer that just marks the linked address
@@ -57,10 +57,7 @@ struct MemorySpace {
 
 impl PartialOrd for MemorySpace {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self.size != other.size {
-            return Some(self.size.cmp(&other.size));
-        }
-        Some(other.address.cmp(&self.address))
+        Some(self.cmp(other))
     }
 }
 
@@ -81,13 +78,19 @@ pub struct Environment {
     memory: Vec<PrimitiveType>,
     // heap stores available memory addresses and their sizes
     heap: BinaryHeap<MemorySpace>,
-    // linked_values stores how many times a variable has been linked
-    // this is used when deallocing to ensure we don't delete expected values
-    // currently not in use, since we do not have links implemented
-    _linked_values: HashMap<usize, i32>,
+    // temp_roots stores pointers that are still in use, but not named by any
+    //  namespace (like links inside of a value being returned from a function)
+    // these are kept alive by the garbage collector until the statement ends
+    temp_roots: Vec<Pointer>,
 }
 
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
     // Create a new instance of the memory
     pub fn new() -> Self {
@@ -96,7 +99,7 @@ impl Environment {
             namespace: Vec::new(),
             memory: Vec::new(),
             heap: BinaryHeap::new(),
-            _linked_values: HashMap::new(),
+            temp_roots: Vec::new(),
         };
 
         // Fill in the first namespace with a hashmap
@@ -131,6 +134,12 @@ impl Environment {
         let addr: usize = mem_space.address;
         let remaining_size: usize = mem_space.size - size;
 
+        // Mark the space as in use, so that it isn't seen as free
+        //  memory before it has been given a value
+        for i in addr..addr+size {
+            self.memory[i] = PrimitiveType::INITIALIZED;
+        }
+
         if remaining_size > 0 {
             self.heap.push(MemorySpace{
                 address: addr + size,
@@ -141,42 +150,12 @@ impl Environment {
         addr
     }
 
-    // Recursively remove all the memory from a provided pointer
-    fn dealloc(&mut self, pointer: Pointer) {
-        match pointer.pointer_type {
-            PointerType::ARRAY(_,_) | PointerType::STRUCTURE(_) => {
-                let mut ptr = Pointer{
-                    pointer_type: PointerType::PRIMITIVE,
-                    address: pointer.address,
-                    size: 1,
-                };
-
-                for i in 0..pointer.size {
-                    ptr.address = pointer.address + i;
-                    self.dealloc(ptr.clone());
-                }
-            },
-            _ => {
-                let val = self.get_value(pointer.clone());
-                match val {
-                    Ok(PrimitiveType::POINTER(p)) => {
-                        match p.pointer_type {
-                            PointerType::LINK(_p2) => (), // TODO
-                            _ => self.dealloc(*p),
-                        };
-                        self.memory[pointer.address] = PrimitiveType::NOTHING
-                    },
-                    _ => self.memory[pointer.address] = PrimitiveType::NOTHING,
-                };}  
-        }
-    }
-
     // Access the data in a given memory address (this returns a clone)
-    fn get_value(&self, pointer: Pointer) -> Result<PrimitiveType, String> {
+    fn get_value(&self, pointer: Pointer) -> Result<PrimitiveType, Diagnostic> {
         debug!{"{:?}", self.memory};
         debug!{"Getting memory address {}", pointer.address}
         if pointer.address >= self.memory.len() {
-            return Err("Accessing a memory address out of bounds".to_string());
+            return Err(runtime_error(diagnostic::INVALID_MEMORY, "Accessing a memory address out of bounds".to_string()));
         }
 
         Ok(self.memory[pointer.address].clone())
@@ -189,10 +168,23 @@ impl Environment {
         self.memory[pointer.address] = value.clone();
     }
 
+    // Copy a block of memory from one address to another
+    // Arrays and structures are stored contiguously, so this copies
+    //  an entire value in one go
+    fn copy(&mut self, from: usize, to: usize, size: usize) {
+        debug!{"Copying {} addresses from {} to {}", size, from, to};
+        if from == to {
+            return;
+        }
+
+        let values: Vec<PrimitiveType> = self.memory[from..from+size].to_vec();
+        self.memory[to..to+size].clone_from_slice(&values);
+    }
+
     // Access the pointer value of a given literal ID
     // If the provided ID does not exist, try to recurse
     //  if provided ID cannot be found, error
-    fn get_id(&self, id: String) -> Result<Pointer, String> {
+    fn get_id(&self, id: String) -> Result<Pointer, Diagnostic> {
         debug!{"Looking for id {}", id};
 
         if self.namespace.len() > 1 {
@@ -208,17 +200,17 @@ impl Environment {
             }
         }
 
-        Err(format!{"Could not find id '{}' in the namespace", id})
+        Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Could not find id '{}' in the namespace", id}))
     }
 
     // Insert a new ID with a pointer value
     // If the ID exists (in the current namespace)
-    fn insert_id(&mut self, id: String, pointer: Pointer) -> Result<(), String> {
+    fn insert_id(&mut self, id: String, pointer: Pointer) -> Result<(), Diagnostic> {
         debug!{"Adding new id {} with value {:?}", id, pointer};
         let len = self.namespace.len()-1;
 
         if self.namespace[len].contains_key(&id) {
-            return Err(format!{"Cannot have duplicate variables {}", id});
+            return Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Cannot have duplicate variables {}", id}));
         }
 
         self.namespace[len].insert(id, pointer);
@@ -232,36 +224,69 @@ impl Environment {
         self.namespace.push(HashMap::new());
     }
 
-    // Scope out, deleting the last namespace and all addresses
-    //  associated with that namespace
+    // Scope out, deleting the last namespace
+    // If any names were removed, run the garbage collector to
+    //  free any memory that can no longer be reached
     fn scope_out(&mut self) {
         let names = self.namespace.pop().expect("Can not scope out further");
 
-        // This is a very inneficient O(n^2)
-        //  check for existing pointers, but
-        //  our language is simple enough
-        //  that it shouldn't be a huge
-        //  time loss
-        for ptr in names.values() {
-            let mut seen = false;
-            for i in 0..self.namespace.len() {
-                if self.namespace[i].values().any(|val| val == ptr) {
-                    seen = true;
-                    break;
+        if !names.is_empty() {
+            self.collect_garbage();
+        }
+    }
+
+    // Go back to an earlier copy of the global names, dropping every
+    //  scope (and name) made since then, and free what they used
+    fn restore_globals(&mut self, globals: HashMap<String, Pointer>) {
+        self.namespace = vec![globals];
+        self.temp_roots.clear();
+        self.collect_garbage();
+    }
+
+    // Keep a pointer alive until the current statement finishes,
+    //  even though no namespace has a name for it
+    fn keep_alive(&mut self, pointer: Pointer) {
+        self.temp_roots.push(pointer);
+    }
+
+    // A mark and sweep garbage collector
+    // Every pointer in every namespace (and every temporary root) is
+    //  followed through structures and links to mark reachable memory.
+    // Anything left unmarked is freed, this includes cycles of links
+    //  that no variable can reach anymore.
+    fn collect_garbage(&mut self) {
+        let mut marked: Vec<bool> = vec![false; self.memory.len()];
+        // Address 0 is never freed
+        marked[0] = true;
+
+        // Mark
+        let mut to_visit: Vec<Pointer> = self.temp_roots.clone();
+        for names in &self.namespace {
+            to_visit.extend(names.values().cloned());
+        }
+
+        while let Some(pointer) = to_visit.pop() {
+            for addr in pointer.address..pointer.address+pointer.size {
+                // Past the end of memory, or already marked
+                match marked.get_mut(addr) {
+                    Some(seen) if !*seen => *seen = true,
+                    _ => continue,
                 }
-            }
-            if !seen {            
-                match &ptr.pointer_type {
-                    PointerType::LINK(_) => {
-                        self.memory[ptr.address] = PrimitiveType::NOTHING;
-                    }
-                    _ => {
-                        self.dealloc(ptr.clone());
-                    }
+
+                // Follow links to wherever they are linked
+                if let Some(PrimitiveType::LINK(p)) = self.memory.get(addr) {
+                    to_visit.push(*p.clone());
                 }
             }
         }
 
+        // Sweep
+        for (cell, seen) in self.memory.iter_mut().zip(marked) {
+            if !seen {
+                *cell = PrimitiveType::NOTHING;
+            }
+        }
+
         self.build_heap();
     }
 
@@ -293,7 +318,7 @@ impl Environment {
 
             self.heap.push(MemorySpace{
                 address: start,
-                size: size,
+                size,
             });
 
             addr += 1;
@@ -302,18 +327,13 @@ impl Environment {
  
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct LiteralType {
-    lit_type: String,
-    is_primitive: bool,
-}
-
 #[derive(Debug, PartialEq, Clone)]
 pub struct LiteralValue {
     lit_type: String,
     is_primitive: bool,
     values: Option<Vec<LiteralValue>>,
     value: Option<PrimitiveType>,
+    bounds: Option<(i32, i32)>, // the bounds of an array taken from memory
 }
 
 impl LiteralValue {
@@ -323,6 +343,7 @@ impl LiteralValue {
             is_primitive: true,
             values: None,
             value: Some(PrimitiveType::NOTHING),
+            bounds: None,
         }
     }
 
@@ -332,6 +353,7 @@ impl LiteralValue {
             is_primitive: true,
             value: Some(PrimitiveType::NUMBER(n)),
             values: None,
+            bounds: None,
         }
     }
 
@@ -341,9 +363,50 @@ impl LiteralValue {
             is_primitive: true,
             value: Some(PrimitiveType::TEXT(t)),
             values: None,
+            bounds: None,
+        }
+    }
+
+    pub fn from_link(pointer: Option<Pointer>) -> Self {
+        LiteralValue {
+            lit_type: "link".to_string(),
+            is_primitive: false,
+            value: pointer.map(|p| PrimitiveType::LINK(Box::new(p))),
+            values: None,
+            bounds: None,
+        }
+    }
+
+    pub fn from_array(values: Vec<LiteralValue>) -> Self {
+        LiteralValue {
+            lit_type: "array".to_string(),
+            is_primitive: false,
+            value: None,
+            values: Some(values),
+            bounds: None,
         }
     }
 
+    // Create an array that only holds the shape of the provided bounds
+    //  (every element is nothing)
+    pub fn from_bounds(bounds: &[(i32, i32)]) -> Self {
+        let num_elements = (bounds[0].0 - bounds[0].1).abs() + 1;
+
+        let mut values: Vec<LiteralValue> = Vec::new();
+        for _ in 0..num_elements {
+            if bounds.len() > 1 {
+                values.push(LiteralValue::from_bounds(&bounds[1..]));
+            }
+            else {
+                values.push(LiteralValue::null());
+            }
+        }
+
+        let mut lit = LiteralValue::from_array(values);
+        lit.bounds = Some(bounds[0]);
+        lit
+    }
+
     pub fn from_bool(b: bool) -> Self {
         if b {
             return LiteralValue::from_number(1.0);
@@ -351,8 +414,52 @@ impl LiteralValue {
         LiteralValue::from_number(0.0)
     }
 
+    // Collect the pointers of every link inside of this value
+    pub fn get_links(&self) -> Vec<Pointer> {
+        let mut links: Vec<Pointer> = Vec::new();
+
+        if let Some(PrimitiveType::LINK(p)) = &self.value {
+            links.push(*p.clone());
+        }
+
+        if let Some(values) = &self.values {
+            for val in values {
+                links.extend(val.get_links());
+            }
+        }
+
+        links
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.lit_type == "array"
+    }
+
+    // What a function without a return value gives back
+    pub fn is_nothing(&self) -> bool {
+        self.lit_type == "nothing"
+    }
+
+    // Get the values inside of an array or structure
+    pub fn extract_values(&self) -> Option<&Vec<LiteralValue>> {
+        self.values.as_ref()
+    }
+
+    // Get the bounds of the first dimension of an array
+    // Array literals that did not come from memory start at 1
+    pub fn extract_bounds(&self) -> Option<(i32, i32)> {
+        if !self.is_array() {
+            return None;
+        }
+
+        match self.bounds {
+            Some(bounds) => Some(bounds),
+            None => Some((1, self.values.as_ref().unwrap().len() as i32)),
+        }
+    }
+
     pub fn extract_number(&self) -> Option<f64> {
-        if self.lit_type != "number".to_string() || !self.is_primitive {
+        if self.lit_type != "number" || !self.is_primitive {
             return None;
         }
         
@@ -363,7 +470,7 @@ impl LiteralValue {
     }
 
     pub fn extract_text(&self) -> Option<String> {
-        if self.lit_type != "text".to_string() || !self.is_primitive {
+        if self.lit_type != "text" || !self.is_primitive {
             return None;
         }
         
@@ -373,49 +480,64 @@ impl LiteralValue {
         }
     }
 
-    pub fn to_string(&self) -> String {
+    // How the value is printed, see the Display impl below
+    fn render(&self) -> String {
         if self.is_primitive {
-            if self.lit_type == "text".to_string() {
+            if self.lit_type == "text" {
                 return self.extract_text().unwrap_or("".to_string());
             }
             else {
                 return format!{"{}", self.extract_number().unwrap_or(0.0)};
             }
         }
+
+        // Links are not followed, since they can link in a cycle
+        if self.lit_type == "link" {
+            return match self.value {
+                Some(_) => "<link>".to_string(),
+                None => "nothing".to_string(),
+            };
+        }
         
         let mut s = String::new();
 
-        if self.lit_type == "array".to_string() {
-            s.push_str("[");
+        if self.lit_type == "array" {
+            s.push('[');
         }
         else {
-            s.push_str("{");
+            s.push('{');
         }
 
         for child in self.values.clone().unwrap() {
             s.push_str(child.to_string().as_str());
-            s.push_str(",");
+            s.push(',');
         }
 
         s.remove(s.len()-1);
 
-        if self.lit_type == "array".to_string() {
-            s.push_str("]");
+        if self.lit_type == "array" {
+            s.push(']');
         }
         else {
-            s.push_str("}");
+            s.push('}');
         }
 
         s
     }
 }
 
+impl fmt::Display for LiteralValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render())
+    }
+}
+
 impl From <LiteralValue> for bool {
     fn from(val: LiteralValue) -> bool {
         if val.is_primitive {
             return bool::from(val.value.unwrap());
         }
-        return false;
+        false
     }
 }
 
@@ -423,38 +545,20 @@ impl From <LiteralValue> for bool {
 // = END MEMORY MANAGEMENT =
 // =========================
 
-pub fn unwrap_id_tree(tree: &ParseTree) -> String {
-    match &tree.token.token_type {
-        TokenType::ID(id) => id.to_string(),
-        _ => "".to_string(),
-    }
-}
-
-pub fn unwrap_type_tree(tree: &ParseTree) -> String {
-    match &tree.token.token_type {
-        TokenType::ID(id) => id.to_string(),
-        TokenType::TEXTTYPE => "text".to_string(),
-        TokenType::NUMTYPE => "number".to_string(),
-        TokenType::NOTHING => "nothing".to_string(),
-        _ => "invalid".to_string(),
-    }
-}
-
-
 #[derive(Clone, PartialEq, Debug)]
 pub enum PrimitiveType {
     NUMBER(f64),
     TEXT(String),
     NOTHING,
     INITIALIZED, // memory created for a variable that isn't in use yet
-    POINTER(Box<Pointer>),
+    LINK(Box<Pointer>), // the memory that a link is currently linked to
     INVALID, // memory that should not be access (address 0)
 }
 
 impl From <PrimitiveType> for bool {
     fn from(t: PrimitiveType) -> bool {
         match t {
-            PrimitiveType::TEXT(t) => t.len() > 0,
+            PrimitiveType::TEXT(t) => !t.is_empty(),
             PrimitiveType::NUMBER(n) => n != 0.0,
             _ => false,
         }
@@ -467,17 +571,61 @@ pub enum LoopStatus {
     BREAK,
     CONTINUE,
     RETURN,
-    QUIT,
+}
+
+// Why running the code stopped before it got to the end
+// A quit has to stop everything, even in the middle of working out a
+//  value (like "x: number = f()" where f quits), so it goes back up
+//  with '?' the same way an error does
+#[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
+enum Unwind {
+    ERROR(Diagnostic),
+    QUIT(i32),  // the exit code
+}
+
+impl From<Diagnostic> for Unwind {
+    fn from(err: Diagnostic) -> Self {
+        Unwind::ERROR(err)
+    }
+}
+
+// What running a piece of code in the REPL gave back
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryResult {
+    VALUE(LiteralValue),    // the value of the last statement, if it was a value on its own
+    NOTHING,
+    QUIT(i32),              // the exit code
 }
 
 #[derive(Debug, Clone)]
 struct InterpreterFunctionObj {
     param_names: Vec<String>,
     param_pointers: Vec<Pointer>,
-    body: ParseTree
+    body: Block
+}
+
+// How an argument gets passed into a function call
+#[allow(clippy::upper_case_acronyms)]
+enum CallArgument {
+    LINK(Option<Pointer>), // the argument memory a changeable parameter is linked to
+    COPY(Pointer), // an array or structure to copy into the parameter
+    VALUE(LiteralValue), // anything else
 }
 
-pub struct Interpreter {
+// What a reference resolves to, either memory or
+//  a single character indexed out of text
+#[allow(clippy::upper_case_acronyms)]
+enum Location {
+    MEMORY(Pointer),
+    CHARACTER(String),
+}
+
+// The interpreter runs on a host, which it uses for everything
+//  outside of the program (like output, see host.rs)
+pub struct Interpreter<H: Host> {
+    // Where the output goes and the input comes from
+    pub host: H,
     // A temporary holder for the current return value
     return_value: LiteralValue,
     // A flag to indicate the status of loops
@@ -493,212 +641,184 @@ pub struct Interpreter {
     structure_keys: HashMap<String, Vec<String>>,
     // A list of pointers for each structure key, for each structure
     structure_defs: HashMap<String, Vec<Pointer>>,
+    structure_defaults: HashMap<String, Vec<Option<Expr>>>,
     // Store the function object of each function
     function_defs: HashMap<String, InterpreterFunctionObj>,
+    // The functions written in Rust that the program can call
+    functions: FunctionRegistry,
     // Store the current position in the text so that users
     //  can get a proper error position
-    err_pos: (u32, u32), // (line, col)
+    err_pos: Span,
 }
 
-impl Interpreter {
+impl<H: Host> Interpreter<H> {
     // Create a new interpreter
-    pub fn new() -> Self {
+    pub fn new(host: H) -> Self {
         Interpreter {
+            host,
             return_value: LiteralValue::null(),
             loop_status: LoopStatus::DEFAULT,
             in_function_call: 0,
             env: Environment::new(),
             structure_keys: HashMap::new(),
             structure_defs: HashMap::new(),
+            structure_defaults: HashMap::new(),
             function_defs: HashMap::new(),
-            err_pos: (0,0),
+            functions: FunctionRegistry::builtins(),
+            err_pos: Span::default(),
         }
     }
 
+    // Use the functions in this registry instead of only the built in
+    //  ones (it should be the same registry the semantic analyzer used)
+    pub fn with_functions(mut self, functions: FunctionRegistry) -> Self {
+        self.functions = functions;
+        self
+    }
+
     // Update the error position to the current tree node
-    pub fn set_pos(&mut self, tree: &ParseTree) {
-        self.err_pos = (tree.token.row, tree.token.col);
+    pub fn set_pos(&mut self, span: Span) {
+        self.err_pos = span;
+    }
+
+    // Give an error without a position the current position
+    fn locate(&self, err: Diagnostic) -> Diagnostic {
+        if err.span.is_unknown() {
+            return err.with_span(self.err_pos);
+        }
+        err
     }
 
     // Get the amount of memory needed for a strucutre,
-    //  this will just be the number of key names in the structure
-    pub fn get_struct_size(&self, id: String) -> Result<usize, String> {
+    //  this is the combined size of every key in the structure
+    pub fn get_struct_size(&self, id: String) -> Result<usize, Diagnostic> {
         if self.structure_defs.contains_key(&id) {
-            return Ok(self.structure_defs[&id].len());
+            return Ok(self.structure_defs[&id].iter().map(|p| p.size).sum());
         }
 
-        Err(format!{"Cannot find structure {}", id})
+        Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Cannot find structure {}", id}))
     }
 
-    // Get the offset of a key in the memory of a structure
-    pub fn get_struct_key_offset(&self, struct_id: String, key_id: String) -> Result<usize, String> {
+    // Get the pointer for a key of a structure
+    // The address of this pointer is the offset of the key
+    //  from the start of the structure in memory
+    pub fn get_struct_key(&self, struct_id: String, key_id: String) -> Result<Pointer, Diagnostic> {
         if self.structure_keys.contains_key(&struct_id) {
             let key_vec = &self.structure_keys[&struct_id];
 
-            let mut offset = 0;
-            for key in key_vec.iter() {
+            for (idx, key) in key_vec.iter().enumerate() {
                 if key == &key_id {
-                    return Ok(offset);
+                    return Ok(self.structure_defs[&struct_id][idx].clone());
+                }
+            }
+        }
+
+        Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Cannot find structure '{}' or key '{}'", struct_id, key_id})) 
+    }
+
+    // Get the amount of memory needed for a pointer type
+    // Everything is sized recursively, so arrays and structures
+    //  take up a single contiguous block of memory
+    pub fn get_type_size(&self, pointer_type: &PointerType) -> Result<usize, Diagnostic> {
+        match pointer_type {
+            PointerType::PRIMITIVE | PointerType::LINK(_) => Ok(1),
+            PointerType::STRUCTURE(name) => self.get_struct_size(name.to_string()),
+            PointerType::ARRAY(bounds, arr_type) => {
+                let mut size = self.get_type_size(arr_type)?;
+                for bound in bounds {
+                    size *= ((bound.0 - bound.1).abs() + 1) as usize;
                 }
-                offset += 1;
+                Ok(size)
             }
         }
+    }
 
-        Err(format!{"Cannot find structure '{}' or key '{}'", struct_id, key_id}) 
+    // Get the pointer type (and size) of a single element in an array
+    //  with the given bounds. For a multi-dimensional array, this will
+    //  be an array with the first dimension removed.
+    fn get_element_pointer(&self, bounds: &[(i32, i32)], arr_type: &PointerType) -> Result<Pointer, Diagnostic> {
+        let pointer_type = match bounds.len() {
+            0 | 1 => arr_type.clone(),
+            _ => PointerType::ARRAY(bounds[1..].to_vec(), Box::new(arr_type.clone())),
+        };
+
+        Ok(Pointer {
+            size: self.get_type_size(&pointer_type)?,
+            pointer_type,
+            address: 0,
+        })
     }
 
-    // A helper method to move a literal value
-    //  into memory, this will allocate any unallocated
-    //  memory for substructures (like arrays of structures, or structs inside structs)
+    // A helper method to move a literal value into memory
+    // Since arrays and structures are sized recursively, this
+    //  only has to move through the memory block of the pointer
     // This does not do type checking, but does do size/structure checking
-    fn set_literal_in_memory(&mut self, pointer: Pointer, lit: LiteralValue) -> Result<(), String> {
+    fn set_literal_in_memory(&mut self, pointer: Pointer, lit: LiteralValue) -> Result<(), Unwind> {
         debug!{"Setting pointer {:?} with value {:?}", pointer, lit};
         
-        match pointer.pointer_type {
+        match pointer.pointer_type.clone() {
             // Just set the value for a primitive
             PointerType::PRIMITIVE => {
-                if !lit.is_primitive || (lit.lit_type != "number".to_string() && lit.lit_type != "text".to_string()) {
-                    return Err("Cannot set a primitive type (text/number) equal to a non-primitive type".to_string());
+                if !lit.is_primitive || (lit.lit_type != "number" && lit.lit_type != "text") {
+                    return Err(runtime_error(diagnostic::INVALID_OPERATION, "Cannot set a primitive type (text/number) equal to a non-primitive type".to_string()).into());
                 }
                 self.env.set_value(pointer, lit.value.unwrap().clone());
             }
             // For an array, set the value for all its children
             PointerType::ARRAY(bounds, arr_pointer_type) => {
                 if lit.is_primitive {
-                    return Err(format!{"Expected array, got a primitive (text/number): {:?}", lit});
-                }
-
-                if (bounds[0].0 - bounds[0].1).abs() + 1 != lit.values.as_ref().unwrap().len() as i32 {
-                    return Err(format!{"Expected array of size {}, got array of size {}", (bounds[0].0 - bounds[0].1).abs() + 1, lit.values.as_ref().unwrap().len()});
+                    return Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Expected array, got a primitive (text/number): {:?}", lit}).into());
                 }
 
-                // If there are more bounds (multi-dimensional array) make a
-                //  new pointer with the new bounds, and find how far to
-                //  offset for each element
-                // (We are serializing the array in memory)
-                let mut pointer_type = *arr_pointer_type.clone();
-                let mut offset_scale = 1;
-                if bounds.len() > 1 {
-                    let mut new_bounds: Vec<(i32, i32)> = Vec::new();
-                    for i in 1..bounds.len() {
-                        new_bounds.push(bounds[i].clone());
-                        offset_scale = offset_scale * ((bounds[i].0 - bounds[i].1).abs() + 1);
-                    }
-                    // re-wrap back into an array
-                    pointer_type = PointerType::ARRAY(new_bounds, Box::new(pointer_type));
+                let num_elements = (bounds[0].0 - bounds[0].1).abs() + 1;
+                if num_elements != lit.values.as_ref().unwrap().len() as i32 {
+                    return Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Expected array of size {}, got array of size {}", num_elements, lit.values.as_ref().unwrap().len()}).into());
                 }
 
-                debug!("Offset scale = {}", offset_scale);
-
-                // Create a running pointer for the array
-                let mut ptr = Pointer {
-                    pointer_type: pointer_type.clone(),
-                    size: offset_scale as usize,
-                    address: pointer.address.clone(),
-                };
+                // Create a running pointer for each element of the array
+                let mut ptr = self.get_element_pointer(&bounds, &arr_pointer_type)?;
+                ptr.address = pointer.address;
 
                 // Recurse to put all values in array
-                for i in 0..((bounds[0].0 - bounds[0].1).abs() + 1) {
-                    let val = &lit.values.as_ref().unwrap()[i as usize];
-                    ptr.address = pointer.address + (i*offset_scale) as usize;
-
-                    // If this is a primitive now, we can just set it by recursing
-                    if val.is_primitive || val.lit_type == "array".to_string() {
-                        self.set_literal_in_memory(ptr.clone(), val.clone())?;
-                    }
-                    // Otherwise, we need to go to the current pointer
-                    //  (or make a new pointer if it doesn't exist)
-                    else {
-                        let at_addr = self.env.get_value(ptr.clone())?;
-                        debug!{"Currently at address {:?}", at_addr};
-                        if at_addr == PrimitiveType::INITIALIZED || at_addr == PrimitiveType::NOTHING {
-                            // create a new pointer for space
-                            let s = match &pointer_type {
-                                PointerType::STRUCTURE(s) => s.to_string(),
-                                _ => "invlalid".to_string(),
-                            };
-
-                            let struct_size = self.get_struct_size(s.clone())?;
-                            let address = self.env.alloc(struct_size);
-                            let struct_ptr = Pointer{
-                                pointer_type: PointerType::STRUCTURE(s),
-                                size: struct_size,
-                                address: address,
-                            };
-
-                            // Set the value and recurse to fill it
-                            self.env.set_value(ptr.clone(), PrimitiveType::POINTER(Box::new(struct_ptr.clone())));
-                            self.set_literal_in_memory(struct_ptr.clone(), val.clone())?;
-                        }
-                        else {
-                            // This means we have a strucure already there, so we
-                            //  can pull the current pointer and recurse
-                            match at_addr {
-                                PrimitiveType::POINTER(p) => self.set_literal_in_memory(*p.clone(), val.clone())?,
-                                _ => return Err("Cannot reference structure pointer in memory".to_string()),
-                            };
-                            
-                        }
-                    } 
+                for val in lit.values.unwrap() {
+                    self.set_literal_in_memory(ptr.clone(), val)?;
+                    ptr.address += ptr.size;
                 }
             }
             PointerType::STRUCTURE(name) => {
                 // We know the number of arguments should match at this point,
                 //  but it doesn't hurt to check anyway
-                let struct_size = self.get_struct_size(name.clone())?;
-                if struct_size != lit.values.as_ref().unwrap().len() {
-                    return Err(format!{"Mismatched number of arguments for Structure '{}'", name});
+                let struct_ptrs = self.structure_defs.get(&name).expect("Could not load strucutre arguments").clone();
+                let struct_defaults = self.structure_defaults[&name].clone();
+                let mut vals = lit.values.unwrap().into_iter();
+                if struct_ptrs.len() < vals.len() {
+                    return Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Mismatched number of arguments for Structure '{}'", name}).into());
                 }
 
-                // Create a running pointer to set values
-                let mut ptr = Pointer {
-                    pointer_type: PointerType::PRIMITIVE,
-                    size: 1,
-                    address: pointer.address.clone(),
-                };
-
-
-                // Loop through each child setting its value appropriately
-                let struct_ptrs = self.structure_defs.get(&name).expect("Could not load strucutre arguments").clone();
-                for (struct_ptr, val) in struct_ptrs.iter().zip(lit.values.unwrap().iter()) {
-                    // If this is a primitive value, get the details and set it
-                    // Also increment the memory pointer
-                    if struct_ptr.pointer_type == PointerType::PRIMITIVE {
-                        ptr.pointer_type = struct_ptr.pointer_type.clone();
-                        ptr.size = struct_ptr.size;
-                        self.set_literal_in_memory(ptr.clone(), val.clone())?;
-                    }
-                    else {
-                        // Check if a pointer already exists
-                        ptr.pointer_type = struct_ptr.pointer_type.clone();
-                        ptr.size = struct_ptr.size;
-                        let at_addr = self.env.get_value(ptr.clone())?;
-
-                        debug!{"Need to set structure key {:?} to value {:?}", struct_ptr, val};
-                        debug!{"We are seeing value {:?}", at_addr};
-
-                        if at_addr == PrimitiveType::INITIALIZED || at_addr == PrimitiveType::NOTHING {
-                            let mut tmp_ptr = struct_ptr.clone();
-                            tmp_ptr.address = self.env.alloc(tmp_ptr.size);
-                            // Add this pointer to the memory block
-                            self.env.set_value(ptr.clone(), PrimitiveType::POINTER(Box::new(tmp_ptr.clone())));
-                            // Recurse to set the actual values
-                            self.set_literal_in_memory(tmp_ptr.clone(), val.clone())?;
-                        }
-                        else {
-                            match at_addr {
-                                PrimitiveType::POINTER(p) => self.set_literal_in_memory(*p.clone(), val.clone())?,
-                                _ => return Err("Cannot reference structure pointer in memory".to_string()),
-                            };
-                        }
-                    }
+                // Set each key at its offset from the start of the structure
+                // Any keys left off the end are set to their default value
+                for (struct_ptr, default) in struct_ptrs.into_iter().zip(struct_defaults) {
+                    let mut ptr = struct_ptr;
+                    ptr.address += pointer.address;
 
-                    // When looping, increment the address
-                    ptr.address += 1;
+                    let val = match (vals.next(), default) {
+                        (Some(val), _) => val,
+                        (None, Some(default)) => self.eval_resolvable(&default)?,
+                        (None, None) => return Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Mismatched number of arguments for Structure '{}'", name}).into()),
+                    };
+                    self.set_literal_in_memory(ptr, val)?;
                 }
             }
-            PointerType::LINK(linked_ptr) => {
-                debug!{"{:?}", linked_ptr};
+            PointerType::LINK(_) => {
+                // A link literal changes what the link is linked to
+                if lit.lit_type == "link" {
+                    self.env.set_value(pointer, lit.value.unwrap_or(PrimitiveType::INITIALIZED));
+                }
+                // Any other value is set in the memory that is linked to
+                else {
+                    let linked_ptr = self.follow_link(pointer)?;
+                    self.set_literal_in_memory(linked_ptr, lit)?;
+                }
             }
         }
 
@@ -707,563 +827,755 @@ impl Interpreter {
         Ok(())
     }
 
+    // Copy the value at one pointer directly into another pointer
+    // Links are followed on both sides, so this copies the linked values
+    // The two pointers are expected to be the same type, but arrays
+    //  are allowed to have different bounds as long as the size matches
+    fn copy_in_memory(&mut self, from: Pointer, to: Pointer) -> Result<(), Diagnostic> {
+        let mut from = from;
+        let mut to = to;
+        if let PointerType::LINK(_) = from.pointer_type {
+            from = self.follow_link(from)?;
+        }
+        if let PointerType::LINK(_) = to.pointer_type {
+            to = self.follow_link(to)?;
+        }
+
+        if from.size != to.size {
+            return Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Expected a value of size {}, got a value of size {}", to.size, from.size}));
+        }
+
+        self.env.copy(from.address, to.address, to.size);
+        Ok(())
+    }
+
+    // Get the pointer that a link is currently linked to (if any)
+    fn get_link(&self, pointer: Pointer) -> Result<Option<Pointer>, Diagnostic> {
+        match self.env.get_value(pointer)? {
+            PrimitiveType::LINK(p) => Ok(Some(*p)),
+            _ => Ok(None),
+        }
+    }
+
+    // Get the pointer that a link is linked to, erroring out
+    //  if the link is not linked to anything
+    fn follow_link(&self, pointer: Pointer) -> Result<Pointer, Diagnostic> {
+        match self.get_link(pointer)? {
+            Some(p) => Ok(p),
+            None => Err(runtime_error(diagnostic::UNLINKED, "Cannot use a link that is not linked to anything".to_string())),
+        }
+    }
+
     // Using the provided pointer, clone and wrap up the provided memory into
     //  a literal value. 
-    // TODO
-    fn get_literal_in_memory(&mut self, pointer: Pointer) -> Result<LiteralValue, String> {
+    fn get_literal_in_memory(&mut self, pointer: Pointer) -> Result<LiteralValue, Diagnostic> {
         debug!{"Getting pointer {:?}", pointer};
 
-        // Check for redirects first, and handle those
-        let at_addr = self.env.get_value(pointer.clone())?;
-        match at_addr {
-            PrimitiveType::POINTER(p) => {
-                if p.pointer_type == pointer.pointer_type {
-                    return self.get_literal_in_memory(*p);
-                }
-            },
-            _ => (),
-        }
-
         match pointer.pointer_type.clone() {
             PointerType::PRIMITIVE => {
                 let val = self.env.get_value(pointer.clone())?;
 
-                return match val {
+                match val {
                     PrimitiveType::NUMBER(n) => Ok(LiteralValue::from_number(n)),
                     PrimitiveType::TEXT(t) => Ok(LiteralValue::from_text(t)),
-                    _ => Err(format!{"Attempting to access invalid memory at address {:?}", pointer.address}),
-                };
+                    // The reference that was read fills in the name (see eval_resolvable)
+                    PrimitiveType::INITIALIZED => Err(runtime_error(diagnostic::UNASSIGNED, "A variable was used before it was given a value".to_string())),
+                    _ => Err(runtime_error(diagnostic::INVALID_MEMORY, format!{"Attempting to access invalid memory at address {:?}", pointer.address})),
+                }
             },
 
             PointerType::ARRAY(bounds, arr_pointer_type) => {
-                // If there are more bounds (multi-dimensional array) make a
-                //  new pointer with the new bounds, and find how far to
-                //  offset for each element
-                // (We need this info to undo the serialization)
-                let mut pointer_type = *arr_pointer_type.clone();
-                let mut offset_scale = 1;
                 let num_elements = (bounds[0].0 - bounds[0].1).abs() + 1;
-                if bounds.len() > 1 {
-                    let mut new_bounds: Vec<(i32, i32)> = Vec::new();
-                    for i in 1..bounds.len() {
-                        new_bounds.push(bounds[i].clone());
-                        offset_scale = offset_scale * ((bounds[i].0 - bounds[i].1).abs() + 1);
-                    }
-                    // re-wrap back into an array
-                    pointer_type = PointerType::ARRAY(new_bounds, Box::new(pointer_type));
-                }
 
-                debug!("Offset scale = {}", offset_scale);
-
-                // Create a running pointer for the array
-                let mut ptr = Pointer {
-                    pointer_type: pointer_type.clone(),
-                    size: offset_scale as usize,
-                    address: pointer.address.clone(),
-                };
+                // Create a running pointer for each element of the array
+                let mut ptr = self.get_element_pointer(&bounds, &arr_pointer_type)?;
+                ptr.address = pointer.address;
 
                 // Create the literal type and recursively add all children
                 let mut lit_vec: Vec<LiteralValue> = Vec::new();
                 
                 for _ in 0..num_elements {
                     lit_vec.push(self.get_literal_in_memory(ptr.clone())?);
-                    ptr.address += offset_scale as usize;
+                    ptr.address += ptr.size;
                 }
 
-                return Ok(LiteralValue{
+                Ok(LiteralValue{
                     lit_type: "array".to_string(),
                     is_primitive: false,
                     values: Some(lit_vec),
                     value: None,
-                });
+                    bounds: Some(bounds[0]),
+                })
             }
 
-            PointerType::LINK(p) => {
-                let mut ptr = pointer.clone();
-                ptr.pointer_type = *p;
-                return self.get_literal_in_memory(ptr);
+            PointerType::LINK(_) => {
+                let linked_ptr = self.follow_link(pointer)?;
+                self.get_literal_in_memory(linked_ptr)
             }
 
             PointerType::STRUCTURE(name) => {
                 // Create a vector to store the structure literals
                 let mut lit_vec: Vec<LiteralValue> = Vec::new();
 
-                // Create a running pointer for the structure
-                let mut ptr = Pointer {
-                    pointer_type: PointerType::PRIMITIVE,
-                    size: 1,
-                    address: pointer.address.clone(),
-                };
-
                 let struct_ptrs = self.structure_defs.get(&name).expect("Could not load strucutre arguments").clone();
-                for struct_ptr in struct_ptrs.iter() {
-                    // If this is a primitive value, just pull its value
-                    if struct_ptr.pointer_type == PointerType::PRIMITIVE {
-                        ptr.pointer_type = struct_ptr.pointer_type.clone();
-                        ptr.size = struct_ptr.size;
-                        lit_vec.push(self.get_literal_in_memory(ptr.clone())?);
+                for struct_ptr in struct_ptrs.into_iter() {
+                    let mut ptr = struct_ptr;
+                    ptr.address += pointer.address;
+
+                    // Links are copied as links, not as the value they link to
+                    if let PointerType::LINK(_) = ptr.pointer_type {
+                        lit_vec.push(LiteralValue::from_link(self.get_link(ptr)?));
                     }
                     else {
-                        // Make sure the pointer exists
-                        ptr.pointer_type = struct_ptr.pointer_type.clone();
-                        ptr.size = struct_ptr.size;
-                        let at_addr = self.env.get_value(ptr.clone())?;
-
-                        debug!{"We are seeing structure value {:?}", at_addr};
-
-                        if at_addr == PrimitiveType::INITIALIZED || at_addr == PrimitiveType::NOTHING {
-                            return Err("Cannot get the value of an uninitialized memory address".to_string());
-                        }
-                        else {
-                            lit_vec.push(match at_addr {
-                                PrimitiveType::POINTER(p) => self.get_literal_in_memory(*p.clone())?,
-                                _ => return Err("Cannot reference structure pointer in memory".to_string()),
-                            });
-                        }
+                        lit_vec.push(self.get_literal_in_memory(ptr)?);
                     }
-
-                    // Increment the pointer
-                    ptr.address += 1;
-
                 }
 
-                return Ok(LiteralValue {
+                Ok(LiteralValue {
                     lit_type: name.to_string(),
                     is_primitive: false,
                     values: Some(lit_vec),
                     value: None,
-                });
+                    bounds: None,
+                })
             }
         }
     }
 
     // This evaluates an entire parse tree
-    pub fn eval(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Run the semantic analyzer first
-        SemanticAnalyzer::new().analyze(tree)?;
-
+    // The tree must have already passed the semantic analyzer
+    // Run a program, giving back the exit code it quit with
+    //  (0 if it reached its end)
+    pub fn eval(&mut self, tree: &ParseTree) -> Result<i32, Diagnostic> {
         // Set out current position
-        self.set_pos(tree);
+        self.set_pos(tree.span);
 
-        // DEF (could be None)
-        if tree.children[0].is_some() {
-            match self.eval_definitions(tree.children[0].as_ref().unwrap()) {
-                Err(s) => return Err(format!{"Error on Line: {}, Column: {}\n{}", self.err_pos.0, self.err_pos.1, s}),
-                _ => (),
-            };
+        // Definitions
+        if let Err(e) = self.eval_definitions(tree) {
+            return self.stopped(e);
         }
 
         // BODY (program section)
-        match self.eval_body(tree.children[1].as_ref().unwrap()) {
-            Err(s) => return Err(format!{"Error on Line: {}, Column: {}\n{}", self.err_pos.0, self.err_pos.1, s}),
-            _ => (),            
-        };
+        if let Err(e) = self.eval_body(&tree.body) {
+            return self.stopped(e);
+        }
 
-        Ok(())
+        Ok(0)
     }
 
-    fn eval_definitions(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
-        
-        // STRUCT DEFS
-        if tree.children[0].is_some() {
-            self.eval_struct_defs(tree.children[0].as_ref().unwrap())?;
-        }
-
-        // GLOBAL DEFS
-        if tree.children[1].is_some() {
-            self.eval_global_defs(tree.children[1].as_ref().unwrap())?;
+    // The program stopped early, either it quit (which isn't an error)
+    //  or something went wrong
+    fn stopped(&self, unwind: Unwind) -> Result<i32, Diagnostic> {
+        match unwind {
+            Unwind::QUIT(code) => Ok(code),
+            Unwind::ERROR(e) => Err(self.locate(e)),
         }
+    }
 
-        // FUNCTION DEFS
-        if tree.children[2].is_some() {
-            self.eval_function_defs(tree.children[2].as_ref().unwrap())?;
+    // Run a piece of code typed into the REPL (repl.rs), keeping what
+    //  it defines for the next one. The tree must have already passed
+    //  the semantic analyzer (see SemanticAnalyzer::analyze_entry)
+    // If it fails, every name and definition it made is forgotten, so
+    //  the interpreter is back to how it was before
+    pub fn eval_entry(&mut self, tree: &ParseTree) -> Result<EntryResult, Diagnostic> {
+        let globals = self.env.namespace[0].clone();
+        let structure_keys = self.structure_keys.clone();
+        let structure_defs = self.structure_defs.clone();
+        let structure_defaults = self.structure_defaults.clone();
+        let function_defs = self.function_defs.clone();
+
+        self.set_pos(tree.span);
+        match self.eval_entry_parts(tree) {
+            Ok(result) => Ok(result),
+            Err(Unwind::QUIT(code)) => Ok(EntryResult::QUIT(code)),
+            Err(Unwind::ERROR(e)) => {
+                self.env.restore_globals(globals);
+                self.structure_keys = structure_keys;
+                self.structure_defs = structure_defs;
+                self.structure_defaults = structure_defaults;
+                self.function_defs = function_defs;
+                self.return_value = LiteralValue::null();
+                self.loop_status = LoopStatus::DEFAULT;
+                Err(self.locate(e))
+            },
         }
-
-        Ok(())
     }
 
-    fn eval_struct_defs(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
-        
-        // Create a pointer structure for each argument
-        //  and store them in a vector for later
-        // Add all structure objects
-        for struct_def_tree in &tree.children {
-            let id = unwrap_id_tree(struct_def_tree.as_ref().unwrap().children[0].as_ref().unwrap());
-
-            let mut struct_ptrs: Vec<Pointer> = Vec::new();
-            let mut struct_keys: Vec<String> = Vec::new();
-
-            for struct_arg in &struct_def_tree.as_ref().unwrap().children[1].as_ref().unwrap().children {
-                // Add the id to the list of structure key ids
-                struct_keys.push(unwrap_id_tree(struct_arg.as_ref().unwrap()));
+    fn eval_entry_parts(&mut self, tree: &ParseTree) -> Result<EntryResult, Unwind> {
+        self.eval_struct_defs(&tree.structures)?;
+        self.eval_function_defs(&tree.functions)?;
+
+        // The same as eval_body, but the value of a value on its
+        //  own is kept to be shown
+        let mut result = EntryResult::NOTHING;
+        for statement in &tree.body.statements {
+            let num_temp_roots = self.env.temp_roots.len();
+
+            result = match &statement.kind {
+                StmtKind::EXPR(expr) => EntryResult::VALUE(self.eval_resolvable(expr)?),
+                _ => {
+                    self.eval_statement(statement)?;
+                    EntryResult::NOTHING
+                },
+            };
 
-                // Find the expected pointer type
-                let expected_type = self.eval_type(struct_arg.as_ref().unwrap().children[1].as_ref().unwrap())?;
-                debug!("expected_type = {:?}", expected_type);
-                struct_ptrs.push(expected_type);
-            }
-            debug!("STRUCTURE POINTERS FOR {}", id);
-            debug!{"{:?}", struct_keys};
-            debug!{"{:?}", struct_ptrs};
-            self.structure_defs.insert(id.clone(), struct_ptrs);
-            self.structure_keys.insert(id.clone(), struct_keys);
+            self.env.temp_roots.truncate(num_temp_roots);
         }
-        
-        Ok(())
-    }
 
-    fn eval_global_defs(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
-        
-        for child in &tree.children {
-            if child.as_ref().unwrap().parse_type == ParseType::ASSIGN {
-                self.eval_assignment(child.as_ref().unwrap())?;
-            }
-            else {
-                self.eval_vardef(child.as_ref().unwrap())?;
-            }
-        }
-        
-        Ok(())
+        Ok(result)
     }
 
-    fn eval_function_defs(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
-
-        // Loop through each child, get its name, params, and arguments
-        for child in &tree.children {
-            let fun_def = child.as_ref().unwrap();
-
-            let function_id = unwrap_id_tree(fun_def.children[0].as_ref().unwrap());
+    // The value of a global variable (like one made in the REPL)
+    pub fn value_of(&mut self, id: &str) -> Result<LiteralValue, Diagnostic> {
+        let pointer = self.env.get_id(id.to_string())?;
+        self.get_literal_in_memory(pointer)
+    }
 
-            let mut param_names: Vec<String> = Vec::new();
-            let mut param_pointers: Vec<Pointer> = Vec::new();
+    // How many cells of memory are in use (free memory at the end is
+    //  given back, so this goes down when the garbage collector frees
+    //  the last values that were made)
+    pub fn memory_size(&self) -> usize {
+        self.env.memory.len()
+    }
 
-            for param in &fun_def.children[1].as_ref().unwrap().children {
-                param_pointers.push(self.eval_type(param.as_ref().unwrap().children[1].as_ref().unwrap())?);
-                param_names.push(unwrap_id_tree(param.as_ref().unwrap().children[0].as_ref().unwrap()));
-            }
+    // Forget everything the program has defined, keeping the host
+    //  and the functions it can call
+    pub fn reset(&mut self) {
+        self.return_value = LiteralValue::null();
+        self.loop_status = LoopStatus::DEFAULT;
+        self.in_function_call = 0;
+        self.env = Environment::new();
+        self.structure_keys.clear();
+        self.structure_defs.clear();
+        self.structure_defaults.clear();
+        self.function_defs.clear();
+        self.err_pos = Span::default();
+    }
 
-            let fn_obj = InterpreterFunctionObj {
-                param_names: param_names,
-                param_pointers: param_pointers,
-                body: fun_def.children[3].as_ref().unwrap().clone(),
-            };
+    fn eval_definitions(&mut self, tree: &ParseTree) -> Result<(), Unwind> {
+        // STRUCT DEFS
+        self.eval_struct_defs(&tree.structures)?;
 
-            self.function_defs.insert(function_id, fn_obj);
-        }
+        // GLOBAL DEFS
+        self.eval_global_defs(&tree.globals)?;
 
-        debug!{"**********FUNCTIONS**************\n{:?}", self.function_defs};
+        // FUNCTION DEFS
+        self.eval_function_defs(&tree.functions)?;
 
         Ok(())
     }
 
-    /// 
-    fn eval_body(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
-        
-        debug!{"EVAL BODY"};
-        let mut is_other: bool = false;
-        for child in &tree.children {
-            match child.as_ref().unwrap().parse_type {
-                ParseType::IF => self.eval_if(child.as_ref().unwrap())?,
-                ParseType::LINK => self.eval_link(child.as_ref().unwrap())?,
-                ParseType::UNLINK => self.eval_unlink(child.as_ref().unwrap())?,
-                ParseType::WHILE => self.eval_while(child.as_ref().unwrap())?,
-                ParseType::REPEAT => self.eval_repeat(child.as_ref().unwrap())?,
-                ParseType::REPEATFOR => self.eval_repeat_for(child.as_ref().unwrap())?,
-                ParseType::REPEATFOREVER => self.eval_repeat_forever(child.as_ref().unwrap())?,
-                ParseType::QUIT => self.eval_quit(child.as_ref().unwrap())?,
-                ParseType::BREAK => self.eval_break(child.as_ref().unwrap())?,
-                ParseType::CONTINUE => self.eval_continue(child.as_ref().unwrap())?,
-                ParseType::ASSIGN => self.eval_assignment(child.as_ref().unwrap())?,
-                ParseType::RETURN => self.eval_return(child.as_ref().unwrap())?,
-                _ => is_other = true,
-            }
-            // Catch anything with an actual return type
-            if is_other {
-                if child.as_ref().unwrap().parse_type == ParseType::VARDEF {
-                    self.eval_vardef(child.as_ref().unwrap())?;
+    fn eval_struct_defs(&mut self, struct_defs: &[StructDef]) -> Result<(), Unwind> {
+        // Structures are stored inline inside of other structures, so a
+        //  structure can only be sized after every structure it contains.
+        // Keep defining every structure whose keys are already known
+        //  until there is nothing left to define
+        let mut remaining: Vec<&StructDef> = struct_defs.iter().collect();
+
+        while !remaining.is_empty() {
+            let mut not_ready: Vec<&StructDef> = Vec::new();
+
+            for struct_def in &remaining {
+                let is_ready = struct_def.fields.iter().all(|field| {
+                    match self.get_contained_struct(&field.field_type) {
+                        Some(name) => self.structure_defs.contains_key(&name),
+                        None => true,
+                    }
+                });
+
+                if is_ready {
+                    self.eval_struct_def(struct_def)?;
                 }
                 else {
-                    self.eval_resolvable(child.as_ref().unwrap())?;
+                    not_ready.push(struct_def);
                 }
-                is_other = false
             }
 
-            if self.loop_status != LoopStatus::DEFAULT {
-                break;
+            // If nothing could be defined, the structures contain each other
+            if not_ready.len() == remaining.len() {
+                let struct_def = not_ready[0];
+                let id = struct_def.name.as_ref().map(|n| n.name.clone()).unwrap_or_default();
+                return Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Structure '{}' cannot contain itself", id})
+                    .with_span(struct_def.name.as_ref().map(|n| n.span).unwrap_or(struct_def.span))
+                    .with_help("use a link to the structure instead".to_string()).into());
             }
+
+            remaining = not_ready;
         }
 
         Ok(())
     }
 
-    /// 
-    fn eval_resolvable(&mut self, tree: &ParseTree) -> Result<LiteralValue, String> {
-        // Set potision
-        self.set_pos(tree);
-        
-        // Catch literals
-        if tree.parse_type == ParseType::LIT {
-            return match &tree.token.token_type {
-                TokenType::NUMBER(n) => Ok(LiteralValue::from_number(n.clone())),
-                TokenType::TEXT(t) => Ok(LiteralValue::from_text(t.clone())),
-                _ => Ok(LiteralValue::null())
-            }
+    // Get the name of the structure that a type stores inline (if any)
+    // Links are not stored inline, so they never count
+    fn get_contained_struct(&self, field_type: &Type) -> Option<String> {
+        let type_node = match &field_type.kind {
+            TypeKind::ARRAY(_, arr_type) => arr_type,
+            TypeKind::LINK(_) => return None,
+            _ => field_type,
+        };
+
+        match &type_node.kind {
+            TypeKind::STRUCTURE(name) => Some(name.clone()),
+            _ => None,
         }
+    }
 
-        // Catch binary operators
-        else if tree.parse_type == ParseType::BINOP {
-            let left = self.eval_resolvable(tree.children[0].as_ref().unwrap())?;
-            let right = self.eval_resolvable(tree.children[1].as_ref().unwrap())?;
+    fn eval_struct_def(&mut self, struct_def: &StructDef) -> Result<(), Unwind> {
+        let id = match &struct_def.name {
+            Some(name) => name.name.clone(),
+            None => return Ok(()),
+        };
 
-            if !left.is_primitive || !right.is_primitive {
-                return Err("Cannot perform binary operations on arrays".to_string());
-            }
+        // Create a pointer structure for each argument
+        //  and store them in a vector for later
+        // The address of each pointer is its offset into the structure
+        let mut struct_ptrs: Vec<Pointer> = Vec::new();
+        let mut struct_keys: Vec<String> = Vec::new();
+        let mut struct_defaults: Vec<Option<Expr>> = Vec::new();
+        let mut offset: usize = 0;
+
+        for field in &struct_def.fields {
+            // Add the id to the list of structure key ids
+            struct_keys.push(field.name.name.clone());
+
+            // Keep the default value to evaluate whenever it is needed
+            struct_defaults.push(field.default.clone());
+
+            // Find the expected pointer type
+            let mut expected_type = self.eval_type(&field.field_type)?;
+            debug!("expected_type = {:?}", expected_type);
+            expected_type.address = offset;
+            offset += expected_type.size;
+            struct_ptrs.push(expected_type);
+        }
+        debug!("STRUCTURE POINTERS FOR {}", id);
+        debug!{"{:?}", struct_keys};
+        debug!{"{:?}", struct_ptrs};
+        self.structure_defs.insert(id.clone(), struct_ptrs);
+        self.structure_keys.insert(id.clone(), struct_keys);
+        self.structure_defaults.insert(id.clone(), struct_defaults);
 
-            if left.lit_type == "text".to_string() {
-                let mut s:String = left.extract_text().unwrap_or("".to_string());
-                s.push_str(&right.extract_text().unwrap_or("".to_string()));
+        Ok(())
+    }
 
-                return Ok(LiteralValue::from_text(s));
-            }
-            else {
-                let left_val: f64 = left.extract_number().unwrap_or(0.0);
-                let right_val: f64 = right.extract_number().unwrap_or(0.0);
+    fn eval_global_defs(&mut self, globals: &[Stmt]) -> Result<(), Unwind> {
+        // Globals are declarations, assignments, or links,
+        //  which run the same way they would in a body
+        for global in globals {
+            self.eval_statement(global)?;
+        }
 
-                if tree.token.token_type == TokenType::DIV &&
-                   right_val == 0.0 {
-                    return Err("Cannot divide by zero".to_string());
-                }
+        Ok(())
+    }
 
-                let result: f64 = match tree.token.token_type {
-                    TokenType::ADD => left_val + right_val,
-                    TokenType::SUB => left_val - right_val,
-                    TokenType::MUL => left_val * right_val,
-                    TokenType::DIV => left_val / right_val,
-                    TokenType::POW => left_val.powf(right_val),
-                    TokenType::MOD => ((left_val as i32) % (right_val as i32)) as f64,
-                    TokenType::BAND => ((left_val as i32) & (right_val as i32)) as f64,
-                    TokenType::BOR => ((left_val as i32) | (right_val as i32)) as f64,
-                    TokenType::BXOR => ((left_val as i32) ^ (right_val as i32)) as f64,
-                    TokenType::BSL => ((left_val as i32) << (right_val as i32)) as f64,
-                    TokenType::BSR => ((left_val as i32) >> (right_val as i32)) as f64,
-                    _ => 0.0,
-                };
+    fn eval_function_defs(&mut self, function_defs: &[FunctionDef]) -> Result<(), Unwind> {
+        // Loop through each function, get its name, params, and arguments
+        for fun_def in function_defs {
+            let function_id = match &fun_def.name {
+                Some(name) => name.name.clone(),
+                None => continue,
+            };
 
-                debug!{"DID MATH, GOT VALUE {} {} {} => {}", left_val, tree.token.lexeme.clone().unwrap_or("".to_string()), right_val, result};
+            let mut param_names: Vec<String> = Vec::new();
+            let mut param_pointers: Vec<Pointer> = Vec::new();
 
-                return Ok(LiteralValue::from_number(result));
+            for param in &fun_def.params {
+                param_pointers.push(self.eval_param(param)?);
+                param_names.push(param.name.name.clone());
             }
-        }
 
-        // Catch negative
-        else if tree.parse_type == ParseType::NEG {
-            let lit = self.eval_resolvable(tree.children[0].as_ref().unwrap())?;
-            let val = lit.extract_number().unwrap_or(0.0);
-            return Ok(LiteralValue::from_number(-val));
+            let fn_obj = InterpreterFunctionObj {
+                param_names,
+                param_pointers,
+                body: fun_def.body.clone(),
+            };
+
+            self.function_defs.insert(function_id, fn_obj);
         }
 
-        // Catch absolute value
-        else if tree.parse_type == ParseType::ABS {
-            let lit = self.eval_resolvable(tree.children[0].as_ref().unwrap())?;
-            let val = lit.extract_number().unwrap_or(0.0);
-            return Ok(LiteralValue::from_number(val.abs()));
+        debug!{"**********FUNCTIONS**************\n{:?}", self.function_defs};
+
+        Ok(())
+    }
+
+    // A changeable parameter is a link to the argument it is given
+    fn eval_param(&mut self, param: &Param) -> Result<Pointer, Unwind> {
+        if !param.changeable {
+            return self.eval_type(&param.param_type);
         }
 
-        // Catch bitwise not
-        else if tree.parse_type == ParseType::BITNOT {
-            let lit = self.eval_resolvable(tree.children[0].as_ref().unwrap())?;
-            let val = lit.extract_number().unwrap_or(0.0);
-            return Ok(LiteralValue::from_number(!(val as i32) as f64));
+        Ok(Pointer {
+            pointer_type: PointerType::LINK(Box::new(self.eval_link_type(&param.param_type)?)),
+            size: 1,
+            address: 0,
+        })
+    }
+
+    fn eval_body(&mut self, block: &Block) -> Result<(), Unwind> {
+        debug!{"EVAL BODY"};
+        for statement in &block.statements {
+            // Anything kept alive during this statement can be let go afterwards
+            let num_temp_roots = self.env.temp_roots.len();
+
+            self.eval_statement(statement)?;
+
+            self.env.temp_roots.truncate(num_temp_roots);
+
+            if self.loop_status != LoopStatus::DEFAULT {
+                break;
+            }
         }
 
-        // Catch references
-        else if tree.parse_type == ParseType::GETINDEX ||
-                tree.parse_type == ParseType::GETSTRUCT ||
-                tree.parse_type == ParseType::ID {
-            let pointer = self.eval_reference(tree)?;
-            debug!("Looking for literal at pointer {:?}", pointer);
-            let val = self.get_literal_in_memory(pointer.clone())?;
-            debug!{"  Found {:?}", val};
-            return Ok(val);
+        Ok(())
+    }
+
+    fn eval_statement(&mut self, statement: &Stmt) -> Result<(), Unwind> {
+        // Set potision
+        self.set_pos(statement.head());
+
+        match &statement.kind {
+            StmtKind::VARDEF(var_def) => self.eval_declaration(var_def)?,
+            StmtKind::ASSIGN(target, value) => self.eval_assignment(statement.span, target, value)?,
+            StmtKind::LINK(target, linked) => self.eval_link(statement.span, target, linked.as_ref())?,
+            StmtKind::IF(if_stmt) => self.eval_if(if_stmt)?,
+            StmtKind::WHILE(condition, body) => self.eval_while(condition, body)?,
+            StmtKind::REPEAT(count, body) => self.eval_repeat(count, body)?,
+            StmtKind::REPEATFOR(variable, values, body) => self.eval_repeat_for(variable.as_ref(), values, body)?,
+            StmtKind::REPEATFOREVER(body) => self.eval_repeat_forever(body)?,
+            StmtKind::UNLINK(reference) => self.eval_unlink(reference)?,
+            StmtKind::RETURN(value) => self.eval_return(value.as_ref())?,
+            StmtKind::QUIT(status) => self.eval_quit(status.as_ref())?,
+            StmtKind::BREAK => self.loop_status = LoopStatus::BREAK,
+            StmtKind::CONTINUE => self.loop_status = LoopStatus::CONTINUE,
+            StmtKind::EXPR(expr) => {
+                self.eval_resolvable(expr)?;
+            }
+            StmtKind::INVALID => (),
         }
 
-        // Catch function calls
-        else if tree.parse_type == ParseType::CALL {
-            // get all argument values (if there are any)
-            let mut vals: Vec<LiteralValue> = Vec::new();
-            if tree.children[1].as_ref().is_some() {
-                for child in &tree.children[1].as_ref().unwrap().children {
-                    vals.push(self.eval_resolvable(child.as_ref().unwrap())?);
+        Ok(())
+    }
+
+    // Read the value in the memory that a reference points to
+    fn read_reference(&mut self, reference: &Reference, pointer: Pointer) -> Result<LiteralValue, Unwind> {
+        debug!("Looking for literal at pointer {:?}", pointer);
+        let val = match self.get_literal_in_memory(pointer) {
+            Ok(val) => val,
+            Err(mut e) => {
+                if e.code == diagnostic::UNASSIGNED {
+                    e.message = format!{"variable `{}` used before it was given a value", reference};
+                }
+                return Err(e.into());
+            },
+        };
+        debug!{"  Found {:?}", val};
+        Ok(val)
+    }
+
+    fn eval_resolvable(&mut self, expr: &Expr) -> Result<LiteralValue, Unwind> {
+        // Set potision
+        self.set_pos(expr.span);
+
+        match &expr.kind {
+            // Catch literals
+            ExprKind::NUMBER(n) => Ok(LiteralValue::from_number(*n)),
+            ExprKind::TEXT(t) => Ok(LiteralValue::from_text(t.clone())),
+
+            // Catch binary operators
+            ExprKind::BINARY(op, left, right) => {
+                let left = self.eval_resolvable(left)?;
+                let right = self.eval_resolvable(right)?;
+                self.set_pos(expr.span);
+
+                if !left.is_primitive || !right.is_primitive {
+                    return Err(runtime_error(diagnostic::INVALID_OPERATION, "Cannot perform binary operations on arrays".to_string()).into());
+                }
+
+                if left.lit_type == "text" {
+                    let mut s:String = left.extract_text().unwrap_or("".to_string());
+                    s.push_str(&right.extract_text().unwrap_or("".to_string()));
+
+                    return Ok(LiteralValue::from_text(s));
                 }
+
+                let left_val: f64 = left.extract_number().unwrap_or(0.0);
+                let right_val: f64 = right.extract_number().unwrap_or(0.0);
+
+                if *op == BinOp::DIV && right_val == 0.0 {
+                    return Err(runtime_error(diagnostic::DIVIDE_BY_ZERO, "Cannot divide by zero".to_string()).into());
+                }
+
+                let result: f64 = match op {
+                    BinOp::ADD => left_val + right_val,
+                    BinOp::SUB => left_val - right_val,
+                    BinOp::MUL => left_val * right_val,
+                    BinOp::DIV => left_val / right_val,
+                    BinOp::POW => left_val.powf(right_val),
+                    BinOp::MOD => ((left_val as i32) % (right_val as i32)) as f64,
+                    BinOp::BAND => ((left_val as i32) & (right_val as i32)) as f64,
+                    BinOp::BOR => ((left_val as i32) | (right_val as i32)) as f64,
+                    BinOp::BXOR => ((left_val as i32) ^ (right_val as i32)) as f64,
+                    BinOp::BSL => ((left_val as i32) << (right_val as i32)) as f64,
+                    BinOp::BSR => ((left_val as i32) >> (right_val as i32)) as f64,
+                };
+
+                debug!{"DID MATH, GOT VALUE {} {} {} => {}", left_val, op, right_val, result};
+
+                Ok(LiteralValue::from_number(result))
             }
 
-            // If this is print, we handle it specially
-            let fn_id = unwrap_id_tree(tree.children[0].as_ref().unwrap());
-            if !self.function_defs.contains_key(&fn_id) {
-                return library_handler::handle_call(fn_id, vals);
+            // Catch negative
+            ExprKind::NEG(value) => {
+                let val = self.eval_resolvable(value)?.extract_number().unwrap_or(0.0);
+                Ok(LiteralValue::from_number(-val))
             }
 
-            
-            // scope in
-            self.env.scope_in();
+            // Catch absolute value
+            ExprKind::ABS(value) => {
+                let val = self.eval_resolvable(value)?.extract_number().unwrap_or(0.0);
+                Ok(LiteralValue::from_number(val.abs()))
+            }
 
-            // for each argument/param
-            //  alloc space
-            //  insert id
-            let param_names: Vec<String>;
-            let param_pointers: Vec<Pointer>;
-            let body: ParseTree;
+            // Catch bitwise not
+            ExprKind::BITNOT(value) => {
+                let val = self.eval_resolvable(value)?.extract_number().unwrap_or(0.0);
+                Ok(LiteralValue::from_number(!(val as i32) as f64))
+            }
 
-            // There is a borrowing issue here, so my solution is to clone it
-            param_names = self.function_defs[&fn_id].param_names.clone();
-            param_pointers = self.function_defs[&fn_id].param_pointers.clone();
-            body = self.function_defs[&fn_id].body.clone();
+            // Catch references
+            ExprKind::REFERENCE(reference) => {
+                let pointer = match self.eval_text_reference(reference)? {
+                    Location::MEMORY(pointer) => pointer,
+                    Location::CHARACTER(c) => return Ok(LiteralValue::from_text(c)),
+                };
+                self.read_reference(reference, pointer)
+            }
 
-            for (((val, name), pointer), i) in vals.iter().zip(param_names.iter()).zip(param_pointers.iter()).zip(0..vals.len()) {
-                let mut p = pointer.clone();
+            // Catch function calls
+            ExprKind::CALL(name, args) => self.eval_call(&name.name, args, expr.span),
 
-                
-                p.address = self.env.alloc(pointer.size);
-                self.env.insert_id(name.clone(), p.clone())?;
-
-                match &p.pointer_type {
-                    PointerType::LINK(_) => {
-                        let ptr_box = Box::new(self.eval_reference(tree.children[1].as_ref().unwrap().children[i].as_ref().unwrap())?);
-                        self.env.set_value(p.clone(), PrimitiveType::POINTER(ptr_box));
-                    },
-                    _ => {
-                        self.set_literal_in_memory(p.clone(), val.clone())?;
-                    }
+            // Catch arrays (just shove in all items)
+            ExprKind::ARRAY(items) => {
+                let mut vec: Vec<LiteralValue> = Vec::new();
+
+                for item in items {
+                    vec.push(self.eval_resolvable(item)?);
                 }
+
+                Ok(LiteralValue::from_array(vec))
             }
 
-            debug!{"{:?}", self.env.memory};
+            // Catch strucutres (just shove in all items)
+            ExprKind::STRUCTURE(items) => {
+                let mut vec: Vec<LiteralValue> = Vec::new();
 
-            // call body and capture return value
-            let prev_return_val = self.return_value.clone();
-            self.in_function_call += 1;
-            self.eval_body(&body)?;
-            let new_return_val = self.return_value.clone();
-            self.return_value = prev_return_val;
+                for item in items {
+                    vec.push(self.eval_resolvable(item)?);
+                }
 
-            // scope out
-            self.env.scope_out();
+                Ok(LiteralValue{
+                    lit_type: "strucutre".to_string(),
+                    is_primitive: false,
+                    values: Some(vec),
+                    value: None,
+                    bounds: None,
+                })
+            }
 
-            // Handle return loop status
-            if self.loop_status == LoopStatus::RETURN {
-                self.loop_status = LoopStatus::DEFAULT;
+            // Catch links
+            ExprKind::LINK(linked) => {
+                let linked_ptr = self.eval_link_target(linked.as_ref())?;
+                Ok(LiteralValue::from_link(linked_ptr))
             }
 
-            // return the return value
-            return Ok(new_return_val);
+            ExprKind::INVALID => Ok(LiteralValue::null()),
         }
+    }
 
-        // Catch arrays (just shove in all children)
-        else if tree.parse_type == ParseType::ARRAYLIT {
-            let mut vec: Vec<LiteralValue> = Vec::new();
-
-            for child in &tree.children {
-                vec.push(self.eval_resolvable(child.as_ref().unwrap())?);
+    fn eval_call(&mut self, fn_id: &str, arg_exprs: &[Expr], span: Span) -> Result<LiteralValue, Unwind> {
+        // Anything that isn't defined in the program is a library function
+        if !self.function_defs.contains_key(fn_id) {
+            // get all argument values (if there are any)
+            let mut vals: Vec<LiteralValue> = Vec::new();
+            for arg_expr in arg_exprs {
+                vals.push(self.eval_library_arg(fn_id, arg_expr)?);
             }
 
-            return Ok(LiteralValue{
-                lit_type: "array".to_string(),
-                is_primitive: false,
-                values: Some(vec),
-                value: None
-            });
+            // Evaluating the arguments moved the position (maybe into
+            //  another function), errors from the call are the call's
+            self.set_pos(span);
+            return Ok(library_handler::handle_call(&self.functions, &mut self.host, fn_id, &vals)?);
+        }
+
+        // There is a borrowing issue here, so my solution is to clone it
+        let param_names: Vec<String> = self.function_defs[fn_id].param_names.clone();
+        let param_pointers: Vec<Pointer> = self.function_defs[fn_id].param_pointers.clone();
+        let body: Block = self.function_defs[fn_id].body.clone();
+
+        // Evaluate every argument before scoping in
+        // Arrays and structures are copied straight from the memory
+        //  they are stored in, everything else is evaluated to a value
+        let mut args: Vec<CallArgument> = Vec::new();
+        for (arg_expr, pointer) in arg_exprs.iter().zip(param_pointers.iter()) {
+            let arg = match &pointer.pointer_type {
+                // Changeable parameters are linked to the memory of the argument,
+                //  so anything done to the parameter is done to the argument
+                PointerType::LINK(_) => match &arg_expr.kind {
+                    ExprKind::REFERENCE(reference) => CallArgument::LINK(self.eval_link_target(Some(reference))?),
+                    _ => return Err(runtime_error(diagnostic::INVALID_OPERATION, "Cannot give a value to a changeable parameter, it must be given a variable".to_string()).into()),
+                },
+                _ => self.eval_copy_or_value(arg_expr)?,
+            };
+            args.push(arg);
         }
 
-        // Catch strucutres (just shove in all children)
-        else if tree.parse_type == ParseType::STRUCTLIT {
-            let mut vec: Vec<LiteralValue> = Vec::new();
+        // scope in
+        self.env.scope_in();
 
-            for child in &tree.children {
-                vec.push(self.eval_resolvable(child.as_ref().unwrap())?);
+        // for each argument/param
+        //  alloc space
+        //  insert id
+        for ((arg, name), pointer) in args.into_iter().zip(param_names.iter()).zip(param_pointers.iter()) {
+            let mut p = pointer.clone();
+
+            p.address = self.env.alloc(pointer.size);
+            self.env.insert_id(name.clone(), p.clone())?;
+
+            match arg {
+                CallArgument::LINK(linked_ptr) => self.set_literal_in_memory(p, LiteralValue::from_link(linked_ptr))?,
+                CallArgument::COPY(from) => self.copy_in_memory(from, p)?,
+                CallArgument::VALUE(val) => self.set_literal_in_memory(p, val)?,
             }
+        }
 
-            return Ok(LiteralValue{
-                lit_type: "strucutre".to_string(),
-                is_primitive: false,
-                values: Some(vec),
-                value: None
-            });
+        debug!{"{:?}", self.env.memory};
+
+        // call body and capture return value
+        let prev_return_val = self.return_value.clone();
+        self.in_function_call += 1;
+        self.eval_body(&body)?;
+        self.set_pos(span);
+        let new_return_val = self.return_value.clone();
+        self.return_value = prev_return_val;
+
+        // keep anything the return value links to alive
+        for linked_ptr in new_return_val.get_links() {
+            self.env.keep_alive(linked_ptr);
         }
 
-        // Catch links
-        else if tree.parse_type == ParseType::LINK {
-            if tree.children[0].is_none() {
-                return Ok(LiteralValue{
-                    lit_type: "link".to_string(),
-                    is_primitive: false,
-                    values: None,
-                    value: None,
-                });
-            }
-            else {
-                return Ok(LiteralValue{
-                    lit_type: "link".to_string(),
-                    is_primitive: false,
-                    values: None,
-                    value: Some(PrimitiveType::POINTER(Box::new(self.eval_reference(tree.children[0].as_ref().unwrap())?))),
-                });
-            }
+        // scope out
+        self.env.scope_out();
+
+        // Handle return loop status
+        if self.loop_status == LoopStatus::RETURN {
+            self.loop_status = LoopStatus::DEFAULT;
+        }
+
+        // return the return value
+        Ok(new_return_val)
+    }
+
+    /// Evaluate an argument for a library function
+    /// Functions registered as "bounds_only" (like the array information
+    ///  functions) only need the bounds of an array, so they can be used
+    ///  on arrays that have not been given values yet
+    fn eval_library_arg(&mut self, fn_id: &str, expr: &Expr) -> Result<LiteralValue, Unwind> {
+        let bounds_only = self.functions.get(fn_id).map(|f| f.bounds_only).unwrap_or(false);
+        if !bounds_only {
+            return self.eval_resolvable(expr);
+        }
+        match self.eval_copy_or_value(expr)? {
+            CallArgument::COPY(pointer) => match &pointer.pointer_type {
+                PointerType::ARRAY(bounds, _) => Ok(LiteralValue::from_bounds(bounds)),
+                _ => Ok(self.get_literal_in_memory(pointer)?),
+            },
+            CallArgument::VALUE(val) => Ok(val),
+            CallArgument::LINK(_) => unreachable!(),
         }
-        
-        Ok(LiteralValue::null())
     }
 
     /// Will evaluate the type of variable name and create memory space
     ///  for it. If it is a pointer, this space will not be created
     ///  (a link will be made for it instead)
     /// Assignment will be in charge of setting the pointer value
-    fn eval_vardef(&mut self, tree: &ParseTree) -> Result<Pointer, String> {
+    fn eval_vardef(&mut self, var_def: &VarDef) -> Result<Pointer, Unwind> {
         // Set potision
-        self.set_pos(tree);
-        
+        self.set_pos(var_def.span);
+
         debug!{"EVAL VARDEF"};
         // Get the pointer
-        let mut pointer = self.eval_type(tree.children[1].as_ref().unwrap())?;
+        let mut pointer = self.eval_type(&var_def.var_type)?;
 
         // Make the actual allocations
-        // ID
-        if tree.children[0].as_ref().unwrap().parse_type == ParseType::ID {
-            let id = unwrap_id_tree(tree.children[0].as_ref().unwrap());
+        for name in &var_def.names {
+            debug!{"Adding symbol {}", name.name};
+            pointer.address = self.env.alloc(pointer.size);
+            self.env.insert_id(name.name.clone(), pointer.clone())?;
+        }
+
+        Ok(pointer)
+    }
+
+    /// Declare variables without giving them a value
+    /// Any structures (even inside of arrays or other structures)
+    ///  start with the default values of their keys
+    fn eval_declaration(&mut self, var_def: &VarDef) -> Result<(), Unwind> {
+        self.eval_vardef(var_def)?;
 
-            debug!{"Adding symbol {}", id};
-            pointer.address = self.env.alloc(pointer.size.clone());
-            self.env.insert_id(id, pointer.clone())?;
+        for name in &var_def.names {
+            let pointer = self.env.get_id(name.name.clone())?;
+            self.set_defaults_in_memory(pointer)?;
         }
-        // IDS
-        else {
-            for id_tree in &tree.children[0].as_ref().unwrap().children {
-                let id = unwrap_id_tree(id_tree.as_ref().unwrap());
 
-                debug!{"Adding symbol {}", id};            
-                pointer.address = self.env.alloc(pointer.size.clone());
-                self.env.insert_id(id, pointer.clone())?;
+        Ok(())
+    }
+
+    // Set the default value of every structure key that has one
+    fn set_defaults_in_memory(&mut self, pointer: Pointer) -> Result<(), Unwind> {
+        match pointer.pointer_type.clone() {
+            PointerType::STRUCTURE(name) => {
+                let struct_ptrs = self.structure_defs[&name].clone();
+                let struct_defaults = self.structure_defaults[&name].clone();
+
+                for (struct_ptr, default) in struct_ptrs.into_iter().zip(struct_defaults) {
+                    let mut ptr = struct_ptr;
+                    ptr.address += pointer.address;
+
+                    match default {
+                        Some(default) => {
+                            let val = self.eval_resolvable(&default)?;
+                            self.set_literal_in_memory(ptr, val)?;
+                        }
+                        None => self.set_defaults_in_memory(ptr)?,
+                    }
+                }
             }
+            PointerType::ARRAY(bounds, arr_type) => {
+                // Only arrays of structures can have defaults
+                if let PointerType::STRUCTURE(_) = *arr_type {
+                    let num_elements = (bounds[0].0 - bounds[0].1).abs() + 1;
+                    let mut ptr = self.get_element_pointer(&bounds, &arr_type)?;
+                    ptr.address = pointer.address;
+
+                    for _ in 0..num_elements {
+                        self.set_defaults_in_memory(ptr.clone())?;
+                        ptr.address += ptr.size;
+                    }
+                }
+            }
+            _ => (),
         }
 
-        Ok(pointer)
+        Ok(())
     }
 
     /// Create a pointer that corresponds to the provided type
     /// This pointer will have an invalid memory address
-    fn eval_type(&mut self, tree: &ParseTree) -> Result<Pointer, String> {
+    fn eval_type(&mut self, var_type: &Type) -> Result<Pointer, Unwind> {
         // Set potision
-        self.set_pos(tree);
-        
+        self.set_pos(var_type.span);
+
         // Get the type of variable
         let mut pointer = Pointer{
             pointer_type: PointerType::PRIMITIVE,
@@ -1271,86 +1583,89 @@ impl Interpreter {
             address: 0,
         };
 
-        // Look for array definition
-        if tree.parse_type == ParseType::ARRAYDEF {
-            // Build the bounds for this array
-            let mut bounds: Vec<(i32, i32)> = Vec::new();
-            let mut size: usize = 1;
-
-            for bound_tree in tree.children[0].as_ref().unwrap().clone().children {
-                let start: i32;
-                let end: i32;
-                let mut res: LiteralValue;
-                if bound_tree.as_ref().unwrap().children[0].is_none() {
-                    start = 1;
+        match &var_type.kind {
+            // Look for array definition
+            TypeKind::ARRAY(bound_nodes, arr_type) => {
+                // Build the bounds for this array
+                let mut bounds: Vec<(i32, i32)> = Vec::new();
+                let mut size: usize = 1;
+
+                for bound in bound_nodes {
+                    let start: i32 = match &bound.start {
+                        Some(start) => self.eval_resolvable(start)?.extract_number().unwrap_or(1.0) as i32,
+                        None => 1,
+                    };
+                    let end: i32 = self.eval_resolvable(&bound.end)?.extract_number().unwrap_or(1.0) as i32;
+
+                    size *= ((end - start).abs() + 1) as usize;
+                    bounds.push((start, end));
+                }
+
+                // Find the actual type
+                // Every element is stored inline, so the array takes up the
+                //  size of an element for every spot in the array
+                if let TypeKind::STRUCTURE(name) = &arr_type.kind {
+                    size *= self.get_struct_size(name.clone())?;
+                    let struct_pointer = PointerType::STRUCTURE(name.clone());
+                    pointer.pointer_type = PointerType::ARRAY(bounds, Box::new(struct_pointer));
                 }
                 else {
-                    res = self.eval_resolvable(bound_tree.as_ref().unwrap().children[0].as_ref().unwrap())?;
-                    start = res.extract_number().unwrap_or(1.0) as i32;
+                    pointer.pointer_type = PointerType::ARRAY(bounds, Box::new(PointerType::PRIMITIVE));
                 }
 
-                res = self.eval_resolvable(bound_tree.as_ref().unwrap().children[1].as_ref().unwrap())?;
-                end = res.extract_number().unwrap_or(1.0) as i32;
-
-                size *= ((end - start).abs() + 1) as usize;
-                bounds.push((start, end));
+                pointer.size = size;
             }
-
-            // Find the actual type
-            let var_type = unwrap_type_tree(tree.children[1].as_ref().unwrap());
-            if var_type != "number".to_string() && var_type != "text".to_string() {
-                let struct_pointer = PointerType::STRUCTURE(var_type);
-                pointer.pointer_type = PointerType::ARRAY(bounds, Box::new(struct_pointer));
+            // Else check for pointers
+            TypeKind::LINK(link_type) => {
+                pointer.pointer_type = PointerType::LINK(Box::new(self.eval_link_type(link_type)?));
             }
-            else {
-                pointer.pointer_type = PointerType::ARRAY(bounds, Box::new(PointerType::PRIMITIVE));
-            }
-
-            pointer.size = size as usize;
-        }
-        // Else check for pointers
-        else if tree.parse_type == ParseType::POINTER {
-            let link_type = self.eval_type(tree.children[0].as_ref().unwrap())?;
-            pointer.pointer_type = PointerType::LINK(Box::new(link_type.pointer_type));
-        }
-        // Otherwise, look for structures/primitives
-        else {
-            let var_type = unwrap_type_tree(&tree);
-            if var_type != "number".to_string() && var_type != "text".to_string() {
-                pointer.pointer_type = PointerType::STRUCTURE(var_type.clone());
-                pointer.size = self.get_struct_size(var_type)?;
+            // Otherwise, look for structures/primitives
+            TypeKind::STRUCTURE(name) => {
+                pointer.pointer_type = PointerType::STRUCTURE(name.clone());
+                pointer.size = self.get_struct_size(name.clone())?;
             }
+            _ => (),
         }
 
         Ok(pointer)
     }
 
-    fn eval_if(&mut self, tree: &ParseTree) -> Result<(), String> {
+    // The pointer type of whatever a link can be linked to
+    fn eval_link_type(&mut self, link_type: &Type) -> Result<PointerType, Unwind> {
+        match &link_type.kind {
+            TypeKind::NUMBER | TypeKind::TEXT => Ok(PointerType::PRIMITIVE),
+            // Don't size structures here, since a structure is allowed
+            //  to link to itself before it has been fully defined
+            TypeKind::STRUCTURE(name) => Ok(PointerType::STRUCTURE(name.clone())),
+            _ => Ok(self.eval_type(link_type)?.pointer_type),
+        }
+    }
+
+    fn eval_if(&mut self, if_stmt: &If) -> Result<(), Unwind> {
         // Set potision
-        self.set_pos(tree);        
+        self.set_pos(if_stmt.head());
 
         // Evaluate the comparison
-        let cond = self.eval_conditional(tree.children[0].as_ref().unwrap())?;
+        let cond = self.eval_conditional(&if_stmt.condition)?;
 
         // Evaluate the body
-        if bool::from(cond) {
+        if cond {
             self.env.scope_in();
-            self.eval_body(tree.children[1].as_ref().unwrap())?;
+            self.eval_body(&if_stmt.body)?;
             self.env.scope_out();
         }
         else {
-            if tree.children[2].is_some() {
+            match &if_stmt.otherwise {
                 // Evaluate an else if
-                if tree.children[2].as_ref().unwrap().parse_type == ParseType::IF {
-                    self.eval_if(tree.children[2].as_ref().unwrap())?;
-                }
-    
+                Some(Else::IF(else_if)) => self.eval_if(else_if)?,
+
                 // Evaluate an else block if it exists
-                else if tree.children[2].as_ref().unwrap().parse_type == ParseType::BLOCK {
+                Some(Else::BLOCK(block)) => {
                     self.env.scope_in();
-                    self.eval_body(tree.children[2].as_ref().unwrap())?;
+                    self.eval_body(block)?;
                     self.env.scope_out();
                 }
+                None => (),
             }
         }
 
@@ -1358,109 +1673,150 @@ impl Interpreter {
     }
 
 
-    fn eval_conditional(&mut self, tree: &ParseTree) -> Result<LiteralValue, String> {
+    fn eval_conditional(&mut self, condition: &Condition) -> Result<bool, Unwind> {
         // Set potision
-        self.set_pos(tree);
-        
-        // Catch binary comparisons
-        if tree.parse_type == ParseType::BINCOMP {
-            let left = self.eval_conditional(tree.children[0].as_ref().unwrap())?;
-            let right = self.eval_conditional(tree.children[1].as_ref().unwrap())?;
+        self.set_pos(condition.span);
+
+        match &condition.kind {
+            // Catch link checks
+            ConditionKind::ISLINKED(reference) | ConditionKind::ISNOTLINKED(reference) => {
+                let pointer = self.eval_reference(reference)?;
+                if !matches!(pointer.pointer_type, PointerType::LINK(_)) {
+                    return Err(runtime_error(diagnostic::INVALID_OPERATION, "Cannot check if a non-link is linked".to_string()).into());
+                }
 
-            if tree.token.token_type == TokenType::EQ {
-                return Ok(LiteralValue::from_bool(left == right));
+                let is_linked = self.get_link(pointer)?.is_some();
+                Ok(is_linked == matches!(condition.kind, ConditionKind::ISLINKED(_)))
             }
 
-            if tree.token.token_type == TokenType::NE {
-                return Ok(LiteralValue::from_bool(left != right));
+            // Catch binary comparisons
+            ConditionKind::COMPARE(op, left, right) => {
+                let left = self.eval_resolvable(left)?;
+                let right = self.eval_resolvable(right)?;
+
+                if *op == CompareOp::EQ {
+                    return Ok(left == right);
+                }
+
+                if *op == CompareOp::NE {
+                    return Ok(left != right);
+                }
+
+                let left_val: f64 = left.extract_number().unwrap_or(0.0);
+                let right_val: f64 = right.extract_number().unwrap_or(0.0);
+
+                let result: bool = match op {
+                    CompareOp::LT => left_val < right_val,
+                    CompareOp::GT => left_val > right_val,
+                    CompareOp::LE => left_val <= right_val,
+                    CompareOp::GE => left_val >= right_val,
+                    _ => false,
+                };
+
+                debug!{"DID COMPARISON, GOT {}", result};
+
+                Ok(result)
             }
-            
-            let left_val: f64 = left.extract_number().unwrap_or(0.0);
-            let right_val: f64 = right.extract_number().unwrap_or(0.0);
-
-            let result: bool = match tree.token.token_type {
-                TokenType::LT => left_val < right_val,
-                TokenType::GT => left_val > right_val,
-                TokenType::LE => left_val <= right_val,
-                TokenType::GE => left_val >= right_val,
-                TokenType::AND => left_val == 1.0 && right_val == 1.0,
-                TokenType::OR => left_val == 1.0 || right_val == 1.0,
-                _ => false,
-            };
 
-            debug!{"DID COMPARISON, GOT {}", result};
+            // The right side is only checked when it can change the result
+            ConditionKind::AND(left, right) => Ok(self.eval_conditional(left)? && self.eval_conditional(right)?),
+            ConditionKind::OR(left, right) => Ok(self.eval_conditional(left)? || self.eval_conditional(right)?),
 
-            return Ok(LiteralValue::from_bool(result));
+            ConditionKind::INVALID => Ok(false),
         }
+    }
 
-        return self.eval_resolvable(tree);
+    /// Find the pointer that a link should be linked to. Linking
+    ///  to another link will link to whatever that link is linked to
+    fn eval_link_target(&mut self, reference: Option<&Reference>) -> Result<Option<Pointer>, Unwind> {
+        // LINK TO NOTHING
+        let reference = match reference {
+            Some(r) => r,
+            None => return Ok(None),
+        };
+
+        let pointer = self.eval_reference(reference)?;
+        match pointer.pointer_type {
+            PointerType::LINK(_) => Ok(self.get_link(pointer)?),
+            _ => Ok(Some(pointer)),
+        }
     }
 
-    /// Todo
-    /// 
-    fn eval_link(&mut self, tree: &ParseTree) -> Result<(), String> {
+    /// Link a link variable to a reference (or to nothing)
+    fn eval_link(&mut self, span: Span, target: &Target, linked: Option<&Reference>) -> Result<(), Unwind> {
         // Set potision
-        self.set_pos(tree);
+        self.set_pos(span);
 
-        Ok(())
+        // Find what we are linking to
+        let linked_ptr = self.eval_link_target(linked)?;
+
+        // Get the link that is changing
+        let pointer = match target {
+            Target::VARDEF(var_def) => self.eval_vardef(var_def)?,
+            Target::REFERENCE(reference) => self.eval_reference(reference)?,
+        };
+
+        self.set_literal_in_memory(pointer, LiteralValue::from_link(linked_ptr))
     }
 
-    /// Todo
-    /// 
-    fn eval_unlink(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
+    /// Remove whatever a link is linked to
+    fn eval_unlink(&mut self, reference: &Reference) -> Result<(), Unwind> {
+        let pointer = self.eval_reference(reference)?;
+        match pointer.pointer_type {
+            PointerType::LINK(_) => self.env.set_value(pointer, PrimitiveType::INITIALIZED),
+            _ => return Err(runtime_error(diagnostic::INVALID_OPERATION, "Cannot unlink something that is not a link".to_string()).into()),
+        };
 
         Ok(())
     }
 
-    /// 
-    fn eval_while(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
-        
+    fn eval_while(&mut self, condition: &Condition, body: &Block) -> Result<(), Unwind> {
         // Evaluate the comparison
-        while bool::from(self.eval_conditional(tree.children[0].as_ref().unwrap())?) {
+        while self.eval_conditional(condition)? {
             // Evaluate the while block
             self.env.scope_in();
-            self.eval_body(tree.children[1].as_ref().unwrap())?;
+            self.eval_body(body)?;
             self.env.scope_out();
 
-            if self.loop_status == LoopStatus::BREAK {
-                self.loop_status = LoopStatus::DEFAULT;
+            if self.loop_done() {
                 break;
             }
-            if self.loop_status == LoopStatus::CONTINUE {
-                self.loop_status = LoopStatus::DEFAULT;
-            }
-            if self.loop_status == LoopStatus::RETURN || self.loop_status == LoopStatus::QUIT {
-                break;
-            }     
         }
 
         Ok(())
     }
 
-    /// 
-    fn eval_repeat(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
-        
-        let repeat_lit = self.eval_resolvable(tree.children[0].as_ref().unwrap())?;
+    // After each time a loop body runs, see if the loop should stop
+    // A break or continue is only for this loop, a return stops it
+    //  and keeps going out to the function (a quit is an Unwind, so
+    //  it never gets here)
+    fn loop_done(&mut self) -> bool {
+        match self.loop_status {
+            LoopStatus::DEFAULT => false,
+            LoopStatus::CONTINUE => {
+                self.loop_status = LoopStatus::DEFAULT;
+                false
+            },
+            LoopStatus::BREAK => {
+                self.loop_status = LoopStatus::DEFAULT;
+                true
+            },
+            LoopStatus::RETURN => true,
+        }
+    }
+
+    fn eval_repeat(&mut self, count: &Expr, body: &Block) -> Result<(), Unwind> {
+        let repeat_lit = self.eval_resolvable(count)?;
         let repeat_val = repeat_lit.extract_number().unwrap_or(0.0) as i32;
 
         debug!{"REPEATING BLOCK {} TIMES", repeat_val};
         for i in 0..repeat_val {
             debug!{"REPEAT LOOP {}", i};
             self.env.scope_in();
-            self.eval_body(tree.children[1].as_ref().unwrap())?;
+            self.eval_body(body)?;
             self.env.scope_out();
 
-            if self.loop_status == LoopStatus::CONTINUE {
-                self.loop_status = LoopStatus::DEFAULT;
-            }
-            else if self.loop_status == LoopStatus::BREAK || self.loop_status == LoopStatus::QUIT {
-                self.loop_status = LoopStatus::DEFAULT;
+            if self.loop_done() {
                 break;
             }
         }
@@ -1468,57 +1824,67 @@ impl Interpreter {
         Ok(())
     }
 
-    fn eval_repeat_for(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
-        
+    fn eval_repeat_for(&mut self, variable: Option<&Ident>, values: &Expr, body: &Block) -> Result<(), Unwind> {
+        // Get the name of the looping variable
+        let id = variable.map(|v| v.name.clone()).unwrap_or_default();
+
         // Get the pointer type of thing to loop over
-        let arr_ptr = self.eval_reference(tree.children[1].as_ref().unwrap())?;
-        
-        // Remove the first layer of the array
-        let mut loop_ptr = match arr_ptr.pointer_type {
-            PointerType::ARRAY(bounds, arr_type) => {
-                // Figure out the new size by dividing by the first bounds length
-                let new_size = arr_ptr.size / (((bounds[0].0 - bounds[0].1).abs() + 1) as usize);
-                // Remove the first bounds to get the new bounds
-                let new_bounds = bounds.clone().into_iter().skip(1).collect();
-                Pointer{
-                    pointer_type: PointerType::ARRAY(new_bounds, arr_type.clone()),
-                    size: new_size,
-                    address: 0
-                }
+        // Anything that isn't a variable can only be text
+        let arr_ptr = match &values.kind {
+            ExprKind::REFERENCE(reference) => match self.eval_text_reference(reference)? {
+                Location::MEMORY(ptr) => ptr,
+                Location::CHARACTER(c) => return self.eval_repeat_for_text(&id, body, c),
             },
-            _ => return Err("Cannot loop through a non-array!".to_string())
+            _ => match self.eval_resolvable(values)?.extract_text() {
+                Some(text) => return self.eval_repeat_for_text(&id, body, text),
+                None => return Err(runtime_error(diagnostic::INVALID_OPERATION, "Cannot loop through a non-array!".to_string()).into()),
+            },
+        };
+
+        // Unwrap link pointers
+        let arr_ptr = match &arr_ptr.pointer_type {
+            PointerType::LINK(_) => self.follow_link(arr_ptr)?,
+            _ => arr_ptr,
         };
 
-        // Get the value of the thing to loop over
-        let val = self.eval_resolvable(tree.children[1].as_ref().unwrap())?;
+        // Loop through each character of text
+        if arr_ptr.pointer_type == PointerType::PRIMITIVE {
+            let text = self.get_literal_in_memory(arr_ptr)?.extract_text();
+            return match text {
+                Some(text) => self.eval_repeat_for_text(&id, body, text),
+                None => Err(runtime_error(diagnostic::INVALID_OPERATION, "Cannot loop through a non-array!".to_string()).into()),
+            };
+        }
 
-        // Get the name of the looping variable
-        let id = unwrap_id_tree(tree.children[0].as_ref().unwrap());
+        // Remove the first layer of the array
+        let (num_elements, mut elem_ptr) = match &arr_ptr.pointer_type {
+            PointerType::ARRAY(bounds, arr_type) => {
+                ((bounds[0].0 - bounds[0].1).abs() + 1, self.get_element_pointer(bounds, arr_type)?)
+            },
+            _ => return Err(runtime_error(diagnostic::INVALID_OPERATION, "Cannot loop through a non-array!".to_string()).into())
+        };
+        elem_ptr.address = arr_ptr.address;
 
         // Start looping through each object
-        for child in val.values.unwrap() {
-            debug!{"CHILD:: {:?}", child};
+        for _ in 0..num_elements {
+            debug!{"CHILD:: {:?}", elem_ptr};
             // Scope in
             self.env.scope_in();
 
-            // Load in the new value
+            // Copy in the new value
+            let mut loop_ptr = elem_ptr.clone();
             loop_ptr.address = self.env.alloc(loop_ptr.size);
             self.env.insert_id(id.clone(), loop_ptr.clone())?;
-            self.set_literal_in_memory(loop_ptr.clone(), child)?;
+            self.copy_in_memory(elem_ptr.clone(), loop_ptr)?;
+            elem_ptr.address += elem_ptr.size;
 
             // Run the body
-            self.eval_body(tree.children[2].as_ref().unwrap())?;
+            self.eval_body(body)?;
             // Scope out
             self.env.scope_out();
 
             // Handle break conditions
-            if self.loop_status == LoopStatus::CONTINUE {
-                self.loop_status = LoopStatus::DEFAULT;
-            }
-            else if self.loop_status == LoopStatus::BREAK || self.loop_status == LoopStatus::QUIT {
-                self.loop_status = LoopStatus::DEFAULT;
+            if self.loop_done() {
                 break;
             }
         }
@@ -1526,280 +1892,294 @@ impl Interpreter {
         Ok(())
     }
 
-    /// 
-    fn eval_repeat_forever(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
+    // Loop through each character of text, one at a time
+    fn eval_repeat_for_text(&mut self, id: &str, body: &Block, text: String) -> Result<(), Unwind> {
+        for c in text.chars() {
+            // Scope in
+            self.env.scope_in();
+
+            // Load in the new character
+            let loop_ptr = Pointer {
+                address: self.env.alloc(1),
+                size: 1,
+                pointer_type: PointerType::PRIMITIVE,
+            };
+            self.env.insert_id(id.to_string(), loop_ptr.clone())?;
+            self.set_literal_in_memory(loop_ptr, LiteralValue::from_text(c.to_string()))?;
+
+            // Run the body
+            self.eval_body(body)?;
+            // Scope out
+            self.env.scope_out();
+
+            // Handle break conditions
+            if self.loop_done() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
 
+    fn eval_repeat_forever(&mut self, body: &Block) -> Result<(), Unwind> {
         // Continually scope in, call the body, and scope out
         loop {
             self.env.scope_in();
-            self.eval_body(tree.children[0].as_ref().unwrap())?;
+            self.eval_body(body)?;
             self.env.scope_out();
 
-            if self.loop_status == LoopStatus::CONTINUE {
-                self.loop_status = LoopStatus::DEFAULT;
-            }
-            else if self.loop_status == LoopStatus::BREAK || self.loop_status == LoopStatus::QUIT {
-                self.loop_status = LoopStatus::DEFAULT;
+            if self.loop_done() {
                 break;
             }
         }
         Ok(())
     }
 
-    /// 
-    fn eval_assignment(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
+    fn eval_assignment(&mut self, span: Span, target: &Target, value: &Expr) -> Result<(), Unwind> {
+        // Arrays and structures are copied straight from memory,
+        //  anything else is evaluated to the value to assign
+        let from = self.eval_copy_or_value(value)?;
 
-        // Get the value to assign
-        let res = self.eval_resolvable(tree.children[1].as_ref().unwrap())?;
+        // Set potision
+        self.set_pos(span);
 
         // Get the address of where to assign it
-        let pointer: Pointer;
-        if tree.children[0].as_ref().unwrap().parse_type == ParseType::VARDEF {
-            pointer = self.eval_vardef(tree.children[0].as_ref().unwrap())?;
-        }
-        else {
-            pointer = self.eval_reference(tree.children[0].as_ref().unwrap())?;
-        }
+        let pointer = match target {
+            Target::VARDEF(var_def) => self.eval_vardef(var_def)?,
+            Target::REFERENCE(reference) => self.eval_reference(reference)?,
+        };
 
         // Make the assignment
-        self.set_literal_in_memory(pointer, res)?;
+        match from {
+            CallArgument::COPY(from) => self.copy_in_memory(from, pointer)?,
+            CallArgument::VALUE(val) => self.set_literal_in_memory(pointer, val)?,
+            CallArgument::LINK(_) => unreachable!(),
+        }
 
         Ok(())
     }
 
+    // If the expression references an array or structure, get the pointer to
+    //  the memory that it is stored in so that it can be copied directly,
+    //  anything else is evaluated to a value
+    // The expression is only evaluated once (an index could read input)
+    fn eval_copy_or_value(&mut self, expr: &Expr) -> Result<CallArgument, Unwind> {
+        let reference = match &expr.kind {
+            ExprKind::REFERENCE(reference) => reference,
+            _ => return Ok(CallArgument::VALUE(self.eval_resolvable(expr)?)),
+        };
+
+        // A character of text is only a value, so it isn't copied
+        let pointer = match self.eval_text_reference(reference)? {
+            Location::MEMORY(ptr) => ptr,
+            Location::CHARACTER(c) => return Ok(CallArgument::VALUE(LiteralValue::from_text(c))),
+        };
+        let target = match pointer.pointer_type {
+            PointerType::LINK(_) => self.follow_link(pointer.clone())?,
+            _ => pointer.clone(),
+        };
+
+        match target.pointer_type {
+            PointerType::ARRAY(_, _) | PointerType::STRUCTURE(_) => Ok(CallArgument::COPY(target)),
+            _ => Ok(CallArgument::VALUE(self.read_reference(reference, pointer)?)),
+        }
+    }
+
     // Given an ID and some different referencing, find the original pointer
     //  and modify it to match the specific referencing
     // Array -> move address and change bounds (or delete bounds)
     // Struct -> move address based on key
-    fn eval_reference(&mut self, tree: &ParseTree) -> Result<Pointer, String> {
+    // An error points at the whole reference (like "h2.next.value"),
+    //  not only at the part of it that was evaluated last
+    fn eval_reference(&mut self, reference: &Reference) -> Result<Pointer, Unwind> {
         // Set potision
-        self.set_pos(tree);
+        self.set_pos(reference.span);
 
-        let mut ptr: Pointer;
+        match &reference.kind {
+            ReferenceKind::NAME(name) => {
+                debug!("FINDING SYMBOL {}", name);
+                Ok(self.env.get_id(name.clone())?)
+            }
 
-        if tree.parse_type != ParseType::ID {
-            ptr = self.eval_reference(tree.children[0].as_ref().unwrap())?;
+            ReferenceKind::INDEX(array, indexes) => {
+                let mut ptr = self.eval_reference(array)?;
+                self.set_pos(reference.span);
 
-            // Unwrap link pointers
-            match &ptr.pointer_type {
-                PointerType::LINK(_) => { 
-                    ptr = match self.env.get_value(ptr.clone())? {
-                        PrimitiveType::POINTER(ptr2) => *ptr2,
-                        _ => { return Err("Cannot get refernce from link".to_string()); }
-                    };
-                },
-                _ => (),
-            };
+                // Unwrap link pointers
+                if let PointerType::LINK(_) = &ptr.pointer_type {
+                    ptr = self.follow_link(ptr)?;
+                }
 
-            if tree.parse_type == ParseType::GETINDEX {
-                let (mut bounds, arr_type) = match ptr.pointer_type.clone(){
-                    PointerType::ARRAY(bounds, arr_type) => (bounds.clone(), arr_type),
-                    _ => return Err("Cannot index a non-array".to_string()),
-                };
+                self.eval_index(ptr, indexes, reference.span)
+            }
 
-                debug!{"STARTING ADDRESS == {} == ", ptr.address};
+            ReferenceKind::FIELD(structure, key) => {
+                let mut ptr = self.eval_reference(structure)?;
+                self.set_pos(reference.span);
 
-                // Figure out how far we need to offset the new pointer
-                let mut offset = 1;
-                for dim in bounds.iter().skip(1) {
-                    offset *= (dim.0 - dim.1).abs() + 1;
+                // Unwrap link pointers
+                if let PointerType::LINK(_) = &ptr.pointer_type {
+                    ptr = self.follow_link(ptr)?;
                 }
 
-                // check all indexes to make sure they are numbers
-                let index_tree = tree.children[1].as_ref().unwrap();
-                for (idx, bound) in index_tree.children.iter().zip(bounds.clone().iter()) {
-                    let idx_val = self.eval_resolvable(idx.as_ref().unwrap())?.extract_number().unwrap_or(0.0) as i32;
-
-                    debug!{"INDEXING ARRAY AT POSISION {}", idx_val};
+                // Find the structure name
+                let struct_name = match &ptr.pointer_type {
+                    PointerType::STRUCTURE(s) => s.to_string(),
+                    _ => { return Err(runtime_error(diagnostic::INVALID_OPERATION, "Cannot key index a non-structure".to_string()).into()); }
+                };
 
-                    // low to high bounds
-                    if bound.0 < bound.1 {
-                        if idx_val < bound.0 || idx_val > bound.1 {
-                            return Err(format!{"Index out of bounds for index {} in range {} to {}", idx_val, bound.0, bound.1});
-                        }
+                // Get the expected pointer type, and move the address
+                //  by the offset of the key in the structure
+                let mut new_ptr = self.get_struct_key(struct_name, key.name.clone())?;
+                new_ptr.address += ptr.address;
 
-                        // remove this dimension
-                        bounds = bounds.into_iter().skip(1).collect();   
-                        // move the address
-                        ptr.address += (offset * (idx_val as i32 - bound.0)) as usize;
-                        // change the remaining offset
-                        offset /= (bound.0 - bound.1).abs() + 1;
-                    }
-                    // high to low bounds
-                    else {
-                        if idx_val > bound.0 || idx_val < bound.1 {
-                            return Err(format!{"Index out of bounds for index {} in range {} to {}", idx_val, bound.0, bound.1});
-                        }
+                Ok(new_ptr)
+            }
+        }
+    }
 
-                        // remove this dimension
-                        bounds = bounds.into_iter().skip(1).collect();
-                        // move the address
-                        ptr.address += (offset * (bound.0 - idx_val as i32)) as usize;
-                        // change the remaining offset
-                        offset /= (bound.0 - bound.1).abs() + 1;
-                    }
+    // The same as eval_reference, but text can also be indexed
+    // Text is immutable, so a character of text is only a value
+    //  and does not have its own memory
+    fn eval_text_reference(&mut self, reference: &Reference) -> Result<Location, Unwind> {
+        let (array, indexes) = match &reference.kind {
+            ReferenceKind::INDEX(array, indexes) => (array, indexes),
+            _ => return Ok(Location::MEMORY(self.eval_reference(reference)?)),
+        };
 
-                    debug!{"MOVED TO ADDRESS == {} == ", ptr.address};
-                    // catch division rounding errors that lowers offset too much
-                    if offset == 0 {
-                        offset = 1;
-                    }
+        let location = self.eval_text_reference(array)?;
+        self.set_pos(reference.span);
+        let text = match location {
+            Location::CHARACTER(c) => c,
+            Location::MEMORY(mut ptr) => {
+                // Unwrap link pointers
+                if let PointerType::LINK(_) = &ptr.pointer_type {
+                    ptr = self.follow_link(ptr)?;
                 }
-                
-                if bounds.len() == 0 {
-                    ptr.pointer_type = *arr_type.clone();
-                    ptr.size = 1;
+
+                if ptr.pointer_type != PointerType::PRIMITIVE {
+                    return Ok(Location::MEMORY(self.eval_index(ptr, indexes, reference.span)?));
                 }
-                else {
-                    ptr.size = (offset * ((bounds[0].0 - bounds[0].1).abs() + 1)) as usize;
-                    ptr.pointer_type = PointerType::ARRAY(bounds, Box::new(*arr_type.clone()));
+
+                match self.get_literal_in_memory(ptr)?.extract_text() {
+                    Some(t) => t,
+                    None => return Err(runtime_error(diagnostic::INVALID_OPERATION, "Cannot index a non-array".to_string()).into()),
                 }
+            }
+        };
 
-                debug!{"ENDING ADDRESS == {} == ", ptr.address};
+        if indexes.len() != 1 {
+            return Err(runtime_error(diagnostic::INVALID_OPERATION, "Text can only be indexed with a single index".to_string()).into());
+        }
 
-                return Ok(ptr);
-            }
+        let idx_val = self.eval_resolvable(&indexes[0])?.extract_number().unwrap_or(0.0) as i32;
+        self.set_pos(reference.span);
+        let len = text.chars().count() as i32;
+        if idx_val < 1 || idx_val > len {
+            return Err(runtime_error(diagnostic::OUT_OF_BOUNDS, format!{"Index out of bounds for index {} in range 1 to {}", idx_val, len}).into());
+        }
 
-            else if tree.parse_type == ParseType::GETSTRUCT {
-                // Find the key name, and structure name
-                let struct_key = unwrap_id_tree(tree.children[1].as_ref().unwrap());
-                let struct_name = match &ptr.pointer_type {
-                    PointerType::STRUCTURE(s) => s.to_string(),
-                    _ => { return Err("Cannot key index a non-structure".to_string()); }
-                };
+        Ok(Location::CHARACTER(text.chars().nth((idx_val - 1) as usize).unwrap().to_string()))
+    }
 
-                // If we came from a complicated pointer, check
-                //  and see if we actually need to move into the
-                //  structure from memory. This will move into
-                //  the structure if the address in memory is
-                //  also a structure of the same name.
-                match &ptr.pointer_type {
-                    PointerType::STRUCTURE(_) => { 
-                        ptr = match self.env.get_value(ptr.clone())? {
-                            PrimitiveType::POINTER(ptr2) => {
-                                match &ptr2.pointer_type {
-                                    PointerType::STRUCTURE(s) => {
-                                        if s == &struct_name.clone() {
-                                            *ptr2
-                                        }
-                                        else {
-                                            ptr
-                                        }
-                                    },
-                                    _ => ptr
-                                }
-                            },
-                            _ => ptr,
-                        };
-                    },
-                    _ => (),
-                };
+    // Move an array pointer to the element (or sub-array) that
+    //  the indexes point to, an error points at 'span' (the whole reference)
+    fn eval_index(&mut self, ptr: Pointer, indexes: &[Expr], span: Span) -> Result<Pointer, Unwind> {
+        let mut ptr = ptr;
+        let (mut bounds, arr_type) = match ptr.pointer_type.clone(){
+            PointerType::ARRAY(bounds, arr_type) => (bounds.clone(), arr_type),
+            _ => return Err(runtime_error(diagnostic::INVALID_OPERATION, "Cannot index a non-array".to_string()).into()),
+        };
 
-                // Calculate the offset in memory
-                let offset = self.get_struct_key_offset(struct_name.clone(), struct_key.clone())?;
+        debug!{"STARTING ADDRESS == {} == ", ptr.address};
 
-                // Get the expected pointer type, and set the address to the proper one
-                let mut new_ptr = self.structure_defs[&struct_name][offset].clone();
-                new_ptr.address = ptr.address + offset;
-                
-                return Ok(new_ptr);
-            }
+        // Figure out how far we need to offset the new pointer
+        //  for each step along the first dimension
+        let mut offset = self.get_type_size(&arr_type)? as i32;
+        for dim in bounds.iter().skip(1) {
+            offset *= (dim.0 - dim.1).abs() + 1;
         }
 
-        debug!("FINDING SYMBOL {}", unwrap_id_tree(&tree));
-        self.env.get_id(unwrap_id_tree(&tree))
-    }
-
-    /// 
-    fn eval_return(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
-        
-        self.return_value = LiteralValue::null();
-        
-        // Check to see if the return type is nothing
-        if tree.children[0].is_some() {
-            self.return_value = self.eval_resolvable(tree.children[0].as_ref().unwrap())?;
+        // check all indexes to make sure they are numbers
+        if indexes.len() > bounds.len() {
+            return Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Cannot index an array with {} dimensions using {} indexes", bounds.len(), indexes.len()}).into());
         }
 
-        self.loop_status = LoopStatus::RETURN;
+        for (idx, bound) in indexes.iter().zip(bounds.clone().iter()) {
+            let idx_val = self.eval_resolvable(idx)?.extract_number().unwrap_or(0.0) as i32;
+            self.set_pos(span);
 
-        // Otherwise return Ok
-        Ok(())
-    }
+            debug!{"INDEXING ARRAY AT POSISION {}", idx_val};
 
-    fn eval_quit(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
+            // low to high bounds
+            if bound.0 <= bound.1 {
+                if idx_val < bound.0 || idx_val > bound.1 {
+                    return Err(runtime_error(diagnostic::OUT_OF_BOUNDS, format!{"Index out of bounds for index {} in range {} to {}", idx_val, bound.0, bound.1}).into());
+                }
 
-        self.loop_status = LoopStatus::QUIT;
-        Ok(())
-    }
+                // move the address
+                ptr.address += (offset * (idx_val - bound.0)) as usize;
+            }
+            // high to low bounds
+            else {
+                if idx_val > bound.0 || idx_val < bound.1 {
+                    return Err(runtime_error(diagnostic::OUT_OF_BOUNDS, format!{"Index out of bounds for index {} in range {} to {}", idx_val, bound.0, bound.1}).into());
+                }
 
-    fn eval_continue(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
-        
-        self.loop_status = LoopStatus::CONTINUE;
-        Ok(())
-    }
+                // move the address
+                ptr.address += (offset * (bound.0 - idx_val)) as usize;
+            }
 
-    fn eval_break(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Set potision
-        self.set_pos(tree);
-        
-        self.loop_status = LoopStatus::BREAK;
-        Ok(())
-    }  
-}
+            // remove this dimension
+            bounds = bounds.into_iter().skip(1).collect();
+            // change the remaining offset
+            if !bounds.is_empty() {
+                offset /= (bounds[0].0 - bounds[0].1).abs() + 1;
+            }
 
+            debug!{"MOVED TO ADDRESS == {} == ", ptr.address};
+        }
 
-// The main function that runs the interpreter and prints
-//  the output. If a filename is provided in the system
-//  arguments, interpret that file instead.
-// To instead run the interpreter as a unit, you would need
-//  to first run the parser, then interpret on the resulting
-//  parse tree, making sure to unwrap any potential errors.
-pub fn main() {
+        if bounds.is_empty() {
+            ptr.pointer_type = *arr_type.clone();
+        }
+        else {
+            ptr.pointer_type = PointerType::ARRAY(bounds, Box::new(*arr_type.clone()));
+        }
+        ptr.size = self.get_type_size(&ptr.pointer_type)?;
 
-    let args: Vec<String> = env::args().collect();
+        debug!{"ENDING ADDRESS == {} == ", ptr.address};
 
-    // create parser
-    let mut p: Parser;
-    // if argument, open file
-    if args.len() > 1 {
-        let fname = &args[1];
-        p = Parser::from_file(fname.to_string()).expect("Could not create lexer");
-    }
-    else {
-        p = Parser::new("
-    program
-        print(\"Hello world!\")
-    end program
-    ".to_string()).expect("Could not create lexer");
+        Ok(ptr)
     }
 
-    let tree = match p.parse() {
-        Ok(t) => t.unwrap(),
-        Err(s) => {
-            log!{"{}", s};
-            return;
-        },
-    };
+    fn eval_return(&mut self, value: Option<&Expr>) -> Result<(), Unwind> {
+        self.return_value = LiteralValue::null();
 
-    if DEBUG {
-        tree.print();
-        println!("\n\n\n\n");
+        // Check to see if the return type is nothing
+        if let Some(value) = value {
+            self.return_value = self.eval_resolvable(value)?;
+        }
+
+        self.loop_status = LoopStatus::RETURN;
+
+        // Otherwise return Ok
+        Ok(())
     }
 
-    let mut int = Interpreter::new();
-    match int.eval(&tree) {
-        Err(s) => log!{"{}", s},
-        Ok(_) => (),
-    };
+    fn eval_quit(&mut self, status: Option<&Expr>) -> Result<(), Unwind> {
+        let mut code = 0;
 
-}
\ No newline at end of file
+        // The status is the exit code of the program, which can
+        //  only be a whole number from 0 to 255
+        if let Some(status) = status {
+            let value = self.eval_resolvable(status)?.extract_number().unwrap_or(0.0);
+            if value.fract() != 0.0 || !(0.0..=255.0).contains(&value) {
+                return Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Cannot quit with {}, the status has to be a whole number from 0 to 255", value}).with_span(status.span).into());
+            }
+            code = value as i32;
+        }
+
+        Err(Unwind::QUIT(code))
+    }
+}
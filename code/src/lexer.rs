@@ -1,6 +1,7 @@
-use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
+use crate::diagnostic::{self, Diagnostic, Span, Stage};
 
 // This is a collection of all of the
 //  token types, some types like
@@ -76,129 +77,365 @@ pub enum TokenType {
     INVALID,   // dummy value for broken tokens
 }
 
+// How a token is written in an error message, the way it looks
+//  in the source ("`while`", "`(`"), or the kind of thing it is
+//  when it could be anything ("a name", "a number")
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let source = match self {
+            TokenType::TEXT(_) => return write!(f, "text"),
+            TokenType::ID(_) => return write!(f, "a name"),
+            TokenType::NUMBER(_) => return write!(f, "a number"),
+            TokenType::EOF => return write!(f, "the end of the file"),
+            TokenType::INVALID => return write!(f, "an unknown character"),
+            TokenType::ADD => "+",
+            TokenType::SUB => "-",
+            TokenType::MUL => "*",
+            TokenType::DIV => "/",
+            TokenType::MOD => "mod",
+            TokenType::POW => "^",
+            TokenType::COLON => ":",
+            TokenType::DEF => "definitions",
+            TokenType::END => "end",
+            TokenType::STRUCT => "structure",
+            TokenType::EQ => "=",
+            TokenType::NE => "!=",
+            TokenType::LT => "<",
+            TokenType::LE => "<=",
+            TokenType::GT => ">",
+            TokenType::GE => ">=",
+            TokenType::IS => "is",
+            TokenType::WORDNOT => "not",
+            TokenType::FUN => "function",
+            TokenType::RETURNS => "returns",
+            TokenType::RETURN => "return",
+            TokenType::LPAREN => "(",
+            TokenType::RPAREN => ")",
+            TokenType::COMMA => ",",
+            TokenType::CHANGEABLE => "changeable",
+            TokenType::ARRAY => "array",
+            TokenType::OF => "of",
+            TokenType::LBRACKET => "[",
+            TokenType::RBRACKET => "]",
+            TokenType::NOTHING => "nothing",
+            TokenType::PROGRAM => "program",
+            TokenType::QUIT => "quit",
+            TokenType::LINK => "link",
+            TokenType::LINKED => "linked",
+            TokenType::UNLINK => "unlink",
+            TokenType::TO => "to",
+            TokenType::BREAK => "break",
+            TokenType::CONTINUE => "continue",
+            TokenType::NUMTYPE => "number",
+            TokenType::TEXTTYPE => "text",
+            TokenType::IF => "if",
+            TokenType::THEN => "then",
+            TokenType::ELSE => "else",
+            TokenType::WHILE => "while",
+            TokenType::REPEAT => "repeat",
+            TokenType::FOREVER => "forever",
+            TokenType::TIMES => "times",
+            TokenType::FOR => "for",
+            TokenType::ALL => "all",
+            TokenType::IN => "in",
+            TokenType::AND => "and",
+            TokenType::OR => "or",
+            TokenType::BOR => "bit_or",
+            TokenType::BXOR => "bit_xor",
+            TokenType::BAND => "bit_and",
+            TokenType::BSL => "bit_sl",
+            TokenType::BSR => "bit_sr",
+            TokenType::BNOT => "bit_not",
+            TokenType::LCURLY => "{",
+            TokenType::RCURLY => "}",
+            TokenType::PERIOD => ".",
+        };
+        write!(f, "`{}`", source)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: Option<String>,
-    pub row: u32,
-    pub col: u32,
+    pub span: Span,
+}
+
+// The text between tokens, which the parser doesn't need
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trivia {
+    WHITESPACE,     // spaces and tabs (on a single line)
+    NEWLINE,        // a single newline
+    COMMENT,        // from a '#' up to (not including) the newline
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamKind {
+    TOKEN(Token),
+    TRIVIA(Trivia),
+}
+
+// One piece of the lossless token stream, with the exact text it
+//  came from and its byte offsets ('end' is one past the last byte)
+// Joining the 'raw' text of every item gives back the whole file
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamItem {
+    pub kind: StreamKind,
+    pub raw: String,
+    pub start: usize,
+    pub end: usize,
 }
 
 #[derive(Debug)]
 pub struct Lexer {
     curr_row: u32,
     curr_col: u32,
+    curr_pos: usize,    // byte offset of curr_char
+    start: Span,        // where the token being lexed starts
+    end: Span,          // where the last consumed character ends
     curr_lex: String,
     curr_char: char,
     pub curr_token: Token,
     raw_text: String,
+    source: String,
+
+    // In lossless mode every token and piece of trivia is also
+    //  added to the stream
+    keep_trivia: bool,
+    stream: Vec<StreamItem>,
 }
 
 impl Lexer {
     // Create a new lexer structure from a provided code String
     // This function also loads in the first character into the
     //  'curr_char' buffer for processing
-    pub fn new(text: String) -> Result<Self, String> {
+    pub fn new(text: String) -> Result<Self, Diagnostic> {
         let mut lex = Lexer {
             raw_text: text.chars().rev().collect::<String>(),
+            source: text,
             curr_row: 1,
             curr_col: 0,
+            curr_pos: 0,
+            start: Span::default(),
+            end: Span::default(),
             curr_lex: "".to_string(),
             curr_char: '\0',
             // The initial token will be an invalid token
             curr_token: Token{
                 token_type: TokenType::INVALID,
                 lexeme: None,
-                row: 0,
-                col: 0,
-            }
+                span: Span::default(),
+            },
+            keep_trivia: false,
+            stream: Vec::new(),
         };
         lex.consume()?;
         Ok(lex)
     }
 
+    // Create a lexer in lossless mode, where the whitespace and
+    //  comments are kept (see 'stream')
+    pub fn with_trivia(text: String) -> Result<Self, Diagnostic> {
+        let mut lex = Lexer::new(text)?;
+        lex.keep_trivia = true;
+        Ok(lex)
+    }
+
+    // Lex all of the code into a lossless stream of tokens and
+    //  trivia, for tools that need to give back the code as it was
+    //  written (like a syntax highlighter)
+    pub fn stream(text: String) -> Result<Vec<StreamItem>, Diagnostic> {
+        let mut lex = Lexer::with_trivia(text)?;
+        while !lex.is_done() {
+            lex.next()?;
+        }
+        Ok(lex.stream)
+    }
+
     // Try to load a string into the lexer from a file
     // This acts as an alternative to 'new', since it creates
     //  its own lexer structure and returns that
-    pub fn from_file(file: String) -> Result<Self, String> {
+    pub fn from_file(file: String) -> Result<Self, Diagnostic> {
+        Lexer::new(Lexer::read_file(file)?)
+    }
+
+    // Read all of the code from a file, this is kept separate
+    //  from 'from_file' so the code can also be used to show errors
+    pub fn read_file(file: String) -> Result<String, Diagnostic> {
         let result = File::open(file.clone());
         if result.is_err() {
-            return Err(format!{"Could not open file '{}'", file});
+            return Err(Diagnostic::new(Stage::LEX, diagnostic::FILE_ERROR, format!{"Could not open file '{}'", file}));
         }
         let mut file_obj = result.unwrap();
         let mut code: String = String::new();
         if file_obj.read_to_string(&mut code).is_err() {
-            return Err(format!{"Could not read from file '{}'", file});
+            return Err(Diagnostic::new(Stage::LEX, diagnostic::FILE_ERROR, format!{"Could not read from file '{}'", file}));
         }
-        Lexer::new(code)
+        Ok(code)
     }
 
     // Consumes a single character from the lexer's raw text
     // Throws an error if non-ASCII characters are consumed
-    pub fn consume(&mut self) -> Result<char, String> {
-        if self.raw_text.len() < 1 {
+    pub fn consume(&mut self) -> Result<char, Diagnostic> {
+        // Once the end of the text is reached, stay there
+        if self.curr_pos == self.source.len() && self.curr_col > 0 {
             self.curr_char = '\0';
             return Ok(self.curr_char);
         }
 
-        self.curr_char = self.raw_text.pop().unwrap_or('\0');
-        self.curr_col += 1;
+        // A token that ends with the character being left behind
+        //  ends right after it
+        self.end = self.point();
+        self.end.end_col += 1;
+        self.end.end = self.curr_pos + self.curr_char.len_utf8();
+
+        // A newline is the last character of its row
         if self.curr_char == '\n' {
             self.curr_col = 0;
             self.curr_row += 1;
         }
+        self.curr_col += 1;
+
+        if self.raw_text.is_empty() {
+            self.curr_char = '\0';
+            self.curr_pos = self.source.len();
+            return Ok(self.curr_char);
+        }
+
+        self.curr_pos = self.source.len() - self.raw_text.len();
+        self.curr_char = self.raw_text.pop().unwrap_or('\0');
         if !self.curr_char.is_ascii() {
-            return Err("Only printable ASCII characters are allowed".to_string());
+            let mut span = self.point();
+            span.end_col += 1;
+            span.end = self.curr_pos + self.curr_char.len_utf8();
+            return Err(Diagnostic::new(Stage::LEX, diagnostic::NON_ASCII, "Only printable ASCII characters are allowed".to_string()).with_span(span));
         }
         Ok(self.curr_char)
     }
 
+    // An empty span at the current character
+    fn point(&self) -> Span {
+        Span {
+            start_row: self.curr_row,
+            start_col: self.curr_col,
+            end_row: self.curr_row,
+            end_col: self.curr_col,
+            start: self.curr_pos,
+            end: self.curr_pos,
+        }
+    }
+
     // Consume all whitespace characters, if the current character
     //  is not whitespace, or is EOF, this does nothing
     // Will consume all trailing whitespace until EOF
-    pub fn consume_whitespace(&mut self) -> Result<(), String> {
+    // Comments are skipped like whitespace (in lossless mode they are
+    //  kept in the stream)
+    pub fn consume_whitespace(&mut self) -> Result<(), Diagnostic> {
         while self.curr_char.is_whitespace() || self.curr_char == '#' {
             if self.curr_char == '\0' {
                 return Ok(());
             }
+            let start = self.curr_pos;
             if self.curr_char == '#' {
+                self.consume()?;
                 while !(self.curr_char == '\n' || self.curr_char == '\0') {
                     self.consume()?;
                 }
+                self.add_trivia(Trivia::COMMENT, start);
+                continue;
             }
-            self.consume()?;
+            if self.curr_char == '\n' {
+                self.consume()?;
+                self.add_trivia(Trivia::NEWLINE, start);
+                continue;
+            }
+            while self.curr_char.is_whitespace() && self.curr_char != '\n' {
+                self.consume()?;
+            }
+            self.add_trivia(Trivia::WHITESPACE, start);
         }
-        return Ok(());
+        Ok(())
+    }
+
+    // Add the text from 'start' up to the current character to the
+    //  lossless stream, if the lexer is keeping it
+    fn add_item(&mut self, kind: StreamKind, start: usize) {
+        if self.keep_trivia {
+            self.stream.push(StreamItem {
+                kind,
+                raw: self.source[start..self.curr_pos].to_string(),
+                start,
+                end: self.curr_pos,
+            });
+        }
+    }
+
+    fn add_trivia(&mut self, trivia: Trivia, start: usize) {
+        self.add_item(StreamKind::TRIVIA(trivia), start);
     }
 
     // Consume and create the next token, store it in curr_token,
     //  and return it. If EOF token is already created, return
     //  it instead, without lexing any further.
-    pub fn next(&mut self) -> Result<Token, String> {
+    // (Not an Iterator, since lexing can fail and EOF repeats forever)
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Token, Diagnostic> {
         if self.curr_token.token_type == TokenType::EOF {
             return Ok(self.curr_token.clone());
         }
         else if self.curr_char == '\0' {
-            self.create_token(self.curr_row, self.curr_col, TokenType::EOF);
+            self.start = self.point();
+            self.create_token(TokenType::EOF);
             return Ok(self.curr_token.clone());
         }
 
         self.consume_whitespace()?;
 
-        if self.lex_single()? {
-            Ok(self.curr_token.clone())
-        }
-        else if self.lex_multi_fixed()? {
-            Ok(self.curr_token.clone())
-        }
-        else if self.lex_other()? {
-            Ok(self.curr_token.clone())
+        // Trailing whitespace (or a comment) can also end the file
+        self.start = self.point();
+        if self.curr_char == '\0' {
+            self.create_token(TokenType::EOF);
+            return Ok(self.curr_token.clone());
         }
-        else {
+
+        if !(self.lex_single()? || self.lex_multi_fixed()? || self.lex_other()?) {
             self.curr_lex = self.curr_char.to_string();
-            self.curr_token = self.create_token(self.curr_row, self.curr_col, TokenType::INVALID);
+            self.create_token(TokenType::INVALID);
             self.consume()?;
-            Ok(self.curr_token.clone())
+        }
+
+        // Every token ends after the last character it consumed
+        self.curr_token.span = self.start.to(self.end);
+        if self.curr_token.token_type == TokenType::INVALID {
+            return Err(self.invalid_token());
+        }
+        self.add_item(StreamKind::TOKEN(self.curr_token.clone()), self.start.start);
+        Ok(self.curr_token.clone())
+    }
+
+    // The error for a token that isn't part of the language
+    fn invalid_token(&self) -> Diagnostic {
+        let span = self.curr_token.span;
+        let lexeme = self.curr_token.lexeme.clone().unwrap_or_default();
+
+        // Text that is never ended goes to the end of the file, so
+        //  only the quote that starts it is pointed at
+        if let Some(quote) = lexeme.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            let mut start = span.start_point();
+            start.end_col += 1;
+            start.end += 1;
+            return Diagnostic::new(Stage::LEX, diagnostic::UNTERMINATED_TEXT, format!{"This text is never ended with a closing {}", quote}).with_span(start)
+                .with_help(format!{"put a {} at the end of the text", quote});
+        }
+
+        let err = Diagnostic::new(Stage::LEX, diagnostic::UNKNOWN_CHARACTER, format!{"Unknown character `{}`", lexeme}).with_span(span);
+        match lexeme.as_str() {
+            "!" => err.with_help("`!=` means not equal, use `not` to turn a condition around".to_string()),
+            "&" => err.with_help("use `and` to join conditions".to_string()),
+            "|" => err.with_help("use `or` to join conditions".to_string()),
+            "%" => err.with_help("use `mod` for the remainder".to_string()),
+            ";" => err.with_help("a statement ends at the end of its line, without a `;`".to_string()),
+            _ => err,
         }
     }
 
@@ -209,7 +446,7 @@ impl Lexer {
 
     // Attempt to create a token for single character tokens
     // This is done with a large match statement
-    pub fn lex_single(&mut self) -> Result<bool, String> {
+    pub fn lex_single(&mut self) -> Result<bool, Diagnostic> {
 
         let t_type : TokenType = match self.curr_char {
             '+' => TokenType::ADD,
@@ -235,7 +472,7 @@ impl Lexer {
         }
         else {
             self.curr_lex.push(self.curr_char);
-            self.create_token(self.curr_row, self.curr_col, t_type);
+            self.create_token(t_type);
             self.consume()?;
             Ok(true)
         }
@@ -243,10 +480,8 @@ impl Lexer {
 
     // Attempt to lex from a multi-character, but fixed, set of tokens
     // This only includes sigils, not letters or keywords
-    pub fn lex_multi_fixed(&mut self) -> Result<bool, String> {
+    pub fn lex_multi_fixed(&mut self) -> Result<bool, Diagnostic> {
         let mut lex: String = self.curr_char.to_string();
-        let start_row = self.curr_row;
-        let start_col = self.curr_col;
 
         let multi_fixed_tokens = [
             ("<", TokenType::LT),
@@ -258,6 +493,8 @@ impl Lexer {
 
         let mut matches: Vec<&(&str, TokenType)> = multi_fixed_tokens.iter().filter(|item| (item.0).starts_with(&lex)).clone().collect();
         let mut new_matches: Vec<&(&str, TokenType)>;
+        // Set once the character after the lexeme has been consumed
+        let mut past_end = false;
         // Keep looping to find 1 (or zero) potential matches
         while matches.len() > 1 {
             self.consume()?;
@@ -265,9 +502,10 @@ impl Lexer {
             new_matches = matches.clone().into_iter().filter(|item| (item.0).starts_with(&lex)).collect();
             // If this sets us to 0 matches, undo the new character
             //  and remove the too-long lexes that didn't match
-            if new_matches.len() == 0 {
+            if new_matches.is_empty() {
                 lex.pop();
-                matches = matches.into_iter().filter(|item| (item.0).len() == lex.len()).collect();
+                past_end = true;
+                matches.retain(|item| (item.0).len() == lex.len());
             }
             else {
                 matches = new_matches;
@@ -276,11 +514,11 @@ impl Lexer {
 
         // 'matches' has only one (or zero) values now, if zero return
         //  an invalid token
-        if lex.len() >= 1 && matches.len() > 0 {
+        if !lex.is_empty() && !matches.is_empty() {
             // We have at least part of a matching fixed lex
             //  keep consuming token until we get a complete match
             //  or a conflicting caracter.
-            let match_lex = matches[0].0.clone();
+            let match_lex = matches[0].0;
             let match_token = matches[0].1.clone();
 
             while lex.len() < match_lex.len() && match_lex.starts_with(&lex) {
@@ -289,17 +527,21 @@ impl Lexer {
             }
 
             if lex == match_lex {
-                // We actually got a match, make the token and consume the character
-                self.consume()?;
+                // We actually got a match, make the token and consume the
+                //  character (unless it was already consumed looking for
+                //  a longer match, like the "1" after a "<")
+                if !past_end {
+                    self.consume()?;
+                }
 
                 self.curr_lex = lex.clone();
-                self.create_token(start_row, start_col, match_token);
+                self.create_token(match_token);
             }
             else {
                 // We didn't get a match, don't consume the current token
                 lex.pop();
                 self.curr_lex = lex.to_string().clone();
-                self.create_token(start_row, start_col, TokenType::INVALID);
+                self.create_token(TokenType::INVALID);
             }
             Ok(true)
         }
@@ -308,7 +550,7 @@ impl Lexer {
             //  point, but now form an invalid token
             lex.pop();
             self.curr_lex = lex.to_string().clone();
-            self.create_token(start_row, start_col, TokenType::INVALID);
+            self.create_token(TokenType::INVALID);
             Ok(true)
         }
         else {
@@ -319,16 +561,16 @@ impl Lexer {
 
     // Attempt to create a token for numbers, variables (id),
     //  text, and keywords
-    pub fn lex_other(&mut self) -> Result<bool, String> {
+    pub fn lex_other(&mut self) -> Result<bool, Diagnostic> {
 
         if self.curr_char.is_numeric() || self.curr_char == '.' {
             return self.lex_number();
         }
         if self.curr_char == '"' || self.curr_char == '\'' {
-            return self.lex_text();
+            self.lex_text()
         }
         else if self.curr_char.is_alphabetic() || self.curr_char == '_' {
-            return self.lex_id();
+            self.lex_id()
         }
         else {
             Ok(false)
@@ -337,9 +579,7 @@ impl Lexer {
 
     // Lex all concurrent letters (and underscores) together into a single id
     // This stops at whitespace (or a non-letter)
-    pub fn lex_id(&mut self) -> Result<bool, String> {
-        let start_row = self.curr_row;
-        let start_col = self.curr_col;
+    pub fn lex_id(&mut self) -> Result<bool, Diagnostic> {
         while self.curr_char.is_alphanumeric() || self.curr_char == '_' {
             self.curr_lex.push(self.curr_char);
             self.consume()?;
@@ -392,15 +632,13 @@ impl Lexer {
             _ => TokenType::ID(lex_val),
         };
 
-        self.create_token(start_row, start_col, token_type);
+        self.create_token(token_type);
         Ok(true)
     }
 
     // Lex all of the characters inside of a string
-    pub fn lex_text(&mut self) -> Result<bool, String> {
+    pub fn lex_text(&mut self) -> Result<bool, Diagnostic> {
         let end_char = self.curr_char;
-        let start_col = self.curr_col;
-        let start_row = self.curr_row;
         self.curr_lex = String::new();
         self.curr_lex.push(self.curr_char);
         self.consume()?;
@@ -442,7 +680,7 @@ impl Lexer {
 
         // If the string didn't close, make an invalid token
         if self.curr_char != end_char {
-            self.create_token(start_row, start_col, TokenType::INVALID);
+            self.create_token(TokenType::INVALID);
         }
         else {
             self.curr_lex.push(self.curr_char);
@@ -450,7 +688,7 @@ impl Lexer {
             let mut lex_val = self.curr_lex.chars();
             lex_val.next();
             lex_val.next_back();
-            self.create_token(start_row, start_col, TokenType::TEXT(lex_val.as_str().to_string()));
+            self.create_token(TokenType::TEXT(lex_val.as_str().to_string()));
         }
 
         Ok(true)
@@ -458,12 +696,10 @@ impl Lexer {
 
     // Lexes all concurrent numbers, creates a token, and returns
     //  true if the token was make successfully
-    pub fn lex_number(&mut self) -> Result<bool, String> {
+    pub fn lex_number(&mut self) -> Result<bool, Diagnostic> {
         if !(self.curr_char.is_numeric() || self.curr_char == '.') {
             return Ok(false);
         }
-        let start_row = self.curr_row;
-        let start_col = self.curr_col;
         while self.curr_char.is_numeric() {
             self.curr_lex.push(self.curr_char);
             self.consume()?;
@@ -476,51 +712,25 @@ impl Lexer {
             self.curr_lex.push(self.curr_char);
             self.consume()?;
         }
-        self.create_token(start_row, start_col, TokenType::NUMBER(self.curr_lex.parse::<f64>().unwrap()));
+        self.create_token(TokenType::NUMBER(self.curr_lex.parse::<f64>().unwrap()));
         Ok(true)
     }
 
-    // Create a token given the provided start row and column
+    // Create a token that starts where the current token started,
+    //  its end is set once all of its characters are consumed
     // Clears out the currently stored lexeme
-    pub fn create_token(&mut self, row: u32, col: u32, token_type: TokenType) -> Token {
+    pub fn create_token(&mut self, token_type: TokenType) -> Token {
         let lexeme: Option<String> = match self.curr_lex.len() {
             0 => None,
             _ => Some(self.curr_lex.clone())
         };
         
         self.curr_token = Token{
-            token_type: token_type,
-            lexeme: lexeme,
-            row: row,
-            col: col,
+            token_type,
+            lexeme,
+            span: self.start,
         };
         self.curr_lex = "".to_string();
         self.curr_token.clone()
     }
 }
-
-// Generic main function that just runs the lexer and prints
-//  the output. If a filename is provided in the system
-//  arguments, lex from that file instead.
-pub fn _test() {
-    let args: Vec<String> = env::args().collect();
-
-    let mut lex: Lexer;
-    let res: Result<Lexer, String>;
-    if args.len() > 1 {
-        let fname = &args[1];
-        res = Lexer::from_file(fname.to_string());
-    }
-    else {
-        res = Lexer::new("hello, world + 123 - 11.4491 = 12333 test.file \n hello again".to_string());
-    }
-
-    lex = match res {
-        Ok(val) => val,
-        Err(val) => panic!("{}", val),
-    };
-    
-    while !lex.is_done() {
-        println!("{:?}", lex.next());
-    }
-}
@@ -0,0 +1,82 @@
+#![recursion_limit="256"]
+// #![allow(warnings)]
+
+// The Gelli language as a library: the lexer, parser, semantic analyzer,
+//  lint pass, formatter, interpreter and REPL. The command line tool (main.rs)
+//  and the WebAssembly build (WebAssemblyWork/JellyASM/jelly-wasm) are
+//  both built on top of it.
+// To run a program from Rust code, use 'run_source', or use each stage
+//  on its own (like the command line tool does) for more control.
+
+pub mod diagnostic;
+pub mod lexer;
+pub mod parser;
+pub mod visitor;
+pub mod semantic_analyzer;
+pub mod lint;
+pub mod formatter;
+pub mod interpreter;
+pub mod library_handler;
+pub mod host;
+pub mod repl;
+
+pub use crate::diagnostic::{Diagnostic, Diagnostics};
+pub use crate::lexer::Lexer;
+pub use crate::parser::Parser;
+pub use crate::semantic_analyzer::SemanticAnalyzer;
+pub use crate::interpreter::Interpreter;
+pub use crate::host::{Host, MemoryHost, StdioHost};
+pub use crate::library_handler::{FunctionRegistry, NativeFunction};
+pub use crate::lint::LintConfig;
+
+use crate::lint::Linter;
+
+// What happened when a program ran to the end (or quit)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub exit_code: i32,         // 0, or the status given to quit
+    pub warnings: Diagnostics,  // from the lint pass
+}
+
+// Parse, analyze and run a program on a host (like a StdioHost, or a
+//  MemoryHost to give the input and keep the output), with the lints
+//  that are turned on in 'lints' (LintConfig::new() has all of them)
+// Stops at the first stage with errors (all of the syntax errors,
+//  all of the semantic errors, or the runtime error), this is what
+//  "code run" does. A runtime error comes first, followed by the
+//  warnings, since they might be why the program failed
+pub fn run_source<H: Host>(src: &str, host: H, lints: LintConfig) -> Result<Outcome, Diagnostics> {
+    run_source_with(src, host, &FunctionRegistry::builtins(), lints)
+}
+
+// The same as run_source, but the program can only call the functions
+//  in this registry (start from FunctionRegistry::builtins() to add
+//  more functions, like for a robot or a sensor)
+pub fn run_source_with<H: Host>(src: &str, host: H, functions: &FunctionRegistry, lints: LintConfig) -> Result<Outcome, Diagnostics> {
+    let tree = match Parser::new(src.to_string()) {
+        Ok(mut p) => match p.parse() {
+            (Some(tree), errors) if errors.is_empty() => tree,
+            (_, errors) => return Err(errors),
+        },
+        Err(e) => return Err(vec![e]),
+    };
+
+    let (symbol_table, errors) = SemanticAnalyzer::new().with_functions(functions).analyze(&tree);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let warnings = Linter::new(lints).lint(&tree, &symbol_table);
+
+    let mut int = Interpreter::new(host).with_functions(functions.clone());
+    match int.eval(&tree) {
+        Ok(exit_code) => Ok(Outcome {
+            exit_code,
+            warnings,
+        }),
+        Err(e) => {
+            let mut errors = vec![e];
+            errors.extend(warnings);
+            Err(errors)
+        },
+    }
+}
@@ -1,20 +1,14 @@
-use crate::semantic_analyzer::{SymbolType, FunctionObject};
-use crate::interpreter::{LiteralValue};
+use crate::semantic_analyzer::{SymbolType, FunctionObject, ANY_ARRAY, ARRAY_OR_TEXT};
+use crate::interpreter::{LiteralValue, runtime_error};
+use crate::diagnostic::{self, Diagnostic};
+use crate::host::Host;
 use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
 
 // A boolean to determine if debug information should be displayed
 static DEBUG: bool = false;
 
-// Handle error reporting through web assembly
-// For right now we just print the error, but later
-//  on this would be passed to JavaScript code
-macro_rules! log {
-    ($($t:tt)*) => (print!("{}",  &format_args!($ ( $t ) *).to_string() ))
-}
-macro_rules! logln {
-    ($($t:tt)*) => (println!("{}",  &format_args!($ ( $t ) *).to_string() ))
-}
-
 // Handle debugging through web assembly
 // For right now we just print the error, but later
 //  on this would be passed to JavaScript code
@@ -29,70 +23,344 @@ macro_rules! debug {
 // The library handler file is in charge of compiling a collection of
 //  all expected external function calles (or build in library functions)
 //  and defining them in a way that allows for error checking.
+// Each function is registered once, with its types (for the semantic
+//  analyzer) and the Rust code that runs it (for the interpreter).
+// Other crates can make their own libraries (like for a classroom
+//  robot) by registering more functions on top of the built in ones.
+
+// Helpers to build the symbol types used by the function definitions
+pub fn number_type() -> SymbolType {
+    SymbolType {
+        basic_type: "number".to_string(),
+        is_pointer: false,
+        array_dimensions: 0,
+    }
+}
+
+pub fn text_type() -> SymbolType {
+    SymbolType {
+        basic_type: "text".to_string(),
+        is_pointer: false,
+        array_dimensions: 0,
+    }
+}
 
-// Collect and return a list of all external functions.
-// For each function, generage a FunctionObject that the semantic
-//  analyzer can use to ensure that the arguments for the function
-//  are passed correctly.
-pub fn get_external_functions() -> HashMap<String, FunctionObject> {
-    let mut map: HashMap<String, FunctionObject> = HashMap::new();
-
-    // Sample definition of the lowerbound function (lwb)
-    // Note that below in the handle_call function, there is
-    //  no actual implementation for the "lwb" or "upb" function yet.
-    let mut lwb = FunctionObject {
-        params: Vec::new(),
-        return_type: "number".to_string(),
-    };
-    // Here, a * means any data type, and array dimenstions of -1
-    //  allow for any sized array
-    lwb.params.push(SymbolType{
+pub fn nothing_type() -> SymbolType {
+    SymbolType {
+        basic_type: "nothing".to_string(),
+        is_pointer: false,
+        array_dimensions: 0,
+    }
+}
+
+// Here, a * means any data type, and array dimenstions of -1
+//  allow for any sized array (or no array at all)
+pub fn any_type() -> SymbolType {
+    SymbolType {
         basic_type: "*".to_string(),
         is_pointer: false,
         array_dimensions: -1,
-    });
-    map.insert("lower_bound".to_string(), lwb);
-
-    // Sample definition of the upperbound function (upb)
-    let mut upb = FunctionObject {
-        params: Vec::new(),
-        return_type: "number".to_string(),
-    };
-    upb.params.push(SymbolType{
+    }
+}
+
+// Any array, with any number of dimensions and any type of element
+pub fn any_array_type() -> SymbolType {
+    SymbolType {
         basic_type: "*".to_string(),
         is_pointer: false,
-        array_dimensions: -1,
-    });
-    map.insert("upper_bound".to_string(), upb);
+        array_dimensions: ANY_ARRAY,
+    }
+}
+
+// Any array, or text (which is like an array of characters)
+pub fn array_or_text_type() -> SymbolType {
+    SymbolType {
+        basic_type: "*".to_string(),
+        is_pointer: false,
+        array_dimensions: ARRAY_OR_TEXT,
+    }
+}
 
+// Get the one expected argument for a function
+fn single_arg(name: &str, vals: &[LiteralValue]) -> Result<LiteralValue, Diagnostic> {
+    if vals.len() != 1 {
+        return Err(runtime_error(diagnostic::INVALID_ARGUMENT, format!{"Function {} expected 1 argument, got {}", name, vals.len()}));
+    }
 
-    map
+    Ok(vals[0].clone())
 }
 
-// Actually handle a function call. The arguments for the function come in from
-//  the interpreter as a LiteralValue. By defining the arguments in "get_external_functions" above,
-//  you can ensure that the arguments will match the expected types. Without a definition above,
-//  any set of arguments will be accpeted as valid (like with the "print" function)
-pub fn handle_call(name: String, vals: Vec<LiteralValue>) -> Result<LiteralValue, String> {
-    debug!{"Attempting to call external function {}", name};
+// Get the one expected number argument for a function
+fn number_arg(name: &str, vals: &[LiteralValue]) -> Result<f64, Diagnostic> {
+    match single_arg(name, vals)?.extract_number() {
+        Some(n) => Ok(n),
+        None => Err(runtime_error(diagnostic::INVALID_ARGUMENT, format!{"Function {} expected a number", name})),
+    }
+}
+
+// Get the one expected array argument for a function
+fn array_arg(name: &str, vals: &[LiteralValue]) -> Result<LiteralValue, Diagnostic> {
+    let val = single_arg(name, vals)?;
+    if !val.is_array() {
+        return Err(runtime_error(diagnostic::INVALID_ARGUMENT, format!{"Function {} expected an array", name}));
+    }
+
+    Ok(val)
+}
+
+// Count every element in an array (in every dimension)
+fn count_elements(val: &LiteralValue) -> usize {
+    if !val.is_array() {
+        return 1;
+    }
+
+    val.extract_values().unwrap().iter().map(count_elements).sum()
+}
+
+// Read a line from the user (without the newline)
+fn read_line(host: &mut dyn Host) -> Result<String, Diagnostic> {
+    host.read_line().map_err(|e| runtime_error(diagnostic::INVALID_INPUT, format!{"Could not read input: {}", e}))
+}
+
+// ====================
+// =     REGISTRY     =
+// ====================
+
+// The Rust code that runs a function, given the host the program runs on
+//  and the values of the arguments
+pub type NativeCall = Rc<dyn Fn(&mut dyn Host, &[LiteralValue]) -> Result<LiteralValue, Diagnostic>>;
+
+// A function written in Rust that Gelli code can call
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub signature: FunctionObject,
+    pub call: NativeCall,
+    // Arrays are given as their bounds (LiteralValue::from_bounds)
+    //  instead of their values, so they don't need values yet
+    pub bounds_only: bool,
+}
+
+impl NativeFunction {
+    pub fn new<F>(params: Vec<SymbolType>, return_type: SymbolType, call: F) -> Self
+    where F: Fn(&mut dyn Host, &[LiteralValue]) -> Result<LiteralValue, Diagnostic> + 'static {
+        NativeFunction {
+            signature: FunctionObject {
+                params,
+                return_type,
+                variadic: false,
+                optional: false,
+            },
+            call: Rc::new(call),
+            bounds_only: false,
+        }
+    }
+
+    // The last parameter can be given any number of times (even none)
+    pub fn variadic(mut self) -> Self {
+        self.signature.variadic = true;
+        self
+    }
 
-    match name.as_str() {
-        // This is the "print" function. It also accepts the name "display". 
+    // The parameters can all be left off (but not just some of them)
+    pub fn optional(mut self) -> Self {
+        self.signature.optional = true;
+        self
+    }
+
+    // The function only needs the bounds of the arrays it is given
+    pub fn bounds_only(mut self) -> Self {
+        self.bounds_only = true;
+        self
+    }
+}
+
+// Every function written in Rust that a program can call, by name
+#[derive(Clone, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, NativeFunction>,
+}
+
+impl FunctionRegistry {
+    // A registry without any functions (not even print)
+    pub fn new() -> Self {
+        FunctionRegistry {
+            functions: HashMap::new(),
+        }
+    }
+
+    // Add a function, replacing any function with the same name
+    pub fn register(&mut self, name: &str, function: NativeFunction) -> &mut Self {
+        self.functions.insert(name.to_string(), function);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NativeFunction> {
+        self.functions.get(name)
+    }
+
+    // The types of every function, for the semantic analyzer
+    pub fn signatures(&self) -> HashMap<String, FunctionObject> {
+        self.functions.iter()
+            .map(|(name, function)| (name.clone(), function.signature.clone()))
+            .collect()
+    }
+
+    // The functions every Gelli program can use
+    pub fn builtins() -> Self {
+        let mut registry = FunctionRegistry::new();
+
+        // This is the "print" function. It also accepts the name "display_line".
         // Each argument (or literal value) is converted to a string and printed.
-        "print" | "display" => {
+        // Displaying output accepts any number of values of any type
+        for name in ["print", "display_line"] {
+            registry.register(name, NativeFunction::new(vec![any_type()], nothing_type(), |host, vals| {
+                for val in vals {
+                    host.write(&val.to_string());
+                }
+                host.write("\n");
+                Ok(LiteralValue::null())
+            }).variadic());
+        }
+
+        // Display is the same as print, but stays on the same line
+        registry.register("display", NativeFunction::new(vec![any_type()], nothing_type(), |host, vals| {
             for val in vals {
-                log!{"{}", val.to_string()};
+                host.write(&val.to_string());
             }
-            logln!{""};
-            return Ok(LiteralValue::null());
-        },
+            Ok(LiteralValue::null())
+        }).variadic());
 
-        // Other external (or library) functions would be handled here
-        // "function_name" => { //todo },
+        // The number of characters in text, or elements in an array
+        registry.register("length", NativeFunction::new(vec![array_or_text_type()], number_type(), |_, vals| {
+            let val = single_arg("length", vals)?;
+            if let Some(t) = val.extract_text() {
+                return Ok(LiteralValue::from_number(t.chars().count() as f64));
+            }
+            if !val.is_array() {
+                return Err(runtime_error(diagnostic::INVALID_ARGUMENT, "Function length expected an array or text".to_string()));
+            }
+            Ok(LiteralValue::from_number(count_elements(&val) as f64))
+        }).bounds_only());
+
+        // The size of each dimension of an array
+        // Text only has one dimension, its length
+        let mut dimensions_type = number_type();
+        dimensions_type.array_dimensions = 1;
+        registry.register("dimensions", NativeFunction::new(vec![array_or_text_type()], dimensions_type, |_, vals| {
+            let val = single_arg("dimensions", vals)?;
+            if let Some(t) = val.extract_text() {
+                return Ok(LiteralValue::from_array(vec![LiteralValue::from_number(t.chars().count() as f64)]));
+            }
 
+            let mut val = array_arg("dimensions", vals)?;
+            let mut dims: Vec<LiteralValue> = Vec::new();
+
+            while val.is_array() {
+                let len = val.extract_values().unwrap().len();
+                dims.push(LiteralValue::from_number(len as f64));
+
+                if len == 0 {
+                    break;
+                }
+                val = val.extract_values().unwrap()[0].clone();
+            }
 
-        // In the default case, we are just returning null. For proper handling, an error
-        //  should be genrated and returned that alerts the user of an invalid function call.
-        _ => { return Ok(LiteralValue::null()); }
+            Ok(LiteralValue::from_array(dims))
+        }).bounds_only());
+
+        registry.register("lower_bound", NativeFunction::new(vec![any_array_type()], number_type(), |_, vals| {
+            let bounds = array_arg("lower_bound", vals)?.extract_bounds().unwrap();
+            Ok(LiteralValue::from_number(bounds.0 as f64))
+        }).bounds_only());
+
+        registry.register("upper_bound", NativeFunction::new(vec![any_array_type()], number_type(), |_, vals| {
+            let bounds = array_arg("upper_bound", vals)?.extract_bounds().unwrap();
+            Ok(LiteralValue::from_number(bounds.1 as f64))
+        }).bounds_only());
+
+        // Input
+        registry.register("input_number", NativeFunction::new(Vec::new(), number_type(), |host, _| {
+            let line = read_line(host)?;
+            match line.trim().parse::<f64>() {
+                Ok(n) => Ok(LiteralValue::from_number(n)),
+                Err(_) => Err(runtime_error(diagnostic::INVALID_INPUT, format!{"Expected a number to be entered, got '{}'", line})),
+            }
+        }));
+
+        registry.register("input_text", NativeFunction::new(Vec::new(), text_type(), |host, _| {
+            Ok(LiteralValue::from_text(read_line(host)?))
+        }));
+
+        // Waiting (in seconds)
+        registry.register("wait", NativeFunction::new(vec![number_type()], nothing_type(), |host, vals| {
+            let seconds = number_arg("wait", vals)?;
+            if seconds.is_nan() {
+                return Err(runtime_error(diagnostic::INVALID_ARGUMENT, "Cannot wait for a time that is not a number".to_string()));
+            }
+            if seconds < 0.0 {
+                return Err(runtime_error(diagnostic::INVALID_ARGUMENT, "Cannot wait for a negative amount of time".to_string()));
+            }
+            if Duration::try_from_secs_f64(seconds).is_err() {
+                return Err(runtime_error(diagnostic::INVALID_ARGUMENT, format!{"Cannot wait for {} seconds, it is too long", seconds}));
+            }
+            host.sleep(seconds);
+            Ok(LiteralValue::null())
+        }));
+
+        // Trig and rounding all take in one number and give back a number
+        type MathFn = fn(f64) -> f64;
+        let math: [(&str, MathFn); 12] = [
+            ("sin", f64::sin),
+            ("cos", f64::cos),
+            ("tan", f64::tan),
+            ("csc", |n| 1.0 / n.sin()),
+            ("sec", |n| 1.0 / n.cos()),
+            ("cot", |n| 1.0 / n.tan()),
+            ("asin", f64::asin),
+            ("acos", f64::acos),
+            ("atan", f64::atan),
+            ("round", f64::round),
+            ("floor", f64::floor),
+            ("ceil", f64::ceil),
+        ];
+        for (name, function) in math {
+            registry.register(name, NativeFunction::new(vec![number_type()], number_type(), move |_, vals| {
+                Ok(LiteralValue::from_number(function(number_arg(name, vals)?)))
+            }));
+        }
+
+        // With no bounds, this is a number from 0 up to 1
+        // With bounds, this is a whole number from min to max (including both)
+        registry.register("random_number", NativeFunction::new(vec![number_type(), number_type()], number_type(), |host, vals| {
+            if vals.is_empty() {
+                return Ok(LiteralValue::from_number(host.random()));
+            }
+            if vals.len() != 2 {
+                return Err(runtime_error(diagnostic::INVALID_ARGUMENT, "Function random_number expected no arguments, or a min and a max".to_string()));
+            }
+
+            let min = vals[0].extract_number().unwrap_or(0.0).ceil();
+            let max = vals[1].extract_number().unwrap_or(0.0).floor();
+            if min > max {
+                return Err(runtime_error(diagnostic::INVALID_ARGUMENT, format!{"Function random_number cannot pick a whole number from {} to {}", min, max}));
+            }
+
+            let n = min + (host.random() * (max - min + 1.0)).floor();
+            Ok(LiteralValue::from_number(n))
+        }).optional());
+
+        registry
+    }
+}
+
+// Actually handle a function call. The arguments for the function come in from
+//  the interpreter as a LiteralValue. By registering the function with its types,
+//  you can ensure that the arguments will match the expected types.
+pub fn handle_call(registry: &FunctionRegistry, host: &mut dyn Host, name: &str, vals: &[LiteralValue]) -> Result<LiteralValue, Diagnostic> {
+    debug!{"Attempting to call external function {}", name};
+
+    match registry.get(name) {
+        Some(function) => (function.call)(host, vals),
+        None => Err(runtime_error(diagnostic::INVALID_OPERATION, format!{"Unknown function {}", name})),
     }
-}
\ No newline at end of file
+}
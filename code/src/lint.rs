@@ -0,0 +1,472 @@
+use std::env;
+use std::collections::{HashMap, HashSet};
+use crate::parser::{Block, Condition, ConditionKind, Else, Expr, ExprKind, FunctionDef, Ident, ParseTree, Reference, ReferenceKind, Stmt, StmtKind, Target, Type, TypeKind, VarDef};
+use crate::diagnostic::{self, Diagnostic, Span, Stage};
+use crate::semantic_analyzer::{SymbolTable};
+use indexmap::{IndexMap};
+
+// The lint pass runs after the semantic analyzer has accepted the
+//  program. It doesn't find errors, only code that is allowed but is
+//  probably a mistake (like a variable that is never used), and
+//  reports it as a warning. The program still runs.
+
+// The environment variable used to turn lints off, it is
+//  a comma separated list of lint names (see LintConfig::set)
+static ALLOW_VAR: &str = "GELLI_ALLOW";
+
+// Which lints are turned on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintConfig {
+    pub unused_variable: bool,    // variables declared but never read
+    pub unused_parameter: bool,   // parameters never used
+    pub unreachable_code: bool,   // statements after return, break, continue, or quit
+    pub shadowed_global: bool,    // local variables with the same name as a global
+    pub uncalled_function: bool,  // functions that are never called
+    pub constant_condition: bool, // while conditions that never change
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LintConfig {
+    // Every lint is on by default
+    pub fn new() -> Self {
+        LintConfig {
+            unused_variable: true,
+            unused_parameter: true,
+            unreachable_code: true,
+            shadowed_global: true,
+            uncalled_function: true,
+            constant_condition: true,
+        }
+    }
+
+    // Start with every lint on, then turn off the lints
+    //  listed in GELLI_ALLOW (unknown names are ignored)
+    pub fn from_env() -> Self {
+        let mut config = LintConfig::new();
+        if let Ok(allowed) = env::var(ALLOW_VAR) {
+            for name in allowed.split(',') {
+                config.set(name.trim(), false);
+            }
+        }
+        config
+    }
+
+    // Turn a lint on or off by name, returns false if there
+    //  is no lint with that name
+    pub fn set(&mut self, name: &str, on: bool) -> bool {
+        match name {
+            "unused-variable" => self.unused_variable = on,
+            "unused-parameter" => self.unused_parameter = on,
+            "unreachable-code" => self.unreachable_code = on,
+            "shadowed-global" => self.shadowed_global = on,
+            "uncalled-function" => self.uncalled_function = on,
+            "constant-condition" => self.constant_condition = on,
+            _ => return false,
+        }
+        true
+    }
+}
+
+// A name that was declared in a scope, and if it has been read yet
+// Giving a value to a changeable parameter or a link variable changes
+//  a variable somewhere else, so for those a write counts as a use
+#[derive(Clone, Debug)]
+struct Local {
+    span: Span,
+    is_param: bool,
+    writes_through: bool,
+    read: bool,
+}
+
+pub struct Linter {
+    config: LintConfig,
+    globals: HashSet<String>,
+    scopes: Vec<IndexMap<String, Local>>,
+    calls: HashMap<String, HashSet<String>>, // function name -> the names of the callers
+    current_function: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+    pub fn new(config: LintConfig) -> Self {
+        Linter {
+            config,
+            globals: HashSet::new(),
+            scopes: Vec::new(),
+            calls: HashMap::new(),
+            current_function: String::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    // Lint a tree that has passed the semantic analyzer, using the
+    //  symbol table it gave back, and give back every warning
+    pub fn lint(&mut self, tree: &ParseTree, symbol_table: &SymbolTable) -> Vec<Diagnostic> {
+        // The outermost scope of the symbol table holds the globals
+        self.globals = symbol_table.symbols[0].keys().cloned().collect();
+
+        // The globals and the program share the outer scope
+        self.scope_in();
+
+        for global in &tree.globals {
+            self.lint_statement(global);
+        }
+
+        for fun_def in &tree.functions {
+            self.lint_function(fun_def);
+        }
+
+        // BODY (program section)
+        self.current_function = String::new();
+        self.lint_block(&tree.body);
+
+        // Globals can be used anywhere, so they are checked last
+        self.scope_out();
+
+        if self.config.uncalled_function {
+            for id in tree.functions.iter().filter_map(|f| f.name.as_ref()) {
+                // Calling itself doesn't count
+                let called = match self.calls.get(&id.name) {
+                    Some(callers) => callers.iter().any(|caller| *caller != id.name),
+                    None => false,
+                };
+                if !called {
+                    self.warn(id.span, diagnostic::UNCALLED_FUNCTION, format!{"Function '{}' is never called", id.name}, "uncalled-function");
+                }
+            }
+        }
+
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    // Record a warning
+    fn warn(&mut self, span: Span, code: &'static str, message: String, lint: &str) {
+        self.diagnostics.push(warning(span, code, message, lint));
+    }
+
+    fn scope_in(&mut self) {
+        self.scopes.push(IndexMap::new());
+    }
+
+    // Leaving a scope is when we know if its names were ever read
+    fn scope_out(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+        for (name, local) in scope {
+            if local.read {
+                continue;
+            }
+            if local.is_param && self.config.unused_parameter {
+                self.warn(local.span, diagnostic::UNUSED_PARAMETER, format!{"Parameter '{}' is never used", name}, "unused-parameter");
+            }
+            else if !local.is_param && self.config.unused_variable {
+                self.warn(local.span, diagnostic::UNUSED_VARIABLE, format!{"Variable '{}' is never used", name}, "unused-variable");
+            }
+        }
+    }
+
+    // Add a name to the current scope
+    fn declare(&mut self, id: &Ident, is_param: bool, writes_through: bool) {
+        // The outer scope is the global scope
+        if self.scopes.len() > 1 && self.globals.contains(&id.name) && self.config.shadowed_global {
+            self.warn(id.span, diagnostic::SHADOWED_GLOBAL, format!{"'{}' hides the global variable with the same name", id.name}, "shadowed-global");
+        }
+
+        self.scopes.last_mut().unwrap().insert(id.name.clone(), Local {
+            span: id.span,
+            is_param,
+            writes_through,
+            read: false,
+        });
+    }
+
+    // Mark the closest name in scope as read
+    fn read(&mut self, name: &str) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(local) = scope.get_mut(name) {
+                local.read = true;
+                return;
+            }
+        }
+    }
+
+    // Mark the closest name in scope as read, if giving it a value
+    //  changes something outside of it
+    fn write(&mut self, name: &str) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(local) = scope.get_mut(name) {
+                local.read = local.read || local.writes_through;
+                return;
+            }
+        }
+    }
+
+    fn lint_function(&mut self, fun_def: &FunctionDef) {
+        self.current_function = match &fun_def.name {
+            Some(id) => id.name.clone(),
+            None => String::new(),
+        };
+
+        // The parameters and the body share a scope
+        self.scope_in();
+        for param in &fun_def.params {
+            let is_link = matches!(param.param_type.kind, TypeKind::LINK(_));
+            self.declare(&param.name, true, param.changeable || is_link);
+        }
+        self.lint_block(&fun_def.body);
+        self.scope_out();
+    }
+
+    // Check each statement in a block, and that nothing comes after
+    //  a statement that leaves the block
+    fn lint_block(&mut self, block: &Block) {
+        let mut leaves_block: Option<&Stmt> = None;
+
+        for statement in &block.statements {
+            if let Some(exit) = leaves_block {
+                if self.config.unreachable_code {
+                    let unreachable = warning(statement.head(), diagnostic::UNREACHABLE_CODE, "This code will never run".to_string(), "unreachable-code")
+                        .with_label(exit.span, format!{"the block is always left by this {}", exit_name(exit)});
+                    self.diagnostics.push(unreachable);
+                }
+                // Only the first statement is reported
+                leaves_block = None;
+                self.lint_statement(statement);
+                continue;
+            }
+
+            self.lint_statement(statement);
+
+            match statement.kind {
+                StmtKind::RETURN(_) | StmtKind::BREAK | StmtKind::CONTINUE | StmtKind::QUIT(_) => {
+                    leaves_block = Some(statement);
+                },
+                _ => (),
+            }
+        }
+    }
+
+    // Lint a block inside of its own scope
+    fn lint_scoped_block(&mut self, block: &Block) {
+        self.scope_in();
+        self.lint_block(block);
+        self.scope_out();
+    }
+
+    fn lint_statement(&mut self, statement: &Stmt) {
+        match &statement.kind {
+            StmtKind::VARDEF(var_def) => self.lint_vardef(var_def),
+            StmtKind::ASSIGN(target, value) => {
+                self.lint_target(target, true);
+                self.lint_uses(value);
+            },
+            StmtKind::LINK(target, linked) => {
+                // Linking a name only changes where it points
+                self.lint_target(target, false);
+                if let Some(reference) = linked {
+                    self.lint_reference(reference);
+                }
+            },
+            StmtKind::IF(if_stmt) => {
+                let mut if_stmt = if_stmt;
+                loop {
+                    self.lint_condition(&if_stmt.condition);
+                    self.lint_scoped_block(&if_stmt.body);
+                    match &if_stmt.otherwise {
+                        Some(Else::IF(else_if)) => if_stmt = else_if,
+                        Some(Else::BLOCK(else_block)) => {
+                            self.lint_scoped_block(else_block);
+                            break;
+                        },
+                        None => break,
+                    }
+                }
+            },
+            StmtKind::WHILE(condition, body) => {
+                if self.config.constant_condition && is_constant_condition(condition) {
+                    self.warn(condition.span, diagnostic::CONSTANT_CONDITION, "This condition is always the same, so the loop will run forever or never".to_string(), "constant-condition");
+                }
+                self.lint_condition(condition);
+                self.lint_scoped_block(body);
+            },
+            StmtKind::REPEAT(count, body) => {
+                self.lint_uses(count);
+                self.lint_scoped_block(body);
+            },
+            StmtKind::REPEATFOR(variable, values, body) => {
+                self.lint_uses(values);
+                self.scope_in();
+                if let Some(id) = variable {
+                    self.declare(id, false, false);
+                }
+                self.lint_block(body);
+                self.scope_out();
+            },
+            StmtKind::REPEATFOREVER(body) => {
+                self.lint_scoped_block(body);
+            },
+            StmtKind::UNLINK(reference) => self.lint_reference(reference),
+            StmtKind::RETURN(Some(value)) | StmtKind::QUIT(Some(value)) | StmtKind::EXPR(value) => self.lint_uses(value),
+            StmtKind::RETURN(None) | StmtKind::QUIT(None) | StmtKind::BREAK | StmtKind::CONTINUE | StmtKind::INVALID => (),
+        }
+    }
+
+    // The left side of an assignment or link
+    // 'to_name' is if giving a value to just a name writes through it
+    fn lint_target(&mut self, target: &Target, to_name: bool) {
+        match target {
+            Target::VARDEF(var_def) => self.lint_vardef(var_def),
+            Target::REFERENCE(Reference { kind: ReferenceKind::NAME(_), .. }) if !to_name => (),
+            Target::REFERENCE(reference) => self.lint_write(reference),
+        }
+    }
+
+    // Giving a variable (or part of it) a value isn't reading it, but
+    //  the indexes along the way are read
+    fn lint_write(&mut self, reference: &Reference) {
+        match &reference.kind {
+            ReferenceKind::NAME(name) => self.write(name),
+            ReferenceKind::INDEX(array, indexes) => {
+                self.lint_write(array);
+                for index in indexes {
+                    self.lint_uses(index);
+                }
+            },
+            ReferenceKind::FIELD(structure, _) => self.lint_write(structure),
+        }
+    }
+
+    fn lint_vardef(&mut self, var_def: &VarDef) {
+        // The type can use variables in its bounds
+        self.lint_type(&var_def.var_type);
+
+        let is_link = matches!(var_def.var_type.kind, TypeKind::LINK(_));
+        for id in &var_def.names {
+            self.declare(id, false, is_link);
+        }
+    }
+
+    fn lint_type(&mut self, type_node: &Type) {
+        match &type_node.kind {
+            TypeKind::LINK(link_type) => self.lint_type(link_type),
+            TypeKind::ARRAY(bounds, _) => {
+                for bound in bounds {
+                    if let Some(start) = &bound.start {
+                        self.lint_uses(start);
+                    }
+                    self.lint_uses(&bound.end);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn lint_condition(&mut self, condition: &Condition) {
+        match &condition.kind {
+            ConditionKind::COMPARE(_, left, right) => {
+                self.lint_uses(left);
+                self.lint_uses(right);
+            },
+            ConditionKind::AND(left, right) | ConditionKind::OR(left, right) => {
+                self.lint_condition(left);
+                self.lint_condition(right);
+            },
+            ConditionKind::ISLINKED(reference) | ConditionKind::ISNOTLINKED(reference) => self.lint_reference(reference),
+            ConditionKind::INVALID => (),
+        }
+    }
+
+    fn lint_reference(&mut self, reference: &Reference) {
+        match &reference.kind {
+            ReferenceKind::NAME(name) => self.read(name),
+            ReferenceKind::INDEX(array, indexes) => {
+                self.lint_reference(array);
+                for index in indexes {
+                    self.lint_uses(index);
+                }
+            },
+            // The key of a structure isn't a variable
+            ReferenceKind::FIELD(structure, _) => self.lint_reference(structure),
+        }
+    }
+
+    // Mark every variable (and function) used in a value
+    fn lint_uses(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::REFERENCE(reference) => self.lint_reference(reference),
+            ExprKind::CALL(name, args) => {
+                self.calls.entry(name.name.clone()).or_default().insert(self.current_function.clone());
+                for arg in args {
+                    self.lint_uses(arg);
+                }
+            },
+            ExprKind::BINARY(_, left, right) => {
+                self.lint_uses(left);
+                self.lint_uses(right);
+            },
+            ExprKind::NEG(value) | ExprKind::ABS(value) | ExprKind::BITNOT(value) => self.lint_uses(value),
+            ExprKind::ARRAY(items) | ExprKind::STRUCTURE(items) => {
+                for item in items {
+                    self.lint_uses(item);
+                }
+            },
+            ExprKind::LINK(Some(reference)) => self.lint_reference(reference),
+            ExprKind::LINK(None) | ExprKind::NUMBER(_) | ExprKind::TEXT(_) | ExprKind::INVALID => (),
+        }
+    }
+}
+
+// Create a warning, with a hint on how to turn the lint off
+fn warning(span: Span, code: &'static str, message: String, lint: &str) -> Diagnostic {
+    Diagnostic::warning(Stage::SEMANTIC, code, message)
+        .with_span(span)
+        .with_help(format!{"add \"{}\" to {} to turn this warning off", lint, ALLOW_VAR})
+}
+
+// The name of a statement that leaves a block
+fn exit_name(statement: &Stmt) -> &'static str {
+    match statement.kind {
+        StmtKind::RETURN(_) => "`return`",
+        StmtKind::BREAK => "`break`",
+        StmtKind::CONTINUE => "`continue`",
+        _ => "`quit`",
+    }
+}
+
+// A condition is constant if it doesn't use any variables or
+//  functions, or it compares something to itself
+fn is_constant_condition(condition: &Condition) -> bool {
+    match &condition.kind {
+        ConditionKind::COMPARE(_, left, right) => is_constant(left) && is_constant(right) || same_value(left, right),
+        // For and/or, both sides have to be constant
+        ConditionKind::AND(left, right) | ConditionKind::OR(left, right) => {
+            is_constant_condition(left) && is_constant_condition(right)
+        },
+        _ => false,
+    }
+}
+
+// Check if a resolvable doesn't use any variables or functions
+fn is_constant(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::REFERENCE(_) | ExprKind::CALL(_, _) | ExprKind::INVALID | ExprKind::LINK(Some(_)) => false,
+        ExprKind::NUMBER(_) | ExprKind::TEXT(_) | ExprKind::LINK(None) => true,
+        ExprKind::BINARY(_, left, right) => is_constant(left) && is_constant(right),
+        ExprKind::NEG(value) | ExprKind::ABS(value) | ExprKind::BITNOT(value) => is_constant(value),
+        ExprKind::ARRAY(items) | ExprKind::STRUCTURE(items) => items.iter().all(is_constant),
+    }
+}
+
+// Check if two resolvables are the same variable or literal
+fn same_value(left: &Expr, right: &Expr) -> bool {
+    match (&left.kind, &right.kind) {
+        (ExprKind::REFERENCE(Reference { kind: ReferenceKind::NAME(l), .. }), ExprKind::REFERENCE(Reference { kind: ReferenceKind::NAME(r), .. })) => l == r,
+        (ExprKind::NUMBER(l), ExprKind::NUMBER(r)) => l == r,
+        (ExprKind::TEXT(l), ExprKind::TEXT(r)) => l == r,
+        _ => false,
+    }
+}
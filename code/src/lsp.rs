@@ -0,0 +1,822 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+use std::process;
+use serde_json::{json, Value};
+use gelli::diagnostic::{Diagnostic, Severity, Span};
+use gelli::parser::{Block, Expr, ExprKind, FunctionDef, Param, ParseTree, Parser, Reference, ReferenceKind, Stmt, StmtKind, StructDef, Type, TypeKind, VarDef};
+use gelli::semantic_analyzer::{self, FunctionObject, SemanticAnalyzer, SymbolTable, SymbolType};
+use gelli::lint::{Linter, LintConfig};
+use gelli::visitor::{self, Visitor};
+
+// =====================
+// =   LANGUAGE SERVER =
+// =====================
+// A Language Server Protocol server, so editors (like VS Code) can
+//  show errors and help while writing Gelli. It speaks JSON-RPC over
+//  stdin/stdout, started with "code lsp".
+// Every time a file changes it is parsed and analyzed again, the
+//  same way as when it is run, and the results are kept to answer
+//  the editor's requests:
+//  - diagnostics (errors and warnings) are sent on every change
+//  - hover shows the type of a name
+//  - go to definition for variables, functions, structures and keys
+//  - completion of keywords, names in scope, and structure keys
+//  - document symbols lists the structures and functions
+
+// The keywords offered by completion
+static KEYWORDS: &[&str] = &[
+    "definitions", "end", "structure", "function", "returns", "return",
+    "changeable", "array", "of", "nothing", "program", "quit", "link",
+    "linked", "unlink", "to", "break", "continue", "number", "text", "if",
+    "then", "else", "while", "repeat", "forever", "times", "for", "all",
+    "in", "and", "or", "is", "not", "mod", "bit_and", "bit_or", "bit_xor",
+    "bit_sl", "bit_sr", "bit_not",
+];
+
+// The kinds of things the editor knows how to show, from the
+//  LSP specification
+const SEVERITY_ERROR: u32 = 1;
+const SEVERITY_WARNING: u32 = 2;
+const COMPLETION_FUNCTION: u32 = 3;
+const COMPLETION_FIELD: u32 = 5;
+const COMPLETION_VARIABLE: u32 = 6;
+const COMPLETION_KEYWORD: u32 = 14;
+const COMPLETION_STRUCT: u32 = 22;
+const SYMBOL_FIELD: u32 = 8;
+const SYMBOL_FUNCTION: u32 = 12;
+const SYMBOL_STRUCT: u32 = 23;
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+// The biggest message the server reads, a bigger Content-Length is
+//  much more likely to be a broken header than a real file
+const MAX_MESSAGE: usize = 8 * 1024 * 1024;
+
+// ====================
+// =   SYMBOL INDEX   =
+// ====================
+// The symbol table only keeps the global names once the analyzer is
+//  done, so the server keeps its own list of every name that is
+//  defined (and where it can be used), and every place a name is used
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+enum DefKind {
+    STRUCTURE,
+    FIELD(String),  // structure name
+    FUNCTION,
+    PARAMETER,
+    VARIABLE,
+}
+
+#[derive(Debug, Clone)]
+struct Definition {
+    name: String,
+    kind: DefKind,
+    symbol_type: Option<SymbolType>,
+    span: Span,     // the name where it is defined
+    scope: Span,    // the code it can be used in
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+enum Usage {
+    VARIABLE(String),
+    FUNCTION(String),
+    STRUCTURE(String),
+    FIELD(Reference, String),   // structure and key
+}
+
+#[derive(Debug, Clone)]
+struct Occurrence {
+    span: Span,
+    usage: Usage,
+}
+
+#[derive(Debug, Default)]
+struct SymbolIndex {
+    definitions: Vec<Definition>,
+    occurrences: Vec<Occurrence>,
+}
+
+impl SymbolIndex {
+    fn build(tree: &ParseTree, symbol_table: &SymbolTable) -> Self {
+        let mut builder = IndexBuilder {
+            index: SymbolIndex::default(),
+            symbol_table,
+            scopes: vec![tree.span],
+        };
+        builder.visit_parse_tree(tree);
+        builder.index
+    }
+
+    // The variable (or parameter) a name means at a byte offset,
+    //  if there is more than one the innermost one wins
+    fn find_variable(&self, name: &str, offset: usize) -> Option<&Definition> {
+        self.definitions.iter()
+            .filter(|d| d.name == name && (d.kind == DefKind::VARIABLE || d.kind == DefKind::PARAMETER))
+            .filter(|d| d.scope.start <= offset && offset <= d.scope.end)
+            .max_by_key(|d| d.scope.start)
+    }
+
+    // Every variable that can be used at a byte offset
+    fn variables_at(&self, offset: usize) -> Vec<&Definition> {
+        let mut found: Vec<&Definition> = Vec::new();
+        for def in &self.definitions {
+            if (def.kind == DefKind::VARIABLE || def.kind == DefKind::PARAMETER) &&
+               !found.iter().any(|d| d.name == def.name) {
+                if let Some(d) = self.find_variable(&def.name, offset) {
+                    found.push(d);
+                }
+            }
+        }
+        found
+    }
+
+    fn find(&self, name: &str, kind: DefKind) -> Option<&Definition> {
+        self.definitions.iter().find(|d| d.name == name && d.kind == kind)
+    }
+
+    // The type of a reference used at a byte offset
+    fn reference_type(&self, symbol_table: &SymbolTable, reference: &Reference, offset: usize) -> Option<SymbolType> {
+        match &reference.kind {
+            ReferenceKind::NAME(name) => self.find_variable(name, offset)?.symbol_type.clone(),
+            ReferenceKind::INDEX(array, indexes) => {
+                let array_type = self.reference_type(symbol_table, array, offset)?;
+                Some(index_type(array_type, indexes.len()))
+            },
+            ReferenceKind::FIELD(structure, key) => {
+                let struct_type = self.reference_type(symbol_table, structure, offset)?;
+                key_type(symbol_table, &struct_type, &key.name)
+            },
+        }
+    }
+}
+
+// The type left after indexing into an array (or text)
+fn index_type(mut array_type: SymbolType, indexes: usize) -> SymbolType {
+    if array_type.basic_type == "text" && array_type.array_dimensions == 0 {
+        return array_type;
+    }
+    array_type.array_dimensions = (array_type.array_dimensions - indexes as i32).max(0);
+    array_type
+}
+
+// The type of a key of a structure type
+fn key_type(symbol_table: &SymbolTable, struct_type: &SymbolType, key: &str) -> Option<SymbolType> {
+    if struct_type.array_dimensions != 0 {
+        return None;
+    }
+    symbol_table.struct_args.get(&struct_type.basic_type)?.get(key).cloned()
+}
+
+struct IndexBuilder<'a> {
+    index: SymbolIndex,
+    symbol_table: &'a SymbolTable,
+    // The spans of the blocks (and functions) the walk is inside of,
+    //  a name defined inside one can be used until its end
+    scopes: Vec<Span>,
+}
+
+impl<'a> IndexBuilder<'a> {
+    fn define(&mut self, name: &str, kind: DefKind, symbol_type: Option<SymbolType>, span: Span, scope: Span) {
+        self.index.definitions.push(Definition {
+            name: name.to_string(),
+            kind,
+            symbol_type,
+            span,
+            scope,
+        });
+    }
+
+    fn used(&mut self, span: Span, usage: Usage) {
+        self.index.occurrences.push(Occurrence {
+            span,
+            usage,
+        });
+    }
+
+    // From a name to the end of the scope it is defined in
+    fn scope_from(&self, span: Span) -> Span {
+        span.to(*self.scopes.last().unwrap())
+    }
+
+    // The type of the values of a "repeat for all" loop
+    fn values_type(&self, values: &Expr) -> Option<SymbolType> {
+        match &values.kind {
+            ExprKind::REFERENCE(reference) => self.index.reference_type(self.symbol_table, reference, values.span.start),
+            ExprKind::CALL(name, _) => self.symbol_table.functions.get(&name.name).map(|f| f.return_type.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Visitor for IndexBuilder<'a> {
+    fn visit_struct_def(&mut self, struct_def: &StructDef) {
+        let file = self.scopes[0];
+        if let Some(name) = &struct_def.name {
+            self.define(&name.name, DefKind::STRUCTURE, None, name.span, file);
+            for field in &struct_def.fields {
+                let field_type = semantic_analyzer::symbol_type_of(&field.field_type);
+                self.define(&field.name.name, DefKind::FIELD(name.name.clone()), Some(field_type), field.name.span, file);
+            }
+        }
+        visitor::walk_struct_def(self, struct_def);
+    }
+
+    fn visit_function_def(&mut self, fun_def: &FunctionDef) {
+        let file = self.scopes[0];
+        if let Some(name) = &fun_def.name {
+            let return_type = semantic_analyzer::symbol_type_of(&fun_def.return_type);
+            self.define(&name.name, DefKind::FUNCTION, Some(return_type), name.span, file);
+        }
+        self.scopes.push(fun_def.span);
+        visitor::walk_function_def(self, fun_def);
+        self.scopes.pop();
+    }
+
+    fn visit_param(&mut self, param: &Param) {
+        let param_type = semantic_analyzer::symbol_type_of(&param.param_type);
+        let scope = self.scope_from(param.name.span);
+        self.define(&param.name.name, DefKind::PARAMETER, Some(param_type), param.name.span, scope);
+        visitor::walk_param(self, param);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.scopes.push(block.span);
+        visitor::walk_block(self, block);
+        self.scopes.pop();
+    }
+
+    fn visit_stmt(&mut self, statement: &Stmt) {
+        // The loop variable can only be used inside of the loop
+        if let StmtKind::REPEATFOR(Some(id), values, body) = &statement.kind {
+            self.visit_expr(values);
+            let value_type = self.values_type(values).map(|t| index_type(t, 1));
+            self.define(&id.name, DefKind::VARIABLE, value_type, id.span, id.span.to(statement.span));
+            self.visit_block(body);
+            return;
+        }
+        visitor::walk_stmt(self, statement);
+    }
+
+    fn visit_var_def(&mut self, var_def: &VarDef) {
+        let var_type = semantic_analyzer::symbol_type_of(&var_def.var_type);
+        for name in &var_def.names {
+            let scope = self.scope_from(name.span);
+            self.define(&name.name, DefKind::VARIABLE, Some(var_type.clone()), name.span, scope);
+        }
+        visitor::walk_var_def(self, var_def);
+    }
+
+    fn visit_type(&mut self, type_node: &Type) {
+        if let TypeKind::STRUCTURE(name) = &type_node.kind {
+            self.used(type_node.span, Usage::STRUCTURE(name.clone()));
+        }
+        visitor::walk_type(self, type_node);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::CALL(name, _) = &expr.kind {
+            self.used(name.span, Usage::FUNCTION(name.name.clone()));
+        }
+        visitor::walk_expr(self, expr);
+    }
+
+    fn visit_reference(&mut self, reference: &Reference) {
+        match &reference.kind {
+            ReferenceKind::NAME(name) => self.used(reference.span, Usage::VARIABLE(name.clone())),
+            ReferenceKind::FIELD(structure, key) => self.used(key.span, Usage::FIELD((**structure).clone(), key.name.clone())),
+            ReferenceKind::INDEX(_, _) => (),
+        }
+        visitor::walk_reference(self, reference);
+    }
+}
+
+// ====================
+// =    DOCUMENTS     =
+// ====================
+
+// An open file, and everything found when it was last analyzed
+struct Document {
+    text: String,
+    tree: Option<ParseTree>,
+    symbol_table: SymbolTable,
+    index: SymbolIndex,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Document {
+    fn new(text: String) -> Self {
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        let tree = match Parser::new(text.clone()) {
+            Ok(mut p) => {
+                let (tree, errors) = p.parse();
+                diagnostics = errors;
+                tree
+            },
+            Err(e) => {
+                diagnostics.push(e);
+                None
+            },
+        };
+
+        // The analyzer still fills in the symbol table for a tree with
+        //  syntax errors, but (like when running a file) its errors are
+        //  only shown once there are no syntax errors, and the warnings
+        //  once there are no errors at all
+        let mut symbol_table = SymbolTable::new();
+        let mut index = SymbolIndex::default();
+        if let Some(tree) = &tree {
+            let (table, errors) = SemanticAnalyzer::new().analyze(tree);
+            if diagnostics.is_empty() {
+                if errors.is_empty() {
+                    diagnostics = Linter::new(LintConfig::from_env()).lint(tree, &table);
+                }
+                else {
+                    diagnostics = errors;
+                }
+            }
+            index = SymbolIndex::build(tree, &table);
+            symbol_table = table;
+        }
+
+        Document {
+            text,
+            tree,
+            symbol_table,
+            index,
+            diagnostics,
+        }
+    }
+
+    // The byte offset of an LSP position (a zero based line and
+    //  character, where the character is counted in UTF-16 code units)
+    fn offset(&self, position: &Value) -> usize {
+        let line = position["line"].as_u64().unwrap_or(0) as usize;
+        let character = position["character"].as_u64().unwrap_or(0) as usize;
+        let mut offset = 0;
+        for (i, text) in self.text.split('\n').enumerate() {
+            if i == line {
+                let mut units = 0;
+                for (b, c) in text.char_indices() {
+                    if units >= character {
+                        return offset + b;
+                    }
+                    units += c.len_utf16();
+                }
+                return offset + text.len();
+            }
+            offset += text.len() + 1;
+        }
+        self.text.len()
+    }
+
+    // The LSP position of a byte offset, the opposite of 'offset'
+    fn position(&self, offset: usize) -> Value {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let before = &self.text[..offset];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        json!({
+            "line": before.matches('\n').count(),
+            "character": before[line_start..].encode_utf16().count(),
+        })
+    }
+
+    // An LSP range is zero based, with the end one past the last character
+    fn range(&self, span: Span) -> Value {
+        json!({ "start": self.position(span.start), "end": self.position(span.end) })
+    }
+
+    // The smallest use of a name that covers an offset
+    fn occurrence_at(&self, offset: usize) -> Option<&Occurrence> {
+        self.index.occurrences.iter()
+            .filter(|o| o.span.start <= offset && offset <= o.span.end)
+            .min_by_key(|o| o.span.end - o.span.start)
+    }
+
+    fn definition_at(&self, offset: usize) -> Option<&Definition> {
+        self.index.definitions.iter()
+            .find(|d| d.span.start <= offset && offset <= d.span.end)
+    }
+
+    // What a use of a name refers to
+    fn definition_of(&self, occurrence: &Occurrence) -> Option<&Definition> {
+        let offset = occurrence.span.start;
+        match &occurrence.usage {
+            Usage::VARIABLE(name) => self.index.find_variable(name, offset),
+            Usage::FUNCTION(name) => self.index.find(name, DefKind::FUNCTION),
+            Usage::STRUCTURE(name) => self.index.find(name, DefKind::STRUCTURE),
+            Usage::FIELD(structure, key) => {
+                let struct_type = self.index.reference_type(&self.symbol_table, structure, offset)?;
+                self.index.find(key, DefKind::FIELD(struct_type.basic_type))
+            },
+        }
+    }
+
+    // ===================
+    // =    REQUESTS     =
+    // ===================
+
+    fn hover(&self, position: &Value) -> Value {
+        let offset = self.offset(position);
+        let (span, text) = if let Some(occurrence) = self.occurrence_at(offset) {
+            let text = match &occurrence.usage {
+                Usage::VARIABLE(name) => self.index.find_variable(name, offset)
+                    .and_then(|d| d.symbol_type.as_ref())
+                    .map(|t| format!{"{}: {}", name, t}),
+                Usage::FUNCTION(name) => self.symbol_table.functions.get(name).map(|f| format!{"function {}", f.signature(name)}),
+                Usage::STRUCTURE(name) => self.structure_text(name),
+                Usage::FIELD(structure, key) => self.index.reference_type(&self.symbol_table, structure, offset)
+                    .and_then(|t| key_type(&self.symbol_table, &t, key))
+                    .map(|t| format!{"{}: {}", key, t}),
+            };
+            (occurrence.span, text)
+        }
+        else if let Some(def) = self.definition_at(offset) {
+            let text = match &def.kind {
+                DefKind::FUNCTION => self.symbol_table.functions.get(&def.name).map(|f| format!{"function {}", f.signature(&def.name)}),
+                DefKind::STRUCTURE => self.structure_text(&def.name),
+                _ => def.symbol_type.as_ref().map(|t| format!{"{}: {}", def.name, t}),
+            };
+            (def.span, text)
+        }
+        else {
+            return Value::Null;
+        };
+
+        match text {
+            Some(text) => json!({
+                "contents": { "kind": "markdown", "value": format!{"```gelli\n{}\n```", text} },
+                "range": self.range(span),
+            }),
+            None => Value::Null,
+        }
+    }
+
+    fn structure_text(&self, name: &str) -> Option<String> {
+        let keys = self.symbol_table.struct_args.get(name)?;
+        let mut text = format!{"structure {}", name};
+        for (key, key_type) in keys {
+            text += &format!{"\n    {}: {}", key, key_type};
+        }
+        Some(text + "\nend structure")
+    }
+
+    fn definition(&self, uri: &str, position: &Value) -> Value {
+        let offset = self.offset(position);
+        let def = match self.occurrence_at(offset) {
+            Some(occurrence) => self.definition_of(occurrence),
+            None => self.definition_at(offset),
+        };
+        match def {
+            Some(def) => json!({ "uri": uri, "range": self.range(def.span) }),
+            None => Value::Null,
+        }
+    }
+
+    fn completion(&self, position: &Value) -> Value {
+        let offset = self.offset(position);
+        let before = &self.text[..offset];
+
+        // After a '.' only the keys of the structure make sense
+        let word = before.trim_end_matches(|c: char| c.is_ascii_alphanumeric() || c == '_');
+        if let Some(chain) = word.strip_suffix('.') {
+            let items: Vec<Value> = match self.chain_type(chain, offset) {
+                Some(struct_type) if struct_type.array_dimensions == 0 => {
+                    match self.symbol_table.struct_args.get(&struct_type.basic_type) {
+                        Some(keys) => keys.iter().map(|(key, key_type)| completion_item(key, COMPLETION_FIELD, key_type.to_string())).collect(),
+                        None => Vec::new(),
+                    }
+                },
+                _ => Vec::new(),
+            };
+            return Value::Array(items);
+        }
+
+        let mut items: Vec<Value> = KEYWORDS.iter().map(|k| completion_item(k, COMPLETION_KEYWORD, String::new())).collect();
+        for def in self.index.variables_at(offset) {
+            let detail = def.symbol_type.as_ref().map(|t| t.to_string()).unwrap_or_default();
+            items.push(completion_item(&def.name, COMPLETION_VARIABLE, detail));
+        }
+        let mut functions: Vec<(&String, &FunctionObject)> = self.symbol_table.functions.iter().collect();
+        functions.sort_by_key(|(name, _)| *name);
+        for (name, function) in functions {
+            items.push(completion_item(name, COMPLETION_FUNCTION, format!{"function {}", function.signature(name)}));
+        }
+        let mut structures: Vec<&String> = self.symbol_table.struct_args.keys().collect();
+        structures.sort();
+        for name in structures {
+            items.push(completion_item(name, COMPLETION_STRUCT, format!{"structure {}", name}));
+        }
+        Value::Array(items)
+    }
+
+    // The type of the code before a '.', like "list[2].next"
+    // The file might not parse while it is being typed, so this
+    //  reads the names and indexes straight from the text
+    fn chain_type(&self, before: &str, offset: usize) -> Option<SymbolType> {
+        // Find where the chain of names starts
+        let chars: Vec<char> = before.chars().collect();
+        let mut start = chars.len();
+        let mut depth = 0;
+        while start > 0 {
+            let c = chars[start - 1];
+            if c == ']' {
+                depth += 1;
+            }
+            else if c == '[' && depth > 0 {
+                depth -= 1;
+            }
+            else if depth == 0 && !(c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+                break;
+            }
+            start -= 1;
+        }
+
+        // Split it into names with the number of indexes after each
+        let mut parts: Vec<(String, usize)> = Vec::new();
+        let mut depth = 0;
+        for c in chars[start..].iter() {
+            match c {
+                '[' => {
+                    if depth == 0 {
+                        parts.last_mut()?.1 += 1;
+                    }
+                    depth += 1;
+                },
+                ']' => depth -= 1,
+                ',' if depth == 1 => parts.last_mut()?.1 += 1,
+                '.' if depth == 0 => parts.push((String::new(), 0)),
+                _ if depth == 0 => {
+                    if parts.is_empty() {
+                        parts.push((String::new(), 0));
+                    }
+                    parts.last_mut()?.0.push(c.to_ascii_lowercase());
+                },
+                _ => (),
+            }
+        }
+
+        let mut parts = parts.into_iter();
+        let (name, indexes) = parts.next()?;
+        let mut curr_type = index_type(self.index.find_variable(&name, offset)?.symbol_type.clone()?, indexes);
+        for (key, indexes) in parts {
+            curr_type = index_type(key_type(&self.symbol_table, &curr_type, &key)?, indexes);
+        }
+        Some(curr_type)
+    }
+
+    fn document_symbols(&self) -> Value {
+        let tree = match &self.tree {
+            Some(t) => t,
+            None => return json!([]),
+        };
+
+        let mut symbols: Vec<Value> = Vec::new();
+        for structure in &tree.structures {
+            if let Some(name) = &structure.name {
+                let fields: Vec<Value> = structure.fields.iter().map(|f| json!({
+                    "name": f.name.name,
+                    "kind": SYMBOL_FIELD,
+                    "detail": semantic_analyzer::symbol_type_of(&f.field_type).to_string(),
+                    "range": self.range(f.span),
+                    "selectionRange": self.range(f.name.span),
+                })).collect();
+                symbols.push(json!({
+                    "name": name.name,
+                    "kind": SYMBOL_STRUCT,
+                    "range": self.range(structure.span),
+                    "selectionRange": self.range(name.span),
+                    "children": fields,
+                }));
+            }
+        }
+        for function in &tree.functions {
+            if let Some(name) = &function.name {
+                let detail = self.symbol_table.functions.get(&name.name).map(|f| format!{"function {}", f.signature(&name.name)}).unwrap_or_default();
+                symbols.push(json!({
+                    "name": name.name,
+                    "kind": SYMBOL_FUNCTION,
+                    "detail": detail,
+                    "range": self.range(function.span),
+                    "selectionRange": self.range(name.span),
+                }));
+            }
+        }
+        Value::Array(symbols)
+    }
+
+    fn diagnostics(&self, uri: &str) -> Value {
+        let diagnostics: Vec<Value> = self.diagnostics.iter().map(|d| {
+            let mut message = d.message.clone();
+            if let Some(help) = &d.help {
+                message += &format!{"\nhelp: {}", help};
+            }
+            let related: Vec<Value> = d.labels.iter().map(|l| json!({
+                "location": { "uri": uri, "range": self.range(l.span) },
+                "message": l.message,
+            })).collect();
+            json!({
+                "range": self.range(d.span),
+                "severity": match d.severity {
+                    Severity::ERROR => SEVERITY_ERROR,
+                    Severity::WARNING => SEVERITY_WARNING,
+                },
+                "code": d.code,
+                "source": "gelli",
+                "message": message,
+                "relatedInformation": related,
+            })
+        }).collect();
+        notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": diagnostics }))
+    }
+}
+
+fn completion_item(label: &str, kind: u32, detail: String) -> Value {
+    json!({ "label": label, "kind": kind, "detail": detail })
+}
+
+// ====================
+// =     JSON-RPC     =
+// ====================
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+// Messages are a "Content-Length" header, a blank line, and the JSON
+// Gives back None at the end of the input (or when the header is broken,
+//  since then there is no way to find where the next message starts),
+//  and an error (with its code) when only the body is broken or too big
+fn read_message(input: &mut impl BufRead) -> Option<Result<Value, (i64, String)>> {
+    let mut length: Option<usize> = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+
+    // A body that is too big is skipped without keeping it, so the
+    //  message after it can still be read
+    let length = length?;
+    if length > MAX_MESSAGE {
+        io::copy(&mut input.by_ref().take(length as u64), &mut io::sink()).ok()?;
+        return Some(Err((INVALID_REQUEST, format!{"The message is {} bytes, the most the server reads is {}", length, MAX_MESSAGE})));
+    }
+
+    let mut body = vec![0; length];
+    input.read_exact(&mut body).ok()?;
+    Some(serde_json::from_slice(&body).map_err(|e| (PARSE_ERROR, format!{"Could not parse the message: {}", e})))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) {
+    let body = message.to_string();
+    let _ = write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+    let _ = output.flush();
+}
+
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+    exit: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Server {
+            documents: HashMap::new(),
+            shutdown: false,
+            exit: false,
+        }
+    }
+
+    // Handle one message from the editor, and give back the messages
+    //  to send back to it
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = match message["method"].as_str() {
+            Some(m) => m,
+            // A response to something the server sent
+            None => return Vec::new(),
+        };
+        let id = message.get("id").cloned();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "completionProvider": { "triggerCharacters": ["."] },
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": { "name": "gelli" },
+            }),
+            "shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            },
+            "exit" => {
+                self.exit = true;
+                return Vec::new();
+            },
+
+            // The whole file is sent on every change
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let text = match method {
+                    "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+                    _ => params["contentChanges"].as_array().and_then(|c| c.last()).and_then(|c| c["text"].as_str()),
+                };
+                let document = Document::new(text.unwrap_or("").to_string());
+                let diagnostics = document.diagnostics(&uri);
+                self.documents.insert(uri, document);
+                return vec![diagnostics];
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": [] }))];
+            },
+
+            "textDocument/hover" |
+            "textDocument/definition" |
+            "textDocument/completion" |
+            "textDocument/documentSymbol" => {
+                let document = match self.documents.get(&uri) {
+                    Some(d) => d,
+                    None => return id.map(|id| response(id, Value::Null)).into_iter().collect(),
+                };
+                match method {
+                    "textDocument/hover" => document.hover(&params["position"]),
+                    "textDocument/definition" => document.definition(&uri, &params["position"]),
+                    "textDocument/completion" => document.completion(&params["position"]),
+                    _ => document.document_symbols(),
+                }
+            },
+
+            // Requests the server doesn't know get an error, other
+            //  notifications are ignored
+            _ => {
+                return match id {
+                    Some(id) => vec![error_response(id, METHOD_NOT_FOUND, format!{"Unknown method '{}'", method})],
+                    None => Vec::new(),
+                };
+            },
+        };
+
+        match id {
+            Some(id) => vec![response(id, result)],
+            None => Vec::new(),
+        }
+    }
+}
+
+// Run the server until the editor tells it to exit
+pub fn main() {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+
+    let mut server = Server::new();
+    while let Some(message) = read_message(&mut input) {
+        // The id of a message that can't be read isn't known
+        let message = match message {
+            Ok(m) => m,
+            Err((code, e)) => {
+                write_message(&mut output, &error_response(Value::Null, code, e));
+                continue;
+            },
+        };
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply);
+        }
+        if server.exit {
+            break;
+        }
+    }
+
+    // Exiting without a shutdown request first is an error
+    process::exit(if server.shutdown { 0 } else { 1 });
+}
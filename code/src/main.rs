@@ -1,21 +1,15 @@
-#![recursion_limit="256"]
-// #![allow(warnings)]
+// The command line tool for Gelli, the language itself is in the
+//  gelli library (lib.rs)
 
-mod lexer;
-mod parser;
-mod semantic_analyzer;
-mod interpreter;
-mod library_handler;
+mod cli;
+mod lsp;
 
 use std::env;
 
 // Cargo requires a main funtion to start execution.
-// For the intepreter, ew just pull in each module file
-//  and call the interpreter's main function.
+// The command line tool (cli.rs) picks what to do from the
+//  arguments, running a file by default.
 fn main() {
     env::set_var("RUST_BACKTRACE", "1");
-    //lexer::main()
-    //parser::main()
-    //semantic_analyzer::main()
-    interpreter::main()
+    cli::main()
 }
@@ -1,12 +1,19 @@
-use std::{env};
+use std::fmt;
 use crate::lexer;
+use crate::diagnostic::{self, Diagnostic, Span, Stage};
 use std::mem;
 
 // This null token can be used as a token placeholder
 //  elsewhere througout the code
 static _NULL_TOKEN: lexer::Token = lexer::Token {
-    row: 0,
-    col: 0,
+    span: Span {
+        start_row: 0,
+        start_col: 0,
+        end_row: 0,
+        end_col: 0,
+        start: 0,
+        end: 0,
+    },
     token_type: lexer::TokenType::INVALID,
     lexeme: None
 };
@@ -17,170 +24,700 @@ static ID_TYPE: lexer::TokenType = lexer::TokenType::ID(String::new());
 static TEXT_TYPE: lexer::TokenType = lexer::TokenType::TEXT(String::new());
 static NUMBER_TYPE: lexer::TokenType = lexer::TokenType::NUMBER(0.0);
 
-// These types represents nodes in the parse tree
-// Each node is something some action that the
-//  interpreter will perform, or an object that
-//  the interpreter will work with.
+// =====================
+// =   SYNTAX TREE     =
+// =====================
+// The parser builds a tree with a type for each part of the
+//  language, so the later stages can't mix up (or forget) the
+//  parts of a node. Every node keeps the span of code it came
+//  from, so errors can point at the whole thing.
+// Code that couldn't be parsed is left as an INVALID node (or a
+//  missing name), its syntax error has already been reported.
+
+// The whole program, the definitions section is split up into
+//  its structures, globals, and functions
 #[derive(Debug, Clone, PartialEq)]
-pub enum ParseType {
-    CODE,       // the start of all trees
-    DEFINITIONS,// the definitions section of the program
-    GLOBALDEFS, // the definition section for global variables
-    FUNDEFS,    // the definition section for functions
-    BLOCK,      // a block of statements to execute
-    FUNDEF,     // function definition
-    VARDEF,     // variable definition
-    VARDEFS,    // variable definition for a list of ids
-    STRUCTDEFS, // define structures
-    STRUCTDEF,  // define a structure
-    ASSIGN,     // assign a value to a variable
-    WHILE,      // while loop
-    IF,         // if statement
-    REPEAT,     // repeat a number of times
-    REPEATFOR,  // repeat for each value in an array
-    REPEATFOREVER, // repeat forever
-    UNLINK,     // unlink
-    QUIT,       // quit
-    BREAK,      // break
-    CONTINUE,   // continue
-    BINOP,      // binary operation (add, sub, div, ...)
-    BINCOMP,    // binary comparison (=, !=, <, ...)
-    ISLINKED,   // conditional for if a variable is linked
-    ISNOTLINKED, // conditional for if a varialbe is unliked
-    BITNOT,     // bitwise not operation
-    NEG,        // negative value operation
-    ABS,        // absolute value operation
-    RETURN,     // return
-    TYPE,       // variable type
-    POINTER,    // type for pointers specifically
-    LINK,       // link modifier for types
-    LINKLIT,    // a literal used to denote that a reference is being linked
-    ARRAYLIT,   // array literal
-    STRUCTLIT,  // structure literal
-    ARRAYDEF,   // define an array variable with (optional) bounds and type
-    BOUNDS,     // bounds for an array
-    BOUND,      // a single bound for an array
-    GETINDEX,      // return the index of an array
-    GETSTRUCT,     // get the value of a structure key
-    CALL,          // call a function
-    ARGS,          // list of values (arguments)
-    PARAMS,        // list of params
-    PARAM,         // a single name and expected value
-    ID,            // some form of name
-    IDS,           // a collection of names (for variable declaration)
-    LIT,           // a literal value
-    STRUCTARGS,    // a list of structure arguments
-    STRUCTARG,     // a name, type, and default value
-    INDEX,         // a set of indecies to get from an array
-
-    INVALID,       // an invalid parse instruction, just for placeholding
+pub struct ParseTree {
+    pub structures: Vec<StructDef>,
+    pub globals: Vec<Stmt>,
+    pub functions: Vec<FunctionDef>,
+    pub body: Block,
+    pub span: Span,
 }
 
+// A name, like a variable, function, or structure key
 #[derive(Debug, Clone, PartialEq)]
-pub struct ParseTree {
-    pub parse_type: ParseType,
-    pub token: lexer::Token,
-    pub children: Vec<Option<ParseTree>>,
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDef {
+    pub name: Option<Ident>,
+    pub fields: Vec<StructField>,
+    pub span: Span,
+}
+
+// A key of a structure, with its type and (optional) default value
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructField {
+    pub name: Ident,
+    pub field_type: Type,
+    pub default: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDef {
+    pub name: Option<Ident>,
+    pub params: Vec<Param>,
+    pub return_type: Type,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: Ident,
+    pub changeable: bool,
+    pub param_type: Type,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Type {
+    pub kind: TypeKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeKind {
+    NUMBER,
+    TEXT,
+    NOTHING,                        // only for function return types
+    STRUCTURE(String),              // structure name
+    LINK(Box<Type>),                // link to a basic type
+    ARRAY(Vec<Bound>, Box<Type>),   // bounds and basic type
+    INVALID,
+}
+
+// A single bound of an array, the start is 1 if it is left off
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bound {
+    pub start: Option<Expr>,
+    pub end: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub statements: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    VARDEF(VarDef),                         // declare variables without a value
+    ASSIGN(Target, Expr),                   // assign a value to a variable
+    LINK(Target, Option<Reference>),        // link to a reference (or nothing)
+    IF(If),                                 // if statement
+    WHILE(Condition, Block),                // while loop
+    REPEAT(Expr, Block),                    // repeat a number of times
+    REPEATFOR(Option<Ident>, Expr, Block),  // repeat for each value in an array (or text)
+    REPEATFOREVER(Block),                   // repeat forever
+    UNLINK(Reference),                      // unlink
+    RETURN(Option<Expr>),                   // return a value (or nothing)
+    QUIT(Option<Expr>),                     // quit (with a status for the exit code)
+    BREAK,                                  // break
+    CONTINUE,                               // continue
+    EXPR(Expr),                             // a value on its own, like a function call
+    INVALID,
+}
+
+// One or more names and their type
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarDef {
+    pub names: Vec<Ident>,
+    pub var_type: Type,
+    pub span: Span,
+}
+
+// The left side of an assignment or link
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    VARDEF(VarDef),
+    REFERENCE(Reference),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct If {
+    pub condition: Condition,
+    pub body: Block,
+    pub otherwise: Option<Else>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Else {
+    IF(Box<If>),
+    BLOCK(Block),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub kind: ConditionKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionKind {
+    COMPARE(CompareOp, Expr, Expr),
+    AND(Box<Condition>, Box<Condition>),
+    OR(Box<Condition>, Box<Condition>),
+    ISLINKED(Reference),
+    ISNOTLINKED(Reference),
+    INVALID,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    EQ,
+    NE,
+    LT,
+    GT,
+    LE,
+    GE,
+}
+
+// Anything that resolves to a value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    NUMBER(f64),
+    TEXT(String),
+    REFERENCE(Reference),
+    CALL(Ident, Vec<Expr>),                 // function name and arguments
+    BINARY(BinOp, Box<Expr>, Box<Expr>),    // binary operation (add, sub, div, ...)
+    NEG(Box<Expr>),                         // negative value
+    ABS(Box<Expr>),                         // absolute value
+    BITNOT(Box<Expr>),                      // bitwise not
+    ARRAY(Vec<Expr>),                       // array literal
+    STRUCTURE(Vec<Expr>),                   // structure literal
+    LINK(Option<Reference>),                // link literal (inside of a structure literal)
+    INVALID,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    ADD,
+    SUB,
+    MUL,
+    DIV,
+    MOD,
+    POW,
+    BAND,
+    BOR,
+    BXOR,
+    BSL,
+    BSR,
+}
+
+// Something with memory, a variable or a part of one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub kind: ReferenceKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReferenceKind {
+    NAME(String),
+    INDEX(Box<Reference>, Vec<Expr>),   // array (or text) and indexes
+    FIELD(Box<Reference>, Ident),       // structure and key
+}
+
+impl Reference {
+    pub fn from_ident(id: Ident) -> Self {
+        Reference {
+            kind: ReferenceKind::NAME(id.name),
+            span: id.span,
+        }
+    }
+}
+
+// How the token that was found is written in an error message
+fn found(token: &lexer::Token) -> String {
+    match &token.token_type {
+        lexer::TokenType::ID(name) => format!{"the name `{}`", name},
+        lexer::TokenType::NUMBER(_) => format!{"the number `{}`", token.lexeme.clone().unwrap_or_default()},
+        token_type => token_type.to_string(),
+    }
+}
+
+// If a token is a keyword (which is written as a word, like "`while`")
+fn is_keyword(token_type: &lexer::TokenType) -> bool {
+    let text = token_type.to_string();
+    text.starts_with('`') && text[1..].starts_with(char::is_alphabetic)
+}
+
+// The span of the keyword at the start of a span
+fn keyword_span(span: Span, len: u32) -> Span {
+    Span {
+        start_row: span.start_row,
+        start_col: span.start_col,
+        end_row: span.start_row,
+        end_col: span.start_col + len,
+        start: span.start,
+        end: span.start + len as usize,
+    }
+}
+
+impl Stmt {
+    // The span of the start of a statement, for statements with a
+    //  block only the keyword is used so it stays on one line
+    pub fn head(&self) -> Span {
+        match self.kind {
+            StmtKind::IF(_) => keyword_span(self.span, 2),
+            StmtKind::WHILE(_, _) => keyword_span(self.span, 5),
+            StmtKind::REPEAT(_, _) | StmtKind::REPEATFOR(_, _, _) | StmtKind::REPEATFOREVER(_) => keyword_span(self.span, 6),
+            _ => self.span,
+        }
+    }
+}
+
+impl If {
+    // The span of the "if" keyword
+    pub fn head(&self) -> Span {
+        keyword_span(self.span, 2)
+    }
+}
+
+// A short name for a reference, like "list[...]" or "node.next"
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ReferenceKind::NAME(name) => write!(f, "{}", name),
+            ReferenceKind::INDEX(array, _) => write!(f, "{}[...]", array),
+            ReferenceKind::FIELD(structure, key) => write!(f, "{}.{}", structure, key.name),
+        }
+    }
+}
+
+// The operators are written the way they are in the code
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self {
+            BinOp::ADD => "+",
+            BinOp::SUB => "-",
+            BinOp::MUL => "*",
+            BinOp::DIV => "/",
+            BinOp::MOD => "mod",
+            BinOp::POW => "^",
+            BinOp::BAND => "bit_and",
+            BinOp::BOR => "bit_or",
+            BinOp::BXOR => "bit_xor",
+            BinOp::BSL => "bit_sl",
+            BinOp::BSR => "bit_sr",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self {
+            CompareOp::EQ => "=",
+            CompareOp::NE => "!=",
+            CompareOp::LT => "<",
+            CompareOp::GT => ">",
+            CompareOp::LE => "<=",
+            CompareOp::GE => ">=",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+// ======================
+// =   TREE PRINTING    =
+// ======================
+
+// Print a single node of the tree, indented to its depth
+fn print_node(tab: usize, text: &str, span: Span) {
+    for _n in 0..tab {
+        print!{"| "};
+    }
+    println!{"{} ({})", text, span};
 }
 
 impl ParseTree {
     // A function to recursively print the entire parse
     //  tree and its children.
     pub fn print(&self) {
-        let len = self.children.len();
-        let mut idx = 0;
-
-        if len == 0 {
-            match self.token.token_type {
-                lexer::TokenType::INVALID => println!{"{:?}", self.parse_type},
-                _ => println!{"{:?} ({:?})", self.parse_type, self.token},
-            };
-            return;
+        print_node(0, "PROGRAM", self.span);
+        for struct_def in &self.structures {
+            struct_def.print_tabbed(1);
         }
+        for global in &self.globals {
+            global.print_tabbed(1);
+        }
+        for fun_def in &self.functions {
+            fun_def.print_tabbed(1);
+        }
+        self.body.print_tabbed(1);
+    }
+}
 
-        for child in self.children.iter() {
-            if idx == 0 {
-                match self.token.token_type {
-                    lexer::TokenType::INVALID => println!{"{:?}", self.parse_type},
-                    _ => println!{"{:?} ({:?})", self.parse_type, self.token},
-                };
+impl StructDef {
+    fn print_tabbed(&self, tab: usize) {
+        match &self.name {
+            Some(name) => print_node(tab, &format!{"STRUCTURE {}", name.name}, self.span),
+            None => print_node(tab, "STRUCTURE <invalid>", self.span),
+        }
+        for field in &self.fields {
+            print_node(tab + 1, &format!{"FIELD {}", field.name.name}, field.span);
+            field.field_type.print_tabbed(tab + 2);
+            if let Some(default) = &field.default {
+                default.print_tabbed(tab + 2);
             }
-            
-            match child {
-                Some(tree) => tree.print_tabbed(1),
-                _ => (),
-            };
+        }
+    }
+}
 
-            idx += 1;
+impl FunctionDef {
+    fn print_tabbed(&self, tab: usize) {
+        match &self.name {
+            Some(name) => print_node(tab, &format!{"FUNCTION {}", name.name}, self.span),
+            None => print_node(tab, "FUNCTION <invalid>", self.span),
+        }
+        for param in &self.params {
+            let changeable = if param.changeable { " (changeable)" } else { "" };
+            print_node(tab + 1, &format!{"PARAM {}{}", param.name.name, changeable}, param.span);
+            param.param_type.print_tabbed(tab + 2);
         }
+        print_node(tab + 1, "RETURNS", self.return_type.span);
+        self.return_type.print_tabbed(tab + 2);
+        self.body.print_tabbed(tab + 1);
     }
+}
 
-    // A helper function to print the parse tree,
-    //  making sure all nodes are properly indented
+impl Type {
     fn print_tabbed(&self, tab: usize) {
-        let len = self.children.len();
-        let mut idx = 0;
+        match &self.kind {
+            TypeKind::NUMBER => print_node(tab, "TYPE number", self.span),
+            TypeKind::TEXT => print_node(tab, "TYPE text", self.span),
+            TypeKind::NOTHING => print_node(tab, "TYPE nothing", self.span),
+            TypeKind::STRUCTURE(name) => print_node(tab, &format!{"TYPE {}", name}, self.span),
+            TypeKind::LINK(link_type) => {
+                print_node(tab, "LINK TO", self.span);
+                link_type.print_tabbed(tab + 1);
+            },
+            TypeKind::ARRAY(bounds, arr_type) => {
+                print_node(tab, "ARRAY", self.span);
+                for bound in bounds {
+                    print_node(tab + 1, "BOUND", bound.span);
+                    if let Some(start) = &bound.start {
+                        start.print_tabbed(tab + 2);
+                    }
+                    bound.end.print_tabbed(tab + 2);
+                }
+                arr_type.print_tabbed(tab + 1);
+            },
+            TypeKind::INVALID => print_node(tab, "INVALID", self.span),
+        }
+    }
+}
 
-        if len == 0 {
-            for _n in 0..tab {
-                print!{"| "};
-            }
-            match self.token.token_type {
-                lexer::TokenType::INVALID => println!{"{:?}", self.parse_type},
-                _ => println!{"{:?} ({:?})", self.parse_type, self.token},
-            };
-            return;
+impl Block {
+    fn print_tabbed(&self, tab: usize) {
+        print_node(tab, "BLOCK", self.span);
+        for statement in &self.statements {
+            statement.print_tabbed(tab + 1);
         }
+    }
+}
 
-        for child in self.children.iter() {
-            if idx == 0 {
-                for _n in 0..tab {
-                    print!{"| "};
+impl VarDef {
+    fn print_tabbed(&self, tab: usize) {
+        let names: Vec<&str> = self.names.iter().map(|id| id.name.as_str()).collect();
+        print_node(tab, &format!{"VARDEF {}", names.join(", ")}, self.span);
+        self.var_type.print_tabbed(tab + 1);
+    }
+}
+
+impl Target {
+    fn print_tabbed(&self, tab: usize) {
+        match self {
+            Target::VARDEF(var_def) => var_def.print_tabbed(tab),
+            Target::REFERENCE(reference) => reference.print_tabbed(tab),
+        }
+    }
+}
+
+impl Stmt {
+    fn print_tabbed(&self, tab: usize) {
+        match &self.kind {
+            StmtKind::VARDEF(var_def) => var_def.print_tabbed(tab),
+            StmtKind::ASSIGN(target, value) => {
+                print_node(tab, "ASSIGN", self.span);
+                target.print_tabbed(tab + 1);
+                value.print_tabbed(tab + 1);
+            },
+            StmtKind::LINK(target, linked) => {
+                print_node(tab, "LINK", self.span);
+                target.print_tabbed(tab + 1);
+                match linked {
+                    Some(reference) => reference.print_tabbed(tab + 1),
+                    None => print_node(tab + 1, "NOTHING", self.span),
                 }
-                match self.token.token_type {
-                    lexer::TokenType::INVALID => println!{"{:?}", self.parse_type},
-                    _ => println!{"{:?} ({:?})", self.parse_type, self.token},
-                };
-            }
-            
-            match child {
-                Some(tree) => tree.print_tabbed(tab + 1),
-                _ => (),
-            };
+            },
+            StmtKind::IF(if_stmt) => if_stmt.print_tabbed(tab),
+            StmtKind::WHILE(condition, body) => {
+                print_node(tab, "WHILE", self.span);
+                condition.print_tabbed(tab + 1);
+                body.print_tabbed(tab + 1);
+            },
+            StmtKind::REPEAT(count, body) => {
+                print_node(tab, "REPEAT", self.span);
+                count.print_tabbed(tab + 1);
+                body.print_tabbed(tab + 1);
+            },
+            StmtKind::REPEATFOR(variable, values, body) => {
+                match variable {
+                    Some(id) => print_node(tab, &format!{"REPEAT FOR ALL {}", id.name}, self.span),
+                    None => print_node(tab, "REPEAT FOR ALL <invalid>", self.span),
+                }
+                values.print_tabbed(tab + 1);
+                body.print_tabbed(tab + 1);
+            },
+            StmtKind::REPEATFOREVER(body) => {
+                print_node(tab, "REPEAT FOREVER", self.span);
+                body.print_tabbed(tab + 1);
+            },
+            StmtKind::UNLINK(reference) => {
+                print_node(tab, "UNLINK", self.span);
+                reference.print_tabbed(tab + 1);
+            },
+            StmtKind::RETURN(value) => {
+                print_node(tab, "RETURN", self.span);
+                if let Some(value) = value {
+                    value.print_tabbed(tab + 1);
+                }
+            },
+            StmtKind::QUIT(status) => {
+                print_node(tab, "QUIT", self.span);
+                if let Some(status) = status {
+                    status.print_tabbed(tab + 1);
+                }
+            },
+            StmtKind::BREAK => print_node(tab, "BREAK", self.span),
+            StmtKind::CONTINUE => print_node(tab, "CONTINUE", self.span),
+            StmtKind::EXPR(value) => value.print_tabbed(tab),
+            StmtKind::INVALID => print_node(tab, "INVALID", self.span),
+        }
+    }
+}
+
+impl If {
+    fn print_tabbed(&self, tab: usize) {
+        print_node(tab, "IF", self.span);
+        self.condition.print_tabbed(tab + 1);
+        self.body.print_tabbed(tab + 1);
+        match &self.otherwise {
+            Some(Else::IF(else_if)) => {
+                print_node(tab + 1, "ELSE", else_if.span);
+                else_if.print_tabbed(tab + 2);
+            },
+            Some(Else::BLOCK(block)) => {
+                print_node(tab + 1, "ELSE", block.span);
+                block.print_tabbed(tab + 2);
+            },
+            None => (),
+        }
+    }
+}
+
+impl Condition {
+    fn print_tabbed(&self, tab: usize) {
+        match &self.kind {
+            ConditionKind::COMPARE(op, left, right) => {
+                print_node(tab, &format!{"COMPARE {}", op}, self.span);
+                left.print_tabbed(tab + 1);
+                right.print_tabbed(tab + 1);
+            },
+            ConditionKind::AND(left, right) => {
+                print_node(tab, "AND", self.span);
+                left.print_tabbed(tab + 1);
+                right.print_tabbed(tab + 1);
+            },
+            ConditionKind::OR(left, right) => {
+                print_node(tab, "OR", self.span);
+                left.print_tabbed(tab + 1);
+                right.print_tabbed(tab + 1);
+            },
+            ConditionKind::ISLINKED(reference) => {
+                print_node(tab, "IS LINKED", self.span);
+                reference.print_tabbed(tab + 1);
+            },
+            ConditionKind::ISNOTLINKED(reference) => {
+                print_node(tab, "IS NOT LINKED", self.span);
+                reference.print_tabbed(tab + 1);
+            },
+            ConditionKind::INVALID => print_node(tab, "INVALID", self.span),
+        }
+    }
+}
+
+impl Expr {
+    fn print_tabbed(&self, tab: usize) {
+        match &self.kind {
+            ExprKind::NUMBER(n) => print_node(tab, &format!{"NUMBER {}", n}, self.span),
+            ExprKind::TEXT(t) => print_node(tab, &format!{"TEXT {:?}", t}, self.span),
+            ExprKind::REFERENCE(reference) => reference.print_tabbed(tab),
+            ExprKind::CALL(name, args) => {
+                print_node(tab, &format!{"CALL {}", name.name}, self.span);
+                for arg in args {
+                    arg.print_tabbed(tab + 1);
+                }
+            },
+            ExprKind::BINARY(op, left, right) => {
+                print_node(tab, &format!{"BINOP {}", op}, self.span);
+                left.print_tabbed(tab + 1);
+                right.print_tabbed(tab + 1);
+            },
+            ExprKind::NEG(value) => {
+                print_node(tab, "NEG", self.span);
+                value.print_tabbed(tab + 1);
+            },
+            ExprKind::ABS(value) => {
+                print_node(tab, "ABS", self.span);
+                value.print_tabbed(tab + 1);
+            },
+            ExprKind::BITNOT(value) => {
+                print_node(tab, "BITNOT", self.span);
+                value.print_tabbed(tab + 1);
+            },
+            ExprKind::ARRAY(items) => {
+                print_node(tab, "ARRAYLIT", self.span);
+                for item in items {
+                    item.print_tabbed(tab + 1);
+                }
+            },
+            ExprKind::STRUCTURE(items) => {
+                print_node(tab, "STRUCTLIT", self.span);
+                for item in items {
+                    item.print_tabbed(tab + 1);
+                }
+            },
+            ExprKind::LINK(linked) => {
+                print_node(tab, "LINK TO", self.span);
+                match linked {
+                    Some(reference) => reference.print_tabbed(tab + 1),
+                    None => print_node(tab + 1, "NOTHING", self.span),
+                }
+            },
+            ExprKind::INVALID => print_node(tab, "INVALID", self.span),
+        }
+    }
+}
 
-            idx += 1;
+impl Reference {
+    fn print_tabbed(&self, tab: usize) {
+        match &self.kind {
+            ReferenceKind::NAME(name) => print_node(tab, &format!{"ID {}", name}, self.span),
+            ReferenceKind::INDEX(array, indexes) => {
+                print_node(tab, "GETINDEX", self.span);
+                array.print_tabbed(tab + 1);
+                for index in indexes {
+                    index.print_tabbed(tab + 1);
+                }
+            },
+            ReferenceKind::FIELD(structure, key) => {
+                print_node(tab, &format!{"GETSTRUCT {}", key.name}, self.span);
+                structure.print_tabbed(tab + 1);
+            },
         }
     }
 }
 
+// =====================
+// =   PARSER          =
+// =====================
+
+// A block that has been opened (like "while") and is
+//  waiting for its "end <keyword>"
+#[derive(Debug)]
+struct OpenBlock {
+    token_type: lexer::TokenType,
+    keyword: &'static str,
+    token: lexer::Token,
+}
+
 #[derive(Debug)]
 pub struct Parser {
     lexer: lexer::Lexer,
     in_fun_def: bool,
     in_loop_block: i32,
     in_if_block: i32,
+    // Every syntax error found so far
+    errors: Vec<Diagnostic>,
+    // Set after an error, so that the errors caused by
+    //  the first one aren't reported until we recover
+    panic_mode: bool,
+    // The blocks that are still open, innermost last
+    block_stack: Vec<OpenBlock>,
+    // A token that has been looked at, but not consumed yet
+    peeked: Option<lexer::Token>,
+    // The span of the last token that was consumed, this
+    //  is where the node being parsed ends
+    last_span: Span,
+    // The type of the last token that was consumed (for hints)
+    last_type: lexer::TokenType,
 }
 
 impl Parser {
     // Construct a lexer for the parser from a String
-    pub fn new(text: String) -> Result<Self, String> {
+    pub fn new(text: String) -> Result<Self, Diagnostic> {
         let lexer = lexer::Lexer::new(text)?;
         Ok(Parser {
-            lexer: lexer,
+            lexer,
             in_fun_def: false,
             in_loop_block: 0,
             in_if_block: 0,
+            errors: Vec::new(),
+            panic_mode: false,
+            block_stack: Vec::new(),
+            peeked: None,
+            last_span: Span::default(),
+            last_type: lexer::TokenType::INVALID,
         })
     }
 
     // Construct a lexer for the parser from a file
-    pub fn from_file(file: String) -> Result<Self, String> {
+    pub fn from_file(file: String) -> Result<Self, Diagnostic> {
         let lexer = lexer::Lexer::from_file(file)?;
         Ok(Parser {
-            lexer: lexer,
+            lexer,
             in_fun_def: false,
             in_loop_block: 0,
             in_if_block: 0,
+            errors: Vec::new(),
+            panic_mode: false,
+            block_stack: Vec::new(),
+            peeked: None,
+            last_span: Span::default(),
+            last_type: lexer::TokenType::INVALID,
         })
     }
 
@@ -190,15 +727,47 @@ impl Parser {
     }
 
     // Consume next token in lexer
-    pub fn next(&mut self) -> Result<lexer::Token, String> {
+    // (Not an Iterator, for the same reasons as Lexer::next)
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<lexer::Token, Diagnostic> {
+        self.last_span = Span::from_token(&self.lexer.curr_token);
+        self.last_type = self.lexer.curr_token.token_type.clone();
+        if let Some(token) = self.peeked.take() {
+            self.lexer.curr_token = token.clone();
+            return Ok(token);
+        }
         self.lexer.next()
     }
 
+    // Look at the token after the current one, without consuming anything
+    fn peek(&mut self) -> Result<lexer::Token, Diagnostic> {
+        if self.peeked.is_none() {
+            let curr = self.curr_token();
+            self.peeked = Some(self.lexer.next()?);
+            self.lexer.curr_token = curr;
+        }
+        Ok(self.peeked.clone().unwrap())
+    }
+
     // Get the current token in the lexer
     pub fn curr_token(&self) -> lexer::Token {
         self.lexer.curr_token.clone()
     }
 
+    // The span of the current token, where the next node starts
+    fn start(&self) -> Span {
+        Span::from_token(&self.lexer.curr_token)
+    }
+
+    // The span of a node from where it started to the last token
+    //  consumed. A node that didn't consume anything is empty.
+    fn span_from(&self, start: Span) -> Span {
+        if self.last_span.end <= start.start {
+            return start.start_point();
+        }
+        start.to(self.last_span)
+    }
+
     // Return a boolean on if the current token matches
     //  the expected token
     fn has(&self, token_type: &lexer::TokenType) -> bool {
@@ -210,207 +779,501 @@ impl Parser {
         mem::discriminant(&self.curr_token().token_type) == mem::discriminant(token_type)
     }
 
+    // The error for when the current token isn't the expected token
+    fn unexpected(&self, token_type: &lexer::TokenType) -> Diagnostic {
+        self.expected(&token_type.to_string(), Some(token_type))
+    }
+
+    // The error for when the current token isn't what was expected,
+    //  described the way it is written ("a value", "`then`")
+    // 'token_type' is the token that was expected, if there was only one
+    fn expected(&self, what: &str, token_type: Option<&lexer::TokenType>) -> Diagnostic {
+        let token = self.curr_token();
+        let err = Diagnostic::new(Stage::PARSE, diagnostic::UNEXPECTED_TOKEN, format!{"Expected {}, found {}", what, found(&token)})
+            .with_span(Span::from_token(&token));
+        match self.hint(token_type, &token.token_type) {
+            Some(help) => err.with_help(help),
+            None => err,
+        }
+    }
+
+    // A hint for the mistakes that are easy to make, mostly
+    //  from writing Gelli like another language
+    fn hint(&self, expected: Option<&lexer::TokenType>, found: &lexer::TokenType) -> Option<String> {
+        let hint = match (expected, &self.last_type, found) {
+            (_, lexer::TokenType::EQ, lexer::TokenType::EQ) => "to compare two values, use a single `=`",
+            (_, lexer::TokenType::ADD | lexer::TokenType::SUB | lexer::TokenType::MUL | lexer::TokenType::DIV, lexer::TokenType::EQ) => "to change a variable, write out the whole calculation, like `x = x + 1`",
+            (_, lexer::TokenType::ID(_), lexer::TokenType::NUMTYPE | lexer::TokenType::TEXTTYPE | lexer::TokenType::ARRAY) => "to declare a variable, put a `:` between its name and its type, like `x: number`",
+            (Some(lexer::TokenType::THEN), _, _) => "an `if` condition ends with `then`, like `if x > 0 then`",
+            (Some(lexer::TokenType::TIMES), _, _) => "write how many times to repeat, like `repeat 3 times`",
+            (Some(lexer::TokenType::OF), _, _) => "write an array type like `array[3] of number`",
+            (_, _, lexer::TokenType::FOR) if self.last_type != lexer::TokenType::REPEAT => "loops start with `repeat`, like `repeat for all x in values`",
+            (Some(lexer::TokenType::ID(_)), _, _) if is_keyword(found) => return Some(format!{"{} is a keyword, so it can't be used as a name", found}),
+            (None, _, lexer::TokenType::END | lexer::TokenType::EOF) => "the line before this one isn't finished, it might be missing a value or a `)`",
+            _ => return None,
+        };
+        Some(hint.to_string())
+    }
+
     // Test if the current token matches the expected token
     // If not, log the error and close the program
     // For now, we just exit, but this can be handled
     //  differently in WebAssembly
-    fn must_be(&self, token_type: &lexer::TokenType) -> Result<bool, String> {
+    fn must_be(&self, token_type: &lexer::TokenType) -> Result<bool, Diagnostic> {
         if !self.has(token_type) {
-            return Err(format!{"Parse Error on Line: {}, Column: {}\nExpected: {:?}\nGot: {:?}", 
-                self.curr_token().row, 
-                self.curr_token().col, 
-                token_type, 
-                self.curr_token().token_type
-            });
+            return Err(self.unexpected(token_type));
         }
         Ok(true)
     }
 
     // Helper function to call 'must_be' and 'next' together
-    fn eat(&mut self, token_type: &lexer::TokenType) -> Result<lexer::Token, String> {
+    fn eat(&mut self, token_type: &lexer::TokenType) -> Result<lexer::Token, Diagnostic> {
         self.must_be(token_type)?;
+        self.panic_mode = false;
         self.next()
     }
 
-    // Run the parser
-    pub fn parse(&mut self) -> Result<Option<ParseTree>, String> {
-        self.code()
+    // Like 'eat', but a missing token is only recorded, and
+    //  parsing carries on as if it was there
+    fn expect(&mut self, token_type: &lexer::TokenType) -> Result<(), Diagnostic> {
+        match self.must_be(token_type) {
+            Ok(_) => {
+                self.eat(token_type)?;
+            },
+            Err(e) => self.report(e),
+        };
+        Ok(())
+    }
+
+    // Record a syntax error, unless we are still recovering
+    //  from the last one
+    fn report(&mut self, err: Diagnostic) {
+        if !self.panic_mode {
+            self.errors.push(err);
+        }
+        self.panic_mode = true;
+    }
+
+    // Placeholders for parts of the tree that could not be parsed
+    fn invalid_statement(start: &lexer::Token) -> Stmt {
+        Stmt {
+            kind: StmtKind::INVALID,
+            span: Span::from_token(start),
+        }
+    }
+
+    fn invalid_expr(start: &lexer::Token) -> Expr {
+        Expr {
+            kind: ExprKind::INVALID,
+            span: Span::from_token(start),
+        }
+    }
+
+    fn invalid_condition(start: &lexer::Token) -> Condition {
+        Condition {
+            kind: ConditionKind::INVALID,
+            span: Span::from_token(start),
+        }
+    }
+
+    // Panic mode recovery, if parsing failed record the error and skip
+    //  ahead to the start of the next statement (or the end of a block),
+    //  giving back None so the caller can leave an INVALID node
+    // Errors from the lexer can't be recovered from, so they are returned
+    fn recover<T>(&mut self, result: Result<T, Diagnostic>, start: lexer::Token) -> Result<Option<T>, Diagnostic> {
+        let err = match result {
+            Ok(node) => return Ok(Some(node)),
+            Err(e) => e,
+        };
+        if err.stage == Stage::LEX {
+            return Err(err);
+        }
+        self.report(err);
+
+        // Always move forward at least one token, so that we
+        //  don't fail on the same token forever
+        if self.curr_token() == start && !(self.has(&lexer::TokenType::END) || self.has(&lexer::TokenType::EOF)) {
+            self.next()?;
+        }
+        self.synchronize(start.span.start_row)?;
+
+        Ok(None)
+    }
+
+    // Skip tokens until one that can start a statement, or end a block
+    // A name (ID) only counts if it is on a later line than the error
+    fn synchronize(&mut self, row: u32) -> Result<(), Diagnostic> {
+        loop {
+            let token = self.curr_token();
+            match token.token_type {
+                lexer::TokenType::EOF |
+                lexer::TokenType::END |
+                lexer::TokenType::ELSE |
+                lexer::TokenType::WHILE |
+                lexer::TokenType::IF |
+                lexer::TokenType::REPEAT |
+                lexer::TokenType::UNLINK |
+                lexer::TokenType::QUIT |
+                lexer::TokenType::RETURN |
+                lexer::TokenType::BREAK |
+                lexer::TokenType::CONTINUE |
+                lexer::TokenType::FUN |
+                lexer::TokenType::STRUCT |
+                lexer::TokenType::DEF |
+                lexer::TokenType::PROGRAM => return Ok(()),
+                lexer::TokenType::ID(_) if token.span.start_row > row => return Ok(()),
+                _ => { self.next()?; },
+            };
+        }
+    }
+
+    // Mark the start of a block that needs an 'end <keyword>'
+    fn open_block(&mut self, token_type: lexer::TokenType, keyword: &'static str, token: lexer::Token) {
+        self.block_stack.push(OpenBlock {
+            token_type,
+            keyword,
+            token,
+        });
+    }
+
+    // Eat the 'end <keyword>' that closes the innermost block
+    // Mistakes are recorded (not returned):
+    //  - if the 'end' belongs to a block further out, this block is
+    //    missing its end, and the 'end' is left for the outer block
+    //  - if the keyword doesn't match any open block, suggest the one
+    //    that does, and treat it as the end of this block
+    fn close_block(&mut self) -> Result<(), Diagnostic> {
+        let block = match self.block_stack.pop() {
+            Some(b) => b,
+            None => return Ok(()),
+        };
+
+        if self.has(&lexer::TokenType::END) {
+            let keyword = self.peek()?;
+            if mem::discriminant(&keyword.token_type) == mem::discriminant(&block.token_type) {
+                self.eat(&lexer::TokenType::END)?;
+                self.eat(&block.token_type)?;
+                return Ok(());
+            }
+
+            if !self.closes_outer_block(&keyword.token_type) {
+                let end_row = self.curr_token().span.start_row;
+                self.next()?;
+                let err = Diagnostic::new(Stage::PARSE, diagnostic::UNEXPECTED_TOKEN, format!{"Expected {} after `end`, found {}",
+                    block.token_type,
+                    found(&keyword)
+                }).with_span(Span::from_token(&keyword))
+                  .with_label(Span::from_token(&block.token), format!{"this `{}` is the block being ended", block.keyword})
+                  .with_help(format!{"did you mean `end {}`?", block.keyword});
+                self.panic_mode = false;
+                self.report(err);
+                // The keyword was probably just misspelled, so it ends this block
+                if keyword.span.start_row == end_row && !self.has(&lexer::TokenType::EOF) {
+                    self.next()?;
+                }
+                return Ok(());
+            }
+        }
+        else if self.has(&lexer::TokenType::ELSE) && !self.closes_outer_block(&lexer::TokenType::IF) {
+            // An 'else' that doesn't belong to an outer 'if' is a
+            //  second 'else', skip it and keep looking for the end
+            self.report(Diagnostic::new(Stage::PARSE, diagnostic::UNEXPECTED_TOKEN, "Unexpected `else`".to_string())
+                .with_span(Span::from_token(&self.curr_token()))
+                .with_help("an `if` can only have one `else`".to_string()));
+            self.next()?;
+            self.block_stack.push(block);
+            self.statements()?;
+            return self.close_block();
+        }
+
+        let curr = self.curr_token();
+        let err = Diagnostic::new(Stage::PARSE, diagnostic::MISSING_END, format!{"This `{}` is missing its `end {}`", block.keyword, block.keyword})
+            .with_span(Span::from_token(&block.token))
+            .with_label(Span::from_token(&curr), format!{"expected `end {}` before this", block.keyword});
+        // A missing end is always reported, it isn't caused by an earlier error
+        self.panic_mode = false;
+        self.report(err);
+        Ok(())
+    }
+
+    // Check if an 'end <keyword>' would end a block outside of the innermost one
+    fn closes_outer_block(&self, token_type: &lexer::TokenType) -> bool {
+        self.block_stack.iter().any(|b| mem::discriminant(&b.token_type) == mem::discriminant(token_type))
+    }
+
+    // Run the parser, giving back the best tree it could make
+    //  (with INVALID nodes where the code couldn't be parsed)
+    //  and every syntax error that was found
+    pub fn parse(&mut self) -> (Option<ParseTree>, Vec<Diagnostic>) {
+        let result = self.code();
+        self.finish(result)
+    }
+
+    // Run the parser on a piece of code typed into the REPL (repl.rs)
+    //  instead of a whole program, see 'entry'
+    pub fn parse_entry(&mut self) -> (Option<ParseTree>, Vec<Diagnostic>) {
+        let result = self.entry();
+        self.finish(result)
+    }
+
+    // Give back the tree (if there is one) with every syntax error
+    fn finish(&mut self, result: Result<ParseTree, Diagnostic>) -> (Option<ParseTree>, Vec<Diagnostic>) {
+        let mut errors = mem::take(&mut self.errors);
+        match result {
+            Ok(tree) => (Some(tree), errors),
+            Err(e) => {
+                errors.push(e);
+                (None, errors)
+            },
+        }
     }
 
     // < CODE >
-    fn code(&mut self) -> Result<Option<ParseTree>, String> {
+    fn code(&mut self) -> Result<ParseTree, Diagnostic> {
         self.next()?;
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::CODE,
-            token: self.curr_token(), 
-            children: Vec::new()
+        let start = self.start();
+        let mut parse_tree = ParseTree {
+            structures: Vec::new(),
+            globals: Vec::new(),
+            functions: Vec::new(),
+            body: Block {
+                statements: Vec::new(),
+                span: start,
+            },
+            span: start,
         };
 
         // [DEF < definitions >]?
         if self.has(&lexer::TokenType::DEF) {
+            let token = self.curr_token();
             self.next()?;
-            parse_tree.children.push(self.definitions()?);
-        }
-        else {
-            parse_tree.children.push(None);
+            self.open_block(lexer::TokenType::DEF, "definitions", token);
+            self.definitions(&mut parse_tree)?;
         }
 
         // < program >
-        parse_tree.children.push(self.program()?);
+        parse_tree.body = self.program()?;
+
+        // Nothing can come after the program
+        if let Err(e) = self.must_be(&lexer::TokenType::EOF) {
+            self.report(e);
+        }
 
-        Ok(Some(parse_tree))
+        parse_tree.span = self.span_from(start);
+        Ok(parse_tree)
     }
 
-    // < definitions >
-    fn definitions(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::DEFINITIONS,
-            token: self.curr_token(), 
-            children: Vec::new()
+    // < entry >
+    // What can be typed into the REPL: structures, functions and
+    //  statements, in any order and without the 'definitions' and
+    //  'program' blocks around them
+    fn entry(&mut self) -> Result<ParseTree, Diagnostic> {
+        self.next()?;
+        let start = self.start();
+        let mut parse_tree = ParseTree {
+            structures: Vec::new(),
+            globals: Vec::new(),
+            functions: Vec::new(),
+            body: Block {
+                statements: Vec::new(),
+                span: start,
+            },
+            span: start,
         };
 
-        // Will check for STRUCT token and return None if there isn't one
+        while !self.has(&lexer::TokenType::EOF) {
+            // < structure-def >
+            if self.has(&lexer::TokenType::STRUCT) {
+                parse_tree.structures.push(self.structure_def()?);
+            }
+            // < function-def >
+            else if self.has(&lexer::TokenType::FUN) {
+                parse_tree.functions.push(self.function_def()?);
+            }
+            // An 'end' without a block to end, skip it and its keyword
+            else if self.has(&lexer::TokenType::END) {
+                self.panic_mode = false;
+                self.report(Diagnostic::new(Stage::PARSE, diagnostic::UNEXPECTED_TOKEN, "Unexpected `end`".to_string())
+                    .with_span(Span::from_token(&self.curr_token()))
+                    .with_help("there is no block here for it to end".to_string()));
+                self.next()?;
+                if !self.has(&lexer::TokenType::EOF) {
+                    self.next()?;
+                }
+            }
+            // < statements >
+            else {
+                parse_tree.body.statements.append(&mut self.statements()?.statements);
+            }
+        }
+
+        parse_tree.body.span = self.span_from(start);
+        parse_tree.span = parse_tree.body.span;
+        Ok(parse_tree)
+    }
+
+    // < definitions >
+    fn definitions(&mut self, parse_tree: &mut ParseTree) -> Result<(), Diagnostic> {
+        // Will check for STRUCT token and be empty if there isn't one
         // < structure-defs >
-        parse_tree.children.push(self.structure_defs()?);
+        parse_tree.structures = self.structure_defs()?;
 
-        // Will check for ID token and return None if there isn't one
+        // Will check for ID token and be empty if there isn't one
         // < global-defs >
-        parse_tree.children.push(self.global_defs()?);
+        let first_global = self.curr_token();
+        parse_tree.globals = self.global_defs()?;
 
-        // Will check for FUN token and return None if there isn't one
+        // Will check for FUN token and be empty if there isn't one
         // < function-defs >
-        parse_tree.children.push(self.function_defs()?);
+        let first_function = self.curr_token();
+        parse_tree.functions = self.function_defs()?;
+
+        // Structures come first, then globals, then functions
+        // Say so directly, and keep parsing the rest of the definitions
+        //  (instead of failing to find the 'end definitions')
+        while self.has(&lexer::TokenType::STRUCT) || self.has(&ID_TYPE) {
+            let (kind, before, first) = match (self.has(&lexer::TokenType::STRUCT), parse_tree.functions.is_empty()) {
+                (true, true) => ("Structures", "global variables", &first_global),
+                (true, false) => ("Structures", "functions", &first_function),
+                (false, _) => ("Global variables", "functions", &first_function),
+            };
+            self.report(Diagnostic::new(Stage::PARSE, diagnostic::OUT_OF_ORDER, format!{"{} must be defined before the {}", kind, before})
+                .with_span(Span::from_token(&self.curr_token()))
+                .with_label(Span::from_token(first), format!{"the {} start here", before})
+                .with_help("move it above them in `definitions`".to_string()));
+
+            parse_tree.structures.append(&mut self.structure_defs()?);
+            parse_tree.globals.append(&mut self.global_defs()?);
+            parse_tree.functions.append(&mut self.function_defs()?);
+        }
 
         // END DEF
-        self.eat(&lexer::TokenType::END)?;
-        self.eat(&lexer::TokenType::DEF)?;
+        self.close_block()?;
 
-        Ok(Some(parse_tree))
+        Ok(())
     }
 
     // < structure-defs >
-    fn structure_defs(&mut self) -> Result<Option<ParseTree>, String> {
-        if !self.has(&lexer::TokenType::STRUCT) {
-            return Ok(None);
-        }
+    fn structure_defs(&mut self) -> Result<Vec<StructDef>, Diagnostic> {
+        let mut struct_defs: Vec<StructDef> = Vec::new();
 
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::STRUCTDEFS,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
-        
         // [< structure-def >]*
         while self.has(&lexer::TokenType::STRUCT) {
-            parse_tree.children.push(self.structure_def()?);
+            struct_defs.push(self.structure_def()?);
         }
 
-        Ok(Some(parse_tree))
+        Ok(struct_defs)
     }
 
     // < structure-def >
-    fn structure_def(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::STRUCTDEF,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+    fn structure_def(&mut self) -> Result<StructDef, Diagnostic> {
+        let start = self.start();
 
         // STRUCT
+        let token = self.curr_token();
         self.eat(&lexer::TokenType::STRUCT)?;
+        self.open_block(lexer::TokenType::STRUCT, "structure", token);
 
         // ID
-        parse_tree.children.push(self.id()?);
+        let id_start = self.curr_token();
+        let id = self.id();
+        let name = self.recover(id, id_start)?;
 
         // < structure-arg >
         // Catch the requirement, but we don't process it here
-        self.must_be(&ID_TYPE)?;
+        if let Err(e) = self.must_be(&ID_TYPE) {
+            self.report(e);
+        }
 
         // < structure-args >
-        parse_tree.children.push(self.structure_args()?);
+        let fields = self.structure_args()?;
 
         // END STRUCT
-        self.eat(&lexer::TokenType::END)?;
-        self.eat(&lexer::TokenType::STRUCT)?;
+        self.close_block()?;
 
-        Ok(Some(parse_tree))
+        Ok(StructDef {
+            name,
+            fields,
+            span: self.span_from(start),
+        })
     }
-    
+
     // ID
-    fn id(&mut self) -> Result<Option<ParseTree>, String> {
+    fn id(&mut self) -> Result<Ident, Diagnostic> {
         // ID
         self.must_be(&ID_TYPE)?;
 
-        let parse_tree = ParseTree{
-            parse_type: ParseType::ID,
-            token: self.curr_token(),
-            children: Vec::new()
+        let token = self.curr_token();
+        let name = match &token.token_type {
+            lexer::TokenType::ID(id) => id.to_string(),
+            _ => String::new(),
         };
 
         self.next()?;
-        Ok(Some(parse_tree))
+        Ok(Ident {
+            name,
+            span: Span::from_token(&token),
+        })
     }
 
     // < structure-args >
-    fn structure_args(&mut self) -> Result<Option<ParseTree>, String> {   
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::STRUCTARGS,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+    // Arguments that couldn't be parsed are left out
+    fn structure_args(&mut self) -> Result<Vec<StructField>, Diagnostic> {
+        let mut fields: Vec<StructField> = Vec::new();
 
         // [< structure-arg >]*
         while self.has(&ID_TYPE) {
-            parse_tree.children.push(self.structure_arg()?);
+            let start = self.curr_token();
+            let arg = self.structure_arg();
+            if let Some(field) = self.recover(arg, start)? {
+                fields.push(field);
+            }
         }
 
-        Ok(Some(parse_tree))
+        Ok(fields)
     }
 
     // < structure-arg >
-    fn structure_arg(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::STRUCTARG,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+    fn structure_arg(&mut self) -> Result<StructField, Diagnostic> {
+        let start = self.start();
 
         // ID
-        parse_tree.children.push(self.id()?);
+        let name = self.id()?;
 
         // COLON
         self.eat(&lexer::TokenType::COLON)?;
 
         // < variable-type >
-        parse_tree.children.push(self.var_type()?);
+        let field_type = self.var_type()?;
 
         // < var-def-equal >
         // But ran internally
         // [EQ]?
+        let mut default: Option<Expr> = None;
         if self.has(&lexer::TokenType::EQ) {
             self.next()?;
 
+            // LINK TO < ref-or-nothing >
+            if self.has(&lexer::TokenType::LINK) {
+                default = Some(self.link_lit()?);
+            }
             // < resolvable >
-            parse_tree.children.push(self.resolvable()?);
-        }
-        else {
-            parse_tree.children.push(None);
+            else {
+                default = Some(self.resolvable()?);
+            }
         }
-        
-        Ok(Some(parse_tree))
+
+        Ok(StructField {
+            name,
+            field_type,
+            default,
+            span: self.span_from(start),
+        })
     }
 
     // < global-defs >
-    fn global_defs(&mut self) -> Result<Option<ParseTree>, String> {
-        if !self.has(&ID_TYPE) {
-            return Ok(None);
-        }
-        
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::GLOBALDEFS,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+    fn global_defs(&mut self) -> Result<Vec<Stmt>, Diagnostic> {
+        let mut globals: Vec<Stmt> = Vec::new();
 
         // [< global-def >]*
         // Here we use a boolean to signify if
@@ -418,29 +1281,19 @@ impl Parser {
         //  resolvable' at the end, this helps
         //  to not repeat code
         while self.has(&ID_TYPE) {
-            parse_tree.children.push(self.assign_or_var_def(false)?);
+            let start = self.curr_token();
+            let def = self.assign_or_var_def(false);
+            let def = self.recover(def, start.clone())?;
+            globals.push(def.unwrap_or_else(|| Parser::invalid_statement(&start)));
         }
 
-        Ok(Some(parse_tree))
+        Ok(globals)
     }
 
-    // Returns either a singular ID tree, or an IDS tree
-    fn ids(&mut self) -> Result<Option<ParseTree>, String> {
+    // Returns every name in a comma separated list of names
+    fn ids(&mut self) -> Result<Vec<Ident>, Diagnostic> {
         // ID
-        let id = self.id()?;
-
-        // This catches just the single ID
-        if !self.has(&lexer::TokenType::COMMA) {
-            return Ok(id);
-        }
-
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::IDS,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
-
-        parse_tree.children.push(id);
+        let mut ids: Vec<Ident> = vec![self.id()?];
 
         // < ids' >
         while self.has(&lexer::TokenType::COMMA) {
@@ -448,62 +1301,56 @@ impl Parser {
 
             // ID
             if self.has(&ID_TYPE) {
-                parse_tree.children.push(self.id()?);
+                ids.push(self.id()?);
             }
             else {
                 break;
             }
         }
 
-        Ok(Some(parse_tree))
+        Ok(ids)
     }
 
     // < function-defs >
-    fn function_defs(&mut self) -> Result<Option<ParseTree>, String> {
-        if !self.has(&lexer::TokenType::FUN) {
-            return Ok(None);
-        }
-        
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::FUNDEFS,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+    fn function_defs(&mut self) -> Result<Vec<FunctionDef>, Diagnostic> {
+        let mut fun_defs: Vec<FunctionDef> = Vec::new();
 
         // [< function-def >]*
         while self.has(&lexer::TokenType::FUN) {
-            parse_tree.children.push(self.function_def()?);
+            fun_defs.push(self.function_def()?);
         }
 
-        Ok(Some(parse_tree))
+        Ok(fun_defs)
     }
 
     // < function-def >
-    fn function_def(&mut self) -> Result<Option<ParseTree>, String> {
+    fn function_def(&mut self) -> Result<FunctionDef, Diagnostic> {
+        let start = self.start();
+
         // FUN
+        let token = self.curr_token();
         self.eat(&lexer::TokenType::FUN)?;
-
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::FUNDEF,
-            token: self.curr_token(), 
-            children: Vec::new()
+        self.open_block(lexer::TokenType::FUN, "function", token);
+
+        let header_start = self.curr_token();
+        let mut fun_def = FunctionDef {
+            name: None,
+            params: Vec::new(),
+            return_type: Type {
+                kind: TypeKind::INVALID,
+                span: Span::from_token(&header_start),
+            },
+            body: Block {
+                statements: Vec::new(),
+                span: start,
+            },
+            span: start,
         };
 
-        // ID
-        parse_tree.children.push(self.id()?);
-        
-        // LPAREN
-        self.eat(&lexer::TokenType::LPAREN)?;
-
-        // < parameters >
-        parse_tree.children.push(self.params()?);
-
-        // RPAREN RETURNS
-        self.eat(&lexer::TokenType::RPAREN)?;
-        self.eat(&lexer::TokenType::RETURNS)?;
-
-        // < fun-return-type >
-        parse_tree.children.push(self.fun_return_type()?);
+        // If the first line is broken, leave the missing
+        //  parts invalid and still check the body
+        let header = self.function_header(&mut fun_def);
+        self.recover(header, header_start)?;
 
         // We cheat having to double up on our BNF by
         //  setting a flag for when we enter a function
@@ -512,46 +1359,61 @@ impl Parser {
         //  caught properly
         self.in_fun_def = true;
         // < fun-statements >
-        parse_tree.children.push(self.statements()?);
+        fun_def.body = self.statements()?;
         self.in_fun_def = false;
 
         // ENF FUN
-        self.eat(&lexer::TokenType::END)?;
-        self.eat(&lexer::TokenType::FUN)?;
+        self.close_block()?;
+
+        fun_def.span = self.span_from(start);
+        Ok(fun_def)
+    }
+
+    // ID LPAREN < parameters > RPAREN RETURNS < fun-return-type >
+    fn function_header(&mut self, fun_def: &mut FunctionDef) -> Result<(), Diagnostic> {
+        // ID
+        fun_def.name = Some(self.id()?);
 
-        Ok(Some(parse_tree))
+        // LPAREN
+        self.eat(&lexer::TokenType::LPAREN)?;
+
+        // < parameters >
+        fun_def.params = self.params()?;
+
+        // RPAREN RETURNS
+        self.eat(&lexer::TokenType::RPAREN)?;
+        self.eat(&lexer::TokenType::RETURNS)?;
+
+        // < fun-return-type >
+        fun_def.return_type = self.fun_return_type()?;
+
+        Ok(())
     }
 
     // < fun-return-type >
-    fn fun_return_type(&mut self) -> Result<Option<ParseTree>, String> {
+    fn fun_return_type(&mut self) -> Result<Type, Diagnostic> {
         // < basic-type >
         if !self.has(&lexer::TokenType::NOTHING) {
             return self.fun_arg_type();
         }
-        
-        // NOTHING
-        let parse_tree = ParseTree{
-            parse_type: ParseType::TYPE,
-            token: self.curr_token(),
-            children: Vec::new()
-        };
 
+        // NOTHING
+        let span = self.start();
         self.next()?;
 
-        Ok(Some(parse_tree))
+        Ok(Type {
+            kind: TypeKind::NOTHING,
+            span,
+        })
     }
 
     // < parameters >
-    fn params(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::PARAMS,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+    fn params(&mut self) -> Result<Vec<Param>, Diagnostic> {
+        let mut params: Vec<Param> = Vec::new();
 
         // [< parameter >]*
         while self.has(&ID_TYPE) {
-            parse_tree.children.push(self.param()?);
+            params.push(self.param()?);
 
             if self.has(&lexer::TokenType::COMMA) {
                 self.next()?;
@@ -561,287 +1423,328 @@ impl Parser {
             }
         }
 
-        Ok(Some(parse_tree))
+        Ok(params)
     }
 
     // < parameter >
-    fn param(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::PARAM,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+    fn param(&mut self) -> Result<Param, Diagnostic> {
+        let start = self.start();
 
         // ID COLON
-        parse_tree.children.push(self.id()?);
+        let name = self.id()?;
         self.eat(&lexer::TokenType::COLON)?;
 
         // [CHANGEABLE]?
-        let type_tree: Option<ParseTree>;
+        let mut changeable = false;
         if self.has(&lexer::TokenType::CHANGEABLE) {
-            let mut changeable_tree = ParseTree {
-                parse_type: ParseType::POINTER,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
             self.next()?;
-
-            // < fun-arg-type >
-            changeable_tree.children.push(self.fun_arg_type()?);
-
-            type_tree = Some(changeable_tree);
-        }
-        // < fun-arg-type >
-        else {
-            type_tree = self.fun_arg_type()?;
+            changeable = true;
         }
 
-        parse_tree.children.push(type_tree);
+        // < fun-arg-type >
+        let param_type = self.fun_arg_type()?;
 
-        Ok(Some(parse_tree))
+        Ok(Param {
+            name,
+            changeable,
+            param_type,
+            span: self.span_from(start),
+        })
     }
 
     // < program >
-    fn program(&mut self) -> Result<Option<ParseTree>, String> {
+    fn program(&mut self) -> Result<Block, Diagnostic> {
         // PROGRAM
-        self.eat(&lexer::TokenType::PROGRAM)?;
-        
+        // Without it there is no block to end, so the 'end program' is
+        //  only eaten if it is there (the missing PROGRAM is the error)
+        let token = self.curr_token();
+        let has_program = self.has(&lexer::TokenType::PROGRAM);
+        self.expect(&lexer::TokenType::PROGRAM)?;
+        if has_program {
+            self.open_block(lexer::TokenType::PROGRAM, "program", token);
+        }
+
         // < statements >
         let body = self.statements()?;
 
         // END PROGRAM
-        self.eat(&lexer::TokenType::END)?;
-        self.eat(&lexer::TokenType::PROGRAM)?;
+        if has_program {
+            self.close_block()?;
+        }
+        else if self.has(&lexer::TokenType::END) && self.peek()?.token_type == lexer::TokenType::PROGRAM {
+            self.next()?;
+            self.next()?;
+        }
 
         Ok(body)
     }
 
     // < statements >
-    fn statements(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::BLOCK,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+    fn statements(&mut self) -> Result<Block, Diagnostic> {
+        let start = self.start();
+        let mut statements: Vec<Stmt> = Vec::new();
 
         // [< statement >]*
         // Also catches when certain end conditions are reached
         //  like the "END" of a loop, or if we hit EOF early
-        while !(self.has(&lexer::TokenType::END) || 
+        while !(self.has(&lexer::TokenType::END) ||
               self.has(&lexer::TokenType::EOF)) {
             if self.in_if_block > 0 && self.has(&lexer::TokenType::ELSE) {
                 break;
             }
 
+            let token = self.curr_token();
+            let statement: Result<Stmt, Diagnostic>;
+
             // < fun-statement > (for return specifically)
             if self.in_fun_def && self.has(&lexer::TokenType::RETURN) {
-                parse_tree.children.push(self.return_statement()?);
+                statement = self.return_statement();
             }
             // < loop-statements > (for BREAK)
             else if self.in_loop_block > 0 && self.has(&lexer::TokenType::BREAK) {
-                let break_tree = ParseTree {
-                    parse_type: ParseType::BREAK,
-                    token: self.curr_token(),
-                    children: Vec::new(),
-                };
                 self.next()?;
-                parse_tree.children.push(Some(break_tree));
+                statement = Ok(Stmt {
+                    kind: StmtKind::BREAK,
+                    span: Span::from_token(&token),
+                });
             }
             // < loop-statements > (for CONTINUE)
             else if self.in_loop_block > 0 && self.has(&lexer::TokenType::CONTINUE) {
-                let continue_tree = ParseTree {
-                    parse_type: ParseType::CONTINUE,
-                    token: self.curr_token(),
-                    children: Vec::new(),
-                };
                 self.next()?;
-                parse_tree.children.push(Some(continue_tree));
+                statement = Ok(Stmt {
+                    kind: StmtKind::CONTINUE,
+                    span: Span::from_token(&token),
+                });
             }
             // < statement >
             else {
-                parse_tree.children.push(self.statement()?);
+                statement = self.statement();
+            }
+
+            // A whole statement was parsed, so we have recovered from any errors
+            if statement.is_ok() {
+                self.panic_mode = false;
             }
+            let statement = self.recover(statement, token.clone())?;
+            statements.push(statement.unwrap_or_else(|| Parser::invalid_statement(&token)));
         }
 
-        Ok(Some(parse_tree))
+        Ok(Block {
+            statements,
+            span: self.span_from(start),
+        })
     }
 
     // < statement >
     // This just runs a match statement with the first token
-    fn statement(&mut self) -> Result<Option<ParseTree>, String> {
+    fn statement(&mut self) -> Result<Stmt, Diagnostic> {
         match self.curr_token().token_type {
             lexer::TokenType::ID(_) => self.assign_or_var_def(true),
             lexer::TokenType::WHILE => self.while_block(),
-            lexer::TokenType::IF => self.if_block(),
+            lexer::TokenType::IF => {
+                let if_block = self.if_block()?;
+                Ok(Stmt {
+                    span: if_block.span,
+                    kind: StmtKind::IF(if_block),
+                })
+            },
             lexer::TokenType::REPEAT => self.repeat(),
             lexer::TokenType::UNLINK => self.unlink(),
             lexer::TokenType::QUIT => self.quit(),
-            _ => self.resolvable(),
+            _ => {
+                let value = self.resolvable()?;
+                Ok(Stmt {
+                    span: value.span,
+                    kind: StmtKind::EXPR(value),
+                })
+            },
         }
     }
 
     // RETURN < return-value >
-    fn return_statement(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::RETURN,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+    fn return_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.start();
 
         // RETURN
         self.eat(&lexer::TokenType::RETURN)?;
 
         // < return-value > ==> NOTHING
+        let mut value: Option<Expr> = None;
         if self.has(&lexer::TokenType::NOTHING) {
             self.next()?;
-            parse_tree.children.push(None);
         }
 
         // < return-value > ==> < resolvable >
         else {
-            parse_tree.children.push(self.resolvable()?);
+            value = Some(self.resolvable()?);
         }
 
-        Ok(Some(parse_tree))
+        Ok(Stmt {
+            kind: StmtKind::RETURN(value),
+            span: self.span_from(start),
+        })
     }
 
     // < asg or var-def or res > and < asg or var-def >
-    fn assign_or_var_def(&mut self, go_to_resolvable: bool) -> Result<Option<ParseTree>, String> {
-        let mut id_tree = self.ids()?;
+    fn assign_or_var_def(&mut self, go_to_resolvable: bool) -> Result<Stmt, Diagnostic> {
+        let start = self.start();
+        let mut ids = self.ids()?;
 
         // handle special < var-def > for multiple ids
-        if id_tree.clone().unwrap().parse_type == ParseType::IDS {
+        if ids.len() > 1 {
             self.eat(&lexer::TokenType::COLON)?;
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::VARDEFS,
-                token: self.curr_token(), 
-                children: Vec::new(),
+            let var_def = VarDef {
+                names: ids,
+                var_type: self.var_type()?,
+                span: self.span_from(start),
             };
-            parse_tree.children.push(id_tree);
-            parse_tree.children.push(self.var_type()?);
             // don't allow for multiple assignment, only type def
-            return Ok(Some(parse_tree));
+            return Ok(Stmt {
+                span: var_def.span,
+                kind: StmtKind::VARDEF(var_def),
+            });
         }
 
         // < var-def >
         if self.has(&lexer::TokenType::COLON) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::VARDEF,
-                token: self.curr_token(), 
-                children: Vec::new(),
-            };
             self.next()?;
 
-            parse_tree.children.push(id_tree);
-            parse_tree.children.push(self.var_type()?);
+            let var_def = VarDef {
+                names: ids,
+                var_type: self.var_type()?,
+                span: self.span_from(start),
+            };
 
             // < var-def-equal >
             if self.has(&lexer::TokenType::EQ) {
-                let mut assign_tree = ParseTree {
-                    parse_type: ParseType::ASSIGN,
-                    token: self.curr_token(), 
-                    children: Vec::new(),
-                };
                 self.next()?;
-
-                assign_tree.children.push(Some(parse_tree));
-
-                assign_tree.children.push(self.resolvable()?);
-                return Ok(Some(assign_tree));
+                return self.assignment2(start, Target::VARDEF(var_def));
             }
 
-            return Ok(Some(parse_tree));
+            return Ok(Stmt {
+                span: var_def.span,
+                kind: StmtKind::VARDEF(var_def),
+            });
         }
 
         // < assignment >
-        id_tree = self.reference2(id_tree)?;
+        let reference = self.reference2(Reference::from_ident(ids.remove(0)))?;
         if self.has(&lexer::TokenType::EQ) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::ASSIGN,
-                token: self.curr_token(), 
-                children: Vec::new(),
-            };
             self.next()?;
-            parse_tree.children.push(id_tree);
-            parse_tree.children.push(self.resolvable()?);
-            return Ok(Some(parse_tree));
+            return self.assignment2(start, Target::REFERENCE(reference));
         }
 
         // < resolvable' >
         if go_to_resolvable {
-            self.resolvable2(id_tree)
+            let value = self.resolvable2(reference)?;
+            Ok(Stmt {
+                span: value.span,
+                kind: StmtKind::EXPR(value),
+            })
         }
         else {
-            self.must_be(&lexer::TokenType::INVALID)?;
-            Ok(None)
+            Err(self.unexpected(&lexer::TokenType::COLON))
+        }
+    }
+
+    // < assignment' >
+    // Takes in where the statement started and the left
+    //  hand side, either a reference or a variable definition
+    fn assignment2(&mut self, start: Span, target: Target) -> Result<Stmt, Diagnostic> {
+        // LINK TO < ref-or-nothing >
+        // Linking is its own statement
+        if self.has(&lexer::TokenType::LINK) {
+            let linked = self.link_to()?;
+            return Ok(Stmt {
+                kind: StmtKind::LINK(target, linked),
+                span: self.span_from(start),
+            });
         }
+
+        // < resolvable >
+        let value = self.resolvable()?;
+        Ok(Stmt {
+            kind: StmtKind::ASSIGN(target, value),
+            span: self.span_from(start),
+        })
+    }
+
+    // < condition >, recovering to an INVALID condition
+    fn recover_condition(&mut self) -> Result<Condition, Diagnostic> {
+        let start = self.curr_token();
+        let condition = self.condition();
+        let condition = self.recover(condition, start.clone())?;
+        Ok(condition.unwrap_or_else(|| Parser::invalid_condition(&start)))
     }
 
     // < while >
-    fn while_block(&mut self) -> Result<Option<ParseTree>, String> {
+    fn while_block(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.start();
+
         // WHILE
+        let token = self.curr_token();
         self.eat(&lexer::TokenType::WHILE)?;
-        
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::WHILE,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+        self.open_block(lexer::TokenType::WHILE, "while", token);
 
         // < condition >
-        parse_tree.children.push(self.condition()?);
+        let condition = self.recover_condition()?;
 
         // < loop-statements >
         // Using a flag to mark when we are in a loop
         //  helps reduce the BNF by not requireing
         //  more special function for BREAK and CONTINUE
         self.in_loop_block += 1;
-        parse_tree.children.push(self.statements()?);
+        let body = self.statements()?;
         self.in_loop_block -= 1;
 
         // END WHILE
-        self.eat(&lexer::TokenType::END)?;
-        self.eat(&lexer::TokenType::WHILE)?;
+        self.close_block()?;
 
-        Ok(Some(parse_tree))
+        Ok(Stmt {
+            kind: StmtKind::WHILE(condition, body),
+            span: self.span_from(start),
+        })
     }
 
     // < if >
-    fn if_block(&mut self) -> Result<Option<ParseTree>, String> {
+    fn if_block(&mut self) -> Result<If, Diagnostic> {
+        let start = self.start();
+
         // IF
+        let token = self.curr_token();
         self.eat(&lexer::TokenType::IF)?;
-        
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::IF,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+        self.open_block(lexer::TokenType::IF, "if", token);
 
         // < condition > THEN
-        parse_tree.children.push(self.condition()?);
-        self.eat(&lexer::TokenType::THEN)?;
+        let condition = self.recover_condition()?;
+        self.expect(&lexer::TokenType::THEN)?;
 
         // < statements >
         // Using a flag to mark when we are in an if
         //  statement to make up for the < if2 elif >
         //  non-terminal
         self.in_if_block += 1;
-        parse_tree.children.push(self.statements()?);
+        let body = self.statements()?;
         self.in_if_block -= 1;
 
         // < if2 >
-        parse_tree.children.push(self.if_block_2()?);
+        let otherwise = self.if_block_2()?;
 
-        Ok(Some(parse_tree))
+        Ok(If {
+            condition,
+            body,
+            otherwise,
+            span: self.span_from(start),
+        })
     }
 
     // < if2 > and < if2 elif > put together
-    fn if_block_2(&mut self) -> Result<Option<ParseTree>, String> {
-        
+    fn if_block_2(&mut self) -> Result<Option<Else>, Diagnostic> {
+
         // END IF
-        if self.has(&lexer::TokenType::END) {
-            self.eat(&lexer::TokenType::END)?;
-            self.eat(&lexer::TokenType::IF)?;
+        // (or a missing end, which close_block reports)
+        if !self.has(&lexer::TokenType::ELSE) {
+            self.close_block()?;
             return Ok(None);
         }
 
@@ -850,8 +1753,11 @@ impl Parser {
 
         // < if2 elif > is handled by < if >
         //  thanks to the in_if_block flag
+        // The 'end if' belongs to the new if, so this one is
+        //  taken off of the block stack
         if self.has(&lexer::TokenType::IF) {
-            return self.if_block();
+            self.block_stack.pop();
+            return Ok(Some(Else::IF(Box::new(self.if_block()?))));
         }
 
         // we don't need to set self.in_if_block here
@@ -861,64 +1767,58 @@ impl Parser {
         let else_block = self.statements()?;
 
         // END IF
-        self.eat(&lexer::TokenType::END)?;
-        self.eat(&lexer::TokenType::IF)?;
+        self.close_block()?;
 
-        Ok(else_block)
+        Ok(Some(Else::BLOCK(else_block)))
     }
 
     // < condition >
-    fn condition(&mut self) -> Result<Option<ParseTree>, String> {
+    fn condition(&mut self) -> Result<Condition, Diagnostic> {
         // < condition >
         let mut left = self.logic_andable()?;
 
-        let mut parse_tree: ParseTree;
-
         // < condition' >
         while self.has(&lexer::TokenType::OR) {
-            parse_tree = ParseTree {
-                parse_type: ParseType::BINCOMP,
-                token: self.curr_token(),
-                children: Vec::new(),
+            self.next()?;
+            let right = self.logic_andable()?;
+            left = Condition {
+                span: left.span.to(right.span),
+                kind: ConditionKind::OR(Box::new(left), Box::new(right)),
             };
-            parse_tree.children.push(left);
-            parse_tree.children.push(self.logic_andable()?);
-            left = Some(parse_tree);
         }
 
         Ok(left)
     }
 
     // < logic-andable >
-    fn logic_andable(&mut self) -> Result<Option<ParseTree>, String> {
+    fn logic_andable(&mut self) -> Result<Condition, Diagnostic> {
         // < logic-paren >
         let mut left = self.logic_paren()?;
 
-        let mut parse_tree: ParseTree;
-
         // < logic-andable' >
         while self.has(&lexer::TokenType::AND) {
-            parse_tree = ParseTree {
-                parse_type: ParseType::BINCOMP,
-                token: self.curr_token(),
-                children: Vec::new(),
+            self.next()?;
+            let right = self.logic_paren()?;
+            left = Condition {
+                span: left.span.to(right.span),
+                kind: ConditionKind::AND(Box::new(left), Box::new(right)),
             };
-            parse_tree.children.push(left);
-            parse_tree.children.push(self.logic_paren()?);
-            left = Some(parse_tree);
         }
 
         Ok(left)
     }
 
     // < logic-paren >
-    fn logic_paren(&mut self) -> Result<Option<ParseTree>, String> {
+    fn logic_paren(&mut self) -> Result<Condition, Diagnostic> {
         // LPAREN < condition > RPAREN
         if self.has(&lexer::TokenType::LPAREN) {
+            let start = self.start();
             self.next()?;
-            let condition_tree = self.condition();
+            let mut condition = self.condition()?;
             self.eat(&lexer::TokenType::RPAREN)?;
-            return condition_tree;
+            // The parenthesis are part of the condition's code
+            condition.span = self.span_from(start);
+            return Ok(condition);
         }
 
         // < comparable >
@@ -926,16 +1826,16 @@ impl Parser {
     }
 
     // < comparable >
-    fn comparable(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut left: Option<ParseTree> = Some( ParseTree {
-            parse_type: ParseType::INVALID,
-            token: self.curr_token(), 
-            children: Vec::new(),
-        });
-        
+    fn comparable(&mut self) -> Result<Condition, Diagnostic> {
+        let start = self.start();
+        let left: Expr;
+
         // < comparable >
         if self.has(&ID_TYPE) {
-            let id_tree = self.id()?;
+            // Pull in the full reference first, so that
+            //  "x.next is linked" can be caught
+            let id = self.id()?;
+            let reference = self.reference2(Reference::from_ident(id))?;
 
             // < comparable' >
             if self.has(&lexer::TokenType::IS) {
@@ -943,112 +1843,90 @@ impl Parser {
                 self.next()?;
                 // LINKED
                 if self.has(&lexer::TokenType::LINKED) {
-                    let mut parse_tree = ParseTree {
-                        parse_type: ParseType::ISLINKED,
-                        token:  self.curr_token(),
-                        children: Vec::new(),
-                    };
-                    parse_tree.children.push(self.reference2(id_tree)?);
                     self.next()?;
-                    return Ok(Some(parse_tree));
+                    return Ok(Condition {
+                        kind: ConditionKind::ISLINKED(reference),
+                        span: self.span_from(start),
+                    });
                 }
                 // WORDNOT LINKED
                 else {
                     self.eat(&lexer::TokenType::WORDNOT)?;
-                    let mut parse_tree = ParseTree {
-                        parse_type: ParseType::ISNOTLINKED,
-                        token: self.curr_token(),
-                        children: Vec::new(),
-                    };
-                    parse_tree.children.push(self.reference2(id_tree)?);
                     self.eat(&lexer::TokenType::LINKED)?;
-                    return Ok(Some(parse_tree));
+                    return Ok(Condition {
+                        kind: ConditionKind::ISNOTLINKED(reference),
+                        span: self.span_from(start),
+                    });
                 }
             }
             else {
                 // < resolvable >
-                left = self.resolvable2(id_tree)?;
+                left = self.resolvable2(reference)?;
             }
         }
-
-        let mut parse_tree: ParseTree = ParseTree {
-            parse_type: ParseType::INVALID,
-            token: self.curr_token(), 
-            children: Vec::new(),
-        };
+        else {
+            left = self.resolvable()?;
+        }
 
         // < comparable'' >
-        if self.has(&lexer::TokenType::GT) ||
-           self.has(&lexer::TokenType::LT) ||
-           self.has(&lexer::TokenType::GE) ||
-           self.has(&lexer::TokenType::LE) ||
-           self.has(&lexer::TokenType::EQ) ||
-           self.must_be(&lexer::TokenType::NE)? {
-            parse_tree = ParseTree {
-                parse_type: ParseType::BINCOMP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
-            self.next()?;
-            parse_tree.children.push(self.resolvable()?);
-        }
+        let op = match self.curr_token().token_type {
+            lexer::TokenType::GT => CompareOp::GT,
+            lexer::TokenType::LT => CompareOp::LT,
+            lexer::TokenType::GE => CompareOp::GE,
+            lexer::TokenType::LE => CompareOp::LE,
+            lexer::TokenType::EQ => CompareOp::EQ,
+            lexer::TokenType::NE => CompareOp::NE,
+            _ => return Err(self.expected("a comparison, like `<` or `=`", None)),
+        };
+        self.next()?;
+        let right = self.resolvable()?;
 
-        Ok(Some(parse_tree))
+        Ok(Condition {
+            kind: ConditionKind::COMPARE(op, left, right),
+            span: self.span_from(start),
+        })
     }
 
     // < repeat >
-    fn repeat(&mut self) -> Result<Option<ParseTree>, String> {
+    fn repeat(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.start();
+
         // REPEAT
+        let token = self.curr_token();
         self.eat(&lexer::TokenType::REPEAT)?;
+        self.open_block(lexer::TokenType::REPEAT, "repeat", token);
+
+        // The loop variable and values for FOR, or the count for TIMES
+        let mut for_all: Option<(Option<Ident>, Expr)> = None;
+        let mut count: Option<Expr> = None;
 
-        let mut parse_tree;
-        
         // FOREVER
         if self.has(&lexer::TokenType::FOREVER) {
             self.eat(&lexer::TokenType::FOREVER)?;
-
-            parse_tree = ParseTree {
-                parse_type: ParseType::REPEATFOREVER,
-                token: self.curr_token(), 
-                children: Vec::new(),
-            };
         }
 
         // FOR ALL ID IN < resolvable >
         else if self.has(&lexer::TokenType::FOR) {
             self.eat(&lexer::TokenType::FOR)?;
-            self.eat(&lexer::TokenType::ALL)?;
 
-            parse_tree = ParseTree {
-                parse_type: ParseType::REPEATFOR,
-                token: self.curr_token(), 
-                children: Vec::new(),
+            let header_start = self.curr_token();
+            let header = self.repeat_for_header();
+            for_all = match self.recover(header, header_start.clone())? {
+                Some((id, values)) => Some((Some(id), values)),
+                None => Some((None, Parser::invalid_expr(&header_start))),
             };
-
-            // ID
-            parse_tree.children.push(self.id()?);
-
-            // IN
-            self.eat(&lexer::TokenType::IN)?;
-
-            // < resolvable >
-            parse_tree.children.push(self.resolvable()?);
         }
 
         // < resolvable > TIMES
         else {
-            parse_tree = ParseTree {
-                parse_type: ParseType::REPEAT,
-                token: self.curr_token(), 
-                children: Vec::new(),
-            };
-
             // < resolvable >
-            parse_tree.children.push(self.resolvable()?);
+            let count_start = self.curr_token();
+            let value = self.resolvable();
+            let value = self.recover(value, count_start.clone())?;
+            count = Some(value.unwrap_or_else(|| Parser::invalid_expr(&count_start)));
 
             // TIMES
-            self.eat(&lexer::TokenType::TIMES)?;
+            self.expect(&lexer::TokenType::TIMES)?;
         }
 
         // < loop-statements >
@@ -1056,243 +1934,237 @@ impl Parser {
         //  helps reduce the BNF by not requireing
         //  more special functions for BREAK and CONTINUE
         self.in_loop_block += 1;
-        parse_tree.children.push(self.statements()?);
+        let body = self.statements()?;
         self.in_loop_block -= 1;
 
         // END REPEAT
-        self.eat(&lexer::TokenType::END)?;
-        self.eat(&lexer::TokenType::REPEAT)?;
+        self.close_block()?;
 
-        Ok(Some(parse_tree))
+        let kind = match (for_all, count) {
+            (Some((variable, values)), _) => StmtKind::REPEATFOR(variable, values, body),
+            (None, Some(count)) => StmtKind::REPEAT(count, body),
+            (None, None) => StmtKind::REPEATFOREVER(body),
+        };
+
+        Ok(Stmt {
+            kind,
+            span: self.span_from(start),
+        })
+    }
+
+    // ALL ID IN < resolvable >
+    fn repeat_for_header(&mut self) -> Result<(Ident, Expr), Diagnostic> {
+        self.eat(&lexer::TokenType::ALL)?;
+
+        // ID
+        let id = self.id()?;
+
+        // IN
+        self.eat(&lexer::TokenType::IN)?;
+
+        // < resolvable >
+        Ok((id, self.resolvable()?))
     }
 
     // < unlink >
-    fn unlink(&mut self) -> Result<Option<ParseTree>, String> {
+    fn unlink(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.start();
+
         // UNLINK
         self.eat(&lexer::TokenType::UNLINK)?;
 
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::UNLINK,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
-
         // < reference >
-        parse_tree.children.push(self.reference()?);
+        let reference = self.reference()?;
 
-        Ok(Some(parse_tree))
+        Ok(Stmt {
+            kind: StmtKind::UNLINK(reference),
+            span: self.span_from(start),
+        })
     }
 
-    // QUIT
-    fn quit(&mut self) -> Result<Option<ParseTree>, String> {
+    // QUIT < quit-status >
+    fn quit(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.start();
+        let row = self.curr_token().span.end_row;
+
         // QUIT
         self.eat(&lexer::TokenType::QUIT)?;
 
-        let parse_tree = ParseTree{
-            parse_type: ParseType::QUIT,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
-
-        Ok(Some(parse_tree))
+        // < quit-status > ==> < resolvable >
+        // The status has to be on the same line as the quit, otherwise
+        //  the statement after it would be taken as the status
+        let mut status: Option<Expr> = None;
+        let token = self.curr_token();
+        match token.token_type {
+            lexer::TokenType::EOF |
+            lexer::TokenType::END |
+            lexer::TokenType::ELSE => (),
+            _ if token.span.start_row == row => status = Some(self.resolvable()?),
+            // < quit-status > ==> ''
+            _ => (),
+        }
+
+        Ok(Stmt {
+            kind: StmtKind::QUIT(status),
+            span: self.span_from(start),
+        })
     }
 
     // < reference >
-    fn reference(&mut self) -> Result<Option<ParseTree>, String> {
+    fn reference(&mut self) -> Result<Reference, Diagnostic> {
         // ID
-        let id_tree = self.id()?;
+        let id = self.id()?;
         // < reference' >
-        self.reference2(id_tree)
+        self.reference2(Reference::from_ident(id))
     }
 
     // < reference' >
-    fn reference2(&mut self, id_tree: Option<ParseTree>) -> Result<Option<ParseTree>, String> {
+    fn reference2(&mut self, reference: Reference) -> Result<Reference, Diagnostic> {
         // LBRACKET < index > RBRACKET < reference' >
         if self.has(&lexer::TokenType::LBRACKET) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::GETINDEX,
-                token: self.curr_token(), 
-                children: Vec::new(),
-            };
             // LBRACKET
             self.next()?;
-            parse_tree.children.push(id_tree);
             // < index >
-            parse_tree.children.push(self.index()?);
+            let indexes = self.index()?;
             // RBRACKET
             self.eat(&lexer::TokenType::RBRACKET)?;
             // < reference' >
-            return self.reference2(Some(parse_tree));
+            let span = reference.span.to(self.last_span);
+            return self.reference2(Reference {
+                kind: ReferenceKind::INDEX(Box::new(reference), indexes),
+                span,
+            });
         }
         // PERIOD ID < reference' >
         else if self.has(&lexer::TokenType::PERIOD) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::GETSTRUCT,
-                token: self.curr_token(), 
-                children: Vec::new(),
-            };
             // PERIOD
             self.next()?;
-            parse_tree.children.push(id_tree);
             // ID
-            parse_tree.children.push(self.id()?);
+            let key = self.id()?;
             // < reference' >
-            return self.reference2(Some(parse_tree));
+            let span = reference.span.to(key.span);
+            return self.reference2(Reference {
+                kind: ReferenceKind::FIELD(Box::new(reference), key),
+                span,
+            });
         }
 
         // ''
-        Ok(id_tree)
+        Ok(reference)
     }
 
     // < index >
-    fn index(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::INDEX,
-            token: self.curr_token(),
-            children: Vec::new()
-        };
+    fn index(&mut self) -> Result<Vec<Expr>, Diagnostic> {
+        let mut indexes: Vec<Expr> = Vec::new();
 
         // [<resolvable>]+
         while !self.has(&lexer::TokenType::RBRACKET) {
-            parse_tree.children.push(self.resolvable()?);
+            indexes.push(self.resolvable()?);
 
             if self.has(&lexer::TokenType::COMMA) {
                 self.next()?;
             }
         }
 
-        Ok(Some(parse_tree))
+        Ok(indexes)
+    }
+
+    // If the current token is one of the given operators, give back
+    //  that operator
+    fn operator(&self, ops: &[BinOp]) -> Option<BinOp> {
+        let op = match self.curr_token().token_type {
+            lexer::TokenType::ADD => BinOp::ADD,
+            lexer::TokenType::SUB => BinOp::SUB,
+            lexer::TokenType::MUL => BinOp::MUL,
+            lexer::TokenType::DIV => BinOp::DIV,
+            lexer::TokenType::MOD => BinOp::MOD,
+            lexer::TokenType::POW => BinOp::POW,
+            lexer::TokenType::BAND => BinOp::BAND,
+            lexer::TokenType::BOR => BinOp::BOR,
+            lexer::TokenType::BXOR => BinOp::BXOR,
+            lexer::TokenType::BSL => BinOp::BSL,
+            lexer::TokenType::BSR => BinOp::BSR,
+            _ => return None,
+        };
+
+        if ops.contains(&op) {
+            return Some(op);
+        }
+        None
     }
 
     // < resolvable >
-    fn resolvable(&mut self) -> Result<Option<ParseTree>, String> {
+    fn resolvable(&mut self) -> Result<Expr, Diagnostic> {
         // < bit-orable >
         self.bit_orable()
     }
 
     // < resolvable' >
-    fn resolvable2(&mut self, id_tree: Option<ParseTree>) -> Result<Option<ParseTree>, String> {
+    fn resolvable2(&mut self, reference: Reference) -> Result<Expr, Diagnostic> {
         // < ref-or-call >
-        let mut left = self.ref_or_call(id_tree)?;
+        let mut left = self.ref_or_call(reference)?;
 
         // < factor' >
-        if self.has(&lexer::TokenType::POW){
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        if let Some(op) = self.operator(&[BinOp::POW]) {
             self.next()?;
-            parse_tree.children.push(self.factor()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.factor()?);
         }
 
         // < term' >
-        while self.has(&lexer::TokenType::MUL) ||
-              self.has(&lexer::TokenType::DIV) ||
-              self.has(&lexer::TokenType::MOD) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        while let Some(op) = self.operator(&[BinOp::MUL, BinOp::DIV, BinOp::MOD]) {
             self.next()?;
-            parse_tree.children.push(self.factor()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.factor()?);
         }
 
         // < expression' >
-        while self.has(&lexer::TokenType::ADD) ||
-              self.has(&lexer::TokenType::SUB) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        while let Some(op) = self.operator(&[BinOp::ADD, BinOp::SUB]) {
             self.next()?;
-            parse_tree.children.push(self.term()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.term()?);
         }
 
         // < bit-shiftable' >
-        while self.has(&lexer::TokenType::BSL) ||
-           self.has(&lexer::TokenType::BSR) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        while let Some(op) = self.operator(&[BinOp::BSL, BinOp::BSR]) {
             self.next()?;
-            parse_tree.children.push(self.expression()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.expression()?);
         }
 
         // < bit-andable' >
-        while self.has(&lexer::TokenType::BAND) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        while let Some(op) = self.operator(&[BinOp::BAND]) {
             self.next()?;
-            parse_tree.children.push(self.bit_shiftable()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.bit_shiftable()?);
         }
 
         // < bit-xorable' >
-        while self.has(&lexer::TokenType::BXOR) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        while let Some(op) = self.operator(&[BinOp::BXOR]) {
             self.next()?;
-            parse_tree.children.push(self.bit_andable()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.bit_andable()?);
         }
 
         // < bit-orable' >
-        while self.has(&lexer::TokenType::BOR) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        while let Some(op) = self.operator(&[BinOp::BOR]) {
             self.next()?;
-            parse_tree.children.push(self.bit_xorable()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.bit_xorable()?);
         }
-        
+
         Ok(left)
     }
 
     // < arg-list >
-    fn arg_list(&mut self) -> Result<Option<ParseTree>, String> {
+    fn arg_list(&mut self) -> Result<Vec<Expr>, Diagnostic> {
         self.eat(&lexer::TokenType::LPAREN)?;
 
+        let mut args: Vec<Expr> = Vec::new();
+
         // handle the empty argument list
         // ''
         if self.has(&lexer::TokenType::RPAREN) {
             self.next()?;
-            return Ok(None);
+            return Ok(args);
         }
 
-        let mut parse_tree = ParseTree {
-            parse_type: ParseType::ARGS,
-            token: self.curr_token(), 
-            children: Vec::new(),
-        };
-
         // [< resolvable >]*
         while !self.has(&lexer::TokenType::RPAREN) {
-            parse_tree.children.push(self.resolvable()?);
+            args.push(self.resolvable()?);
 
             if self.has(&lexer::TokenType::COMMA) {
                 self.next()?;
@@ -1301,186 +2173,129 @@ impl Parser {
 
         self.eat(&lexer::TokenType::RPAREN)?;
 
-        Ok(Some(parse_tree))
+        Ok(args)
     }
 
     // < bit-orable>
-    fn bit_orable(&mut self) -> Result<Option<ParseTree>, String> {
+    fn bit_orable(&mut self) -> Result<Expr, Diagnostic> {
         // < bit-xorable >
         let mut left = self.bit_xorable()?;
 
-
         // < bit-orable' >
-        while self.has(&lexer::TokenType::BOR) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        while let Some(op) = self.operator(&[BinOp::BOR]) {
             self.next()?;
-            parse_tree.children.push(self.bit_xorable()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.bit_xorable()?);
         }
 
         Ok(left)
     }
 
     // < bit-xorable >
-    fn bit_xorable(&mut self) -> Result<Option<ParseTree>, String> {
+    fn bit_xorable(&mut self) -> Result<Expr, Diagnostic> {
         // < bit-andable >
         let mut left = self.bit_andable()?;
 
-
         // < bit-xorable' >
-        while self.has(&lexer::TokenType::BXOR) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        while let Some(op) = self.operator(&[BinOp::BXOR]) {
             self.next()?;
-            parse_tree.children.push(self.bit_andable()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.bit_andable()?);
         }
 
         Ok(left)
     }
 
     // < bit-andable >
-    fn bit_andable(&mut self) -> Result<Option<ParseTree>, String> {
+    fn bit_andable(&mut self) -> Result<Expr, Diagnostic> {
         // < bit-shiftable >
         let mut left = self.bit_shiftable()?;
 
         // < bit-andable' >
-        while self.has(&lexer::TokenType::BAND) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        while let Some(op) = self.operator(&[BinOp::BAND]) {
             self.next()?;
-            parse_tree.children.push(self.bit_shiftable()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.bit_shiftable()?);
         }
 
         Ok(left)
     }
 
     // < bit-shiftable >
-    fn bit_shiftable(&mut self) -> Result<Option<ParseTree>, String> {
+    fn bit_shiftable(&mut self) -> Result<Expr, Diagnostic> {
         // < expression >
         let mut left = self.expression()?;
 
         // < bit-shiftable' >
-        while self.has(&lexer::TokenType::BSL) ||
-           self.has(&lexer::TokenType::BSR) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        while let Some(op) = self.operator(&[BinOp::BSL, BinOp::BSR]) {
             self.next()?;
-            parse_tree.children.push(self.expression()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.expression()?);
         }
 
         Ok(left)
     }
 
     // < expression >
-    fn expression(&mut self) -> Result<Option<ParseTree>, String> {
+    fn expression(&mut self) -> Result<Expr, Diagnostic> {
         // < term >
         let mut left = self.term()?;
 
         // < expression' >
-        while self.has(&lexer::TokenType::ADD) ||
-              self.has(&lexer::TokenType::SUB) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        while let Some(op) = self.operator(&[BinOp::ADD, BinOp::SUB]) {
             self.next()?;
-            parse_tree.children.push(self.term()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.term()?);
         }
 
         Ok(left)
     }
 
     // < term >
-    fn term(&mut self) -> Result<Option<ParseTree>, String> {
+    fn term(&mut self) -> Result<Expr, Diagnostic> {
         // < factor >
         let mut left = self.factor()?;
 
         // < term' >
-        while self.has(&lexer::TokenType::MUL) ||
-              self.has(&lexer::TokenType::DIV) ||
-              self.has(&lexer::TokenType::MOD) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        while let Some(op) = self.operator(&[BinOp::MUL, BinOp::DIV, BinOp::MOD]) {
             self.next()?;
-            parse_tree.children.push(self.factor()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.factor()?);
         }
 
         Ok(left)
     }
 
     // < factor >
-    fn factor(&mut self) -> Result<Option<ParseTree>, String> {
+    fn factor(&mut self) -> Result<Expr, Diagnostic> {
         // [SUB]* < factor >
         if self.has(&lexer::TokenType::SUB) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::NEG,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
+            let start = self.start();
             self.next()?;
-            parse_tree.children.push(self.factor()?);
-            return Ok(Some(parse_tree));
+            let value = self.factor()?;
+            return Ok(Expr {
+                kind: ExprKind::NEG(Box::new(value)),
+                span: self.span_from(start),
+            });
         }
 
         // < bit-notable >
         let mut left = self.bit_notable()?;
 
         // < factor' >
-        if self.has(&lexer::TokenType::POW){
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BINOP,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            parse_tree.children.push(left);
+        if let Some(op) = self.operator(&[BinOp::POW]) {
             self.next()?;
-            parse_tree.children.push(self.factor()?);
-            left = Some(parse_tree);
+            left = binary(op, left, self.factor()?);
         }
 
         Ok(left)
     }
 
     // < bit-notable >
-    fn bit_notable(&mut self) -> Result<Option<ParseTree>, String> {
+    fn bit_notable(&mut self) -> Result<Expr, Diagnostic> {
         // [BNOT]* < exponent >
         if self.has(&lexer::TokenType::BNOT) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::BITNOT,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
+            let start = self.start();
             self.next()?;
-            parse_tree.children.push(self.exponent()?);
-            return Ok(Some(parse_tree));
+            let value = self.exponent()?;
+            return Ok(Expr {
+                kind: ExprKind::BITNOT(Box::new(value)),
+                span: self.span_from(start),
+            });
         }
 
         // < exponent >
@@ -1488,96 +2303,104 @@ impl Parser {
     }
 
     // < exponent >
-    fn exponent(&mut self) -> Result<Option<ParseTree>, String> {
+    fn exponent(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.start();
+
         // ADD < exponent >
         if self.has(&lexer::TokenType::ADD) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::ABS,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
             self.next()?;
-            parse_tree.children.push(self.exponent()?);
-            return Ok(Some(parse_tree));
+            let value = self.exponent()?;
+            Ok(Expr {
+                kind: ExprKind::ABS(Box::new(value)),
+                span: self.span_from(start),
+            })
         }
         // LPAREN < resolvable > RPAREN
         else if self.has(&lexer::TokenType::LPAREN) {
             self.next()?;
-            let resolvable_tree = self.resolvable();
+            let mut value = self.resolvable()?;
             self.eat(&lexer::TokenType::RPAREN)?;
-            return resolvable_tree;
+            // The parenthesis are part of the value's code, so an
+            //  operation with it covers them too
+            value.span = self.span_from(start);
+            Ok(value)
         }
         // NUMBER | TEXT
         else if self.has(&NUMBER_TYPE) ||
                 self.has(&TEXT_TYPE)   {
-            let parse_tree = ParseTree {
-                parse_type: ParseType::LIT,
-                token: self.curr_token(),
-                children: Vec::new()
+            let kind = match self.curr_token().token_type {
+                lexer::TokenType::NUMBER(n) => ExprKind::NUMBER(n),
+                lexer::TokenType::TEXT(t) => ExprKind::TEXT(t),
+                _ => ExprKind::INVALID,
             };
             self.next()?;
-            return Ok(Some(parse_tree))
+            Ok(Expr {
+                kind,
+                span: start,
+            })
         }
-        // ID
+        // ID < reference' > < ref-or-call >
         else if self.has(&ID_TYPE) {
-            let id_tree = self.id()?;
-            return self.ref_or_call(id_tree);
+            let id = self.id()?;
+            let reference = self.reference2(Reference::from_ident(id))?;
+            self.ref_or_call(reference)
         }
         // (LCURLY) < struct-lit >
         else if self.has(&lexer::TokenType::LCURLY) {
-            return self.struct_lit();
+            self.struct_lit()
         }
         // (LBRACKET) < array-lit >
         else if self.has(&lexer::TokenType::LBRACKET) {
-            return self.array_lit();
+            self.array_lit()
         }
         // Catch unexpected resolvable
         else {
-            self.must_be(&NUMBER_TYPE)?;
-            return Ok(Some(ParseTree{
-                parse_type: ParseType::INVALID,
-                token: self.curr_token(),
-                children: Vec::new(),
-            }));
+            Err(self.expected("a value", None))
         }
     }
 
     // < ref-or-call >
-    // Feeds in the id that has been seen
-    fn ref_or_call(&mut self, id_tree: Option<ParseTree>) -> Result<Option<ParseTree>, String> {
+    // Feeds in the reference that has been seen
+    fn ref_or_call(&mut self, reference: Reference) -> Result<Expr, Diagnostic> {
         // < call >
         if self.has(&lexer::TokenType::LPAREN) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::CALL,
-                token: self.curr_token(), 
-                children: Vec::new(),
+            let name = match reference.kind {
+                ReferenceKind::NAME(name) => Ident {
+                    name,
+                    span: reference.span,
+                },
+                _ => return Err(Diagnostic::new(Stage::PARSE, diagnostic::UNEXPECTED_TOKEN, "Only a function name can be called".to_string())
+                    .with_span(reference.span)),
             };
-            parse_tree.children.push(id_tree);
+
             // < arg-list > techinically handles the RPAREN
             // and the case of no arguments, though they should
             // be handled here
-            parse_tree.children.push(self.arg_list()?);
-            return Ok(Some(parse_tree));
+            let args = self.arg_list()?;
+            return Ok(Expr {
+                span: name.span.to(self.last_span),
+                kind: ExprKind::CALL(name, args),
+            });
         }
 
         // < reference >
-        self.reference2(id_tree)
+        Ok(Expr {
+            span: reference.span,
+            kind: ExprKind::REFERENCE(reference),
+        })
     }
 
     // < array-lit >
-    fn array_lit(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::ARRAYLIT,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+    fn array_lit(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.start();
+        let mut items: Vec<Expr> = Vec::new();
 
         // LBRACKET
         self.eat(&lexer::TokenType::LBRACKET)?;
 
         // [< resolvable >]*
         while !self.has(&lexer::TokenType::RBRACKET) {
-            parse_tree.children.push(self.resolvable()?);
+            items.push(self.resolvable()?);
 
             if self.has(&lexer::TokenType::COMMA) {
                 self.next()?;
@@ -1590,16 +2413,16 @@ impl Parser {
         // RBRACKET
         self.eat(&lexer::TokenType::RBRACKET)?;
 
-        Ok(Some(parse_tree))
+        Ok(Expr {
+            kind: ExprKind::ARRAY(items),
+            span: self.span_from(start),
+        })
     }
 
     // < struct-lit >
-    fn struct_lit(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::STRUCTLIT,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+    fn struct_lit(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.start();
+        let mut items: Vec<Expr> = Vec::new();
 
         // LCURLY
         self.eat(&lexer::TokenType::LCURLY)?;
@@ -1608,28 +2431,11 @@ impl Parser {
         while !self.has(&lexer::TokenType::RCURLY) {
             // LINK TO < ref-or-nothing >
             if self.has(&lexer::TokenType::LINK) {
-                let mut child = ParseTree {
-                    parse_type: ParseType::LINKLIT,
-                    token: self.curr_token(),
-                    children: Vec::new(),
-                };
-                self.eat(&lexer::TokenType::LINK)?;
-                self.eat(&lexer::TokenType::TO)?;
-                
-                // < ref-or-nothing >
-                if self.has(&lexer::TokenType::NOTHING) {
-                    self.next()?;
-                    child.children.push(None);
-                }
-                else {
-                    child.children.push(self.reference()?);
-                }
-
-                parse_tree.children.push(Some(child));
+                items.push(self.link_lit()?);
             }
             // < resolvable >
             else {
-                parse_tree.children.push(self.resolvable()?);
+                items.push(self.resolvable()?);
             }
 
             if self.has(&lexer::TokenType::COMMA) {
@@ -1643,43 +2449,43 @@ impl Parser {
         // RCURLY
         self.eat(&lexer::TokenType::RCURLY)?;
 
-        Ok(Some(parse_tree))
+        Ok(Expr {
+            kind: ExprKind::STRUCTURE(items),
+            span: self.span_from(start),
+        })
     }
 
-    // < fun-arg-type >
-    fn fun_arg_type(&mut self) -> Result<Option<ParseTree>, String> {
-        // ARRAY < type or bounds >
-        if self.has(&lexer::TokenType::ARRAY) {
-            let mut parse_tree = ParseTree{
-                parse_type: ParseType::ARRAYDEF,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            self.next()?;
-
+    // LINK TO < ref-or-nothing >, as a value
+    fn link_lit(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.start();
+        let linked = self.link_to()?;
 
-            // FOR NOW, WE ARE GOING TO EXPECT BOUNDS
-            // IF NOT PROVIDING BOUNDS, ONE WOULD NEED
-            // TO DYNAMICALLY FIND AND SET THE PROPER
-            // SIZE IN MEMORY
-            // TO UNDO THIS, REMOVE THE FOLLOWING LINE
-            self.must_be(&lexer::TokenType::LBRACKET)?;
+        Ok(Expr {
+            kind: ExprKind::LINK(linked),
+            span: self.span_from(start),
+        })
+    }
 
-            // < type or bounds >
-            if self.has(&lexer::TokenType::LBRACKET) {
-                parse_tree.children.push(self.bounds()?);
-            }
-            else {
-                parse_tree.children.push(None);
-            }
+    // LINK TO < ref-or-nothing >
+    fn link_to(&mut self) -> Result<Option<Reference>, Diagnostic> {
+        // LINK TO
+        self.eat(&lexer::TokenType::LINK)?;
+        self.eat(&lexer::TokenType::TO)?;
 
-            // OF
-            self.eat(&lexer::TokenType::OF)?;
+        // < ref-or-nothing >
+        if self.has(&lexer::TokenType::NOTHING) {
+            self.next()?;
+            return Ok(None);
+        }
 
-            // < basic-type >
-            parse_tree.children.push(self.basic_type()?);
+        Ok(Some(self.reference()?))
+    }
 
-            return Ok(Some(parse_tree));
+    // < fun-arg-type >
+    fn fun_arg_type(&mut self) -> Result<Type, Diagnostic> {
+        // ARRAY < type or bounds >
+        if self.has(&lexer::TokenType::ARRAY) {
+            return self.array_type();
         }
 
         // < basic-type >
@@ -1687,100 +2493,90 @@ impl Parser {
     }
 
     // < variable-type >
-    fn var_type(&mut self) -> Result<Option<ParseTree>, String> {
+    fn var_type(&mut self) -> Result<Type, Diagnostic> {
         // LINK TO < basic-type >
         if self.has(&lexer::TokenType::LINK) {
-            let mut parse_tree = ParseTree {
-                parse_type: ParseType::POINTER,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
+            let start = self.start();
 
             // LINK TO
             self.eat(&lexer::TokenType::LINK)?;
             self.eat(&lexer::TokenType::TO)?;
 
             // < basic-type >
-            parse_tree.children.push(self.basic_type()?);
+            let link_type = self.basic_type()?;
 
-            return Ok(Some(parse_tree));
+            return Ok(Type {
+                kind: TypeKind::LINK(Box::new(link_type)),
+                span: self.span_from(start),
+            });
         }
 
         // ARRAY < type or bounds >
         else if self.has(&lexer::TokenType::ARRAY) {
-            let mut parse_tree = ParseTree{
-                parse_type: ParseType::ARRAYDEF,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
-            self.next()?;
-            
-            // FOR NOW, WE ARE GOING TO EXPECT BOUNDS
-            // IF NOT PROVIDING BOUNDS, ONE WOULD NEED
-            // TO DYNAMICALLY FIND AND SET THE PROPER
-            // SIZE IN MEMORY
-            // TO UNDO THIS, REMOVE THE FOLLOWING LINE
-            self.must_be(&lexer::TokenType::LBRACKET)?;
-
-            // < type or bounds >
-            if self.has(&lexer::TokenType::LBRACKET) {
-                parse_tree.children.push(self.bounds()?);
-            }
-            else {
-                parse_tree.children.push(None);
-            }
+            return self.array_type();
+        }
+
+        // < basic-type >
+        self.basic_type()
+    }
 
-            // OF
-            self.eat(&lexer::TokenType::OF)?;
+    // ARRAY < type or bounds >
+    fn array_type(&mut self) -> Result<Type, Diagnostic> {
+        let start = self.start();
 
-            // < basic-type >
-            parse_tree.children.push(self.basic_type()?);
+        // ARRAY
+        self.eat(&lexer::TokenType::ARRAY)?;
 
-            return Ok(Some(parse_tree));
-        }
+        // FOR NOW, WE ARE GOING TO EXPECT BOUNDS
+        // IF NOT PROVIDING BOUNDS, ONE WOULD NEED
+        // TO DYNAMICALLY FIND AND SET THE PROPER
+        // SIZE IN MEMORY
+        self.must_be(&lexer::TokenType::LBRACKET)?;
+
+        // < type or bounds >
+        let bounds = self.bounds()?;
+
+        // OF
+        self.eat(&lexer::TokenType::OF)?;
 
         // < basic-type >
-        self.basic_type()
+        let arr_type = self.basic_type()?;
+
+        Ok(Type {
+            kind: TypeKind::ARRAY(bounds, Box::new(arr_type)),
+            span: self.span_from(start),
+        })
     }
 
     // < basic-type >
-    fn basic_type(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut parse_tree = ParseTree {
-            parse_type: ParseType::INVALID,
-            token: self.curr_token(),
-            children: Vec::new(),
-        };
-
+    fn basic_type(&mut self) -> Result<Type, Diagnostic> {
         // NUMBER | TEXT | ID
-        if self.has(&lexer::TokenType::NUMTYPE) ||
-           self.has(&lexer::TokenType::TEXTTYPE) || 
-           self.must_be(&ID_TYPE)? {
-            parse_tree = ParseTree {
-                parse_type: ParseType::TYPE,
-                token: self.curr_token(),
-                children: Vec::new(),
-            };
+        let kind = match self.curr_token().token_type {
+            lexer::TokenType::NUMTYPE => TypeKind::NUMBER,
+            lexer::TokenType::TEXTTYPE => TypeKind::TEXT,
+            lexer::TokenType::ID(id) => TypeKind::STRUCTURE(id),
+            _ => return Err(self.expected("a type, like `number` or `text`", None)),
+        };
 
-            self.next()?;
-        }
+        let span = self.start();
+        self.next()?;
 
-        Ok(Some(parse_tree))
+        Ok(Type {
+            kind,
+            span,
+        })
     }
 
     // < bounds > and < bound-list >
-    fn bounds(&mut self) -> Result<Option<ParseTree>, String> {
+    fn bounds(&mut self) -> Result<Vec<Bound>, Diagnostic> {
         // LBRACKET
         self.eat(&lexer::TokenType::LBRACKET)?;
 
-        let mut parse_tree = ParseTree {
-            parse_type: ParseType::BOUNDS,
-            token: self.curr_token(), 
-            children: Vec::new(),
-        };
+        let mut bounds: Vec<Bound> = Vec::new();
 
         // [< bound >]*
         while !self.has(&lexer::TokenType::RBRACKET) {
-            parse_tree.children.push(self.bound()?);
+            bounds.push(self.bound()?);
 
             if self.has(&lexer::TokenType::COMMA) {
                 self.next()?;
@@ -1793,19 +2589,15 @@ impl Parser {
         // RBRACKET
         self.eat(&lexer::TokenType::RBRACKET)?;
 
-        Ok(Some(parse_tree))
+        Ok(bounds)
     }
 
     // < bound >
-    fn bound(&mut self) -> Result<Option<ParseTree>, String> {
-        let mut parse_tree = ParseTree{
-            parse_type: ParseType::BOUND,
-            token: self.curr_token(), 
-            children: Vec::new()
-        };
+    fn bound(&mut self) -> Result<Bound, Diagnostic> {
+        let start = self.start();
 
         // < resolvable >
-        let tree = self.resolvable()?;
+        let first = self.resolvable()?;
 
         // < bound' >
         // TO < resolvable >
@@ -1813,44 +2605,28 @@ impl Parser {
             // TO
             self.next()?;
 
-            parse_tree.children.push(tree);
             // < resolvable >
-            parse_tree.children.push(self.resolvable()?);
-        }
-        // ''
-        else {
-            parse_tree.children.push(None);
-            parse_tree.children.push(tree);
+            let end = self.resolvable()?;
+            return Ok(Bound {
+                start: Some(first),
+                end,
+                span: self.span_from(start),
+            });
         }
 
-        Ok(Some(parse_tree))
+        // ''
+        Ok(Bound {
+            start: None,
+            end: first,
+            span: self.span_from(start),
+        })
     }
 }
 
-// Generic main function that just runs the parser and prints
-//  the resulting parse tree. If a filename is provided in the 
-//  system arguments, parse from that file instead.
-pub fn _test() {
-    let args: Vec<String> = env::args().collect();
-
-    // create parser
-    let mut p: Parser;
-    // if argument, open file
-    if args.len() > 1 {
-        let fname = &args[1];
-        p = Parser::from_file(fname.to_string()).expect("Could not create lexer");
-    }
-    else {
-        p = Parser::new("
-    program
-        print(\"Hello world!\")
-    end program
-    ".to_string()).expect("Could not create lexer");
-    }
-
-    let tree = p.parse().expect("Error");
-    match tree {
-        Some(t) => t.print(),
-        _ => ()
-    };
-}
\ No newline at end of file
+// Build a binary operation, it covers both of its sides
+fn binary(op: BinOp, left: Expr, right: Expr) -> Expr {
+    Expr {
+        span: left.span.to(right.span),
+        kind: ExprKind::BINARY(op, Box::new(left), Box::new(right)),
+    }
+}
@@ -0,0 +1,216 @@
+use crate::lexer::{Lexer, TokenType};
+use crate::parser::{ParseTree, Parser, StmtKind};
+use crate::semantic_analyzer::{SemanticAnalyzer, SymbolType};
+use crate::interpreter::{EntryResult, Interpreter};
+use crate::library_handler::FunctionRegistry;
+use crate::host::Host;
+use crate::diagnostic::Diagnostics;
+
+// ====================
+// =       REPL       =
+// ====================
+// Runs Gelli a piece at a time, like "code repl" does in the terminal.
+// One SemanticAnalyzer and one Interpreter are kept for the whole
+//  session, so the structures, functions and variables from one entry
+//  can be used in the next. An entry can be a statement, a value on
+//  its own (which is shown), or a structure or function definition,
+//  without the 'definitions' and 'program' blocks around them.
+// An entry with errors (of any stage) is forgotten, so it can be fixed
+//  and typed in again, anything it displayed stays displayed though.
+// Every entry is kept in 'source', and each one is parsed as if it came
+//  after all of the others, so an error always points into 'source'
+//  (even one inside a function that was defined a while ago).
+// Entries that start with ':' are commands for the REPL itself.
+
+static HELP: &str = "\
+:vars           list the variables and their values
+:funcs          list the functions that have been defined
+:type VALUE     show the type of a value, without running it
+:reset          forget everything that has been defined
+:help           show this list
+:quit           leave (or use quit in the code)";
+
+// What the REPL has to show after an entry
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    NOTHING,
+    TEXT(String),   // a value, or the answer to a command
+    QUIT(i32),      // the exit code
+}
+
+pub struct Repl<H: Host> {
+    analyzer: SemanticAnalyzer,
+    interpreter: Interpreter<H>,
+    functions: FunctionRegistry,
+    // Every entry so far, one after the other
+    source: String,
+}
+
+impl<H: Host> Repl<H> {
+    pub fn new(host: H) -> Self {
+        Repl {
+            analyzer: SemanticAnalyzer::new(),
+            interpreter: Interpreter::new(host),
+            functions: FunctionRegistry::builtins(),
+            source: String::new(),
+        }
+    }
+
+    // Use the functions in this registry instead of only the built in ones
+    pub fn with_functions(mut self, functions: FunctionRegistry) -> Self {
+        self.analyzer = SemanticAnalyzer::new().with_functions(&functions);
+        self.interpreter = self.interpreter.with_functions(functions.clone());
+        self.functions = functions;
+        self
+    }
+
+    // The host the code runs on
+    pub fn host(&mut self) -> &mut H {
+        &mut self.interpreter.host
+    }
+
+    // Every entry so far, which the errors point into
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // Run one entry (or a command)
+    pub fn eval(&mut self, entry: &str) -> Result<Reply, Diagnostics> {
+        // Blank out the entries before this one, so this one starts at
+        //  the same line and column it has in the source
+        let mut code: String = self.source.chars().map(|c| if c == '\n' { c } else { ' ' }).collect();
+        code.push_str(entry);
+        self.source.push_str(entry);
+        if !entry.ends_with('\n') {
+            self.source.push('\n');
+        }
+
+        if let Some(command) = entry.trim().strip_prefix(':') {
+            return self.command(&code, command);
+        }
+
+        let tree = parse(&code)?;
+        let saved = self.analyzer.symbol_table().clone();
+        let errors = self.analyzer.analyze_entry(&tree);
+        if !errors.is_empty() {
+            self.analyzer.set_symbol_table(saved);
+            return Err(errors);
+        }
+
+        match self.interpreter.eval_entry(&tree) {
+            Ok(EntryResult::VALUE(value)) if !value.is_nothing() => Ok(Reply::TEXT(value.to_string())),
+            Ok(EntryResult::QUIT(code)) => Ok(Reply::QUIT(code)),
+            Ok(_) => Ok(Reply::NOTHING),
+            Err(e) => {
+                self.analyzer.set_symbol_table(saved);
+                Err(vec![e])
+            },
+        }
+    }
+
+    fn command(&mut self, code: &str, command: &str) -> Result<Reply, Diagnostics> {
+        let name = command.split_whitespace().next().unwrap_or("");
+        let reply = match name {
+            "vars" => self.vars(),
+            "funcs" => self.funcs(),
+            // The command is blanked out too, leaving the value
+            "type" => return self.type_of(&code.replacen(":type", "     ", 1)),
+            "reset" => {
+                self.analyzer = SemanticAnalyzer::new().with_functions(&self.functions);
+                self.interpreter.reset();
+                self.source.clear();
+                return Ok(Reply::NOTHING);
+            },
+            "help" => HELP.to_string(),
+            "quit" => return Ok(Reply::QUIT(0)),
+            _ => format!{"Unknown command ':{}'\n{}", name, HELP},
+        };
+        Ok(Reply::TEXT(reply))
+    }
+
+    // Every variable with its type, and its value if it has one
+    fn vars(&mut self) -> String {
+        let mut names: Vec<(String, SymbolType)> = match self.analyzer.symbol_table().symbols.first() {
+            Some(globals) => globals.iter().map(|(name, var_type)| (name.clone(), var_type.clone())).collect(),
+            None => Vec::new(),
+        };
+        names.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut lines: Vec<String> = Vec::new();
+        for (name, var_type) in names {
+            match self.interpreter.value_of(&name) {
+                Ok(value) => lines.push(format!{"{}: {} = {}", name, var_type, value}),
+                Err(_) => lines.push(format!{"{}: {}", name, var_type}),
+            }
+        }
+        lines.join("\n")
+    }
+
+    // The functions defined in the REPL (not the built in ones)
+    fn funcs(&self) -> String {
+        let mut lines: Vec<String> = self.analyzer.symbol_table().functions.iter()
+            .filter(|(name, _)| self.functions.get(name.as_str()).is_none())
+            .map(|(name, function)| function.signature(name))
+            .collect();
+        lines.sort();
+        lines.join("\n")
+    }
+
+    // The type of a value, it is checked but never run
+    fn type_of(&mut self, code: &str) -> Result<Reply, Diagnostics> {
+        let tree = parse(code)?;
+        let expr = match tree.body.statements.as_slice() {
+            [statement] if tree.structures.is_empty() && tree.functions.is_empty() => match &statement.kind {
+                StmtKind::EXPR(expr) => expr,
+                _ => return Ok(Reply::TEXT("Only a value has a type, like ':type length(list)'".to_string())),
+            },
+            _ => return Ok(Reply::TEXT("Give a single value, like ':type length(list)'".to_string())),
+        };
+
+        // Only the type is wanted, nothing it does is kept
+        let saved = self.analyzer.symbol_table().clone();
+        let (expr_type, errors) = self.analyzer.analyze_expr(expr);
+        self.analyzer.set_symbol_table(saved);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(Reply::TEXT(expr_type.to_string()))
+    }
+}
+
+fn parse(code: &str) -> Result<ParseTree, Diagnostics> {
+    let mut parser = Parser::new(code.to_string()).map_err(|e| vec![e])?;
+    match parser.parse_entry() {
+        (Some(tree), errors) if errors.is_empty() => Ok(tree),
+        (_, errors) => Err(errors),
+    }
+}
+
+// How many blocks the code has started without ending them yet, the
+//  REPL keeps reading lines until every block is ended
+// Code the lexer can't read counts as finished, so its error is shown
+pub fn open_blocks(code: &str) -> usize {
+    let mut lexer = match Lexer::new(code.to_string()) {
+        Ok(l) => l,
+        Err(_) => return 0,
+    };
+
+    let mut depth: usize = 0;
+    let mut last = TokenType::EOF;
+    loop {
+        let token = match lexer.next() {
+            Ok(t) => t.token_type,
+            Err(_) => return 0,
+        };
+
+        match token {
+            TokenType::EOF => return depth,
+            TokenType::END => depth = depth.saturating_sub(1),
+            // 'end if' ends a block, and 'else if' continues one
+            _ if last == TokenType::END || last == TokenType::ELSE => (),
+            TokenType::STRUCT | TokenType::FUN | TokenType::IF | TokenType::WHILE | TokenType::REPEAT => depth += 1,
+            _ => (),
+        }
+        last = token;
+    }
+}
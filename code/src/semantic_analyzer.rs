@@ -1,8 +1,9 @@
-use std::{env};
-use std::collections::HashMap;
-use crate::lexer::{TokenType};
-use crate::parser::{ParseTree, Parser, ParseType};
-use crate::library_handler;
+use std::{fmt, mem};
+use std::collections::{HashMap, HashSet};
+use crate::parser::{BinOp, Block, CompareOp, Condition, ConditionKind, Else, Expr, ExprKind, FunctionDef, Ident, If, Param, ParseTree, Reference, ReferenceKind, Stmt, StmtKind, StructDef, Target, Type, TypeKind, VarDef};
+use crate::diagnostic::{self, Diagnostic, Label, Span, Stage};
+use crate::library_handler::FunctionRegistry;
+use crate::visitor::{Visitor, walk_stmt};
 use indexmap::{IndexMap};
 
 // A boolean to determine if debug information should be displayed
@@ -12,12 +13,17 @@ static DEBUG: bool = false;
 // These are added immediately into the symbol table
 static PRIMATIVES: &[&str] = &["number", "text", "nothing"];
 
-// Handle error reporting through web assembly
-// For right now we just print the error, but later
-//  on this would be passed to JavaScript code
-macro_rules! log {
-    ($($t:tt)*) => (println!("{}",  &format_args!($ ( $t ) *).to_string() ))
-}
+// The type given to anything that already had an error
+// It matches every other type, so that one mistake doesn't
+//  cause more errors further up the tree
+// (it isn't a valid name, so it can't clash with a structure)
+static ERROR_TYPE: &str = "<error>";
+
+// Array dimensions only used by the parameters of library functions:
+//  any array (with at least one dimension, of any size), and any
+//  array or text (for "length" and "dimensions")
+pub const ANY_ARRAY: i32 = -2;
+pub const ARRAY_OR_TEXT: i32 = -3;
 
 // Handle debugging through web assembly
 // For right now we just print the error, but later
@@ -43,9 +49,16 @@ pub struct SymbolType {
 //  ignores array dimensions of -1 (undefined size)
 impl PartialEq for SymbolType {
     fn eq(&self, other: &Self) -> bool {
+        if self.is_error() || other.is_error() {
+            return true;
+        }
+        if let Some(accepted) = self.accepts(other).or_else(|| other.accepts(self)) {
+            return accepted;
+        }
+
         let mut basic = true;
         let mut arr = true;
-        if self.basic_type != "*".to_string() && other.basic_type != "*".to_string() {
+        if self.basic_type != "*" && other.basic_type != "*" {
             basic = self.basic_type == other.basic_type;
         }
         if self.array_dimensions >= 0 && other.array_dimensions >= 0 {
@@ -56,10 +69,98 @@ impl PartialEq for SymbolType {
     }
 }
 
+impl SymbolType {
+    // The type of an expression that had an error
+    pub fn error() -> Self {
+        SymbolType {
+            basic_type: ERROR_TYPE.to_string(),
+            is_pointer: false,
+            array_dimensions: -1,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.basic_type == ERROR_TYPE
+    }
+
+    // For a parameter that takes any array (or text), if the other
+    //  type is one (a type with an unknown size might be an array)
+    fn accepts(&self, other: &Self) -> Option<bool> {
+        match self.array_dimensions {
+            ANY_ARRAY => Some(other.array_dimensions != 0),
+            ARRAY_OR_TEXT => Some(other.array_dimensions != 0 || other.basic_type == "text"),
+            _ => None,
+        }
+    }
+}
+
+// A small number written as a word ("three"), bigger ones as digits
+fn number_word(n: i32) -> String {
+    let words = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten"];
+    match words.get(n as usize) {
+        Some(word) => word.to_string(),
+        None => n.to_string(),
+    }
+}
+
+// Written close to the way the type is in the code, like "link to node"
+// A symbol type only knows how many dimensions an array has (not its
+//  bounds), so the dimensions are written out in words, like
+//  "array of number" or "two-dimensional array of person"
+impl fmt::Display for SymbolType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut name = self.basic_type.clone();
+        if self.basic_type == "*" {
+            name = "anything".to_string();
+        }
+
+        if self.array_dimensions == ANY_ARRAY {
+            name = format!{"array of {}", name};
+        }
+        else if self.array_dimensions == ARRAY_OR_TEXT {
+            name = "array or text".to_string();
+        }
+        else if self.array_dimensions == 1 {
+            name = format!{"array of {}", name};
+        }
+        else if self.array_dimensions > 1 {
+            name = format!{"{}-dimensional array of {}", number_word(self.array_dimensions), name};
+        }
+        // A "*" with any size is any type at all, not only arrays
+        else if self.array_dimensions < 0 && !self.is_error() && self.basic_type != "*" {
+            name = format!{"array of {}", name};
+        }
+        if self.is_pointer {
+            name = format!{"link to {}", name};
+        }
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct FunctionObject {
     pub params: Vec<SymbolType>,
-    pub return_type: String,
+    pub return_type: SymbolType,
+    pub variadic: bool, // the last parameter can be given any number of times (even none)
+    pub optional: bool, // the parameters can all be left off, but not just some of them
+}
+
+impl FunctionObject {
+    // Written like "add(number, number) returns number", optional
+    //  parameters are in brackets like "random_number([number, number])"
+    pub fn signature(&self, name: &str) -> String {
+        let mut params: Vec<String> = self.params.iter().map(|p| p.to_string()).collect();
+        if self.variadic {
+            if let Some(last) = params.last_mut() {
+                last.push_str(", ...");
+            }
+        }
+        let mut params = params.join(", ");
+        if self.optional && !params.is_empty() {
+            params = format!{"[{}]", params};
+        }
+        format!{"{}({}) returns {}", name, params, self.return_type}
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -67,10 +168,17 @@ pub struct SymbolTable {
     pub symbols: Vec<HashMap<String, SymbolType>>,
     pub basic_types: Vec<String>,
     pub struct_args: HashMap<String, IndexMap<String, SymbolType>>,
+    pub struct_defaults: HashMap<String, Vec<String>>,
     pub functions: HashMap<String, FunctionObject>,
     pub depth: usize,
 }
 
+impl Default for SymbolTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SymbolTable {
     // Create a new Symbol Table
     // Pull in all external functions, and load
@@ -80,7 +188,8 @@ impl SymbolTable {
             symbols: Vec::new(),
             basic_types: Vec::new(),
             struct_args: HashMap::new(),
-            functions: library_handler::get_external_functions(),
+            struct_defaults: HashMap::new(),
+            functions: FunctionRegistry::builtins().signatures(),
             depth: 0,
         };
 
@@ -95,9 +204,9 @@ impl SymbolTable {
 
     // Add a new type into the Symbol Table
     // Used when defining structures
-    pub fn add_type(&mut self, basic_type: String) -> Result<(), String> {
+    pub fn add_type(&mut self, basic_type: String) -> Result<(), Diagnostic> {
         if self.basic_types.contains(&basic_type) {
-            return Err(format!{"Type '{}' already exists", basic_type});
+            return Err(Diagnostic::new(Stage::SEMANTIC, diagnostic::DUPLICATE_NAME, format!{"Type '{}' already exists", basic_type}));
         }
 
         self.basic_types.push(basic_type);
@@ -106,13 +215,13 @@ impl SymbolTable {
     }
 
     // Add a new symbol (varaiable name) and its corresponding type
-    pub fn add_symbol(&mut self, symbol: String, symbol_type: SymbolType) -> Result<(), String> {
+    pub fn add_symbol(&mut self, symbol: String, symbol_type: SymbolType) -> Result<(), Diagnostic> {
         if self.symbols[self.depth].contains_key(&symbol) {
-            return Err(format!("Symbol '{}' already exists", &symbol));
+            return Err(Diagnostic::new(Stage::SEMANTIC, diagnostic::DUPLICATE_NAME, format!("Symbol '{}' already exists", &symbol)));
         }
 
-        if !self.basic_types.contains(&symbol_type.basic_type) {
-            return Err(format!("Unknown type: {}", &symbol_type.basic_type));
+        if !self.basic_types.contains(&symbol_type.basic_type) && !symbol_type.is_error() {
+            return Err(Diagnostic::new(Stage::SEMANTIC, diagnostic::UNKNOWN_NAME, format!("Unknown type: {}", &symbol_type.basic_type)));
         }
 
         self.symbols[self.depth].insert(symbol, symbol_type);
@@ -123,27 +232,27 @@ impl SymbolTable {
     // Look through all scopes of the symbol table
     //  to find the corresponding type of the symbol (id)
     // If the symbol can't be found in the table, error
-    pub fn find_symbol(&mut self, symbol: String) -> Result<SymbolType, String> {
+    pub fn find_symbol(&mut self, symbol: String) -> Result<SymbolType, Diagnostic> {
         for i in (0..self.depth+1).rev() {
-            if self.symbols[i as usize].contains_key(&symbol) {
-                return Ok(self.symbols[i as usize].get(&symbol).unwrap().clone());
+            if self.symbols[i].contains_key(&symbol) {
+                return Ok(self.symbols[i].get(&symbol).unwrap().clone());
             }
         }
 
-        Err(format!("Unknown symbol '{}'", &symbol))
+        Err(Diagnostic::new(Stage::SEMANTIC, diagnostic::UNKNOWN_NAME, format!("Unknown symbol '{}'", &symbol)))
     }
 
     // Add a new function definition into the symbol table
-    pub fn add_function(&mut self, id: String, obj: FunctionObject) -> Result<(), String> {
+    pub fn add_function(&mut self, id: String, obj: FunctionObject) -> Result<(), Diagnostic> {
         // Check if the function is already defined
         if self.functions.contains_key(&id) {
-            return Err(format!("Function {} has already been defined", &id));
+            return Err(Diagnostic::new(Stage::SEMANTIC, diagnostic::DUPLICATE_NAME, format!("Function {} has already been defined", &id)));
         }
 
         // Check all the parameters to make sure they are actual types
         for symbol_type in &obj.params {
-            if !self.basic_types.contains(&symbol_type.basic_type) {
-                return Err(format!("Unknown type: {}", &symbol_type.basic_type));
+            if !self.basic_types.contains(&symbol_type.basic_type) && !symbol_type.is_error() {
+                return Err(Diagnostic::new(Stage::SEMANTIC, diagnostic::UNKNOWN_NAME, format!("Unknown type: {}", &symbol_type.basic_type)));
             }
         }
 
@@ -168,16 +277,16 @@ impl SymbolTable {
     }
 
     // Add a new structure definition into the Symbol Table
-    pub fn add_struct_keys(&mut self, struct_id: String, struct_keys: IndexMap<String, SymbolType>) -> Result<(), String> {
+    pub fn add_struct_keys(&mut self, struct_id: String, struct_keys: IndexMap<String, SymbolType>) -> Result<(), Diagnostic> {
         // Make sure a structure with the same name doesn't exist
         if self.struct_args.contains_key(&struct_id) {
-            return Err(format!("Structure '{}' has already been defined", &struct_id));
+            return Err(Diagnostic::new(Stage::SEMANTIC, diagnostic::DUPLICATE_NAME, format!("Structure '{}' has already been defined", &struct_id)));
         }
 
         // Check all structure arguments to ensure they are valid
         for (_key, value) in &struct_keys {
-            if !self.basic_types.contains(&value.basic_type) {
-                return Err(format!("Unknown type: {}", &value.basic_type));
+            if !self.basic_types.contains(&value.basic_type) && !value.is_error() {
+                return Err(Diagnostic::new(Stage::SEMANTIC, diagnostic::UNKNOWN_NAME, format!("Unknown type: {}", &value.basic_type)));
             }
         }
 
@@ -186,16 +295,29 @@ impl SymbolTable {
         Ok(())
     }
 
+    // Mark which keys of a structure have a default value
+    pub fn add_struct_defaults(&mut self, struct_id: String, keys: Vec<String>) {
+        self.struct_defaults.insert(struct_id, keys);
+    }
+
+    // Check if a structure key has a default value
+    pub fn has_struct_default(&self, struct_id: &String, key_id: &String) -> bool {
+        match self.struct_defaults.get(struct_id) {
+            Some(keys) => keys.contains(key_id),
+            None => false,
+        }
+    }
+
     // Get the type of a structure key
-    pub fn get_struct_key(&self, struct_id: String, key_id: String) -> Result<SymbolType, String> {
+    pub fn get_struct_key(&self, struct_id: String, key_id: String) -> Result<SymbolType, Diagnostic> {
         // Find the structure object
         if !self.struct_args.contains_key(&struct_id) {
-            return Err(format!("Unknown Structure {}", &struct_id));
+            return Err(Diagnostic::new(Stage::SEMANTIC, diagnostic::UNKNOWN_NAME, format!("Unknown Structure {}", &struct_id)));
         }
 
         // Find the key
         if !self.struct_args.get(&struct_id).unwrap().contains_key(&key_id) {
-            return Err(format!("Unknown key {} for structure {}", &key_id, &struct_id));
+            return Err(Diagnostic::new(Stage::SEMANTIC, diagnostic::UNKNOWN_NAME, format!("Unknown key {} for structure {}", &key_id, &struct_id)));
         }
 
         // Return the SymbolType of that key
@@ -203,38 +325,68 @@ impl SymbolTable {
     }
 }
 
-// Helper method to pull the ID String from a parse tree node
-pub fn unwrap_id_tree(tree: &ParseTree) -> String {
-    match &tree.token.token_type {
-        TokenType::ID(id) => id.to_string(),
-        _ => "".to_string(),
-    }
+
+// Helper method to point a symbol table error at a part of the code
+//  used as `.map_err(at(span))`
+fn at(span: Span) -> impl Fn(Diagnostic) -> Diagnostic {
+    move |err| err.with_span(span)
 }
 
-// Helper method to pull the type String from a parse tree node
-pub fn unwrap_type_tree(tree: &ParseTree) -> String {
-    match &tree.token.token_type {
-        TokenType::ID(id) => id.to_string(),
-        TokenType::TEXTTYPE => "text".to_string(),
-        TokenType::NUMTYPE => "number".to_string(),
-        TokenType::NOTHING => "nothing".to_string(),
+// Helper method to get the name of a basic type
+pub fn basic_type_name(kind: &TypeKind) -> String {
+    match kind {
+        TypeKind::NUMBER => "number".to_string(),
+        TypeKind::TEXT => "text".to_string(),
+        TypeKind::NOTHING => "nothing".to_string(),
+        TypeKind::STRUCTURE(id) => id.to_string(),
         _ => "invalid".to_string(),
     }
 }
 
-// Helper method to get the type of a Literal in a parse tree
-pub fn unwrap_lit_tree(tree: &ParseTree) -> String {
-    match &tree.token.token_type {
-        TokenType::NUMBER(_x) => "number".to_string(),
-        TokenType::TEXT(_x) => "text".to_string(),
-        _ => "invalid".to_string(),
+// The symbol type of a type in the code, this doesn't check that
+//  the type exists or that the array bounds are numbers
+pub fn symbol_type_of(type_node: &Type) -> SymbolType {
+    let mut curr_type = type_node;
+    let mut is_pointer = false;
+    let mut array_dimensions = 0;
+
+    // Catch pointers and unwrap
+    if let TypeKind::LINK(link_type) = &curr_type.kind {
+        is_pointer = true;
+        curr_type = link_type;
+    }
+
+    // Catch arrays and unwrap
+    if let TypeKind::ARRAY(bounds, arr_type) = &curr_type.kind {
+        array_dimensions = bounds.len() as i32;
+        curr_type = arr_type;
+    }
+
+    // Types that couldn't be parsed already have an error
+    if curr_type.kind == TypeKind::INVALID {
+        return SymbolType::error();
+    }
+
+    SymbolType{
+        basic_type: basic_type_name(&curr_type.kind),
+        array_dimensions,
+        is_pointer,
     }
 }
 
 pub struct SemanticAnalyzer {
     symbol_table: SymbolTable,
     expected_return_type: Option<String>,
+    expected_return_span: Span,
     expected_resolve_type: Option<SymbolType>,
+    // Every error found so far
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for SemanticAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SemanticAnalyzer {
@@ -243,687 +395,816 @@ impl SemanticAnalyzer {
         SemanticAnalyzer {
             symbol_table: SymbolTable::new(),
             expected_return_type: None,
+            expected_return_span: Span::default(),
             expected_resolve_type: None,
+            diagnostics: Vec::new(),
         }
     }
 
-    // Helper method to get information about the current tree for errors
-    fn err_header(&mut self, tree: &ParseTree) -> String {
-        format!{"Error on line {}:{} - ", tree.token.row, tree.token.col}
+    // Check calls against the functions in this registry, instead of
+    //  only the built in ones
+    pub fn with_functions(mut self, functions: &FunctionRegistry) -> Self {
+        self.symbol_table.functions = functions.signatures();
+        self
+    }
+
+    // Create an error pointing at the provided part of the code
+    fn error(&self, span: Span, code: &'static str, message: String) -> Diagnostic {
+        Diagnostic::new(Stage::SEMANTIC, code, message).with_span(span)
+    }
+
+    // Record an error and keep going
+    // Parts of the tree can be checked more than once (like a reference
+    //  that is also checked by is_text_index), so repeats are ignored
+    fn report(&mut self, err: Diagnostic) {
+        if !self.diagnostics.contains(&err) {
+            self.diagnostics.push(err);
+        }
     }
 
-    // CODE tree
-    pub fn analyze(&mut self, tree: &ParseTree) -> Result<SymbolTable, String> {
-        // DEF (could be None)
-        if tree.children[0].is_some() {
-            self.analyze_definitions(tree.children[0].as_ref().unwrap())?;
+    // Record the error of a result (if there is one)
+    fn record<T>(&mut self, result: Result<T, Diagnostic>) -> Option<T> {
+        match result {
+            Ok(val) => Some(val),
+            Err(err) => {
+                self.report(err);
+                None
+            },
         }
+    }
+
+    // The whole program
+    // Gives back the global symbol table and every error that was found
+    pub fn analyze(&mut self, tree: &ParseTree) -> (SymbolTable, Vec<Diagnostic>) {
+        // Definitions (each part could be empty)
+        self.analyze_struct_defs(&tree.structures);
+        self.analyze_global_defs(&tree.globals);
+        self.analyze_function_defs(&tree.functions);
+
         let symtab = self.symbol_table.clone();
         // BODY (program section)
-        self.analyze_body(tree.children[1].as_ref().unwrap())?;
-        Ok(symtab)
+        self.analyze_body(&tree.body);
+        self.analyze_assignments(&[], &tree.body);
+        (symtab, mem::take(&mut self.diagnostics))
     }
 
-    // DEFINITION tree
-    fn analyze_definitions(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // STRUCT DEFS (could be none)
-        if tree.children[0].is_some() {
-            self.analyze_struct_defs(tree.children[0].as_ref().unwrap())?;
-        }
+    // A piece of code typed into the REPL (repl.rs), checked against
+    //  everything that was defined before it
+    // Everything it defines is kept, even when there are errors (the
+    //  REPL puts back the table it had before, see 'set_symbol_table')
+    pub fn analyze_entry(&mut self, tree: &ParseTree) -> Vec<Diagnostic> {
+        self.analyze_struct_defs(&tree.structures);
+        self.analyze_function_defs(&tree.functions);
+        self.analyze_body(&tree.body);
+        self.analyze_assignments(&[], &tree.body);
+        mem::take(&mut self.diagnostics)
+    }
 
-        // GLOBAL DEFS (could be none)
-        if tree.children[1].is_some() {
-            self.analyze_global_defs(tree.children[1].as_ref().unwrap())?;
-        }
+    // The type of a value on its own, against everything defined so far
+    pub fn analyze_expr(&mut self, expr: &Expr) -> (SymbolType, Vec<Diagnostic>) {
+        let expr_type = self.analyze_resolvable(expr);
+        (expr_type, mem::take(&mut self.diagnostics))
+    }
 
-        // FUNCTION DEFS (could be none)
-        if tree.children[2].is_some() {
-            self.analyze_function_defs(tree.children[2].as_ref().unwrap())?;
-        }
+    // The structures, functions and variables that have been defined
+    pub fn symbol_table(&self) -> &SymbolTable {
+        &self.symbol_table
+    }
 
-        Ok(())
+    pub fn set_symbol_table(&mut self, symbol_table: SymbolTable) {
+        self.symbol_table = symbol_table;
     }
 
-    fn analyze_struct_defs(&mut self, tree: &ParseTree) -> Result<(), String> {
+    fn analyze_struct_defs(&mut self, structures: &[StructDef]) {
+        // Structures that couldn't be parsed are skipped
+        let struct_defs: Vec<(&Ident, &StructDef)> = structures.iter()
+            .filter_map(|s| s.name.as_ref().map(|name| (name, s)))
+            .collect();
+
         // Add all strucutre names to the basic types
-        for struct_def_tree in &tree.children {
-            let id = unwrap_id_tree(struct_def_tree.as_ref().unwrap().children[0].as_ref().unwrap());
-            self.symbol_table.add_type(id)?;
+        for (id, _struct_def) in &struct_defs {
+            let result = self.symbol_table.add_type(id.name.clone()).map_err(at(id.span));
+            self.record(result);
         }
 
         // Add all structure objects one by one
-        for struct_def_tree in &tree.children {
-            let id = unwrap_id_tree(struct_def_tree.as_ref().unwrap().children[0].as_ref().unwrap());
-
+        for (id, struct_def) in &struct_defs {
             let mut struct_keys: IndexMap<String, SymbolType> = IndexMap::new();
+            let mut struct_defaults: Vec<String> = Vec::new();
 
-            for struct_arg in &struct_def_tree.as_ref().unwrap().children[1].as_ref().unwrap().children {
-                let key_name = unwrap_id_tree(struct_arg.as_ref().unwrap().children[0].as_ref().unwrap());
-                let key_type = self.analyze_type(struct_arg.as_ref().unwrap().children[1].as_ref().unwrap())?;
+            for field in &struct_def.fields {
+                let key_type = self.analyze_type(&field.field_type);
 
-                struct_keys.insert(key_name, key_type);
+                if field.default.is_some() {
+                    struct_defaults.push(field.name.name.clone());
+                }
+
+                struct_keys.insert(field.name.name.clone(), key_type);
             }
 
-            self.symbol_table.add_struct_keys(id, struct_keys)?;
+            let result = self.symbol_table.add_struct_keys(id.name.clone(), struct_keys).map_err(at(id.span));
+            self.record(result);
+            self.symbol_table.add_struct_defaults(id.name.clone(), struct_defaults);
         }
-        
-        Ok(())
-    }
 
-    fn analyze_global_defs(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Analyze each of the assignments one by one
-        for child in &tree.children {
-            if child.as_ref().unwrap().parse_type == ParseType::ASSIGN {
-                self.analyze_assignment(child.as_ref().unwrap())?;
-            }
-            else {
-                self.analyze_vardef(child.as_ref().unwrap())?;
+        // Check the default values once every structure is known,
+        //  since a default can be a literal of another structure
+        for (id, struct_def) in &struct_defs {
+            for field in &struct_def.fields {
+                let default = match &field.default {
+                    Some(d) => d,
+                    None => continue,
+                };
+
+                let key_name = &field.name.name;
+                let result = self.symbol_table.get_struct_key(id.name.clone(), key_name.clone()).map_err(at(field.name.span));
+                let key_type = match self.record(result) {
+                    Some(t) => t,
+                    None => continue,
+                };
+
+                // Links can only default to a link literal
+                let is_link_lit = matches!(default.kind, ExprKind::LINK(_));
+                if key_type.is_pointer && !is_link_lit {
+                    self.report(self.error(default.span, diagnostic::INVALID_LINK, format!{"Default value for structure item '{}' must be set with 'link to'", key_name}));
+                    continue;
+                }
+                if !key_type.is_pointer && is_link_lit {
+                    self.report(self.error(default.span, diagnostic::INVALID_LINK, format!{"Structure item '{}' is not a link", key_name}));
+                    continue;
+                }
+
+                self.expected_resolve_type = Some(key_type.clone());
+                let res_type = self.analyze_resolvable(default);
+                self.expected_resolve_type = None;
+
+                if res_type != key_type {
+                    self.report(self.error(default.span, diagnostic::TYPE_MISMATCH, format!{"Default value for structure item '{}' does not match expected type {}, got {}", key_name, key_type, res_type}));
+                }
             }
         }
-        
-        Ok(())
     }
 
-    fn analyze_function_defs(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Loop through each child, add its function object to symbol table
-        for child in &tree.children {
-            let fun_def = child.as_ref().unwrap();
+    fn analyze_global_defs(&mut self, globals: &[Stmt]) {
+        // Globals are declarations, assignments, or links,
+        //  which are checked the same way as in a body
+        for global in globals {
+            self.visit_stmt(global);
+        }
+    }
 
-            // Get the function id
-            let function_id = unwrap_id_tree(fun_def.children[0].as_ref().unwrap());
+    // The type of a parameter, changeable parameters share
+    //  memory with their argument like a link
+    fn analyze_param(&mut self, param: &Param) -> SymbolType {
+        let mut param_type = self.analyze_type(&param.param_type);
+        if param.changeable && !param_type.is_error() {
+            param_type.is_pointer = true;
+        }
+        param_type
+    }
+
+    fn analyze_function_defs(&mut self, functions: &[FunctionDef]) {
+        // Loop through each function, add its function object to symbol table
+        for fun_def in functions {
+            // A function without a name can't be called
+            let id = match &fun_def.name {
+                Some(id) => id,
+                None => continue,
+            };
 
             // Fill a vector with the function parameters
             let mut params: Vec<SymbolType> = Vec::new();
-            for param in &fun_def.children[1].as_ref().unwrap().children {
-                params.push(self.analyze_type(param.as_ref().unwrap().children[1].as_ref().unwrap())?);
+            for param in &fun_def.params {
+                params.push(self.analyze_param(param));
             }
 
-            // Get the return type
-            let ret_type = unwrap_type_tree(fun_def.children[2].as_ref().unwrap());
-
             // Create the funciton object
             let fn_obj = FunctionObject {
-                params: params,
-                return_type: ret_type,
+                params,
+                return_type: self.analyze_type(&fun_def.return_type),
+                variadic: false,
+                optional: false,
             };
 
-            debug!{"Adding function {} of type {:?}", function_id, fn_obj};
+            debug!{"Adding function {} of type {:?}", id.name, fn_obj};
 
             // Insert it
-            self.symbol_table.add_function(function_id, fn_obj)?;
+            let result = self.symbol_table.add_function(id.name.clone(), fn_obj).map_err(at(id.span));
+            self.record(result);
         }
 
-        // Loop through each child, and check the function bodies
-        for child in &tree.children {
-            let fun_def = child.as_ref().unwrap();
-
+        // Loop through each function, and check the function bodies
+        for fun_def in functions {
             // Scope in and define the parameters
             self.symbol_table.scope_in();
-            for param in &fun_def.children[1].as_ref().unwrap().children {
-                let param_name = unwrap_id_tree(param.as_ref().unwrap().children[0].as_ref().unwrap());
-                let param_type = self.analyze_type(param.as_ref().unwrap().children[1].as_ref().unwrap())?;
-                self.symbol_table.add_symbol(param_name, param_type)?;
+            for param in &fun_def.params {
+                let param_type = self.analyze_param(param);
+                let result = self.symbol_table.add_symbol(param.name.name.clone(), param_type).map_err(at(param.name.span));
+                self.record(result);
             }
 
             // Set the expected return type
-            self.expected_return_type = Some(unwrap_type_tree(fun_def.children[2].as_ref().unwrap()));
+            self.expected_return_type = match fun_def.return_type.kind {
+                TypeKind::INVALID => Some(ERROR_TYPE.to_string()),
+                _ => Some(self.analyze_type(&fun_def.return_type).basic_type),
+            };
+            self.expected_return_span = fun_def.return_type.span;
             // Check the body
-            self.analyze_body(fun_def.children[3].as_ref().unwrap())?;
+            self.analyze_body(&fun_def.body);
+            self.analyze_return_paths(fun_def);
+            self.analyze_assignments(&fun_def.params, &fun_def.body);
             // Reset the expected return type
             self.expected_return_type = None;
             // Scope out
             self.symbol_table.scope_out();
         }
-        
-        Ok(())
     }
 
-    fn analyze_assignment(&mut self, tree: &ParseTree) -> Result<(), String> {
-        let left_type: SymbolType;
+    fn analyze_assignment(&mut self, span: Span, target: &Target, value: &Expr) -> Result<(), Diagnostic> {
+        let left_type = match target {
+            Target::VARDEF(var_def) => self.analyze_vardef(var_def),
+            Target::REFERENCE(reference) => {
+                let left_type = self.analyze_reference(reference);
 
-        // VARDEF
-        if tree.children[0].as_ref().unwrap().parse_type == ParseType::VARDEF {
-            left_type = self.analyze_vardef(tree.children[0].as_ref().unwrap())?;
-        }
-        else {
-            left_type = self.analyze_reference(tree.children[0].as_ref().unwrap())?;
-        }
+                if self.is_text_index(reference) {
+                    return Err(self.error(span, diagnostic::UNCHANGEABLE, "Cannot change a single character of text, text cannot be changed".to_string()));
+                }
+                left_type
+            },
+        };
 
         self.expected_resolve_type = Some(left_type.clone());
-        let right_type = self.analyze_resolvable(tree.children[1].as_ref().unwrap())?;
+        let right_type = self.analyze_resolvable(value);
         self.expected_resolve_type = None;
 
         // Make sure the variable type (left_type) matches
         //  the literal value (right_type)
         if left_type != right_type {
-            return Err(format!{"{} Type mismatch between {:?} and {:?}", self.err_header(tree), left_type, right_type});
+            return Err(self.error(span, diagnostic::TYPE_MISMATCH, format!{"Type mismatch between {} and {}", left_type, right_type}));
         }
 
         Ok(())
     }
 
-    fn analyze_type(&mut self, tree: &ParseTree) -> Result<SymbolType, String> {
-        let mut curr_tree = tree;
-        let mut is_pointer = false;
-        let mut array_dimensions = 0;
+    fn analyze_type(&mut self, type_node: &Type) -> SymbolType {
+        let mut curr_type = type_node;
 
         // Catch pointers and unwrap
-        if curr_tree.parse_type == ParseType::POINTER {
-            is_pointer = true;
-            curr_tree = curr_tree.children[0].as_ref().unwrap();
+        if let TypeKind::LINK(link_type) = &curr_type.kind {
+            curr_type = link_type;
         }
 
         // Catch arrays and unwrap
-        if curr_tree.parse_type == ParseType::ARRAYDEF {
-            // If there are defined bounds, make sure they are integers
-            // This will also set the array_dimensions
-            // Otherwise, we don't know the current dimensions until later
-            if curr_tree.children[0].as_ref().is_some() {
-                let bounds_tree = curr_tree.children[0].as_ref().unwrap();
-
-                for bound in &bounds_tree.children {
-                    array_dimensions += 1;
-                    let mut bound_type: SymbolType;
-                    if bound.as_ref().unwrap().children[0].as_ref().is_some() {
-                        bound_type = self.analyze_resolvable(bound.as_ref().unwrap().children[0].as_ref().unwrap())?;
-                        if bound_type.basic_type != "number".to_string() {
-                            return Err(format!{"{} Cannot set bounds of an array to a non-number!", self.err_header(curr_tree)});
-                        }
+        if let TypeKind::ARRAY(bounds, _) = &curr_type.kind {
+            // Make sure the bounds are integers
+            for bound in bounds {
+                let mut bound_type: SymbolType;
+                if let Some(start) = &bound.start {
+                    bound_type = self.analyze_resolvable(start);
+                    if bound_type.basic_type != "number" && !bound_type.is_error() {
+                        self.report(self.error(curr_type.span, diagnostic::TYPE_MISMATCH, "Cannot set bounds of an array to a non-number!".to_string()));
                     }
+                }
 
-                    bound_type = self.analyze_resolvable(bound.as_ref().unwrap().children[1].as_ref().unwrap())?;
-                    if bound_type.basic_type != "number".to_string() {
-                        return Err(format!{"{} Cannot set bounds of an array to a non-number!", self.err_header(curr_tree)});
-                    }
+                bound_type = self.analyze_resolvable(&bound.end);
+                if bound_type.basic_type != "number" && !bound_type.is_error() {
+                    self.report(self.error(curr_type.span, diagnostic::TYPE_MISMATCH, "Cannot set bounds of an array to a non-number!".to_string()));
                 }
             }
-            // This is for an array with unknown dimensions
-            else {
-                array_dimensions = -1;
-            }
-
-            curr_tree = curr_tree.children[1].as_ref().unwrap();
         }
 
-        // Get the basic type
-        let basic_type = unwrap_type_tree(&curr_tree);
-
         // Return the symbol type
-        Ok(SymbolType{
-            basic_type: basic_type,
-            array_dimensions: array_dimensions,
-            is_pointer: is_pointer,
-        })
+        symbol_type_of(type_node)
     }
 
-    fn analyze_resolvable(&mut self, tree: &ParseTree) -> Result<SymbolType, String> {
-        // Catch just pure literal values
-        if tree.parse_type == ParseType::LIT {
-            return Ok(SymbolType{
-                basic_type: unwrap_lit_tree(&tree),
-                is_pointer: false,
-                array_dimensions: 0,
-            });
+    // Find the type of a resolvable, if it has an error the error is
+    //  recorded and the error type is given back instead
+    fn analyze_resolvable(&mut self, expr: &Expr) -> SymbolType {
+        match self.resolve(expr) {
+            Ok(t) => t,
+            Err(err) => {
+                self.report(err);
+                SymbolType::error()
+            },
         }
+    }
 
-        // Catch binary operations
-        else if tree.parse_type == ParseType::BINOP {
-            // Get the types of the left and right children
-            let left_type = self.analyze_resolvable(tree.children[0].as_ref().unwrap())?;
-            let right_type = self.analyze_resolvable(tree.children[1].as_ref().unwrap())?;
-
-            // Check for addition of text (non-arrays)
-            if tree.token.token_type == TokenType::ADD &&
-               left_type.basic_type == "text".to_string() &&
-               right_type.basic_type == "text".to_string() &&
-               left_type.array_dimensions <= 0 &&
-               right_type.array_dimensions <= 0 {
-                return Ok(SymbolType{
-                    basic_type: "text".to_string(),
-                    is_pointer: false,
-                    array_dimensions: 0,
-                });
-            }
-
-            // Otherwise, they both must be numbers (non-arrays)
-            if left_type.basic_type != "number".to_string() ||
-               left_type.array_dimensions > 0 ||
-               right_type.basic_type != "number".to_string() ||
-               right_type.array_dimensions > 0 {
-                return Err(format!{"{} Cannot perform binary operations on non-numbers", self.err_header(tree)});
-            }
+    fn resolve(&mut self, expr: &Expr) -> Result<SymbolType, Diagnostic> {
+        match &expr.kind {
+            // Code that couldn't be parsed already has an error
+            ExprKind::INVALID => Ok(SymbolType::error()),
 
-            // Return Ok
-            return Ok(SymbolType{
+            // Catch just pure literal values
+            ExprKind::NUMBER(_) => Ok(SymbolType{
                 basic_type: "number".to_string(),
                 is_pointer: false,
                 array_dimensions: 0,
-            });
-        }
+            }),
+            ExprKind::TEXT(_) => Ok(SymbolType{
+                basic_type: "text".to_string(),
+                is_pointer: false,
+                array_dimensions: 0,
+            }),
 
-        // Catch negative
-        else if tree.parse_type == ParseType::NEG {
-            let child_type = self.analyze_resolvable(tree.children[0].as_ref().unwrap())?;
+            // Catch binary operations
+            ExprKind::BINARY(op, left, right) => self.resolve_binary(expr.span, *op, left, right),
 
-            // These must both be numbers
-            if child_type.basic_type != "number".to_string() ||
-               child_type.array_dimensions > 0 {
-                return Err(format!{"{} Cannot perform negative operation on non-number", self.err_header(tree)});
-            }
+            // Catch negative
+            ExprKind::NEG(value) => self.resolve_unary(value, "negative"),
 
-            // Return Ok
-            return Ok(SymbolType{
-                basic_type: "number".to_string(),
-                is_pointer: false,
-                array_dimensions: 0,
-            });
-        }
+            // Catch absolute value
+            ExprKind::ABS(value) => self.resolve_unary(value, "absolute value"),
 
-        // Catch absolute value
-        else if tree.parse_type == ParseType::ABS {
-            let child_type = self.analyze_resolvable(tree.children[0].as_ref().unwrap())?;
+            // Catch bitwise not
+            ExprKind::BITNOT(value) => self.resolve_unary(value, "bitwise not"),
 
-            // These must both be numbers
-            if child_type.basic_type != "number".to_string() ||
-               child_type.array_dimensions > 0 {
-                return Err(format!{"{} Cannot perform absolute value operation on non-number", self.err_header(tree)});
-            }
+            // Catch references
+            ExprKind::REFERENCE(reference) => Ok(self.analyze_reference(reference)),
 
-            // Return Ok
-            return Ok(SymbolType{
-                basic_type: "number".to_string(),
-                is_pointer: false,
-                array_dimensions: 0,
-            });
+            // Catch function calls
+            ExprKind::CALL(name, args) => self.resolve_call(expr.span, name, args),
+
+            // Catch arrays (recursively unwrap them)
+            ExprKind::ARRAY(items) => self.resolve_array(expr, items),
+
+            // Catch structures (and match children types)
+            ExprKind::STRUCTURE(items) => self.resolve_structure(expr.span, items),
+
+            // Catch link literals
+            ExprKind::LINK(linked) => {
+                let ex_res_type = match self.expected_resolve_type.clone() {
+                    Some(t) => t,
+                    None => return Err(self.error(expr.span, diagnostic::UNEXPECTED, "Unexpected link".to_string())),
+                };
+
+                if let Some(reference) = linked {
+                    let res_type = self.analyze_link_target(reference)?;
+                    if res_type != ex_res_type {
+                        return Err(self.error(reference.span, diagnostic::TYPE_MISMATCH, format!{"Link to {} does not match expected link type {}", res_type, ex_res_type}));
+                    }
+                }
+                Ok(ex_res_type)
+            },
         }
+    }
 
-        // Catch bitwise not
-        else if tree.parse_type == ParseType::BITNOT {
-            let child_type = self.analyze_resolvable(tree.children[0].as_ref().unwrap())?;
+    fn resolve_binary(&mut self, span: Span, op: BinOp, left: &Expr, right: &Expr) -> Result<SymbolType, Diagnostic> {
+        // Get the types of the left and right children
+        let left_type = self.analyze_resolvable(left);
+        let right_type = self.analyze_resolvable(right);
 
-            // These must both be numbers
-            if child_type.basic_type != "number".to_string() ||
-               child_type.array_dimensions != 0 {
-                return Err(format!{"{} Cannot perform bitwise not operation on non-number", self.err_header(tree)});
-            }
+        // If either side had an error, we can't tell the type
+        if left_type.is_error() || right_type.is_error() {
+            return Ok(SymbolType::error());
+        }
 
-            // Return Ok
+        // Check for addition of text (non-arrays)
+        if op == BinOp::ADD &&
+           left_type.basic_type == "text" &&
+           right_type.basic_type == "text" &&
+           left_type.array_dimensions <= 0 &&
+           right_type.array_dimensions <= 0 {
             return Ok(SymbolType{
-                basic_type: "number".to_string(),
+                basic_type: "text".to_string(),
                 is_pointer: false,
                 array_dimensions: 0,
             });
         }
 
-        // Catch references
-        else if tree.parse_type == ParseType::GETINDEX ||
-                tree.parse_type == ParseType::GETSTRUCT ||
-                tree.parse_type == ParseType::ID {
-            return self.analyze_reference(tree);
+        // Otherwise, they both must be numbers (non-arrays)
+        if left_type.basic_type != "number" ||
+           left_type.array_dimensions > 0 ||
+           right_type.basic_type != "number" ||
+           right_type.array_dimensions > 0 {
+            return Err(self.error(span, diagnostic::TYPE_MISMATCH, "Cannot perform binary operations on non-numbers".to_string()));
         }
-        
-        // Catch function calls
-        else if tree.parse_type == ParseType::CALL {
-            let fun_name = unwrap_id_tree(tree.children[0].as_ref().unwrap());
 
-            debug!("Checking function {}", fun_name);
+        // Return Ok
+        Ok(SymbolType{
+            basic_type: "number".to_string(),
+            is_pointer: false,
+            array_dimensions: 0,
+        })
+    }
 
+    // Negative, absolute value, and bitwise not all need a number
+    fn resolve_unary(&mut self, value: &Expr, operation: &str) -> Result<SymbolType, Diagnostic> {
+        let child_type = self.analyze_resolvable(value);
 
-            if self.symbol_table.functions.contains_key(&fun_name) {
-                let fun_obj = self.symbol_table.functions.get(&fun_name).expect("Could not load function map").clone();
-                
-                let ret = SymbolType {
-                    basic_type: fun_obj.return_type.clone(),
-                    is_pointer: false,
-                    array_dimensions: 0,
-                };
+        // These must both be numbers
+        if !child_type.is_error() && child_type.basic_type != "number" ||
+           child_type.array_dimensions > 0 {
+            return Err(self.error(value.span, diagnostic::TYPE_MISMATCH, format!{"Cannot perform {} operation on non-number", operation}));
+        }
 
-                let ex_res_type = self.expected_resolve_type.clone();
+        // Return Ok
+        Ok(SymbolType{
+            basic_type: "number".to_string(),
+            is_pointer: false,
+            array_dimensions: 0,
+        })
+    }
 
-                // Functions with no arguments
-                if fun_obj.params.len() == 0 {
-                    if tree.children[1].as_ref().is_some() {
-                        return Err(format!{"{} Function {} does not expect arguments", self.err_header(tree), fun_name})
-                    }
-                }
-                // Functions with arguments
-                else {
-                    if tree.children[0].as_ref().is_none() {
-                        return Err(format!{"{} Function {} expected arguments", self.err_header(tree), fun_name})
-                    }
+    fn resolve_call(&mut self, span: Span, name: &Ident, args: &[Expr]) -> Result<SymbolType, Diagnostic> {
+        let fun_name = &name.name;
 
-                    for (fun_arg, child) in fun_obj.params.into_iter().zip(tree.children[1].as_ref().unwrap().children.iter()) {
-                        self.expected_resolve_type = Some(fun_arg.clone());
-                        let res_type = self.analyze_resolvable(child.as_ref().unwrap())?;
-        
-                        if res_type != fun_arg.clone() {
-                            return Err(format!{"{} Function argument {:?} does not match expected parameter type {:?}", self.err_header(child.as_ref().unwrap()), fun_arg.clone(), res_type});
-                        }
-                    }
-                }
+        debug!("Checking function {}", fun_name);
 
-                // Set the expected resolve type
-                self.expected_resolve_type = ex_res_type;
+        let fun_obj = match self.symbol_table.functions.get(fun_name) {
+            Some(f) => f.clone(),
+            None => return Err(self.error(name.span, diagnostic::UNKNOWN_NAME, format!{"Unknown function {}", fun_name})),
+        };
+        let ret = fun_obj.return_type.clone();
 
-                return Ok(ret)
+        let ex_res_type = self.expected_resolve_type.clone();
 
+        // Functions with no arguments
+        if fun_obj.params.is_empty() {
+            if !args.is_empty() {
+                self.report(self.error(span, diagnostic::ARGUMENT_COUNT, format!{"Function {} does not expect arguments", fun_name}));
             }
-
-            return Ok(SymbolType {
-                basic_type: "external_function_call".to_string(),
-                array_dimensions: 0, 
-                is_pointer: false
-            });
         }
-        
-        // Catch arrays (recursively unwrap them)
-        else if tree.parse_type == ParseType::ARRAYLIT {
-            debug!{"Expected resolve type: {:?}", self.expected_resolve_type};
-
-            // Catch unexpected arrays
-            if self.expected_resolve_type.is_none() {
-                return Err(format!{"{} Unexpected array literal", self.err_header(tree)});
+        // Functions with arguments
+        else {
+            // A variadic function needs every parameter before the last one
+            if fun_obj.variadic && args.len() < fun_obj.params.len() - 1 {
+                self.report(self.error(span, diagnostic::ARGUMENT_COUNT, format!{"Function {} expected at least {} arguments, got {}", fun_name, fun_obj.params.len() - 1, args.len()}));
+            }
+            // Optional parameters are all given, or none of them are
+            if fun_obj.optional && !fun_obj.variadic && !args.is_empty() && args.len() != fun_obj.params.len() {
+                self.report(self.error(span, diagnostic::ARGUMENT_COUNT, format!{"Function {} expected 0 or {} arguments, got {}", fun_name, fun_obj.params.len(), args.len()}));
+            }
+            if !fun_obj.optional && !fun_obj.variadic && args.len() != fun_obj.params.len() {
+                self.report(self.error(span, diagnostic::ARGUMENT_COUNT, format!{"Function {} expected {} arguments, got {}", fun_name, fun_obj.params.len(), args.len()}));
             }
 
-            // Mark the current expected resolve type (this should be Some)
-            let mut ex_res_type = self.expected_resolve_type.clone().unwrap();
-
-            // Catch all arrays first
-            // Compare size if we actually know it
-            if ex_res_type.array_dimensions > 0 {
-
-                // Take out a layer of array, then check all children
-                let new_ex_res_type = SymbolType {
-                    basic_type: ex_res_type.basic_type.clone(),
-                    is_pointer: ex_res_type.is_pointer,
-                    array_dimensions: ex_res_type.array_dimensions - 1,
+            for (i, arg) in args.iter().enumerate() {
+                // Only the last parameter of a variadic function takes more
+                //  than one argument. Arguments past the end were already
+                //  reported as the wrong number, so only their names are checked
+                let fun_arg = match fun_obj.params.get(i) {
+                    Some(param) => param.clone(),
+                    None if fun_obj.variadic => fun_obj.params[fun_obj.params.len() - 1].clone(),
+                    None => {
+                        self.expected_resolve_type = None;
+                        self.analyze_resolvable(arg);
+                        continue;
+                    },
                 };
 
-                // Check each child
-                self.expected_resolve_type = Some(new_ex_res_type.clone());
-                for child in &tree.children {
-                    debug!("Checking array children for type {:?}", self.expected_resolve_type);
-                    let element_type = self.analyze_resolvable(child.as_ref().unwrap())?;
-                    if element_type != new_ex_res_type {
-                        return Err(format!{"{} Type mis-match inside of array literal", self.err_header(child.as_ref().unwrap())});
+                // Changeable parameters share the memory of the argument,
+                //  so the argument has to be something that has memory
+                if fun_arg.is_pointer {
+                    // Written the way it is declared, like "changeable number"
+                    let declared = SymbolType {
+                        is_pointer: false,
+                        ..fun_arg.clone()
+                    };
+                    match &arg.kind {
+                        ExprKind::REFERENCE(reference) if self.is_text_index(reference) => self.report(self.error(arg.span, diagnostic::UNCHANGEABLE, format!{"A changeable {} parameter cannot be given a single character of text", declared})),
+                        ExprKind::REFERENCE(_) => (),
+                        _ => self.report(self.error(arg.span, diagnostic::UNCHANGEABLE, format!{"A changeable {} parameter must be given a variable, not a value", declared}).with_help("store the value in a variable first, and pass that variable".to_string())),
                     }
                 }
 
-                debug!("Processed array of type {:?}", ex_res_type);
-
-                self.expected_resolve_type = Some(ex_res_type.clone());
+                self.expected_resolve_type = Some(fun_arg.clone());
+                let res_type = self.analyze_resolvable(arg);
 
-                return Ok(ex_res_type);
-            }
-            // Otherwise, attempt to predict size from the literal
-            else {
-                let mut arr_depth = 0;
-                let mut t = tree;
-                while t.parse_type == ParseType::ARRAYLIT {
-                    t = t.children[0].as_ref().unwrap();
-                    arr_depth += 1;
+                if res_type != fun_arg.clone() {
+                    self.report(self.error(arg.span, diagnostic::TYPE_MISMATCH, format!{"Function argument {} does not match expected parameter type {}", res_type, fun_arg}));
                 }
-
-                ex_res_type.array_dimensions = arr_depth;
-                self.expected_resolve_type = Some(ex_res_type);
-                // With a predicted size, now try to resolve
-                return self.analyze_resolvable(tree);
             }
         }
 
-        // Catch structures (and match children types)
-        else if tree.parse_type == ParseType::STRUCTLIT {
-            debug!{"IN STRUCT > Expected resolve type: {:?}", self.expected_resolve_type};
+        // Set the expected resolve type
+        self.expected_resolve_type = ex_res_type;
 
-            // Catch unexpected structures
-            if self.expected_resolve_type.is_none() {
-                return Err(format!{"{} Unexpected structure literal", self.err_header(tree)});
-            }
+        Ok(ret)
+    }
 
-            // Mark the current expected resolve type (this should be Some)
-            let ex_res_type = self.expected_resolve_type.clone().unwrap();
+    fn resolve_array(&mut self, expr: &Expr, items: &[Expr]) -> Result<SymbolType, Diagnostic> {
+        debug!{"Expected resolve type: {:?}", self.expected_resolve_type};
 
-            // Get the structure expected arguments
-            let struct_args = self.symbol_table.struct_args.get(&ex_res_type.basic_type).expect("Could not load structure arguments").clone();
+        // Catch unexpected arrays
+        let mut ex_res_type = match self.expected_resolve_type.clone() {
+            Some(t) => t,
+            None => return Err(self.error(expr.span, diagnostic::UNEXPECTED, "Unexpected array literal".to_string())),
+        };
+
+        // Catch all arrays first
+        // Compare size if we actually know it
+        if ex_res_type.array_dimensions > 0 {
 
-            debug!("Structure args: {:?}", struct_args);
+            // Take out a layer of array, then check all children
+            let new_ex_res_type = SymbolType {
+                basic_type: ex_res_type.basic_type.clone(),
+                is_pointer: ex_res_type.is_pointer,
+                array_dimensions: ex_res_type.array_dimensions - 1,
+            };
 
-            if struct_args.keys().len() != tree.children.len() {
-                return Err(format!{"{} Number of items does not match number of structure elements", self.err_header(tree)});
+            // Check each child
+            self.expected_resolve_type = Some(new_ex_res_type.clone());
+            for item in items {
+                debug!("Checking array children for type {:?}", self.expected_resolve_type);
+                let element_type = self.analyze_resolvable(item);
+                if element_type != new_ex_res_type {
+                    self.report(self.error(item.span, diagnostic::TYPE_MISMATCH, "Type mis-match inside of array literal".to_string()));
+                }
             }
 
-            for (struct_arg, child) in struct_args.values().zip(tree.children.iter()) {
-                self.expected_resolve_type = Some(struct_arg.clone());
-                let res_type = self.analyze_resolvable(child.as_ref().unwrap())?;
+            debug!("Processed array of type {:?}", ex_res_type);
+
+            self.expected_resolve_type = Some(ex_res_type.clone());
 
-                if res_type != struct_arg.clone() {
-                    return Err(format!{"{} Structure item {:?} does not match expected type {:?}", self.err_header(child.as_ref().unwrap()), struct_arg.clone(), res_type});
+            Ok(ex_res_type)
+        }
+        // Otherwise, attempt to predict size from the literal
+        else {
+            let mut arr_depth = 0;
+            let mut item = expr;
+            while let ExprKind::ARRAY(inner) = &item.kind {
+                arr_depth += 1;
+                match inner.first() {
+                    Some(first) => item = first,
+                    None => break,
                 }
             }
 
-            self.expected_resolve_type = Some(ex_res_type.clone());
+            ex_res_type.array_dimensions = arr_depth;
+            self.expected_resolve_type = Some(ex_res_type);
+            // With a predicted size, now try to resolve
+            Ok(self.analyze_resolvable(expr))
+        }
+    }
+
+    fn resolve_structure(&mut self, span: Span, items: &[Expr]) -> Result<SymbolType, Diagnostic> {
+        debug!{"IN STRUCT > Expected resolve type: {:?}", self.expected_resolve_type};
+
+        // Catch unexpected structures
+        let ex_res_type = match self.expected_resolve_type.clone() {
+            Some(t) => t,
+            None => return Err(self.error(span, diagnostic::UNEXPECTED, "Unexpected structure literal".to_string())),
+        };
+
+        if ex_res_type.is_error() {
             return Ok(ex_res_type);
         }
 
-        // Catch link literals
-        else if tree.parse_type == ParseType::LINKLIT {
-            if self.expected_resolve_type.is_none() {
-                return Err(format!{"{} Unexpected link", self.err_header(tree)});
+        // Get the structure expected arguments
+        let struct_args = match self.symbol_table.struct_args.get(&ex_res_type.basic_type) {
+            Some(args) if ex_res_type.array_dimensions <= 0 => args.clone(),
+            _ => return Err(self.error(span, diagnostic::TYPE_MISMATCH, format!{"Structure literal given where {} was expected", ex_res_type})),
+        };
+
+        debug!("Structure args: {:?}", struct_args);
+
+        if struct_args.keys().len() < items.len() {
+            return Err(self.error(span, diagnostic::ARGUMENT_COUNT, "Number of items does not match number of structure elements".to_string()));
+        }
+
+        // Any items left off the end must have a default value
+        for key in struct_args.keys().skip(items.len()) {
+            if !self.symbol_table.has_struct_default(&ex_res_type.basic_type, key) {
+                self.report(self.error(span, diagnostic::ARGUMENT_COUNT, format!{"Structure item '{}' has no default value, so it must be given", key}));
             }
+        }
 
-            if tree.children[0].is_some() {
-                let res_type = self.analyze_reference(tree.children[0].as_ref().unwrap())?;
-                if res_type != self.expected_resolve_type.clone().unwrap() {
-                    return Err(format!{"{} Link to {:?} does not match expected link type {:?}", self.err_header(tree.children[0].as_ref().unwrap()), self.expected_resolve_type, res_type});
-                }
+        for (struct_arg, item) in struct_args.values().zip(items.iter()) {
+            // Links can only be set with a link literal, and link literals
+            //  can only be used to set links
+            let is_link_lit = matches!(item.kind, ExprKind::LINK(_));
+            if struct_arg.is_pointer && !is_link_lit {
+                self.report(self.error(item.span, diagnostic::INVALID_LINK, format!{"Structure item of type {} must be set with 'link to'", struct_arg}));
+                continue;
+            }
+            if !struct_arg.is_pointer && is_link_lit {
+                self.report(self.error(item.span, diagnostic::INVALID_LINK, format!{"Structure item of type {} is not a link", struct_arg}));
+                continue;
+            }
+
+            self.expected_resolve_type = Some(struct_arg.clone());
+            let res_type = self.analyze_resolvable(item);
+
+            if res_type != struct_arg.clone() {
+                self.report(self.error(item.span, diagnostic::TYPE_MISMATCH, format!{"Structure item of type {} does not match expected type {}", res_type, struct_arg}));
             }
-            return Ok(self.expected_resolve_type.clone().unwrap());
         }
-        
-        //Err("error".to_string())
-        debug!("Parse type: {:?}", &tree.parse_type);
-        Ok(SymbolType{
-            basic_type: "invalid".to_string(),
-            array_dimensions: 1,
-            is_pointer: false,
-        })
+
+        self.expected_resolve_type = Some(ex_res_type.clone());
+        Ok(ex_res_type)
     }
 
-    fn analyze_conditional(&mut self, tree: &ParseTree) -> Result<(), String> {
-        match tree.parse_type {
-            ParseType::BINCOMP => {
+    fn analyze_conditional(&mut self, condition: &Condition) -> Result<(), Diagnostic> {
+        match &condition.kind {
+            ConditionKind::COMPARE(op, left, right) => {
                 // Get the types of the left and right children
-                let left_type = self.analyze_resolvable(tree.children[0].as_ref().unwrap())?;
-                let right_type = self.analyze_resolvable(tree.children[1].as_ref().unwrap())?;
+                let left_type = self.analyze_resolvable(left);
+                let right_type = self.analyze_resolvable(right);
+
+                // Errors on either side were already recorded
+                if left_type.is_error() || right_type.is_error() {
+                    return Ok(());
+                }
 
                 // Check for comparison of text
-                if tree.token.token_type == TokenType::EQ &&
-                    left_type.basic_type == "text".to_string() &&
-                    right_type.basic_type == "text".to_string() &&
+                if *op == CompareOp::EQ &&
+                    left_type.basic_type == "text" &&
+                    right_type.basic_type == "text" &&
                     left_type.array_dimensions <= 0 &&
                     right_type.array_dimensions <= 0 {
                     return Ok(());
                 }
 
                 // Otherwise, they both must be numbers (non-arrays)
-                if left_type.basic_type != "number".to_string() ||
+                if left_type.basic_type != "number" ||
                     left_type.array_dimensions > 0 ||
-                    right_type.basic_type != "number".to_string() ||
+                    right_type.basic_type != "number" ||
                     right_type.array_dimensions > 0 {
                         debug!{"{:?} <<<>>> {:?}", left_type, right_type};
-                        return Err(format!{"{} Cannot perform binary comparison on non-numbers", self.err_header(tree)});
+                        return Err(self.error(condition.span, diagnostic::TYPE_MISMATCH, "Cannot perform binary comparison on non-numbers".to_string()));
                     }
-                return Ok(());
+                Ok(())
             },
-            ParseType::ISLINKED | ParseType::ISNOTLINKED => {
-                if !self.analyze_reference(tree.children[0].as_ref().unwrap())?.is_pointer {
-                    return Err(format!{"{} Cannot check status of a non-linable object", self.err_header(tree)});
+            ConditionKind::AND(left, right) | ConditionKind::OR(left, right) => {
+                let result = self.analyze_conditional(left);
+                self.record(result);
+                self.analyze_conditional(right)
+            },
+            ConditionKind::ISLINKED(reference) | ConditionKind::ISNOTLINKED(reference) => {
+                let ref_type = self.analyze_reference(reference);
+                if !ref_type.is_pointer && !ref_type.is_error() {
+                    return Err(self.error(condition.span, diagnostic::INVALID_LINK, "Cannot check status of a non-linable object".to_string()));
                 }
-                return Ok(());
+                Ok(())
+            },
+            // Code that couldn't be parsed already has an error
+            ConditionKind::INVALID => Ok(()),
+        }
+    }
+
+    // Find the type of a reference, if it has an error the error is
+    //  recorded and the error type is given back instead
+    fn analyze_reference(&mut self, reference: &Reference) -> SymbolType {
+        match self.resolve_reference(reference) {
+            Ok(t) => t,
+            Err(err) => {
+                self.report(err);
+                SymbolType::error()
             },
-            _ => return Err(format!{"{} Must have a conditional", self.err_header(tree)}),
         }
     }
 
-    fn analyze_reference(&mut self, tree: &ParseTree) -> Result<SymbolType, String> {
-        let ref_type: SymbolType;
+    fn resolve_reference(&mut self, reference: &Reference) -> Result<SymbolType, Diagnostic> {
+        match &reference.kind {
+            ReferenceKind::NAME(name) => {
+                debug!("FINDING SYMBOL {}", name);
+                self.symbol_table.find_symbol(name.clone()).map_err(at(reference.span))
+            },
+            ReferenceKind::INDEX(array, indexes) => {
+                let ref_type = self.analyze_reference(array);
 
-        if tree.parse_type != ParseType::ID {
-            ref_type = self.analyze_reference(tree.children[0].as_ref().unwrap())?;
+                // Don't look inside of something that already had an error
+                if ref_type.is_error() {
+                    return Ok(ref_type);
+                }
 
-            if tree.parse_type == ParseType::GETINDEX {
                 let mut arr_dims = ref_type.array_dimensions;
 
+                // Text can be indexed (once) to get a single character
+                if self.is_text_index(reference) {
+                    if indexes.len() != 1 {
+                        return Err(self.error(reference.span, diagnostic::INVALID_INDEX, "Text can only be indexed with a single index".to_string()));
+                    }
+                    arr_dims = 1;
+                }
+                else if arr_dims != -1 && indexes.len() as i32 > arr_dims {
+                    return Err(self.error(reference.span, diagnostic::INVALID_INDEX, "Cannot index a non-array".to_string()));
+                }
+
                 // check all indecies to make sure they are numbers
-                let index_tree = tree.children[1].as_ref().unwrap();
-                for idx in &index_tree.children {
+                for idx in indexes {
                     // We can't do much about unknown array dimensions yet
                     if arr_dims != -1 {
                         arr_dims -= 1;
                     }
-                    let idx_type = self.analyze_resolvable(idx.as_ref().unwrap())?;
-                    if idx_type.basic_type != "number".to_string() {
-                        return Err(format!{"{} Cannot index using a non-number", self.err_header(tree)});
+                    let idx_type = self.analyze_resolvable(idx);
+                    if idx_type.basic_type != "number" && !idx_type.is_error() {
+                        self.report(self.error(idx.span, diagnostic::INVALID_INDEX, "Cannot index using a non-number".to_string()));
                     }
                 }
 
-                return Ok(SymbolType{
+                Ok(SymbolType{
                     basic_type: ref_type.basic_type,
                     is_pointer: ref_type.is_pointer,
                     array_dimensions: arr_dims,
-                });
-            }
+                })
+            },
+            ReferenceKind::FIELD(structure, key) => {
+                let ref_type = self.analyze_reference(structure);
 
-            else if tree.parse_type == ParseType::GETSTRUCT {
-                let struct_key = unwrap_id_tree(tree.children[1].as_ref().unwrap());
+                // Don't look inside of something that already had an error
+                if ref_type.is_error() {
+                    return Ok(ref_type);
+                }
 
-                return self.symbol_table.get_struct_key(ref_type.basic_type, struct_key);
-            }
+                self.symbol_table.get_struct_key(ref_type.basic_type, key.name.clone()).map_err(at(key.span))
+            },
         }
-
-        debug!("FINDING SYMBOL {}", unwrap_id_tree(&tree));
-        self.symbol_table.find_symbol(unwrap_id_tree(&tree))
     }
 
-    fn analyze_vardef(&mut self, tree: &ParseTree) -> Result<SymbolType, String> {
-        //tree.print();
-        let sym_type = self.analyze_type(tree.children[1].as_ref().unwrap())?;
+    // Check if a reference is indexing into text (instead of an array)
+    // Text cannot be changed, so these can't be assigned to or linked to
+    fn is_text_index(&mut self, reference: &Reference) -> bool {
+        let array = match &reference.kind {
+            ReferenceKind::INDEX(array, _) => array,
+            _ => return false,
+        };
 
-        // ID
-        if tree.children[0].as_ref().unwrap().parse_type == ParseType::ID {
-            let id = unwrap_id_tree(tree.children[0].as_ref().unwrap());
+        let ref_type = self.analyze_reference(array);
+        ref_type.basic_type == "text" && ref_type.array_dimensions == 0
+    }
 
-            debug!{"Adding symbol {} of type {:?}", id, sym_type};
-            self.symbol_table.add_symbol(id, sym_type.clone())?;
-        }
-        // IDS
-        else {
-            for id_tree in &tree.children[0].as_ref().unwrap().children {
-                let id = unwrap_id_tree(id_tree.as_ref().unwrap());
+    // Add the variable(s) to the symbol table, giving back their type
+    fn analyze_vardef(&mut self, var_def: &VarDef) -> SymbolType {
+        let sym_type = self.analyze_type(&var_def.var_type);
 
-                debug!{"Adding symbol {} of type  {:?}", id, sym_type};
-                self.symbol_table.add_symbol(id, sym_type.clone())?;
-            }
-        }
-        
-        Ok(sym_type)
-    }
-
-    fn analyze_body(&mut self, tree: &ParseTree) -> Result<(), String> {
-        let mut is_other: bool = false;
-        for child in &tree.children {
-            match child.as_ref().unwrap().parse_type {
-                ParseType::IF => self.analyze_if(child.as_ref().unwrap())?,
-                ParseType::LINK => (),
-                ParseType::UNLINK => self.analyze_unlink(child.as_ref().unwrap())?,
-                ParseType::WHILE => self.analyze_while(child.as_ref().unwrap())?,
-                ParseType::REPEAT => self.analyze_repeat(child.as_ref().unwrap())?,
-                ParseType::REPEATFOR => self.analyze_repeat_for(child.as_ref().unwrap())?,
-                ParseType::REPEATFOREVER => self.analyze_repeat_forever(child.as_ref().unwrap())?,
-                ParseType::QUIT | ParseType::BREAK | ParseType::CONTINUE => (),
-                ParseType::ASSIGN => self.analyze_assignment(child.as_ref().unwrap())?,
-                ParseType::RETURN => self.analyze_return(child.as_ref().unwrap())?,
-                _ => is_other = true,
-            }
-            // Catch anything with an actual return type
-            if is_other {
-                if child.as_ref().unwrap().parse_type == ParseType::VARDEF {
-                    self.analyze_vardef(child.as_ref().unwrap())?;
-                }
-                else {
-                    self.analyze_resolvable(child.as_ref().unwrap())?;
-                }
-                is_other = false
-            }
+        for id in &var_def.names {
+            debug!{"Adding symbol {} of type {:?}", id.name, sym_type};
+            let result = self.symbol_table.add_symbol(id.name.clone(), sym_type.clone()).map_err(at(id.span));
+            self.record(result);
         }
 
-        Ok(())
+        sym_type
     }
 
-    fn analyze_if(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Analyze the comparison
-        self.analyze_conditional(tree.children[0].as_ref().unwrap())?;
+    // Check every statement in a block (see visit_stmt)
+    fn analyze_body(&mut self, block: &Block) {
+        self.visit_block(block);
+    }
 
-        // Analyze the body
+    // Check a block in its own scope
+    fn analyze_scoped_body(&mut self, block: &Block) {
         self.symbol_table.scope_in();
-        self.analyze_body(tree.children[1].as_ref().unwrap())?;
+        self.analyze_body(block);
         self.symbol_table.scope_out();
+    }
 
-        if tree.children[2].is_some() {
-            // Analyze an else if
-            if tree.children[2].as_ref().unwrap().parse_type == ParseType::IF {
-                self.analyze_if(tree.children[2].as_ref().unwrap())?;
-            }
+    fn analyze_if(&mut self, if_stmt: &If) -> Result<(), Diagnostic> {
+        // Analyze the comparison
+        self.visit_condition(&if_stmt.condition);
 
-            // Analyze an else block if it exists
-            else if tree.children[2].as_ref().unwrap().parse_type == ParseType::BLOCK {
-                self.symbol_table.scope_in();
-                self.analyze_body(tree.children[2].as_ref().unwrap())?;
-                self.symbol_table.scope_out();
-            }
+        // Analyze the body
+        self.analyze_scoped_body(&if_stmt.body);
 
-            else {
-                return Err(format!("{} Received illegal if-child {:?}",
-                    self.err_header(tree.children[2].as_ref().unwrap()), 
-                    tree.children[2].as_ref().unwrap().parse_type));
-            }
+        match &if_stmt.otherwise {
+            // Analyze an else if
+            Some(Else::IF(else_if)) => self.analyze_if(else_if)?,
+            // Analyze an else block if it exists
+            Some(Else::BLOCK(else_block)) => self.analyze_scoped_body(else_block),
+            None => (),
         }
 
         Ok(())
     }
 
-    fn analyze_while(&mut self, tree: &ParseTree) -> Result<(), String> {
+    fn analyze_while(&mut self, condition: &Condition, body: &Block) -> Result<(), Diagnostic> {
         // Analyze the comparison
-        self.analyze_conditional(tree.children[0].as_ref().unwrap())?;
+        self.visit_condition(condition);
 
         // Analyze the while block
-        self.symbol_table.scope_in();
-        self.analyze_body(tree.children[1].as_ref().unwrap())?;
-        self.symbol_table.scope_out();
+        self.analyze_scoped_body(body);
 
         Ok(())
     }
 
-    fn analyze_repeat(&mut self, tree: &ParseTree) -> Result<(), String> {
+    fn analyze_repeat(&mut self, count: &Expr, body: &Block) -> Result<(), Diagnostic> {
         // Check if a number was passed to repeat that amount of times
-        let repeat_type = self.analyze_resolvable(tree.children[0].as_ref().unwrap())?;
-        if repeat_type.basic_type != "number".to_string() || repeat_type.array_dimensions != 0 {
-            return Err(format!{"{} Repeat must be provided a number literal", self.err_header(tree.children[0].as_ref().unwrap())});
+        let repeat_type = self.analyze_resolvable(count);
+        if !repeat_type.is_error() && (repeat_type.basic_type != "number" || repeat_type.array_dimensions != 0) {
+            self.report(self.error(count.span, diagnostic::TYPE_MISMATCH, "Repeat must be provided a number literal".to_string()));
         }
 
         // Analyze the body
-        self.symbol_table.scope_in();
-        self.analyze_body(tree.children[1].as_ref().unwrap())?;
-        self.symbol_table.scope_out();
+        self.analyze_scoped_body(body);
+
+        Ok(())
+    }
 
+    fn analyze_quit(&mut self, status: &Expr) -> Result<(), Diagnostic> {
+        // The status becomes the exit code, so it has to be a number
+        let status_type = self.analyze_resolvable(status);
+        if !status_type.is_error() && (status_type.basic_type != "number" || status_type.array_dimensions != 0) {
+            return Err(self.error(status.span, diagnostic::TYPE_MISMATCH, format!{"Quit must be given a number, not {}", status_type}));
+        }
         Ok(())
     }
 
-    fn analyze_repeat_for(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Make sure the second item is an array
-        let repeat_type = self.analyze_resolvable(tree.children[1].as_ref().unwrap())?;
-        if repeat_type.array_dimensions == 0 {
-            return Err(format!{"{} Repeat must have an array to loop over", self.err_header(tree.children[1].as_ref().unwrap())});
+    fn analyze_repeat_for(&mut self, variable: Option<&Ident>, values: &Expr, body: &Block) -> Result<(), Diagnostic> {
+        // Make sure the second item is an array (or text)
+        let repeat_type = self.analyze_resolvable(values);
+        let is_text = repeat_type.basic_type == "text" && repeat_type.array_dimensions == 0;
+        if repeat_type.array_dimensions == 0 && !is_text {
+            self.report(self.error(values.span, diagnostic::TYPE_MISMATCH, "Repeat must have an array or text to loop over".to_string()));
         }
 
         // Analyze body:
@@ -936,11 +1217,13 @@ impl SemanticAnalyzer {
         if symbol_type.array_dimensions > 0 {
             symbol_type.array_dimensions -= 1;
         }
-        let symbol = unwrap_id_tree(tree.children[0].as_ref().unwrap());
-        self.symbol_table.add_symbol(symbol, symbol_type)?;
+        if let Some(id) = variable {
+            let result = self.symbol_table.add_symbol(id.name.clone(), symbol_type).map_err(at(id.span));
+            self.record(result);
+        }
 
         // Check Body
-        self.analyze_body(tree.children[2].as_ref().unwrap())?;
+        self.analyze_body(body);
 
         // Scope out
         self.symbol_table.scope_out();
@@ -948,84 +1231,598 @@ impl SemanticAnalyzer {
         Ok(())
     }
 
-    fn analyze_repeat_forever(&mut self, tree: &ParseTree) -> Result<(), String> {
+    fn analyze_repeat_forever(&mut self, body: &Block) -> Result<(), Diagnostic> {
         // Analyze the body
-        self.symbol_table.scope_in();
-        self.analyze_body(tree.children[0].as_ref().unwrap())?;
-        self.symbol_table.scope_out();
+        self.analyze_scoped_body(body);
 
         Ok(())
     }
 
-    fn analyze_return(&mut self, tree: &ParseTree) -> Result<(), String> {
-        // Make sure we are expecting a return
-        if self.expected_return_type.is_none() {
-            return Err(format!{"{} Unexpected return statement", self.err_header(tree)});
+    // Make sure a function that returns a value can't reach
+    //  the end of its body without returning
+    fn analyze_return_paths(&mut self, fun_def: &FunctionDef) {
+        let ret_type = match self.expected_return_type.clone() {
+            Some(t) => t,
+            None => return,
+        };
+        if ret_type == "nothing" || ret_type == ERROR_TYPE {
+            return;
+        }
+
+        let id = match &fun_def.name {
+            Some(id) => id,
+            None => return,
+        };
+
+        let path = match fall_through_path(&fun_def.body) {
+            Some(p) => p,
+            None => return,
+        };
+
+        let mut err = self.error(id.span, diagnostic::MISSING_RETURN, format!{"Function '{}' can reach its end without returning a {}", id.name, ret_type});
+        err.labels = path;
+        self.report(err.with_help(format!{"add a return to the end of '{}'", id.name}));
+    }
+
+    // Make sure variables are given a value before they are used
+    // The parameters (if any) always have a value
+    fn analyze_assignments(&mut self, params: &[Param], body: &Block) {
+        let mut checker = AssignmentChecker::new(&self.symbol_table.functions);
+
+        let mut param_scope: HashMap<String, Option<usize>> = HashMap::new();
+        for param in params {
+            param_scope.insert(param.name.name.clone(), None);
         }
+        checker.scopes.push(param_scope);
+        checker.block(body, Some(HashSet::new()));
 
-        let expected_type = self.expected_return_type.clone().unwrap();
+        for err in mem::take(&mut checker.diagnostics) {
+            self.report(err);
+        }
+    }
+
+    fn analyze_return(&mut self, span: Span, value: Option<&Expr>) -> Result<(), Diagnostic> {
+        // Make sure we are expecting a return
+        let expected_type = match self.expected_return_type.clone() {
+            Some(t) => t,
+            None => return Err(self.error(span, diagnostic::UNEXPECTED, "Unexpected return statement".to_string())),
+        };
         let mut ret_type: String = "nothing".to_string();
 
         // Check to see if the return type is nothing
-        if tree.children[0].is_some() {
-            let sym_type = self.analyze_resolvable(tree.children[0].as_ref().unwrap())?;
+        if let Some(value) = value {
+            let sym_type = self.analyze_resolvable(value);
             ret_type = sym_type.basic_type;
         }
 
+        // Errors in the function's return type, or the value, were already recorded
+        if expected_type == ERROR_TYPE || ret_type == ERROR_TYPE {
+            return Ok(());
+        }
+
         // Make sure the expected type matches the return type
         if expected_type != ret_type {
-            return Err(format!{"{} Mismatched return types. Expected {:?}, got {:?}", self.err_header(tree), expected_type, ret_type}.to_string());
+            let mut err = self.error(span, diagnostic::TYPE_MISMATCH, format!{"Mismatched return types. Expected {}, got {}", expected_type, ret_type});
+            if !self.expected_return_span.is_unknown() {
+                err = err.with_label(self.expected_return_span, format!{"The function returns {} here", expected_type});
+            }
+            return Err(err);
         }
 
         // Otherwise return Ok
         Ok(())
     }
 
-    /// 
-    fn analyze_unlink(&mut self, tree: &ParseTree) -> Result<(), String> {
-        if !self.analyze_reference(tree.children[0].as_ref().unwrap())?.is_pointer {
-            return Err(format!("{} {:?} is not a linkable object", self.err_header(tree.children[0].as_ref().unwrap()), unwrap_id_tree(tree.children[0].as_ref().unwrap())));
+    fn analyze_link(&mut self, span: Span, target: &Target, linked: Option<&Reference>) -> Result<(), Diagnostic> {
+        let left_type = match target {
+            Target::VARDEF(var_def) => self.analyze_vardef(var_def),
+            Target::REFERENCE(reference) => self.analyze_reference(reference),
+        };
+
+        // Only links can be linked
+        if !left_type.is_pointer && !left_type.is_error() {
+            return Err(self.error(span, diagnostic::INVALID_LINK, "Cannot link a variable that is not a link".to_string()));
+        }
+
+        // Linking to nothing is always allowed, otherwise
+        //  the reference has to match the link type
+        if let Some(reference) = linked {
+            let right_type = self.analyze_link_target(reference)?;
+            if left_type != right_type {
+                return Err(self.error(span, diagnostic::TYPE_MISMATCH, format!{"Link to {} does not match expected link type {}", right_type, left_type}));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Analyze the reference that a link is being linked to
+    fn analyze_link_target(&mut self, reference: &Reference) -> Result<SymbolType, Diagnostic> {
+        let target_type = self.analyze_reference(reference);
+
+        if self.is_text_index(reference) {
+            return Err(self.error(reference.span, diagnostic::UNCHANGEABLE, "Cannot link to a single character of text".to_string()));
+        }
+
+        Ok(target_type)
+    }
+
+    fn analyze_unlink(&mut self, reference: &Reference) -> Result<(), Diagnostic> {
+        let ref_type = self.analyze_reference(reference);
+        if !ref_type.is_pointer && !ref_type.is_error() {
+            return Err(self.error(reference.span, diagnostic::INVALID_LINK, format!{"'{}' is not a linkable object", reference}));
         }
         Ok(())
     }
 }
 
-// Generic main function that just runs the SA and prints
-//  the resulting symbol table. If a filename is provided
-//  in the system arguments, analyze that file instead.
-pub fn _test() {
-    let args: Vec<String> = env::args().collect();
-
-    // create parser
-    let mut p: Parser;
-    // if argument, open file
-    if args.len() > 1 {
-        let fname = &args[1];
-        p = Parser::from_file(fname.to_string()).expect("Could not create lexer");
-    }
-    else {
-        p = Parser::new("
-    program
-        print(\"Hello world!\")
-    end program
-    ".to_string()).expect("Could not create lexer");
-    }
-
-    let tree = match p.parse() {
-        Ok(t) => t.unwrap(),
-        Err(s) => {
-            log!{"Parser Error: {}", s};
-            return;
+// The statements of a body are walked with the Visitor, the parts
+//  of a statement that have a type (values, references, and types)
+//  are checked by the analyze_ methods, which give back that type
+impl Visitor for SemanticAnalyzer {
+    // An error in one statement doesn't stop the others from being checked
+    fn visit_stmt(&mut self, statement: &Stmt) {
+        let result = match &statement.kind {
+            StmtKind::IF(if_stmt) => self.analyze_if(if_stmt),
+            StmtKind::LINK(target, linked) => self.analyze_link(statement.span, target, linked.as_ref()),
+            StmtKind::UNLINK(reference) => self.analyze_unlink(reference),
+            StmtKind::WHILE(condition, body) => self.analyze_while(condition, body),
+            StmtKind::REPEAT(count, body) => self.analyze_repeat(count, body),
+            StmtKind::REPEATFOR(variable, values, body) => self.analyze_repeat_for(variable.as_ref(), values, body),
+            StmtKind::REPEATFOREVER(body) => self.analyze_repeat_forever(body),
+            StmtKind::ASSIGN(target, value) => self.analyze_assignment(statement.span, target, value),
+            StmtKind::RETURN(value) => self.analyze_return(statement.span, value.as_ref()),
+            StmtKind::QUIT(Some(status)) => self.analyze_quit(status),
+            // Declarations and values on their own (like a function call)
+            //  are checked when they are visited
+            _ => {
+                walk_stmt(self, statement);
+                Ok(())
+            },
+        };
+        self.record(result);
+    }
+
+    fn visit_var_def(&mut self, var_def: &VarDef) {
+        self.analyze_vardef(var_def);
+    }
+
+    fn visit_condition(&mut self, condition: &Condition) {
+        let result = self.analyze_conditional(condition);
+        self.record(result);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.analyze_resolvable(expr);
+    }
+}
+
+// =======================
+// =   RETURN PATHS      =
+// =======================
+// A block "falls through" if running it can reach its end (instead
+//  of always returning or quitting). When it does, the labels
+//  describe one way to get there, like "when this condition is false".
+
+fn path_label(span: Span, message: &str) -> Label {
+    Label {
+        span,
+        message: message.to_string(),
+    }
+}
+
+// Give back how a block can fall through, or None if it can't
+fn fall_through_path(block: &Block) -> Option<Vec<Label>> {
+    let mut path: Vec<Label> = Vec::new();
+
+    for statement in &block.statements {
+        // Once a statement can't be passed, the rest of the
+        //  block can't be reached
+        path = fall_through_statement(statement)?;
+    }
+
+    // A statement that just runs doesn't need its own label,
+    //  point at the last thing the block runs instead
+    if path.is_empty() {
+        match block.statements.last() {
+            Some(last) => path.push(path_label(last.head(), "nothing is returned after this")),
+            None => path.push(path_label(block.span, "nothing is returned in this block")),
+        }
+    }
+
+    Some(path)
+}
+
+// Give back how running a statement can reach the next one,
+//  or None if it never can
+fn fall_through_statement(statement: &Stmt) -> Option<Vec<Label>> {
+    match &statement.kind {
+        StmtKind::RETURN(_) | StmtKind::QUIT(_) => None,
+        StmtKind::IF(if_stmt) => fall_through_if(if_stmt),
+        // The loop might not run at all, so it can always fall through
+        StmtKind::WHILE(condition, _) => Some(vec![path_label(condition.span, "when this condition is false")]),
+        StmtKind::REPEAT(_, _) | StmtKind::REPEATFOR(_, _, _) => Some(vec![path_label(statement.head(), "when this loop is done (or doesn't run at all)")]),
+        // A loop that runs forever can only be left with a break
+        StmtKind::REPEATFOREVER(body) => {
+            let break_span = find_break(body)?;
+            Some(vec![path_label(break_span, "when the loop is left by this break")])
         },
+        _ => Some(Vec::new()),
+    }
+}
+
+// Either branch of an if can fall through
+fn fall_through_if(if_stmt: &If) -> Option<Vec<Label>> {
+    let head = if_stmt.condition.span;
+    if let Some(mut path) = fall_through_path(&if_stmt.body) {
+        path.insert(0, path_label(head, "when this condition is true"));
+        return Some(path);
+    }
+
+    let mut path = match &if_stmt.otherwise {
+        None => Vec::new(),
+        Some(Else::IF(else_if)) => fall_through_if(else_if)?,
+        Some(Else::BLOCK(else_block)) => fall_through_path(else_block)?,
     };
+    path.insert(0, path_label(head, "when this condition is false"));
+    Some(path)
+}
+
+// Find a break that leaves the loop the block belongs to
+fn find_break(block: &Block) -> Option<Span> {
+    let mut finder = BreakFinder { found: None };
+    finder.visit_block(block);
+    finder.found
+}
+
+struct BreakFinder {
+    // The first break that was found
+    found: Option<Span>,
+}
+
+impl Visitor for BreakFinder {
+    fn visit_stmt(&mut self, statement: &Stmt) {
+        match &statement.kind {
+            StmtKind::BREAK => {
+                if self.found.is_none() {
+                    self.found = Some(statement.span);
+                }
+            },
+            // Breaks inside of another loop only leave that loop
+            StmtKind::WHILE(_, _) | StmtKind::REPEAT(_, _) | StmtKind::REPEATFOR(_, _, _) | StmtKind::REPEATFOREVER(_) => (),
+            _ => walk_stmt(self, statement),
+        }
+    }
+
+    // Only statements can break
+    fn visit_condition(&mut self, _condition: &Condition) {}
+    fn visit_expr(&mut self, _expr: &Expr) {}
+}
+
+// ===========================
+// =   DEFINITE ASSIGNMENT   =
+// ===========================
+// A variable declared without a value (like "x: number") can't be
+//  read until it has been given a value on every path that gets to
+//  the read. Only numbers and text are checked, arrays and structures
+//  are filled in when they are declared, and links start unlinked
+//  (which is checked when the program runs).
+
+// The declarations that might not have a value yet at a point in
+//  the code, or None if that point can't be reached
+type Unassigned = Option<HashSet<usize>>;
+
+// Join the paths of code that meet at the same point
+fn merge(left: Unassigned, right: Unassigned) -> Unassigned {
+    match (left, right) {
+        (None, other) | (other, None) => other,
+        (Some(mut left), Some(right)) => {
+            left.extend(right);
+            Some(left)
+        },
+    }
+}
+
+struct AssignmentChecker<'a> {
+    functions: &'a HashMap<String, FunctionObject>,
+    // Each checked declaration, its name and type
+    declarations: Vec<(String, String, Span)>,
+    // Each name in scope and its declaration (None if it isn't checked)
+    scopes: Vec<HashMap<String, Option<usize>>>,
+    // The paths that break out of each loop we are in
+    breaks: Vec<Unassigned>,
+    // Declarations that already have an error
+    reported: HashSet<usize>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> AssignmentChecker<'a> {
+    fn new(functions: &'a HashMap<String, FunctionObject>) -> Self {
+        AssignmentChecker {
+            functions,
+            declarations: Vec::new(),
+            scopes: Vec::new(),
+            breaks: Vec::new(),
+            reported: HashSet::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    // Find the declaration a name belongs to
+    fn find(&self, name: &str) -> Option<usize> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(decl) = scope.get(name) {
+                return *decl;
+            }
+        }
+        None
+    }
+
+    fn declare(&mut self, id: &Ident, checked_type: Option<String>, mut state: Unassigned) -> Unassigned {
+        let decl = match checked_type {
+            Some(type_name) => {
+                self.declarations.push((id.name.clone(), type_name, id.span));
+                let decl = self.declarations.len() - 1;
+                if let Some(unassigned) = state.as_mut() {
+                    unassigned.insert(decl);
+                }
+                Some(decl)
+            },
+            None => None,
+        };
+        self.scopes.last_mut().unwrap().insert(id.name.clone(), decl);
+        state
+    }
+
+    fn declare_vardef(&mut self, var_def: &VarDef, has_value: bool, mut state: Unassigned) -> Unassigned {
+        // Only numbers and text without a value are checked
+        let mut checked_type: Option<String> = None;
+        if !has_value {
+            checked_type = match var_def.var_type.kind {
+                TypeKind::NUMBER => Some("number".to_string()),
+                TypeKind::TEXT => Some("text".to_string()),
+                _ => None,
+            };
+        }
+
+        for id in &var_def.names {
+            state = self.declare(id, checked_type.clone(), state);
+        }
+        state
+    }
+
+    // Give a variable a value
+    fn assign(&mut self, name: &str, mut state: Unassigned) -> Unassigned {
+        if let (Some(decl), Some(unassigned)) = (self.find(name), state.as_mut()) {
+            unassigned.remove(&decl);
+        }
+        state
+    }
+
+    // Check a block in its own scope
+    fn block(&mut self, block: &Block, mut state: Unassigned) -> Unassigned {
+        self.scopes.push(HashMap::new());
+        for statement in &block.statements {
+            state = self.statement(statement, state);
+        }
+        self.scopes.pop();
+        state
+    }
+
+    // Check the body of a loop, giving back the paths that break out of it
+    fn loop_body(&mut self, block: &Block, state: Unassigned) -> Unassigned {
+        self.breaks.push(None);
+        self.block(block, state);
+        self.breaks.pop().unwrap()
+    }
+
+    fn statement(&mut self, statement: &Stmt, state: Unassigned) -> Unassigned {
+        match &statement.kind {
+            StmtKind::VARDEF(var_def) => self.declare_vardef(var_def, false, state),
+            StmtKind::ASSIGN(target, value) => {
+                let mut state = match target {
+                    Target::REFERENCE(reference) if !matches!(reference.kind, ReferenceKind::NAME(_)) => self.reads_reference(reference, state),
+                    _ => state,
+                };
+                state = self.reads(value, state);
+                match target {
+                    Target::VARDEF(var_def) => self.declare_vardef(var_def, true, state),
+                    Target::REFERENCE(Reference { kind: ReferenceKind::NAME(name), .. }) => self.assign(name, state),
+                    _ => state,
+                }
+            },
+            // Linking to a variable doesn't read it
+            StmtKind::LINK(Target::VARDEF(var_def), _) => self.declare_vardef(var_def, true, state),
+            StmtKind::LINK(_, _) => state,
+            StmtKind::IF(if_stmt) => self.if_statement(if_stmt, state),
+            // A loop might not run at all, so it can't give variables a value
+            StmtKind::WHILE(condition, body) => {
+                let state = self.reads_condition(condition, state);
+                self.loop_body(body, state.clone());
+                state
+            },
+            StmtKind::REPEAT(count, body) => {
+                let state = self.reads(count, state);
+                self.loop_body(body, state.clone());
+                state
+            },
+            StmtKind::REPEATFOR(variable, values, body) => {
+                let state = self.reads(values, state);
+                let mut loop_scope = HashMap::new();
+                if let Some(id) = variable {
+                    loop_scope.insert(id.name.clone(), None);
+                }
+                self.scopes.push(loop_scope);
+                self.loop_body(body, state.clone());
+                self.scopes.pop();
+                state
+            },
+            // The only way out of a forever loop is a break
+            StmtKind::REPEATFOREVER(body) => self.loop_body(body, state),
+            StmtKind::BREAK => {
+                if let Some(breaks) = self.breaks.last_mut() {
+                    *breaks = merge(breaks.take(), state);
+                }
+                None
+            },
+            StmtKind::RETURN(value) | StmtKind::QUIT(value) => {
+                if let Some(value) = value {
+                    self.reads(value, state);
+                }
+                None
+            },
+            StmtKind::CONTINUE => None,
+            StmtKind::UNLINK(_) | StmtKind::INVALID => state,
+            StmtKind::EXPR(value) => self.reads(value, state),
+        }
+    }
+
+    fn if_statement(&mut self, if_stmt: &If, state: Unassigned) -> Unassigned {
+        let state = self.reads_condition(&if_stmt.condition, state);
+        let then_state = self.block(&if_stmt.body, state.clone());
+        let else_state = match &if_stmt.otherwise {
+            None => state,
+            Some(Else::IF(else_if)) => self.if_statement(else_if, state),
+            Some(Else::BLOCK(else_block)) => self.block(else_block, state),
+        };
+        merge(then_state, else_state)
+    }
+
+    fn reads_condition(&mut self, condition: &Condition, state: Unassigned) -> Unassigned {
+        match &condition.kind {
+            ConditionKind::COMPARE(_, left, right) => {
+                let state = self.reads(left, state);
+                self.reads(right, state)
+            },
+            ConditionKind::AND(left, right) | ConditionKind::OR(left, right) => {
+                let state = self.reads_condition(left, state);
+                self.reads_condition(right, state)
+            },
+            ConditionKind::ISLINKED(reference) | ConditionKind::ISNOTLINKED(reference) => self.reads_reference(reference, state),
+            ConditionKind::INVALID => state,
+        }
+    }
+
+    // Check every variable that is read in a value
+    fn reads(&mut self, expr: &Expr, mut state: Unassigned) -> Unassigned {
+        match &expr.kind {
+            ExprKind::REFERENCE(reference) => self.reads_reference(reference, state),
+            ExprKind::CALL(name, args) => {
+                let params: Vec<SymbolType> = match self.functions.get(&name.name) {
+                    Some(f) => f.params.clone(),
+                    None => Vec::new(),
+                };
+
+                // A variable given to a changeable parameter is
+                //  given a value by the function
+                let mut changed: Vec<&String> = Vec::new();
+                for (i, arg) in args.iter().enumerate() {
+                    let param = params.get(i).or(params.last());
+                    let is_changeable = match param {
+                        Some(p) => p.is_pointer,
+                        None => false,
+                    };
+
+                    match &arg.kind {
+                        ExprKind::REFERENCE(Reference { kind: ReferenceKind::NAME(arg_name), .. }) if is_changeable => changed.push(arg_name),
+                        _ => state = self.reads(arg, state),
+                    }
+                }
+                for arg_name in changed {
+                    state = self.assign(arg_name, state);
+                }
+                state
+            },
+            ExprKind::BINARY(_, left, right) => {
+                state = self.reads(left, state);
+                self.reads(right, state)
+            },
+            ExprKind::NEG(value) | ExprKind::ABS(value) | ExprKind::BITNOT(value) => self.reads(value, state),
+            ExprKind::ARRAY(items) | ExprKind::STRUCTURE(items) => {
+                for item in items {
+                    state = self.reads(item, state);
+                }
+                state
+            },
+            // The target of a link isn't read
+            ExprKind::LINK(_) => state,
+            ExprKind::NUMBER(_) | ExprKind::TEXT(_) | ExprKind::INVALID => state,
+        }
+    }
+
+    fn reads_reference(&mut self, reference: &Reference, mut state: Unassigned) -> Unassigned {
+        match &reference.kind {
+            ReferenceKind::NAME(name) => {
+                let decl = match self.find(name) {
+                    Some(decl) => decl,
+                    None => return state,
+                };
+                let unassigned = match state.as_ref() {
+                    Some(unassigned) => unassigned.contains(&decl),
+                    None => false,
+                };
 
-    if DEBUG {
-        tree.print();
-        println!("\n\n\n\n");
+                if unassigned && self.reported.insert(decl) {
+                    let (name, type_name, span) = self.declarations[decl].clone();
+                    let example = if type_name == "text" { "\"\"" } else { "0" };
+                    self.diagnostics.push(Diagnostic::new(Stage::SEMANTIC, diagnostic::MAYBE_UNASSIGNED, format!{"Variable '{}' might be used before it is given a value", name})
+                        .with_span(reference.span)
+                        .with_label(span, format!{"'{}' is declared here without a value", name})
+                        .with_help(format!{"give '{}' a value where it is declared, like {}: {} = {}", name, name, type_name, example}));
+                }
+                state
+            },
+            ReferenceKind::INDEX(array, indexes) => {
+                state = self.reads_reference(array, state);
+                for index in indexes {
+                    state = self.reads(index, state);
+                }
+                state
+            },
+            // The key of a structure isn't a variable
+            ReferenceKind::FIELD(structure, _) => self.reads_reference(structure, state),
+        }
     }
+}
 
-    let mut sa = SemanticAnalyzer::new();
+// ======================
+// =  TABLE PRINTING    =
+// ======================
 
-    sa.analyze(&tree).expect("Something errored");
-    println!("{:?}", sa.symbol_table);
+impl SymbolTable {
+    // Print the structures, functions and global variables, sorted by
+    //  name (the built in functions are left out)
+    pub fn print(&self) {
+        let library = FunctionRegistry::builtins().signatures();
+
+        let mut structures: Vec<&String> = self.struct_args.keys().collect();
+        structures.sort();
+        for name in structures {
+            println!{"STRUCTURE {}", name};
+            let defaults = self.struct_defaults.get(name);
+            for (key, key_type) in &self.struct_args[name] {
+                let default = match defaults {
+                    Some(keys) if keys.contains(key) => " (has a default)",
+                    _ => "",
+                };
+                println!{"| KEY {}: {}{}", key, key_type, default};
+            }
+        }
+
+        let mut functions: Vec<(&String, &FunctionObject)> = self.functions.iter()
+            .filter(|(name, function)| library.get(*name) != Some(function))
+            .collect();
+        functions.sort_by_key(|(name, _)| *name);
+        for (name, function) in functions {
+            println!{"FUNCTION {}", function.signature(name)};
+        }
+
+        if let Some(globals) = self.symbols.first() {
+            let mut names: Vec<(&String, &SymbolType)> = globals.iter().collect();
+            names.sort_by_key(|(name, _)| *name);
+            for (name, var_type) in names {
+                println!{"VARIABLE {}: {}", name, var_type};
+            }
+        }
+    }
 }
@@ -0,0 +1,868 @@
+use crate::parser::{Block, Bound, Condition, ConditionKind, Else, Expr, ExprKind, FunctionDef, Ident, If, Param, ParseTree, Reference, ReferenceKind, Stmt, StmtKind, StructDef, StructField, Target, Type, TypeKind, VarDef};
+
+// Walking the syntax tree is the same for every stage, so instead of
+//  matching on every kind of node, a stage can implement one of these
+//  traits and only override the nodes it cares about:
+//
+//  - Visitor looks at the tree
+//  - VisitorMut can change the tree in place
+//  - Folder takes the tree apart and builds a new one
+//
+// Every method has a default that walks into the children of the node
+//  (with the matching walk_/fold_ function below). An override that
+//  still wants to see the children can call that function itself.
+//
+//  struct CallCounter { calls: usize }
+//
+//  impl Visitor for CallCounter {
+//      fn visit_expr(&mut self, expr: &Expr) {
+//          if let ExprKind::CALL(_, _) = expr.kind {
+//              self.calls += 1;
+//          }
+//          walk_expr(self, expr);
+//      }
+//  }
+
+// =====================
+// =   VISITOR         =
+// =====================
+
+pub trait Visitor {
+    fn visit_parse_tree(&mut self, tree: &ParseTree) {
+        walk_parse_tree(self, tree);
+    }
+
+    fn visit_struct_def(&mut self, struct_def: &StructDef) {
+        walk_struct_def(self, struct_def);
+    }
+
+    fn visit_struct_field(&mut self, field: &StructField) {
+        walk_struct_field(self, field);
+    }
+
+    fn visit_function_def(&mut self, fun_def: &FunctionDef) {
+        walk_function_def(self, fun_def);
+    }
+
+    fn visit_param(&mut self, param: &Param) {
+        walk_param(self, param);
+    }
+
+    fn visit_type(&mut self, type_node: &Type) {
+        walk_type(self, type_node);
+    }
+
+    fn visit_bound(&mut self, bound: &Bound) {
+        walk_bound(self, bound);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block);
+    }
+
+    fn visit_stmt(&mut self, statement: &Stmt) {
+        walk_stmt(self, statement);
+    }
+
+    fn visit_var_def(&mut self, var_def: &VarDef) {
+        walk_var_def(self, var_def);
+    }
+
+    fn visit_target(&mut self, target: &Target) {
+        walk_target(self, target);
+    }
+
+    fn visit_if(&mut self, if_stmt: &If) {
+        walk_if(self, if_stmt);
+    }
+
+    fn visit_else(&mut self, otherwise: &Else) {
+        walk_else(self, otherwise);
+    }
+
+    fn visit_condition(&mut self, condition: &Condition) {
+        walk_condition(self, condition);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_reference(&mut self, reference: &Reference) {
+        walk_reference(self, reference);
+    }
+
+    // Names have no children
+    fn visit_ident(&mut self, _id: &Ident) {}
+}
+
+pub fn walk_parse_tree<V: Visitor + ?Sized>(visitor: &mut V, tree: &ParseTree) {
+    for struct_def in &tree.structures {
+        visitor.visit_struct_def(struct_def);
+    }
+    for global in &tree.globals {
+        visitor.visit_stmt(global);
+    }
+    for fun_def in &tree.functions {
+        visitor.visit_function_def(fun_def);
+    }
+    visitor.visit_block(&tree.body);
+}
+
+pub fn walk_struct_def<V: Visitor + ?Sized>(visitor: &mut V, struct_def: &StructDef) {
+    if let Some(name) = &struct_def.name {
+        visitor.visit_ident(name);
+    }
+    for field in &struct_def.fields {
+        visitor.visit_struct_field(field);
+    }
+}
+
+pub fn walk_struct_field<V: Visitor + ?Sized>(visitor: &mut V, field: &StructField) {
+    visitor.visit_ident(&field.name);
+    visitor.visit_type(&field.field_type);
+    if let Some(default) = &field.default {
+        visitor.visit_expr(default);
+    }
+}
+
+pub fn walk_function_def<V: Visitor + ?Sized>(visitor: &mut V, fun_def: &FunctionDef) {
+    if let Some(name) = &fun_def.name {
+        visitor.visit_ident(name);
+    }
+    for param in &fun_def.params {
+        visitor.visit_param(param);
+    }
+    visitor.visit_type(&fun_def.return_type);
+    visitor.visit_block(&fun_def.body);
+}
+
+pub fn walk_param<V: Visitor + ?Sized>(visitor: &mut V, param: &Param) {
+    visitor.visit_ident(&param.name);
+    visitor.visit_type(&param.param_type);
+}
+
+pub fn walk_type<V: Visitor + ?Sized>(visitor: &mut V, type_node: &Type) {
+    match &type_node.kind {
+        TypeKind::ARRAY(bounds, arr_type) => {
+            for bound in bounds {
+                visitor.visit_bound(bound);
+            }
+            visitor.visit_type(arr_type);
+        },
+        TypeKind::LINK(link_type) => visitor.visit_type(link_type),
+        _ => (),
+    }
+}
+
+pub fn walk_bound<V: Visitor + ?Sized>(visitor: &mut V, bound: &Bound) {
+    if let Some(start) = &bound.start {
+        visitor.visit_expr(start);
+    }
+    visitor.visit_expr(&bound.end);
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block) {
+    for statement in &block.statements {
+        visitor.visit_stmt(statement);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, statement: &Stmt) {
+    match &statement.kind {
+        StmtKind::VARDEF(var_def) => visitor.visit_var_def(var_def),
+        StmtKind::ASSIGN(target, value) => {
+            visitor.visit_target(target);
+            visitor.visit_expr(value);
+        },
+        StmtKind::LINK(target, linked) => {
+            visitor.visit_target(target);
+            if let Some(linked) = linked {
+                visitor.visit_reference(linked);
+            }
+        },
+        StmtKind::IF(if_stmt) => visitor.visit_if(if_stmt),
+        StmtKind::WHILE(condition, body) => {
+            visitor.visit_condition(condition);
+            visitor.visit_block(body);
+        },
+        StmtKind::REPEAT(count, body) => {
+            visitor.visit_expr(count);
+            visitor.visit_block(body);
+        },
+        StmtKind::REPEATFOR(variable, values, body) => {
+            if let Some(variable) = variable {
+                visitor.visit_ident(variable);
+            }
+            visitor.visit_expr(values);
+            visitor.visit_block(body);
+        },
+        StmtKind::REPEATFOREVER(body) => visitor.visit_block(body),
+        StmtKind::UNLINK(reference) => visitor.visit_reference(reference),
+        StmtKind::RETURN(value) | StmtKind::QUIT(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        },
+        StmtKind::EXPR(value) => visitor.visit_expr(value),
+        StmtKind::BREAK | StmtKind::CONTINUE | StmtKind::INVALID => (),
+    }
+}
+
+pub fn walk_var_def<V: Visitor + ?Sized>(visitor: &mut V, var_def: &VarDef) {
+    for name in &var_def.names {
+        visitor.visit_ident(name);
+    }
+    visitor.visit_type(&var_def.var_type);
+}
+
+pub fn walk_target<V: Visitor + ?Sized>(visitor: &mut V, target: &Target) {
+    match target {
+        Target::VARDEF(var_def) => visitor.visit_var_def(var_def),
+        Target::REFERENCE(reference) => visitor.visit_reference(reference),
+    }
+}
+
+pub fn walk_if<V: Visitor + ?Sized>(visitor: &mut V, if_stmt: &If) {
+    visitor.visit_condition(&if_stmt.condition);
+    visitor.visit_block(&if_stmt.body);
+    if let Some(otherwise) = &if_stmt.otherwise {
+        visitor.visit_else(otherwise);
+    }
+}
+
+pub fn walk_else<V: Visitor + ?Sized>(visitor: &mut V, otherwise: &Else) {
+    match otherwise {
+        Else::IF(else_if) => visitor.visit_if(else_if),
+        Else::BLOCK(block) => visitor.visit_block(block),
+    }
+}
+
+pub fn walk_condition<V: Visitor + ?Sized>(visitor: &mut V, condition: &Condition) {
+    match &condition.kind {
+        ConditionKind::COMPARE(_, left, right) => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        },
+        ConditionKind::AND(left, right) | ConditionKind::OR(left, right) => {
+            visitor.visit_condition(left);
+            visitor.visit_condition(right);
+        },
+        ConditionKind::ISLINKED(reference) | ConditionKind::ISNOTLINKED(reference) => visitor.visit_reference(reference),
+        ConditionKind::INVALID => (),
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match &expr.kind {
+        ExprKind::REFERENCE(reference) => visitor.visit_reference(reference),
+        ExprKind::CALL(name, args) => {
+            visitor.visit_ident(name);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        },
+        ExprKind::BINARY(_, left, right) => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        },
+        ExprKind::NEG(value) | ExprKind::ABS(value) | ExprKind::BITNOT(value) => visitor.visit_expr(value),
+        ExprKind::ARRAY(items) | ExprKind::STRUCTURE(items) => {
+            for item in items {
+                visitor.visit_expr(item);
+            }
+        },
+        ExprKind::LINK(linked) => {
+            if let Some(linked) = linked {
+                visitor.visit_reference(linked);
+            }
+        },
+        ExprKind::NUMBER(_) | ExprKind::TEXT(_) | ExprKind::INVALID => (),
+    }
+}
+
+pub fn walk_reference<V: Visitor + ?Sized>(visitor: &mut V, reference: &Reference) {
+    match &reference.kind {
+        ReferenceKind::NAME(_) => (),
+        ReferenceKind::INDEX(array, indexes) => {
+            visitor.visit_reference(array);
+            for index in indexes {
+                visitor.visit_expr(index);
+            }
+        },
+        ReferenceKind::FIELD(structure, key) => {
+            visitor.visit_reference(structure);
+            visitor.visit_ident(key);
+        },
+    }
+}
+
+// =====================
+// =   VISITOR MUT     =
+// =====================
+
+pub trait VisitorMut {
+    fn visit_parse_tree_mut(&mut self, tree: &mut ParseTree) {
+        walk_parse_tree_mut(self, tree);
+    }
+
+    fn visit_struct_def_mut(&mut self, struct_def: &mut StructDef) {
+        walk_struct_def_mut(self, struct_def);
+    }
+
+    fn visit_struct_field_mut(&mut self, field: &mut StructField) {
+        walk_struct_field_mut(self, field);
+    }
+
+    fn visit_function_def_mut(&mut self, fun_def: &mut FunctionDef) {
+        walk_function_def_mut(self, fun_def);
+    }
+
+    fn visit_param_mut(&mut self, param: &mut Param) {
+        walk_param_mut(self, param);
+    }
+
+    fn visit_type_mut(&mut self, type_node: &mut Type) {
+        walk_type_mut(self, type_node);
+    }
+
+    fn visit_bound_mut(&mut self, bound: &mut Bound) {
+        walk_bound_mut(self, bound);
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block);
+    }
+
+    fn visit_stmt_mut(&mut self, statement: &mut Stmt) {
+        walk_stmt_mut(self, statement);
+    }
+
+    fn visit_var_def_mut(&mut self, var_def: &mut VarDef) {
+        walk_var_def_mut(self, var_def);
+    }
+
+    fn visit_target_mut(&mut self, target: &mut Target) {
+        walk_target_mut(self, target);
+    }
+
+    fn visit_if_mut(&mut self, if_stmt: &mut If) {
+        walk_if_mut(self, if_stmt);
+    }
+
+    fn visit_else_mut(&mut self, otherwise: &mut Else) {
+        walk_else_mut(self, otherwise);
+    }
+
+    fn visit_condition_mut(&mut self, condition: &mut Condition) {
+        walk_condition_mut(self, condition);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+
+    fn visit_reference_mut(&mut self, reference: &mut Reference) {
+        walk_reference_mut(self, reference);
+    }
+
+    // Names have no children
+    fn visit_ident_mut(&mut self, _id: &mut Ident) {}
+}
+
+pub fn walk_parse_tree_mut<V: VisitorMut + ?Sized>(visitor: &mut V, tree: &mut ParseTree) {
+    for struct_def in &mut tree.structures {
+        visitor.visit_struct_def_mut(struct_def);
+    }
+    for global in &mut tree.globals {
+        visitor.visit_stmt_mut(global);
+    }
+    for fun_def in &mut tree.functions {
+        visitor.visit_function_def_mut(fun_def);
+    }
+    visitor.visit_block_mut(&mut tree.body);
+}
+
+pub fn walk_struct_def_mut<V: VisitorMut + ?Sized>(visitor: &mut V, struct_def: &mut StructDef) {
+    if let Some(name) = &mut struct_def.name {
+        visitor.visit_ident_mut(name);
+    }
+    for field in &mut struct_def.fields {
+        visitor.visit_struct_field_mut(field);
+    }
+}
+
+pub fn walk_struct_field_mut<V: VisitorMut + ?Sized>(visitor: &mut V, field: &mut StructField) {
+    visitor.visit_ident_mut(&mut field.name);
+    visitor.visit_type_mut(&mut field.field_type);
+    if let Some(default) = &mut field.default {
+        visitor.visit_expr_mut(default);
+    }
+}
+
+pub fn walk_function_def_mut<V: VisitorMut + ?Sized>(visitor: &mut V, fun_def: &mut FunctionDef) {
+    if let Some(name) = &mut fun_def.name {
+        visitor.visit_ident_mut(name);
+    }
+    for param in &mut fun_def.params {
+        visitor.visit_param_mut(param);
+    }
+    visitor.visit_type_mut(&mut fun_def.return_type);
+    visitor.visit_block_mut(&mut fun_def.body);
+}
+
+pub fn walk_param_mut<V: VisitorMut + ?Sized>(visitor: &mut V, param: &mut Param) {
+    visitor.visit_ident_mut(&mut param.name);
+    visitor.visit_type_mut(&mut param.param_type);
+}
+
+pub fn walk_type_mut<V: VisitorMut + ?Sized>(visitor: &mut V, type_node: &mut Type) {
+    match &mut type_node.kind {
+        TypeKind::ARRAY(bounds, arr_type) => {
+            for bound in bounds {
+                visitor.visit_bound_mut(bound);
+            }
+            visitor.visit_type_mut(arr_type);
+        },
+        TypeKind::LINK(link_type) => visitor.visit_type_mut(link_type),
+        _ => (),
+    }
+}
+
+pub fn walk_bound_mut<V: VisitorMut + ?Sized>(visitor: &mut V, bound: &mut Bound) {
+    if let Some(start) = &mut bound.start {
+        visitor.visit_expr_mut(start);
+    }
+    visitor.visit_expr_mut(&mut bound.end);
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, block: &mut Block) {
+    for statement in &mut block.statements {
+        visitor.visit_stmt_mut(statement);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut Stmt) {
+    match &mut statement.kind {
+        StmtKind::VARDEF(var_def) => visitor.visit_var_def_mut(var_def),
+        StmtKind::ASSIGN(target, value) => {
+            visitor.visit_target_mut(target);
+            visitor.visit_expr_mut(value);
+        },
+        StmtKind::LINK(target, linked) => {
+            visitor.visit_target_mut(target);
+            if let Some(linked) = linked {
+                visitor.visit_reference_mut(linked);
+            }
+        },
+        StmtKind::IF(if_stmt) => visitor.visit_if_mut(if_stmt),
+        StmtKind::WHILE(condition, body) => {
+            visitor.visit_condition_mut(condition);
+            visitor.visit_block_mut(body);
+        },
+        StmtKind::REPEAT(count, body) => {
+            visitor.visit_expr_mut(count);
+            visitor.visit_block_mut(body);
+        },
+        StmtKind::REPEATFOR(variable, values, body) => {
+            if let Some(variable) = variable {
+                visitor.visit_ident_mut(variable);
+            }
+            visitor.visit_expr_mut(values);
+            visitor.visit_block_mut(body);
+        },
+        StmtKind::REPEATFOREVER(body) => visitor.visit_block_mut(body),
+        StmtKind::UNLINK(reference) => visitor.visit_reference_mut(reference),
+        StmtKind::RETURN(value) | StmtKind::QUIT(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr_mut(value);
+            }
+        },
+        StmtKind::EXPR(value) => visitor.visit_expr_mut(value),
+        StmtKind::BREAK | StmtKind::CONTINUE | StmtKind::INVALID => (),
+    }
+}
+
+pub fn walk_var_def_mut<V: VisitorMut + ?Sized>(visitor: &mut V, var_def: &mut VarDef) {
+    for name in &mut var_def.names {
+        visitor.visit_ident_mut(name);
+    }
+    visitor.visit_type_mut(&mut var_def.var_type);
+}
+
+pub fn walk_target_mut<V: VisitorMut + ?Sized>(visitor: &mut V, target: &mut Target) {
+    match target {
+        Target::VARDEF(var_def) => visitor.visit_var_def_mut(var_def),
+        Target::REFERENCE(reference) => visitor.visit_reference_mut(reference),
+    }
+}
+
+pub fn walk_if_mut<V: VisitorMut + ?Sized>(visitor: &mut V, if_stmt: &mut If) {
+    visitor.visit_condition_mut(&mut if_stmt.condition);
+    visitor.visit_block_mut(&mut if_stmt.body);
+    if let Some(otherwise) = &mut if_stmt.otherwise {
+        visitor.visit_else_mut(otherwise);
+    }
+}
+
+pub fn walk_else_mut<V: VisitorMut + ?Sized>(visitor: &mut V, otherwise: &mut Else) {
+    match otherwise {
+        Else::IF(else_if) => visitor.visit_if_mut(else_if),
+        Else::BLOCK(block) => visitor.visit_block_mut(block),
+    }
+}
+
+pub fn walk_condition_mut<V: VisitorMut + ?Sized>(visitor: &mut V, condition: &mut Condition) {
+    match &mut condition.kind {
+        ConditionKind::COMPARE(_, left, right) => {
+            visitor.visit_expr_mut(left);
+            visitor.visit_expr_mut(right);
+        },
+        ConditionKind::AND(left, right) | ConditionKind::OR(left, right) => {
+            visitor.visit_condition_mut(left);
+            visitor.visit_condition_mut(right);
+        },
+        ConditionKind::ISLINKED(reference) | ConditionKind::ISNOTLINKED(reference) => visitor.visit_reference_mut(reference),
+        ConditionKind::INVALID => (),
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    match &mut expr.kind {
+        ExprKind::REFERENCE(reference) => visitor.visit_reference_mut(reference),
+        ExprKind::CALL(name, args) => {
+            visitor.visit_ident_mut(name);
+            for arg in args {
+                visitor.visit_expr_mut(arg);
+            }
+        },
+        ExprKind::BINARY(_, left, right) => {
+            visitor.visit_expr_mut(left);
+            visitor.visit_expr_mut(right);
+        },
+        ExprKind::NEG(value) | ExprKind::ABS(value) | ExprKind::BITNOT(value) => visitor.visit_expr_mut(value),
+        ExprKind::ARRAY(items) | ExprKind::STRUCTURE(items) => {
+            for item in items {
+                visitor.visit_expr_mut(item);
+            }
+        },
+        ExprKind::LINK(linked) => {
+            if let Some(linked) = linked {
+                visitor.visit_reference_mut(linked);
+            }
+        },
+        ExprKind::NUMBER(_) | ExprKind::TEXT(_) | ExprKind::INVALID => (),
+    }
+}
+
+pub fn walk_reference_mut<V: VisitorMut + ?Sized>(visitor: &mut V, reference: &mut Reference) {
+    match &mut reference.kind {
+        ReferenceKind::NAME(_) => (),
+        ReferenceKind::INDEX(array, indexes) => {
+            visitor.visit_reference_mut(array);
+            for index in indexes {
+                visitor.visit_expr_mut(index);
+            }
+        },
+        ReferenceKind::FIELD(structure, key) => {
+            visitor.visit_reference_mut(structure);
+            visitor.visit_ident_mut(key);
+        },
+    }
+}
+
+// =====================
+// =   FOLDER          =
+// =====================
+// A folder gives back a new node for every node it is given, the
+//  defaults rebuild the same node (keeping its span) out of the folded
+//  children. Overriding a method can replace a node with a different
+//  kind of node, like replacing "1 + 2" with "3".
+
+pub trait Folder {
+    fn fold_parse_tree(&mut self, tree: ParseTree) -> ParseTree {
+        fold_parse_tree(self, tree)
+    }
+
+    fn fold_struct_def(&mut self, struct_def: StructDef) -> StructDef {
+        fold_struct_def(self, struct_def)
+    }
+
+    fn fold_struct_field(&mut self, field: StructField) -> StructField {
+        fold_struct_field(self, field)
+    }
+
+    fn fold_function_def(&mut self, fun_def: FunctionDef) -> FunctionDef {
+        fold_function_def(self, fun_def)
+    }
+
+    fn fold_param(&mut self, param: Param) -> Param {
+        fold_param(self, param)
+    }
+
+    fn fold_type(&mut self, type_node: Type) -> Type {
+        fold_type(self, type_node)
+    }
+
+    fn fold_bound(&mut self, bound: Bound) -> Bound {
+        fold_bound(self, bound)
+    }
+
+    fn fold_block(&mut self, block: Block) -> Block {
+        fold_block(self, block)
+    }
+
+    fn fold_stmt(&mut self, statement: Stmt) -> Stmt {
+        fold_stmt(self, statement)
+    }
+
+    fn fold_var_def(&mut self, var_def: VarDef) -> VarDef {
+        fold_var_def(self, var_def)
+    }
+
+    fn fold_target(&mut self, target: Target) -> Target {
+        fold_target(self, target)
+    }
+
+    fn fold_if(&mut self, if_stmt: If) -> If {
+        fold_if(self, if_stmt)
+    }
+
+    fn fold_else(&mut self, otherwise: Else) -> Else {
+        fold_else(self, otherwise)
+    }
+
+    fn fold_condition(&mut self, condition: Condition) -> Condition {
+        fold_condition(self, condition)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_expr(self, expr)
+    }
+
+    fn fold_reference(&mut self, reference: Reference) -> Reference {
+        fold_reference(self, reference)
+    }
+
+    // Names have no children
+    fn fold_ident(&mut self, id: Ident) -> Ident {
+        id
+    }
+}
+
+pub fn fold_parse_tree<F: Folder + ?Sized>(folder: &mut F, tree: ParseTree) -> ParseTree {
+    ParseTree {
+        structures: tree.structures.into_iter().map(|s| folder.fold_struct_def(s)).collect(),
+        globals: tree.globals.into_iter().map(|g| folder.fold_stmt(g)).collect(),
+        functions: tree.functions.into_iter().map(|f| folder.fold_function_def(f)).collect(),
+        body: folder.fold_block(tree.body),
+        span: tree.span,
+    }
+}
+
+pub fn fold_struct_def<F: Folder + ?Sized>(folder: &mut F, struct_def: StructDef) -> StructDef {
+    StructDef {
+        name: struct_def.name.map(|n| folder.fold_ident(n)),
+        fields: struct_def.fields.into_iter().map(|f| folder.fold_struct_field(f)).collect(),
+        span: struct_def.span,
+    }
+}
+
+pub fn fold_struct_field<F: Folder + ?Sized>(folder: &mut F, field: StructField) -> StructField {
+    StructField {
+        name: folder.fold_ident(field.name),
+        field_type: folder.fold_type(field.field_type),
+        default: field.default.map(|d| folder.fold_expr(d)),
+        span: field.span,
+    }
+}
+
+pub fn fold_function_def<F: Folder + ?Sized>(folder: &mut F, fun_def: FunctionDef) -> FunctionDef {
+    FunctionDef {
+        name: fun_def.name.map(|n| folder.fold_ident(n)),
+        params: fun_def.params.into_iter().map(|p| folder.fold_param(p)).collect(),
+        return_type: folder.fold_type(fun_def.return_type),
+        body: folder.fold_block(fun_def.body),
+        span: fun_def.span,
+    }
+}
+
+pub fn fold_param<F: Folder + ?Sized>(folder: &mut F, param: Param) -> Param {
+    Param {
+        name: folder.fold_ident(param.name),
+        changeable: param.changeable,
+        param_type: folder.fold_type(param.param_type),
+        span: param.span,
+    }
+}
+
+pub fn fold_type<F: Folder + ?Sized>(folder: &mut F, type_node: Type) -> Type {
+    let kind = match type_node.kind {
+        TypeKind::ARRAY(bounds, arr_type) => TypeKind::ARRAY(
+            bounds.into_iter().map(|b| folder.fold_bound(b)).collect(),
+            Box::new(folder.fold_type(*arr_type)),
+        ),
+        TypeKind::LINK(link_type) => TypeKind::LINK(Box::new(folder.fold_type(*link_type))),
+        kind => kind,
+    };
+
+    Type {
+        kind,
+        span: type_node.span,
+    }
+}
+
+pub fn fold_bound<F: Folder + ?Sized>(folder: &mut F, bound: Bound) -> Bound {
+    Bound {
+        start: bound.start.map(|s| folder.fold_expr(s)),
+        end: folder.fold_expr(bound.end),
+        span: bound.span,
+    }
+}
+
+pub fn fold_block<F: Folder + ?Sized>(folder: &mut F, block: Block) -> Block {
+    Block {
+        statements: block.statements.into_iter().map(|s| folder.fold_stmt(s)).collect(),
+        span: block.span,
+    }
+}
+
+pub fn fold_stmt<F: Folder + ?Sized>(folder: &mut F, statement: Stmt) -> Stmt {
+    let kind = match statement.kind {
+        StmtKind::VARDEF(var_def) => StmtKind::VARDEF(folder.fold_var_def(var_def)),
+        StmtKind::ASSIGN(target, value) => {
+            let target = folder.fold_target(target);
+            StmtKind::ASSIGN(target, folder.fold_expr(value))
+        },
+        StmtKind::LINK(target, linked) => {
+            let target = folder.fold_target(target);
+            StmtKind::LINK(target, linked.map(|l| folder.fold_reference(l)))
+        },
+        StmtKind::IF(if_stmt) => StmtKind::IF(folder.fold_if(if_stmt)),
+        StmtKind::WHILE(condition, body) => {
+            let condition = folder.fold_condition(condition);
+            StmtKind::WHILE(condition, folder.fold_block(body))
+        },
+        StmtKind::REPEAT(count, body) => {
+            let count = folder.fold_expr(count);
+            StmtKind::REPEAT(count, folder.fold_block(body))
+        },
+        StmtKind::REPEATFOR(variable, values, body) => {
+            let variable = variable.map(|v| folder.fold_ident(v));
+            let values = folder.fold_expr(values);
+            StmtKind::REPEATFOR(variable, values, folder.fold_block(body))
+        },
+        StmtKind::REPEATFOREVER(body) => StmtKind::REPEATFOREVER(folder.fold_block(body)),
+        StmtKind::UNLINK(reference) => StmtKind::UNLINK(folder.fold_reference(reference)),
+        StmtKind::RETURN(value) => StmtKind::RETURN(value.map(|v| folder.fold_expr(v))),
+        StmtKind::QUIT(status) => StmtKind::QUIT(status.map(|s| folder.fold_expr(s))),
+        StmtKind::EXPR(value) => StmtKind::EXPR(folder.fold_expr(value)),
+        kind => kind,
+    };
+
+    Stmt {
+        kind,
+        span: statement.span,
+    }
+}
+
+pub fn fold_var_def<F: Folder + ?Sized>(folder: &mut F, var_def: VarDef) -> VarDef {
+    VarDef {
+        names: var_def.names.into_iter().map(|n| folder.fold_ident(n)).collect(),
+        var_type: folder.fold_type(var_def.var_type),
+        span: var_def.span,
+    }
+}
+
+pub fn fold_target<F: Folder + ?Sized>(folder: &mut F, target: Target) -> Target {
+    match target {
+        Target::VARDEF(var_def) => Target::VARDEF(folder.fold_var_def(var_def)),
+        Target::REFERENCE(reference) => Target::REFERENCE(folder.fold_reference(reference)),
+    }
+}
+
+pub fn fold_if<F: Folder + ?Sized>(folder: &mut F, if_stmt: If) -> If {
+    If {
+        condition: folder.fold_condition(if_stmt.condition),
+        body: folder.fold_block(if_stmt.body),
+        otherwise: if_stmt.otherwise.map(|o| folder.fold_else(o)),
+        span: if_stmt.span,
+    }
+}
+
+pub fn fold_else<F: Folder + ?Sized>(folder: &mut F, otherwise: Else) -> Else {
+    match otherwise {
+        Else::IF(else_if) => Else::IF(Box::new(folder.fold_if(*else_if))),
+        Else::BLOCK(block) => Else::BLOCK(folder.fold_block(block)),
+    }
+}
+
+pub fn fold_condition<F: Folder + ?Sized>(folder: &mut F, condition: Condition) -> Condition {
+    let kind = match condition.kind {
+        ConditionKind::COMPARE(op, left, right) => {
+            let left = folder.fold_expr(left);
+            ConditionKind::COMPARE(op, left, folder.fold_expr(right))
+        },
+        ConditionKind::AND(left, right) => {
+            let left = folder.fold_condition(*left);
+            ConditionKind::AND(Box::new(left), Box::new(folder.fold_condition(*right)))
+        },
+        ConditionKind::OR(left, right) => {
+            let left = folder.fold_condition(*left);
+            ConditionKind::OR(Box::new(left), Box::new(folder.fold_condition(*right)))
+        },
+        ConditionKind::ISLINKED(reference) => ConditionKind::ISLINKED(folder.fold_reference(reference)),
+        ConditionKind::ISNOTLINKED(reference) => ConditionKind::ISNOTLINKED(folder.fold_reference(reference)),
+        ConditionKind::INVALID => ConditionKind::INVALID,
+    };
+
+    Condition {
+        kind,
+        span: condition.span,
+    }
+}
+
+pub fn fold_expr<F: Folder + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    let kind = match expr.kind {
+        ExprKind::REFERENCE(reference) => ExprKind::REFERENCE(folder.fold_reference(reference)),
+        ExprKind::CALL(name, args) => {
+            let name = folder.fold_ident(name);
+            ExprKind::CALL(name, args.into_iter().map(|a| folder.fold_expr(a)).collect())
+        },
+        ExprKind::BINARY(op, left, right) => {
+            let left = folder.fold_expr(*left);
+            ExprKind::BINARY(op, Box::new(left), Box::new(folder.fold_expr(*right)))
+        },
+        ExprKind::NEG(value) => ExprKind::NEG(Box::new(folder.fold_expr(*value))),
+        ExprKind::ABS(value) => ExprKind::ABS(Box::new(folder.fold_expr(*value))),
+        ExprKind::BITNOT(value) => ExprKind::BITNOT(Box::new(folder.fold_expr(*value))),
+        ExprKind::ARRAY(items) => ExprKind::ARRAY(items.into_iter().map(|i| folder.fold_expr(i)).collect()),
+        ExprKind::STRUCTURE(items) => ExprKind::STRUCTURE(items.into_iter().map(|i| folder.fold_expr(i)).collect()),
+        ExprKind::LINK(linked) => ExprKind::LINK(linked.map(|l| folder.fold_reference(l))),
+        kind => kind,
+    };
+
+    Expr {
+        kind,
+        span: expr.span,
+    }
+}
+
+pub fn fold_reference<F: Folder + ?Sized>(folder: &mut F, reference: Reference) -> Reference {
+    let kind = match reference.kind {
+        ReferenceKind::NAME(name) => ReferenceKind::NAME(name),
+        ReferenceKind::INDEX(array, indexes) => {
+            let array = folder.fold_reference(*array);
+            ReferenceKind::INDEX(Box::new(array), indexes.into_iter().map(|i| folder.fold_expr(i)).collect())
+        },
+        ReferenceKind::FIELD(structure, key) => {
+            let structure = folder.fold_reference(*structure);
+            ReferenceKind::FIELD(Box::new(structure), folder.fold_ident(key))
+        },
+    };
+
+    Reference {
+        kind,
+        span: reference.span,
+    }
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use gelli::diagnostic;
+use common::{errors_for, run_program};
+
+// A program with a 'point' structure and these functions
+fn with_functions(functions: &str, body: &str) -> String {
+    format!{"definitions
+structure point
+    x: number
+    y: number
+end structure
+{}
+end definitions
+program
+{}
+end program
+", functions, body}
+}
+
+const SWAP: &str = "function swap(a: changeable number, b: changeable number) returns nothing
+    t: number = a
+    a = b
+    b = t
+end function";
+
+#[test]
+fn changeable_parameters_change_the_arguments() {
+    let (result, host) = run_program(&with_functions(SWAP, "x: number = 1\ny: number = 2\nswap(x, y)\nprint(x, y)"), "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "21\n");
+}
+
+#[test]
+fn elements_and_keys_can_be_changeable_arguments() {
+    let body = "arr: array[2] of number = [1, 2]\np: point = {3, 4}\nswap(arr[1], p.y)\nprint(arr[1], arr[2], p.x, p.y)";
+    let (result, host) = run_program(&with_functions(SWAP, body), "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "4231\n");
+}
+
+#[test]
+fn changeable_arrays_and_structures_are_not_copied() {
+    let functions = "function fill(arr: changeable array[3] of number, p: changeable point) returns nothing
+    i: number = 1
+    repeat 3 times
+        arr[i] = i * 10
+        i = i + 1
+    end repeat
+    p.x = 5
+end function
+function try_to_fill(arr: array[3] of number) returns nothing
+    arr[1] = 0
+end function";
+    let body = "arr: array[3] of number = [1, 2, 3]\np: point = {1, 2}\nfill(arr, p)\ntry_to_fill(arr)\nprint(arr[1], arr[2], arr[3], p.x)";
+    let (result, host) = run_program(&with_functions(functions, body), "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "1020305\n");
+}
+
+#[test]
+fn a_changeable_parameter_needs_a_variable() {
+    assert_eq!(errors_for(&with_functions(SWAP, "x: number = 1\nswap(x, 2)")), vec![diagnostic::UNCHANGEABLE]);
+    assert_eq!(errors_for(&with_functions(SWAP, "x: number = 1\nswap(x, x + 1)")), vec![diagnostic::UNCHANGEABLE]);
+}
@@ -0,0 +1,36 @@
+// Helpers shared by the test files (each one has "mod common;",
+//  and only uses some of them)
+#![allow(dead_code)]
+
+use std::fmt::Debug;
+use gelli::{run_source, Diagnostics, LintConfig, MemoryHost, Parser, SemanticAnalyzer};
+
+// The codes of the semantic errors for a whole program, which
+//  must not have any syntax errors
+pub fn errors_for(src: &str) -> Vec<&'static str> {
+    let (tree, errors) = Parser::new(src.to_string()).unwrap().parse();
+    assert!(errors.is_empty(), "syntax errors: {:?}", errors);
+    let (_, errors) = SemanticAnalyzer::new().analyze(&tree.unwrap());
+    errors.iter().map(|e| e.code).collect()
+}
+
+// The code of the first error, for something that should have failed
+pub fn error_code<T: Debug>(result: Result<T, Diagnostics>) -> &'static str {
+    match result {
+        Ok(value) => panic!("expected an error, got {:?}", value),
+        Err(errors) => errors[0].code,
+    }
+}
+
+// Run a program body on a MemoryHost with the given input, with
+//  every lint turned on
+pub fn run(body: &str, input: &str) -> (Result<i32, Diagnostics>, MemoryHost) {
+    run_program(&format!{"program\n{}\nend program\n", body}, input)
+}
+
+// The same as 'run', for a whole program (with definitions)
+pub fn run_program(src: &str, input: &str) -> (Result<i32, Diagnostics>, MemoryHost) {
+    let mut host = MemoryHost::new(input);
+    let result = run_source(src, &mut host, LintConfig::new()).map(|outcome| outcome.exit_code);
+    (result, host)
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use gelli::diagnostic;
+use common::{error_code, run};
+
+// The codes of the semantic errors for a program body
+fn body_errors(definitions: &str, body: &str) -> Vec<&'static str> {
+    common::errors_for(&format!{"{}\nprogram\nn: number = 1\n{}\nend program\n", definitions, body})
+}
+
+#[test]
+fn assigned_before_read() {
+    assert_eq!(body_errors("", "x: number\nx = 2\nprint(x)"), Vec::<&str>::new());
+}
+
+#[test]
+fn read_before_assigned() {
+    assert_eq!(body_errors("", "x: number\nprint(x)"), vec![diagnostic::MAYBE_UNASSIGNED]);
+    assert_eq!(body_errors("", "t: text\nprint(t)"), vec![diagnostic::MAYBE_UNASSIGNED]);
+}
+
+#[test]
+fn assigned_on_only_one_branch() {
+    let body = "x: number\nif n > 0 then\nx = 1\nend if\nprint(x)";
+    assert_eq!(body_errors("", body), vec![diagnostic::MAYBE_UNASSIGNED]);
+}
+
+#[test]
+fn assigned_on_every_branch() {
+    let body = "x: number\nif n > 0 then\nx = 1\nelse\nx = 2\nend if\nprint(x)";
+    assert_eq!(body_errors("", body), Vec::<&str>::new());
+}
+
+#[test]
+fn loops_might_not_run() {
+    let body = "x: number\nwhile n < 0\nx = 1\nend while\nprint(x)";
+    assert_eq!(body_errors("", body), vec![diagnostic::MAYBE_UNASSIGNED]);
+
+    let body = "x: number\nrepeat forever\nx = 1\nbreak\nend repeat\nprint(x)";
+    assert_eq!(body_errors("", body), Vec::<&str>::new());
+}
+
+#[test]
+fn changeable_parameter_gives_a_value() {
+    let definitions = "definitions\nfunction set(v: changeable number) returns nothing\nv = 3\nend function\nend definitions";
+    assert_eq!(body_errors(definitions, "x: number\nset(x)\nprint(x)"), Vec::<&str>::new());
+}
+
+#[test]
+fn unassigned_array_element_is_a_runtime_error() {
+    let (result, host) = run("a: array[3] of number\na[1] = 1\nprint(a[1])\nprint(a[2])", "");
+    assert_eq!(error_code(result), diagnostic::UNASSIGNED);
+    assert_eq!(host.output, "1\n");
+}
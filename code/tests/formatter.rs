@@ -0,0 +1,41 @@
+use std::{env, fs};
+use std::process::{Command, Stdio};
+use gelli::formatter::Formatter;
+
+const MESSY: &str = "program\n  x:number=1   # count\nif x>0 then\nprint( x , \"a\" )\n   end if\nend program\n";
+const FORMATTED: &str = "program\n    x: number = 1 # count\n    if x > 0 then\n        print(x, \"a\")\n    end if\nend program\n";
+
+// Format code, which must not have any syntax errors
+fn format(code: &str) -> String {
+    Formatter::format(code).unwrap_or_else(|errors| panic!("syntax errors: {:?}", errors))
+}
+
+// Run "code fmt --check" on a file with this code, and give back its exit code
+fn check(name: &str, code: &str) -> i32 {
+    let path = env::temp_dir().join(format!{"gelli-{}-{}.gelli", std::process::id(), name});
+    fs::write(&path, code).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_code")).args(["fmt", "--check"]).arg(&path).stderr(Stdio::null()).status().unwrap();
+    fs::remove_file(&path).unwrap();
+    status.code().unwrap()
+}
+
+#[test]
+fn code_is_indented_and_spaced() {
+    assert_eq!(format(MESSY), FORMATTED);
+}
+
+#[test]
+fn formatting_twice_changes_nothing() {
+    let definitions = "definitions\n# a point\nstructure point\nx: number\ny: number = 2\nend structure\n\n\nfunction f(p: changeable point) returns number\nreturn p.x+p.y # sum\nend function\nend definitions\nprogram\np: point = {1, 2}\n\n\nprint(f(p))\nend program\n";
+    for code in [MESSY, definitions, include_str!("../test1.gelli"), include_str!("../test2.gelli"), include_str!("../test3.gelli")] {
+        let once = format(code);
+        assert_eq!(format(&once), once);
+    }
+}
+
+#[test]
+fn check_only_passes_formatted_code() {
+    assert_eq!(check("formatted", FORMATTED), 0);
+    assert_eq!(check("messy", MESSY), 1);
+    assert_eq!(check("broken", "program\nprint(\nend program\n"), 2);
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use gelli::diagnostic;
+use common::{errors_for, run};
+
+// The codes of the semantic errors for a program body
+fn body_errors(body: &str) -> Vec<&'static str> {
+    errors_for(&format!{"program\n{}\nend program\n", body})
+}
+
+#[test]
+fn array_functions_only_need_the_bounds() {
+    let body = "arr: array[2 to 4, 3] of number\nprint(length(arr), lower_bound(arr), upper_bound(arr))\nd: array[2] of number = dimensions(arr)\nprint(d[1], d[2])\nprint(length(\"hello\"))";
+    let (result, host) = run(body, "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "924\n33\n5\n");
+}
+
+#[test]
+fn math_functions() {
+    let (result, host) = run("print(round(2.5), floor(2.5), ceil(2.1), sin(0))", "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "3230\n");
+}
+
+#[test]
+fn random_number_takes_no_arguments_or_a_range() {
+    assert_eq!(body_errors("print(random_number())\nprint(random_number(1, 6))"), Vec::<&str>::new());
+    assert_eq!(body_errors("print(random_number(6))"), vec![diagnostic::ARGUMENT_COUNT]);
+    assert_eq!(body_errors("print(random_number(1, 2, 3))"), vec![diagnostic::ARGUMENT_COUNT]);
+
+    let (result, host) = run("x: number = random_number(1, 6)\nif x >= 1 and x <= 6 and x = round(x) then\nprint(\"ok\")\nend if", "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "ok\n");
+}
+
+#[test]
+fn extra_arguments_are_only_counted() {
+    // The extra argument has the wrong type, but it isn't a parameter
+    assert_eq!(body_errors("print(round(1.5, \"a\"))"), vec![diagnostic::ARGUMENT_COUNT]);
+    assert_eq!(body_errors("print(length(\"a\", 2))"), vec![diagnostic::ARGUMENT_COUNT]);
+}
+
+#[test]
+fn arguments_are_type_checked() {
+    assert_eq!(body_errors("print(round(\"a\"))"), vec![diagnostic::TYPE_MISMATCH]);
+    assert_eq!(body_errors("print(lower_bound(3))"), vec![diagnostic::TYPE_MISMATCH]);
+}
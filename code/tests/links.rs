@@ -0,0 +1,56 @@
+mod common;
+
+use gelli::diagnostic;
+use common::{error_code, run_program};
+
+// A program with a linked list node, and the given program body
+fn with_node(body: &str) -> String {
+    format!{"definitions
+structure node
+    value: number
+    next: link to node
+end structure
+end definitions
+program
+{}
+end program
+", body}
+}
+
+#[test]
+fn a_link_sees_changes_to_what_it_is_linked_to() {
+    let body = "a: node = {1, link to nothing}\nb: node = {2, link to a}\nprint(b.next.value)\na.value = 5\nprint(b.next.value)";
+    let (result, host) = run_program(&with_node(body), "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "1\n5\n");
+}
+
+#[test]
+fn unlink_and_is_linked() {
+    let body = "a: node = {1, link to nothing}
+b: node = {2, link to a}
+if b.next is linked then
+    print(\"linked\")
+end if
+unlink b.next
+if b.next is not linked then
+    print(\"unlinked\")
+end if";
+    let (result, host) = run_program(&with_node(body), "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "linked\nunlinked\n");
+}
+
+#[test]
+fn following_an_unlinked_link_is_an_error() {
+    let (result, host) = run_program(&with_node("a: node = {1, link to nothing}\nprint(\"before\")\nprint(a.next.value)"), "");
+    assert_eq!(error_code(result), diagnostic::UNLINKED);
+    assert_eq!(host.output, "before\n");
+}
+
+#[test]
+fn a_link_can_point_back_to_itself() {
+    let (result, host) = run_program(&with_node("d: node = {7, link to nothing}\nd.next = link to d\nprint(d.next.next.next.value)"), "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "7\n");
+}
@@ -0,0 +1,76 @@
+use gelli::lexer::{Lexer, StreamKind, TokenType, Trivia};
+
+const SOURCES: [&str; 5] = [
+    "program\n    print(\"hi\") # say hi\nend program\n",
+    "program\r\n\tx: number = 1   # tabs and CRLF\r\n\r\nend program",
+    "# only a comment",
+    "",
+    "definitions\nstructure p\n  t: text = \"a \\\"quoted\\\" \\n word\"\nend structure\nend definitions\nprogram\nend program\n\n\n",
+];
+
+// The token types from lexing the usual way, without trivia
+// The end of the file has no text, so it isn't in the stream
+fn token_types(src: &str) -> Vec<TokenType> {
+    let mut lex = Lexer::new(src.to_string()).unwrap();
+    let mut types = Vec::new();
+    while !lex.is_done() {
+        match lex.next().unwrap().token_type {
+            TokenType::EOF => (),
+            token_type => types.push(token_type),
+        }
+    }
+    types
+}
+
+#[test]
+fn the_stream_gives_back_the_code() {
+    for src in SOURCES {
+        let stream = Lexer::stream(src.to_string()).unwrap();
+        let joined: String = stream.iter().map(|item| item.raw.as_str()).collect();
+        assert_eq!(joined, src);
+    }
+}
+
+#[test]
+fn offsets_point_at_the_raw_text() {
+    for src in SOURCES {
+        let mut end = 0;
+        for item in Lexer::stream(src.to_string()).unwrap() {
+            assert_eq!(item.start, end);
+            assert_eq!(&src[item.start..item.end], item.raw);
+            end = item.end;
+        }
+        assert_eq!(end, src.len());
+    }
+}
+
+#[test]
+fn the_stream_has_the_same_tokens() {
+    for src in SOURCES {
+        let tokens: Vec<TokenType> = Lexer::stream(src.to_string()).unwrap().into_iter()
+            .filter_map(|item| match item.kind {
+                StreamKind::TOKEN(token) => Some(token.token_type),
+                StreamKind::TRIVIA(_) => None,
+            })
+            .collect();
+        assert_eq!(tokens, token_types(src));
+    }
+}
+
+#[test]
+fn trivia_is_sorted_by_kind() {
+    let stream = Lexer::stream("program  # note\nend program\n".to_string()).unwrap();
+    let trivia: Vec<(Trivia, &str)> = stream.iter()
+        .filter_map(|item| match item.kind {
+            StreamKind::TRIVIA(kind) => Some((kind, item.raw.as_str())),
+            StreamKind::TOKEN(_) => None,
+        })
+        .collect();
+    assert_eq!(trivia, vec![
+        (Trivia::WHITESPACE, "  "),
+        (Trivia::COMMENT, "# note"),
+        (Trivia::NEWLINE, "\n"),
+        (Trivia::WHITESPACE, " "),
+        (Trivia::NEWLINE, "\n"),
+    ]);
+}
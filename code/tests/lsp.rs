@@ -0,0 +1,93 @@
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use serde_json::{json, Value};
+
+const URI: &str = "file:///test.gelli";
+
+// Add the Content-Length header to a message
+fn frame(body: &[u8]) -> Vec<u8> {
+    let mut message = format!{"Content-Length: {}\r\n\r\n", body.len()}.into_bytes();
+    message.extend_from_slice(body);
+    message
+}
+
+// Start "code lsp", open a document with this code, send the raw
+//  messages, then shut down. Gives back every message the server
+//  sent (after the reply to initialize) and its exit code
+fn session(code: &str, messages: &[Vec<u8>]) -> (Vec<Value>, i32) {
+    let mut input = frame(json!({"jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {}}).to_string().as_bytes());
+    input.extend(frame(json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {"textDocument": {"uri": URI, "text": code}}}).to_string().as_bytes()));
+    for message in messages {
+        input.extend(message);
+    }
+    input.extend(frame(json!({"jsonrpc": "2.0", "id": 99, "method": "shutdown"}).to_string().as_bytes()));
+    input.extend(frame(json!({"jsonrpc": "2.0", "method": "exit"}).to_string().as_bytes()));
+
+    let mut server = Command::new(env!("CARGO_BIN_EXE_code")).arg("lsp")
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::null())
+        .spawn().unwrap();
+    server.stdin.take().unwrap().write_all(&input).unwrap();
+    let output = server.wait_with_output().unwrap();
+
+    let mut replies: Vec<Value> = Vec::new();
+    let mut rest = String::from_utf8(output.stdout).unwrap();
+    while let Some((header, body)) = rest.split_once("\r\n\r\n") {
+        let length: usize = header.trim_start_matches("Content-Length: ").parse().unwrap();
+        replies.push(serde_json::from_str(&body[..length]).unwrap());
+        rest = body[length..].to_string();
+    }
+    assert_eq!(replies[0]["id"], 0);
+    (replies[1..].to_vec(), output.status.code().unwrap())
+}
+
+// A request about a position in the open document
+fn request(id: i64, method: &str, line: u32, character: u32) -> Vec<u8> {
+    frame(json!({"jsonrpc": "2.0", "id": id, "method": method, "params": {
+        "textDocument": {"uri": URI},
+        "position": {"line": line, "character": character},
+    }}).to_string().as_bytes())
+}
+
+fn range(start: (u32, u32), end: (u32, u32)) -> Value {
+    json!({"start": {"line": start.0, "character": start.1}, "end": {"line": end.0, "character": end.1}})
+}
+
+#[test]
+fn opening_a_document_publishes_its_errors() {
+    let (replies, status) = session("program\n    y: number = \"a\"\nend program\n", &[]);
+    assert_eq!(replies[0]["method"], "textDocument/publishDiagnostics");
+    let diagnostics = replies[0]["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["code"], "S003");
+    assert_eq!(diagnostics[0]["range"], range((1, 4), (1, 19)));
+    assert_eq!(replies.last().unwrap()["id"], 99);
+    assert_eq!(status, 0);
+}
+
+#[test]
+fn hover_and_definition() {
+    let code = "program\n    x: number = 1\n    print(x)\nend program\n";
+    let (replies, _) = session(code, &[request(1, "textDocument/hover", 2, 10), request(2, "textDocument/definition", 2, 10)]);
+    assert_eq!(replies[1]["id"], 1);
+    assert_eq!(replies[1]["result"]["contents"]["value"], "```gelli\nx: number\n```");
+    assert_eq!(replies[2]["id"], 2);
+    assert_eq!(replies[2]["result"]["range"], range((1, 4), (1, 5)));
+}
+
+#[test]
+fn positions_are_in_utf16_code_units() {
+    // The emoji is one character, but two UTF-16 code units
+    let (replies, _) = session("program\n    print(\"\u{1F600}\")\nend program\n", &[]);
+    assert_eq!(replies[0]["params"]["diagnostics"][0]["range"], range((1, 11), (1, 13)));
+}
+
+#[test]
+fn bad_messages_get_errors_and_the_server_keeps_going() {
+    let too_big = frame(&vec![b' '; 9 * 1024 * 1024]);
+    let (replies, status) = session("program\nend program\n", &[frame(b"{not json"), too_big, request(1, "foo/bar", 0, 0)]);
+    let codes: Vec<&Value> = replies[1..4].iter().map(|r| &r["error"]["code"]).collect();
+    assert_eq!(codes, vec![-32700, -32600, -32601]);
+    assert_eq!(replies[4]["id"], 99);
+    assert_eq!(status, 0);
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use gelli::{Interpreter, MemoryHost, Parser};
+use common::run_program;
+
+// Run a program, and give back how much memory it used at the end
+fn memory_after(src: &str) -> usize {
+    let (tree, errors) = Parser::new(src.to_string()).unwrap().parse();
+    assert!(errors.is_empty(), "syntax errors: {:?}", errors);
+    let mut int = Interpreter::new(MemoryHost::new(""));
+    assert_eq!(int.eval(&tree.unwrap()), Ok(0));
+    int.memory_size()
+}
+
+// Call a function that makes a cycle of two linked nodes (which
+//  nothing can reach once it returns) a number of times
+fn build_cycles(times: u32) -> String {
+    format!{"definitions
+structure node
+    value: number
+    next: link to node
+end structure
+function build() returns nothing
+    a: node = {{1, link to nothing}}
+    b: node = {{2, link to a}}
+    a.next = link to b
+end function
+end definitions
+program
+x: number = 1
+repeat {} times
+    build()
+end repeat
+end program
+", times}
+}
+
+#[test]
+fn unreachable_cycles_are_freed() {
+    assert_eq!(memory_after(&build_cycles(1)), memory_after(&build_cycles(50)));
+}
+
+#[test]
+fn linked_values_outlive_the_function_that_made_them() {
+    let src = "definitions
+structure node
+    value: number
+    next: link to node
+end structure
+function push(v: number, rest: node) returns node
+    n: node = {v, link to rest}
+    return n
+end function
+end definitions
+program
+a: node = {1, link to nothing}
+b: node = push(2, a)
+c: node = push(3, b)
+a.value = 9
+print(c.value, c.next.value, c.next.next.value)
+end program
+";
+    let (result, host) = run_program(src, "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "321\n");
+}
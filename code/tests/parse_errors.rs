@@ -0,0 +1,45 @@
+use gelli::Parser;
+
+// The message and help of the first syntax error in a program body
+fn first_error(body: &str) -> (String, Option<String>) {
+    let src = format!{"program\n{}\nend program\n", body};
+    let (_, errors) = Parser::new(src).unwrap().parse();
+    let error = errors.first().expect("expected a syntax error");
+    (error.message.clone(), error.help.as_ref().map(|h| h.to_string()))
+}
+
+#[test]
+fn tokens_are_described_the_way_they_are_written() {
+    let (message, _) = first_error("x: number = 1 +");
+    assert_eq!(message, "Expected a value, found `end`");
+    let (message, _) = first_error("x: number = 1\nif x > 0\nprint(x)\nend if");
+    assert_eq!(message, "Expected `then`, found the name `print`");
+    let (message, _) = first_error("x: array[3] number");
+    assert_eq!(message, "Expected `of`, found `number`");
+}
+
+#[test]
+fn mistakes_from_other_languages_have_hints() {
+    let (_, help) = first_error("x: number = 1\nif x == 1 then\nprint(x)\nend if");
+    assert_eq!(help.unwrap(), "to compare two values, use a single `=`");
+    let (_, help) = first_error("x: number = 1\nx += 1");
+    assert!(help.unwrap().contains("`x = x + 1`"));
+    let (_, help) = first_error("x number = 1");
+    assert!(help.unwrap().contains("`x: number`"));
+    let (_, help) = first_error("for i in 1 to 3\nend for");
+    assert!(help.unwrap().contains("`repeat for all"));
+}
+
+#[test]
+fn a_keyword_cannot_be_a_name() {
+    let (message, help) = first_error("repeat for all text in \"abc\"\nprint(1)\nend repeat");
+    assert_eq!(message, "Expected a name, found `text`");
+    assert_eq!(help.unwrap(), "`text` is a keyword, so it can't be used as a name");
+}
+
+#[test]
+fn a_mismatched_end_names_both_blocks() {
+    let (message, help) = first_error("x: number = 1\nwhile x < 3\nx = x + 1\nend if");
+    assert_eq!(message, "Expected `while` after `end`, found `if`");
+    assert_eq!(help.unwrap(), "did you mean `end while`?");
+}
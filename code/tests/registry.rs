@@ -0,0 +1,61 @@
+use gelli::{run_source_with, FunctionRegistry, LintConfig, MemoryHost, NativeFunction};
+use gelli::library_handler::{number_type, nothing_type, text_type};
+use gelli::interpreter::LiteralValue;
+use gelli::diagnostic;
+
+// The built in functions, and two more like a robot library would add
+fn robot_functions() -> FunctionRegistry {
+    let mut functions = FunctionRegistry::builtins();
+    functions.register("double", NativeFunction::new(vec![number_type()], number_type(), |_, vals| {
+        Ok(LiteralValue::from_number(vals[0].extract_number().unwrap() * 2.0))
+    }));
+    functions.register("beep", NativeFunction::new(vec![text_type()], nothing_type(), |host, vals| {
+        host.write(&format!{"BEEP {}\n", vals[0].extract_text().unwrap()});
+        Ok(LiteralValue::null())
+    }));
+    functions
+}
+
+fn run_with(functions: &FunctionRegistry, body: &str) -> (Result<i32, gelli::Diagnostics>, MemoryHost) {
+    let mut host = MemoryHost::new("");
+    let src = format!{"program\n{}\nend program\n", body};
+    let result = run_source_with(&src, &mut host, functions, LintConfig::new()).map(|outcome| outcome.exit_code);
+    (result, host)
+}
+
+#[test]
+fn registered_functions_can_be_called() {
+    let (result, host) = run_with(&robot_functions(), "print(double(21))\nbeep(\"hi\")");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "42\nBEEP hi\n");
+}
+
+#[test]
+fn registered_functions_are_type_checked() {
+    let (result, host) = run_with(&robot_functions(), "beep(\"first\")\nprint(double(\"a\"))");
+    assert_eq!(result.unwrap_err()[0].code, diagnostic::TYPE_MISMATCH);
+    assert_eq!(host.output, "");
+}
+
+#[test]
+fn only_registered_functions_can_be_called() {
+    let (result, _) = run_with(&FunctionRegistry::builtins(), "print(double(21))");
+    assert_eq!(result.unwrap_err()[0].code, diagnostic::UNKNOWN_NAME);
+
+    let mut functions = FunctionRegistry::new();
+    functions.register("double", robot_functions().get("double").unwrap().clone());
+    let (result, _) = run_with(&functions, "x: number = double(2)\nprint(x)");
+    assert_eq!(result.unwrap_err()[0].code, diagnostic::UNKNOWN_NAME);
+}
+
+#[test]
+fn a_registered_function_replaces_a_built_in_one() {
+    let mut functions = FunctionRegistry::builtins();
+    functions.register("print", NativeFunction::new(vec![text_type()], nothing_type(), |host, vals| {
+        host.write(&format!{"> {}\n", vals[0].extract_text().unwrap()});
+        Ok(LiteralValue::null())
+    }));
+    let (result, host) = run_with(&functions, "print(\"hello\")");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "> hello\n");
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use gelli::repl::{open_blocks, Repl, Reply};
+use gelli::diagnostic;
+use gelli::MemoryHost;
+use common::error_code;
+
+fn text(reply: Result<Reply, gelli::Diagnostics>) -> String {
+    match reply {
+        Ok(Reply::TEXT(t)) => t,
+        other => panic!("expected text, got {:?}", other),
+    }
+}
+
+#[test]
+fn values_on_their_own_are_shown() {
+    let mut repl = Repl::new(MemoryHost::new(""));
+    assert_eq!(repl.eval("1 + 2"), Ok(Reply::TEXT("3".to_string())));
+    assert_eq!(repl.eval("\"a\" + \"b\""), Ok(Reply::TEXT("ab".to_string())));
+    assert_eq!(repl.eval("x: number = 4"), Ok(Reply::NOTHING));
+    assert_eq!(repl.eval("print(x)"), Ok(Reply::NOTHING));
+    assert_eq!(repl.host().output, "4\n");
+}
+
+#[test]
+fn definitions_are_kept_between_entries() {
+    let mut repl = Repl::new(MemoryHost::new(""));
+    repl.eval("structure point\n    x: number\n    y: number\nend structure\n").unwrap();
+    repl.eval("function sum(p: point) returns number\n    return p.x + p.y\nend function\n").unwrap();
+    repl.eval("p: point = {3, 4}").unwrap();
+    assert_eq!(text(repl.eval("sum(p)")), "7");
+    assert_eq!(text(repl.eval(":funcs")), "sum(point) returns number");
+    assert_eq!(text(repl.eval(":vars")), "p: point = {3,4}");
+}
+
+#[test]
+fn entries_with_errors_are_forgotten() {
+    let mut repl = Repl::new(MemoryHost::new(""));
+    assert_eq!(error_code(repl.eval("x: number = \"text\"")), diagnostic::TYPE_MISMATCH);
+    assert_eq!(error_code(repl.eval("y: number = 1 / 0")), diagnostic::DIVIDE_BY_ZERO);
+    assert_eq!(text(repl.eval(":vars")), "");
+
+    // Both names can still be defined
+    repl.eval("x: number = 1").unwrap();
+    repl.eval("y: number = 2").unwrap();
+    assert_eq!(text(repl.eval("x + y")), "3");
+}
+
+#[test]
+fn errors_point_into_the_source() {
+    let mut repl = Repl::new(MemoryHost::new(""));
+    repl.eval("function half(n: number) returns number\n    return n / 0\nend function\n").unwrap();
+    repl.eval("a: number = 1").unwrap();
+    let errors = repl.eval("half(a)").unwrap_err();
+    assert_eq!(errors[0].span.start_row, 2);
+    assert_eq!(errors[0].span.start_col, 12);
+    assert_eq!(repl.source().lines().nth(1), Some("    return n / 0"));
+}
+
+#[test]
+fn type_shows_without_running() {
+    let mut repl = Repl::new(MemoryHost::new(""));
+    repl.eval("list: array[3] of text").unwrap();
+    assert_eq!(text(repl.eval(":type list")), "array of text");
+    assert_eq!(text(repl.eval(":type length(list) + 1")), "number");
+    assert_eq!(error_code(repl.eval(":type missing")), diagnostic::UNKNOWN_NAME);
+    assert_eq!(text(repl.eval(":type print(\"never\")")), "nothing");
+    assert_eq!(repl.host().output, "");
+}
+
+#[test]
+fn reset_forgets_everything() {
+    let mut repl = Repl::new(MemoryHost::new(""));
+    repl.eval("x: number = 1").unwrap();
+    repl.eval("function f() returns number\n    return 1\nend function\n").unwrap();
+    assert_eq!(repl.eval(":reset"), Ok(Reply::NOTHING));
+    assert_eq!(text(repl.eval(":vars")), "");
+    assert_eq!(text(repl.eval(":funcs")), "");
+    assert_eq!(error_code(repl.eval("x")), diagnostic::UNKNOWN_NAME);
+    repl.eval("x: text = \"again\"").unwrap();
+    assert_eq!(text(repl.eval("x")), "again");
+}
+
+#[test]
+fn quit_ends_the_session() {
+    let mut repl = Repl::new(MemoryHost::new(""));
+    assert_eq!(repl.eval("quit 4"), Ok(Reply::QUIT(4)));
+    assert_eq!(repl.eval(":quit"), Ok(Reply::QUIT(0)));
+}
+
+#[test]
+fn blocks_are_read_until_their_end() {
+    assert_eq!(open_blocks("x: number = 1\n"), 0);
+    assert_eq!(open_blocks("if x > 1 then\n"), 1);
+    assert_eq!(open_blocks("if x > 1 then\nprint(1)\nelse if x < 0 then\n"), 1);
+    assert_eq!(open_blocks("function f() returns nothing\nrepeat 2 times\n"), 2);
+    assert_eq!(open_blocks("while x < 3\nx = x + 1\nend while\n"), 0);
+    assert_eq!(open_blocks("structure s\na: number\nend structure\n"), 0);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use gelli::diagnostic;
+
+// The codes of the semantic errors for a program with one function
+//  'f' that returns a number
+fn function_errors(body: &str) -> Vec<&'static str> {
+    common::errors_for(&format!{"definitions\nfunction f(n: number) returns number\n{}\nend function\nend definitions\nprogram\nprint(f(1))\nend program\n", body})
+}
+
+#[test]
+fn return_at_the_end() {
+    assert_eq!(function_errors("print(n)\nreturn n"), Vec::<&str>::new());
+}
+
+#[test]
+fn empty_body_is_missing_a_return() {
+    assert_eq!(function_errors(""), vec![diagnostic::MISSING_RETURN]);
+}
+
+#[test]
+fn if_and_else_both_return() {
+    assert_eq!(function_errors("if n > 0 then\nreturn 1\nelse\nreturn 2\nend if"), Vec::<&str>::new());
+}
+
+#[test]
+fn if_without_else_can_fall_through() {
+    assert_eq!(function_errors("if n > 0 then\nreturn 1\nend if"), vec![diagnostic::MISSING_RETURN]);
+}
+
+#[test]
+fn else_if_chain_needs_a_final_else() {
+    let body = "if n > 0 then\nreturn 1\nelse if n < 0 then\nreturn 2\nend if";
+    assert_eq!(function_errors(body), vec![diagnostic::MISSING_RETURN]);
+
+    let body = "if n > 0 then\nreturn 1\nelse if n < 0 then\nreturn 2\nelse\nreturn 3\nend if";
+    assert_eq!(function_errors(body), Vec::<&str>::new());
+}
+
+#[test]
+fn loops_that_might_not_run_dont_count() {
+    assert_eq!(function_errors("while n > 0\nreturn 1\nend while"), vec![diagnostic::MISSING_RETURN]);
+    assert_eq!(function_errors("repeat n times\nreturn 1\nend repeat"), vec![diagnostic::MISSING_RETURN]);
+}
+
+#[test]
+fn repeat_forever_is_only_left_by_break() {
+    assert_eq!(function_errors("repeat forever\nreturn 1\nend repeat"), Vec::<&str>::new());
+    assert_eq!(function_errors("repeat forever\nbreak\nend repeat"), vec![diagnostic::MISSING_RETURN]);
+}
+
+#[test]
+fn quit_counts_as_a_return() {
+    assert_eq!(function_errors("if n > 0 then\nreturn 1\nend if\nquit 2"), Vec::<&str>::new());
+}
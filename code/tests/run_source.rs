@@ -0,0 +1,201 @@
+mod common;
+
+use gelli::{run_source, LintConfig, MemoryHost};
+use gelli::diagnostic;
+use common::{error_code, run, run_program};
+
+#[test]
+fn characters_that_are_not_tokens_are_lexer_errors() {
+    let (result, _) = run("x: number = 1 @ 2", "");
+    assert_eq!(error_code(result), diagnostic::UNKNOWN_CHARACTER);
+    let (result, _) = run("print(\"never ended)", "");
+    assert_eq!(error_code(result), diagnostic::UNTERMINATED_TEXT);
+}
+
+#[test]
+fn warnings_come_after_a_runtime_error() {
+    let mut host = MemoryHost::new("");
+    let src = "program\n    unused: number = 1\n    x: number = 0\n    print(1 / x)\nend program\n";
+    let errors = run_source(src, &mut host, LintConfig::new()).unwrap_err();
+    let codes: Vec<&str> = errors.iter().map(|e| e.code).collect();
+    assert_eq!(codes, vec![diagnostic::DIVIDE_BY_ZERO, diagnostic::UNUSED_VARIABLE]);
+}
+
+#[test]
+fn lints_that_are_turned_off_give_no_warnings() {
+    let src = "program\n    unused: number = 1\nend program\n";
+    let outcome = run_source(src, &mut MemoryHost::new(""), LintConfig::new()).unwrap();
+    assert_eq!(outcome.warnings[0].code, diagnostic::UNUSED_VARIABLE);
+
+    let mut lints = LintConfig::new();
+    lints.set("unused-variable", false);
+    let outcome = run_source(src, &mut MemoryHost::new(""), lints).unwrap();
+    assert!(outcome.warnings.is_empty());
+}
+
+// The text of the program that an error points at
+fn error_text(src: &str) -> String {
+    let errors = run_source(src, &mut MemoryHost::new(""), LintConfig::new()).unwrap_err();
+    src[errors[0].span.start..errors[0].span.end].to_string()
+}
+
+#[test]
+fn runtime_errors_point_at_the_whole_reference() {
+    let src = "definitions
+    structure node
+        value: number
+        next: link to node
+    end structure
+end definitions
+program
+    h: node = {2, link to nothing}
+    print(h.next.value)
+end program
+";
+    assert_eq!(error_text(src), "h.next.value");
+    let src = "program\n    arr: array[2] of number = [1, 2]\n    i: number = 2\n    print(arr[i + 1])\nend program\n";
+    assert_eq!(error_text(src), "arr[i + 1]");
+    let src = "program\n    t: text = \"ab\"\n    print(t[3])\nend program\n";
+    assert_eq!(error_text(src), "t[3]");
+    // An error inside of an index still points at the index
+    let src = "program\n    arr: array[2] of number = [1, 2]\n    i: number = 0\n    print(arr[1 / i])\nend program\n";
+    assert_eq!(error_text(src), "1 / i");
+}
+
+#[test]
+fn output_is_kept() {
+    let (result, host) = run("print(\"a\", 1)\ndisplay(\"b\")\ndisplay_line(2)", "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "a1\nb2\n");
+}
+
+#[test]
+fn a_character_of_text_can_be_assigned() {
+    let (result, host) = run("t: text = \"ab\"\nc: text = t[2]\nprint(c)\nc = t[1]\nprint(c)", "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "b\na\n");
+    let (result, _) = run("t: text = \"ab\"\nc: text = t[3]\nprint(c)", "");
+    assert_eq!(error_code(result), diagnostic::OUT_OF_BOUNDS);
+}
+
+#[test]
+fn an_assigned_value_is_only_evaluated_once() {
+    let body = "arr: array[3] of number = [10, 20, 30]\nx: number = arr[input_number()]\nprint(x, input_number())";
+    let (result, host) = run(body, "1\n2\n");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "102\n");
+}
+
+#[test]
+fn input_is_read_a_line_at_a_time() {
+    let body = "name: text = input_text()\nn: number = input_number()\nprint(name, n * 2)";
+    let (result, host) = run(body, "Ada\n21\n");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.output, "Ada42\n");
+    assert!(host.input.is_empty());
+}
+
+#[test]
+fn reading_past_the_input_is_an_error() {
+    let (result, host) = run("display(\"first\")\nt: text = input_text()\nprint(t)", "");
+    assert_eq!(error_code(result), diagnostic::INVALID_INPUT);
+    assert_eq!(host.output, "first");
+}
+
+#[test]
+fn input_number_rejects_text() {
+    let (result, _) = run("n: number = input_number()\nprint(n)", "ten\n");
+    assert_eq!(error_code(result), diagnostic::INVALID_INPUT);
+}
+
+#[test]
+fn quit_status_is_the_exit_code() {
+    let (result, host) = run("print(\"before\")\nquit 7\nprint(\"after\")", "");
+    assert_eq!(result, Ok(7));
+    assert_eq!(host.output, "before\n");
+}
+
+#[test]
+fn quit_inside_a_loop_stops_the_program() {
+    let (result, host) = run("repeat 5 times\n    print(\"once\")\n    quit 3\nend repeat", "");
+    assert_eq!(result, Ok(3));
+    assert_eq!(host.output, "once\n");
+}
+
+#[test]
+fn quit_inside_a_function_stops_the_program() {
+    let src = "definitions
+    function f() returns number
+        quit 4
+        return 1
+    end function
+end definitions
+program
+    print(\"before\")
+    x: number = f()
+    print(\"after\", x)
+end program
+";
+    let (result, host) = run_program(src, "");
+    assert_eq!(result, Ok(4));
+    assert_eq!(host.output, "before\n");
+}
+
+#[test]
+fn quit_status_must_be_a_byte() {
+    let (result, _) = run("quit 300", "");
+    assert_eq!(error_code(result), diagnostic::INVALID_OPERATION);
+}
+
+#[test]
+fn wait_only_moves_the_clock() {
+    let (result, host) = run("wait(1.5)\nwait(2)", "");
+    assert_eq!(result, Ok(0));
+    assert_eq!(host.time, 3.5);
+}
+
+#[test]
+fn wait_rejects_negative_time() {
+    let (result, host) = run("wait(-1)", "");
+    assert_eq!(error_code(result), diagnostic::INVALID_ARGUMENT);
+    assert_eq!(host.time, 0.0);
+}
+
+#[test]
+fn wait_rejects_times_that_are_too_long() {
+    for body in ["wait(100000000000000000000)", "x: number = 2 ^ 1024\nwait(x)", "x: number = 2 ^ 1024\nwait(x - x)"] {
+        let (result, host) = run(body, "");
+        assert_eq!(error_code(result), diagnostic::INVALID_ARGUMENT);
+        assert_eq!(host.time, 0.0);
+    }
+}
+
+#[test]
+fn random_numbers_come_from_the_seed() {
+    let body = "print(random_number(1, 100))\nprint(random_number(1, 100))\nprint(random_number(1, 100))";
+    let draw = |seed: u64| {
+        let mut host = MemoryHost::new("").with_seed(seed);
+        let src = format!{"program\n{}\nend program\n", body};
+        run_source(&src, &mut host, LintConfig::new()).unwrap();
+        host.output
+    };
+    assert_eq!(draw(42), draw(42));
+    assert_ne!(draw(42), draw(43));
+}
+
+#[test]
+fn first_random_number_is_not_always_zero() {
+    for seed in 0..4 {
+        let mut host = MemoryHost::new("").with_seed(seed);
+        run_source("program\nprint(random_number())\nend program\n", &mut host, LintConfig::new()).unwrap();
+        let n: f64 = host.output.trim().parse().unwrap();
+        assert!(n > 0.001 && n < 1.0, "seed {} gave {}", seed, n);
+    }
+}
+
+#[test]
+fn errors_stop_before_running() {
+    let (result, host) = run("print(\"never\")\nx: number = \"text\"", "");
+    assert_eq!(error_code(result), diagnostic::TYPE_MISMATCH);
+    assert_eq!(host.output, "");
+}